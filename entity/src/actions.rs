@@ -25,6 +25,9 @@ pub struct Model {
     #[serde(skip_deserializing)]
     #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
     pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -37,6 +40,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     CoachingSessions,
+    #[sea_orm(has_many = "super::status_transitions::Entity")]
+    StatusTransitions,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -53,6 +58,12 @@ impl Related<super::coaching_sessions::Entity> for Entity {
     }
 }
 
+impl Related<super::status_transitions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StatusTransitions.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
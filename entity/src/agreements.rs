@@ -18,9 +18,20 @@ pub struct Model {
     #[serde(skip_deserializing)]
     pub user_id: Id,
     #[serde(skip_deserializing)]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub coach_acknowledged_at: Option<DateTimeWithTimeZone>,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub coachee_acknowledged_at: Option<DateTimeWithTimeZone>,
+    #[serde(skip_deserializing)]
+    pub acknowledged_revision_id: Option<Id>,
+    #[serde(skip_deserializing)]
     pub created_at: DateTimeWithTimeZone,
     #[serde(skip_deserializing)]
     pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -33,6 +44,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     CoachingSessions,
+    #[sea_orm(has_many = "super::revisions::Entity")]
+    Revisions,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -49,6 +62,12 @@ impl Related<super::coaching_sessions::Entity> for Entity {
     }
 }
 
+impl Related<super::revisions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Revisions.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
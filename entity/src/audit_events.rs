@@ -0,0 +1,29 @@
+use crate::{audit_action::AuditAction, Id};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A write made to a record, kept for compliance. Audit Events are only ever appended, never
+/// changed or removed, and outlive the records they describe.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = entity::audit_events::Model)] // OpenAPI schema
+#[sea_orm(schema_name = "refactor_platform", table_name = "audit_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Id,
+    pub organization_id: Option<Id>,
+    pub actor_id: Option<Id>,
+    pub action: AuditAction,
+    pub entity_type: String,
+    pub entity_id: Id,
+    #[schema(value_type = Vec<String>)] // Applies to OpenAPI schema
+    pub changed_fields: Json,
+    pub request_id: Option<String>,
+    #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,6 +1,6 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
-use crate::Id;
+use crate::{relationship_status::RelationshipStatus, Id};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -15,16 +15,32 @@ pub struct Model {
     #[serde(skip_deserializing)]
     #[sea_orm(primary_key)]
     pub id: Id,
-    #[sea_orm(unique)]
     pub organization_id: Id,
     pub coach_id: Id,
     pub coachee_id: Id,
+    #[serde(default)]
+    pub status: RelationshipStatus,
+    #[serde(default = "today")]
+    #[schema(value_type = String, format = Date)] // Applies to OpenAPI schema
+    pub start_date: Date,
+    #[schema(value_type = Option<String>, format = Date)] // Applies to OpenAPI schema
+    pub end_date: Option<Date>,
+    #[serde(skip_deserializing)]
+    pub reassigned_to_id: Option<Id>,
     #[serde(skip_deserializing)]
     #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
     pub created_at: DateTimeWithTimeZone,
     #[serde(skip_deserializing)]
     #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
     pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+}
+
+/// The current date in UTC, which a relationship starts on unless it names another date.
+pub fn today() -> Date {
+    chrono::Utc::now().date_naive()
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -53,6 +69,14 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Coachees,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ReassignedToId",
+        to = "Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    SelfRef,
 }
 
 impl Related<super::organizations::Entity> for Entity {
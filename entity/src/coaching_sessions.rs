@@ -21,6 +21,9 @@ pub struct Model {
     #[serde(skip_deserializing)]
     #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
     pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,6 +32,8 @@ pub enum Relation {
     Actions,
     #[sea_orm(has_many = "super::agreements::Entity")]
     Agreements,
+    #[sea_orm(has_many = "super::attachments::Entity")]
+    Attachments,
     #[sea_orm(
         belongs_to = "super::coaching_relationships::Entity",
         from = "Column::CoachingRelationshipId",
@@ -55,6 +60,12 @@ impl Related<super::agreements::Entity> for Entity {
     }
 }
 
+impl Related<super::attachments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachments.def()
+    }
+}
+
 impl Related<super::coaching_relationships::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::CoachingRelationships.def()
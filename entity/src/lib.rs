@@ -4,15 +4,23 @@ pub mod prelude;
 
 pub mod actions;
 pub mod agreements;
+pub mod attachments;
+pub mod audit_action;
+pub mod audit_events;
 pub mod coachees;
 pub mod coaches;
 pub mod coaching_relationships;
 pub mod coaching_sessions;
 pub mod notes;
+pub mod organization_admins;
 pub mod organizations;
 pub mod overarching_goals;
+pub mod relationship_status;
+pub mod revisions;
 pub mod status;
+pub mod status_transitions;
 pub mod users;
+pub mod visibility;
 
 /// A type alias that represents any Entity's internal id field data type.
 /// Aliased so that it's easy to change the underlying type if necessary.
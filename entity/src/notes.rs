@@ -1,6 +1,6 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
-use crate::Id;
+use crate::{visibility::Visibility, Id};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -16,10 +16,14 @@ pub struct Model {
     pub body: Option<String>,
     #[serde(skip_deserializing)]
     pub user_id: Id,
+    #[serde(default)]
+    pub visibility: Visibility,
     #[serde(skip_deserializing)]
     pub created_at: DateTimeWithTimeZone,
     #[serde(skip_deserializing)]
     pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_deserializing)]
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -32,6 +36,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     CoachingSessions,
+    #[sea_orm(has_many = "super::revisions::Entity")]
+    Revisions,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -48,6 +54,12 @@ impl Related<super::coaching_sessions::Entity> for Entity {
     }
 }
 
+impl Related<super::revisions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Revisions.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
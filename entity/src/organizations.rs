@@ -21,12 +21,17 @@ pub struct Model {
     #[serde(skip_deserializing)]
     #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
     pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::coaching_relationships::Entity")]
     CoachingRelationships,
+    #[sea_orm(has_many = "super::organization_admins::Entity")]
+    OrganizationAdmins,
 }
 
 impl Related<super::coaching_relationships::Entity> for Entity {
@@ -35,4 +40,10 @@ impl Related<super::coaching_relationships::Entity> for Entity {
     }
 }
 
+impl Related<super::organization_admins::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrganizationAdmins.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
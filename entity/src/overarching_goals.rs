@@ -27,6 +27,8 @@ pub struct Model {
     pub created_at: DateTimeWithTimeZone,
     #[serde(skip_deserializing)]
     pub updated_at: DateTimeWithTimeZone,
+    #[serde(skip_deserializing)]
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -39,6 +41,10 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     CoachingSessions,
+    #[sea_orm(has_many = "super::revisions::Entity")]
+    Revisions,
+    #[sea_orm(has_many = "super::status_transitions::Entity")]
+    StatusTransitions,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -55,6 +61,18 @@ impl Related<super::coaching_sessions::Entity> for Entity {
     }
 }
 
+impl Related<super::revisions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Revisions.def()
+    }
+}
+
+impl Related<super::status_transitions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StatusTransitions.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
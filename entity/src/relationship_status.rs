@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Where a Coaching Relationship is in its lifecycle.
+#[derive(
+    Debug, Clone, Default, Eq, PartialEq, EnumIter, Deserialize, Serialize, DeriveActiveEnum,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "relationship_status"
+)]
+pub enum RelationshipStatus {
+    /// Coaching is ongoing
+    #[sea_orm(string_value = "active")]
+    #[default]
+    Active,
+    /// Coaching is on hold, e.g. while the coachee is on leave
+    #[sea_orm(string_value = "paused")]
+    Paused,
+    /// Coaching is over for good
+    #[sea_orm(string_value = "ended")]
+    Ended,
+}
+
+impl RelationshipStatus {
+    /// Whether a relationship may move from this status to `next`. A relationship can be
+    /// paused and resumed any number of times, but once it has ended it stays ended.
+    pub fn can_transition_to(&self, next: &RelationshipStatus) -> bool {
+        self == next || *self != RelationshipStatus::Ended
+    }
+}
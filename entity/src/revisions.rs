@@ -0,0 +1,85 @@
+use crate::Id;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A snapshot of the body of a Note, Agreement or Overarching Goal. Exactly one of
+/// `note_id`, `agreement_id` or `overarching_goal_id` is set.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = entity::revisions::Model)] // OpenAPI schema
+#[sea_orm(schema_name = "refactor_platform", table_name = "revisions")]
+pub struct Model {
+    #[serde(skip_deserializing)]
+    #[sea_orm(primary_key)]
+    pub id: Id,
+    pub note_id: Option<Id>,
+    pub agreement_id: Option<Id>,
+    pub overarching_goal_id: Option<Id>,
+    pub body: Option<String>,
+    pub user_id: Id,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::notes::Entity",
+        from = "Column::NoteId",
+        to = "super::notes::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Notes,
+    #[sea_orm(
+        belongs_to = "super::agreements::Entity",
+        from = "Column::AgreementId",
+        to = "super::agreements::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Agreements,
+    #[sea_orm(
+        belongs_to = "super::overarching_goals::Entity",
+        from = "Column::OverarchingGoalId",
+        to = "super::overarching_goals::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    OverarchingGoals,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Users,
+}
+
+impl Related<super::notes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notes.def()
+    }
+}
+
+impl Related<super::agreements::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Agreements.def()
+    }
+}
+
+impl Related<super::overarching_goals::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OverarchingGoals.def()
+    }
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
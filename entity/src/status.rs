@@ -1,5 +1,7 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Eq, PartialEq, EnumIter, Deserialize, Serialize, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "status")]
@@ -20,14 +22,81 @@ impl std::default::Default for Status {
     }
 }
 
-impl From<&str> for Status {
-    fn from(value: &str) -> Self {
+impl Status {
+    /// Whether moving from this status to `next` is permitted by `policy`. Staying
+    /// on the same status is always permitted.
+    pub fn can_transition_to(&self, next: &Status, policy: &TransitionPolicy) -> bool {
+        self == next || policy.allows(self, next)
+    }
+}
+
+/// The set of status changes that one kind of entity permits.
+#[derive(Debug)]
+pub struct TransitionPolicy {
+    transitions: &'static [(Status, Status)],
+}
+
+impl TransitionPolicy {
+    pub const fn new(transitions: &'static [(Status, Status)]) -> Self {
+        Self { transitions }
+    }
+
+    fn allows(&self, from: &Status, to: &Status) -> bool {
+        self.transitions
+            .iter()
+            .any(|(allowed_from, allowed_to)| allowed_from == from && allowed_to == to)
+    }
+}
+
+/// Actions must be started before they can be completed, and can be reopened
+/// after being completed or set aside.
+pub const ACTION_TRANSITION_POLICY: TransitionPolicy = TransitionPolicy::new(&[
+    (Status::NotStarted, Status::InProgress),
+    (Status::NotStarted, Status::WontDo),
+    (Status::InProgress, Status::NotStarted),
+    (Status::InProgress, Status::Completed),
+    (Status::InProgress, Status::WontDo),
+    (Status::Completed, Status::InProgress),
+    (Status::WontDo, Status::NotStarted),
+    (Status::WontDo, Status::InProgress),
+]);
+
+/// Overarching Goals follow the same lifecycle as Actions, except that a completed
+/// goal can also be set aside directly when it turns out not to have been met.
+pub const OVERARCHING_GOAL_TRANSITION_POLICY: TransitionPolicy = TransitionPolicy::new(&[
+    (Status::NotStarted, Status::InProgress),
+    (Status::NotStarted, Status::WontDo),
+    (Status::InProgress, Status::NotStarted),
+    (Status::InProgress, Status::Completed),
+    (Status::InProgress, Status::WontDo),
+    (Status::Completed, Status::InProgress),
+    (Status::Completed, Status::WontDo),
+    (Status::WontDo, Status::NotStarted),
+    (Status::WontDo, Status::InProgress),
+]);
+
+/// Returned when a string does not name any known Status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownStatus(pub String);
+
+impl fmt::Display for UnknownStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown status: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownStatus {}
+
+impl FromStr for Status {
+    type Err = UnknownStatus;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
         match value {
-            "not_started" => Self::NotStarted,
-            "in_progress" => Self::InProgress,
-            "completed" => Self::Completed,
-            "wont_do" => Self::WontDo,
-            _ => Self::InProgress,
+            "not_started" => Ok(Self::NotStarted),
+            "in_progress" => Ok(Self::InProgress),
+            "completed" => Ok(Self::Completed),
+            "wont_do" => Ok(Self::WontDo),
+            _ => Err(UnknownStatus(value.to_owned())),
         }
     }
 }
@@ -0,0 +1,71 @@
+use crate::{status::Status, Id};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A single recorded change of an Action's or Overarching Goal's status. Exactly one
+/// of `action_id` or `overarching_goal_id` is set.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = entity::status_transitions::Model)] // OpenAPI schema
+#[sea_orm(schema_name = "refactor_platform", table_name = "status_transitions")]
+pub struct Model {
+    #[serde(skip_deserializing)]
+    #[sea_orm(primary_key)]
+    pub id: Id,
+    pub action_id: Option<Id>,
+    pub overarching_goal_id: Option<Id>,
+    pub from_status: Option<Status>,
+    pub to_status: Status,
+    pub user_id: Id,
+    #[serde(skip_deserializing)]
+    #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::actions::Entity",
+        from = "Column::ActionId",
+        to = "super::actions::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Actions,
+    #[sea_orm(
+        belongs_to = "super::overarching_goals::Entity",
+        from = "Column::OverarchingGoalId",
+        to = "super::overarching_goals::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    OverarchingGoals,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Users,
+}
+
+impl Related<super::actions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Actions.def()
+    }
+}
+
+impl Related<super::overarching_goals::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OverarchingGoals.def()
+    }
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
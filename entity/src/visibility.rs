@@ -0,0 +1,17 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Who may read a Note.
+#[derive(
+    Debug, Clone, Default, Eq, PartialEq, EnumIter, Deserialize, Serialize, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "visibility")]
+pub enum Visibility {
+    /// Anyone in the coaching session may read it
+    #[sea_orm(string_value = "shared")]
+    #[default]
+    Shared,
+    /// Only the Note's author may read it
+    #[sea_orm(string_value = "private")]
+    Private,
+}
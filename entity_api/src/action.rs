@@ -1,21 +1,29 @@
-use super::error::{EntityApiErrorCode, Error};
-use crate::uuid_parse_str;
+use super::error::Error;
+use crate::audit::{self, Within};
+use crate::coaching_session::with_member;
+use crate::precondition::{self, Precondition};
+use crate::soft_delete;
+use crate::status_transition::{self, Subject};
+use crate::{unknown_query_param, uuid_parse_str};
 use entity::actions::{self, ActiveModel, Entity, Model};
-use entity::{status::Status, Id};
+use entity::{
+    audit_action::AuditAction,
+    status::{Status, ACTION_TRANSITION_POLICY},
+    Id,
+};
 use sea_orm::{
     entity::prelude::*,
     ActiveValue::{Set, Unchanged},
-    DatabaseConnection, TryIntoModel,
+    ConnectionTrait, TransactionTrait, TryIntoModel,
 };
 use std::collections::HashMap;
 
 use log::*;
 
-pub async fn create(
-    db: &DatabaseConnection,
-    action_model: Model,
-    user_id: Id,
-) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, action_model: Model, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!("New Action Model to be inserted: {:?}", action_model);
 
     let now = chrono::Utc::now();
@@ -32,137 +40,295 @@ pub async fn create(
         ..Default::default()
     };
 
-    Ok(action_active_model.save(db).await?.try_into_model()?)
-}
+    let changed_fields = audit::changed_fields(&action_active_model);
 
-pub async fn update(db: &DatabaseConnection, id: Id, model: Model) -> Result<Model, Error> {
-    let result = Entity::find_by_id(id).one(db).await?;
+    let txn = db.begin().await?;
+    let action = action_active_model.save(&txn).await?.try_into_model()?;
 
-    match result {
-        Some(action) => {
-            debug!("Existing Action model to be Updated: {:?}", action);
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        action.id,
+        changed_fields,
+        Within::CoachingSession(action.coaching_session_id),
+    )
+    .await?;
 
-            let active_model: ActiveModel = ActiveModel {
-                id: Unchanged(action.id),
-                coaching_session_id: Unchanged(action.coaching_session_id),
-                user_id: Unchanged(model.user_id),
-                body: Set(model.body),
-                due_by: Set(model.due_by),
-                status: Set(model.status),
-                status_changed_at: Set(chrono::Utc::now().into()),
-                updated_at: Set(chrono::Utc::now().into()),
-                created_at: Unchanged(action.created_at),
-            };
+    txn.commit().await?;
 
-            Ok(active_model.update(db).await?.try_into_model()?)
-        }
-        None => {
-            error!("Action with id {} not found", id);
+    Ok(action)
+}
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
-    }
+/// Changes to some of an Action's fields. A field left as `None` keeps its current value,
+/// while `Some(None)` clears a field that may be empty.
+#[derive(Debug, Default)]
+pub struct Patch {
+    pub body: Option<Option<String>>,
+    pub due_by: Option<Option<DateTimeWithTimeZone>>,
+    pub status: Option<Status>,
+}
+
+pub async fn update<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    model: Model,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let changes = Patch {
+        body: Some(model.body),
+        due_by: Some(model.due_by),
+        status: Some(model.status),
+    };
+
+    patch(db, id, precondition, changes, user_id).await
 }
 
-pub async fn update_status(
-    db: &DatabaseConnection,
+pub async fn update_status<C>(
+    db: &C,
     id: Id,
+    precondition: Precondition,
     status: Status,
-) -> Result<Model, Error> {
-    let result = Entity::find_by_id(id).one(db).await?;
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let changes = Patch {
+        status: Some(status),
+        ..Default::default()
+    };
+
+    patch(db, id, precondition, changes, user_id).await
+}
+
+/// Changes only the fields of an Action that `changes` names. Its `status_changed_at` moves
+/// only when its status actually changes.
+pub async fn patch<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    changes: Patch,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id).one(&txn).await?;
 
     match result {
         Some(action) => {
             debug!("Existing Action model to be Updated: {:?}", action);
 
-            let active_model: ActiveModel = ActiveModel {
+            precondition.check("Action", id, &action.updated_at)?;
+
+            let now = chrono::Utc::now();
+            let mut active_model: ActiveModel = ActiveModel {
                 id: Unchanged(action.id),
-                coaching_session_id: Unchanged(action.coaching_session_id),
-                user_id: Unchanged(action.user_id),
-                body: Unchanged(action.body),
-                due_by: Unchanged(action.due_by),
-                status: Set(status),
-                status_changed_at: Set(chrono::Utc::now().into()),
-                updated_at: Set(chrono::Utc::now().into()),
-                created_at: Unchanged(action.created_at),
+                updated_at: Set(now.into()),
+                ..Default::default()
             };
 
-            Ok(active_model.update(db).await?.try_into_model()?)
-        }
-        None => {
-            error!("Action with id {} not found", id);
+            if let Some(body) = changes.body {
+                active_model.body = Set(body);
+            }
+            if let Some(due_by) = changes.due_by {
+                active_model.due_by = Set(due_by);
+            }
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
+            let new_status = changes.status.filter(|status| *status != action.status);
+            if let Some(status) = &new_status {
+                status_transition::check(&action.status, status, &ACTION_TRANSITION_POLICY)?;
+
+                active_model.status = Set(status.clone());
+                active_model.status_changed_at = Set(now.into());
+            }
+
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let updated_action = precondition::update_unchanged(
+                &txn,
+                "Action",
+                id,
+                active_model,
+                actions::Column::UpdatedAt,
+                action.updated_at,
+            )
+            .await?;
+
+            if let Some(status) = new_status {
+                status_transition::create(
+                    &txn,
+                    Subject::Action(action.id),
+                    Some(action.status),
+                    status,
+                    user_id,
+                )
+                .await?;
+            }
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(action.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(updated_action)
         }
+        None => Err(Error::not_found("Action", id)),
     }
 }
 
-pub async fn delete_by_id(db: &DatabaseConnection, id: Id) -> Result<(), Error> {
-    let result = find_by_id(db, id).await?;
+/// Deletes an Action by hiding it until it is restored or purged.
+pub async fn delete_by_id<C>(db: &C, id: Id, precondition: Precondition) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let action_model = find_by_id(db, id).await?;
+    debug!("Existing Action model to be deleted: {:?}", action_model);
 
-    match result {
-        Some(action_model) => {
-            debug!("Existing Action model to be deleted: {:?}", action_model);
+    precondition.check("Action", id, &action_model.updated_at)?;
 
-            action_model.delete(db).await?;
-            Ok(())
-        }
-        None => Err(Error {
-            inner: None,
-            error_code: EntityApiErrorCode::RecordNotFound,
-        }),
-    }
+    let now = chrono::Utc::now();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(now.into())),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    precondition::update_unchanged(
+        &txn,
+        "Action",
+        id,
+        active_model,
+        actions::Column::UpdatedAt,
+        action_model.updated_at,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::CoachingSession(action_model.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
 }
 
-pub async fn find_by_id(db: &DatabaseConnection, id: Id) -> Result<Option<Model>, Error> {
-    match Entity::find_by_id(id).one(db).await {
-        Ok(Some(action)) => {
-            debug!("Action found: {:?}", action);
+/// Restores a deleted Action that hasn't been purged yet.
+pub async fn restore<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let action_model = soft_delete::find_deleted_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Action", id))?;
+    debug!("Deleted Action model to be restored: {:?}", action_model);
+
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        updated_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
 
-            Ok(Some(action))
-        }
-        Ok(None) => {
-            error!("Action with id {} not found", id);
+    let txn = db.begin().await?;
+    let restored_action = active_model.update(&txn).await?;
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
-        Err(err) => {
-            error!("Action with id {} not found and returned error {}", id, err);
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
-    }
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::CoachingSession(restored_action.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(restored_action)
+}
+
+pub async fn find_by_id<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let action = soft_delete::find_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Action", id))?;
+    debug!("Action found: {:?}", action);
+
+    Ok(action)
 }
 
-pub async fn find_by(
-    db: &DatabaseConnection,
-    query_params: HashMap<String, String>,
-) -> Result<Vec<Model>, Error> {
-    let mut query = Entity::find();
+/// Finds an Action that `user_id` takes part in, as either the coach or the coachee of the
+/// relationship its coaching session belongs to. Any other user is told it was not found.
+pub async fn find_by_id_for_member<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let action = with_member(soft_delete::find_by_id::<Entity>(id), user_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Action", id))?;
+    debug!("Action found: {:?}", action);
+
+    Ok(action)
+}
+
+/// Like `find_by_id_for_member`, for an Action that has been deleted.
+pub async fn find_deleted_by_id_for_member<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let action = with_member(soft_delete::find_deleted_by_id::<Entity>(id), user_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Action", id))?;
+    debug!("Deleted Action found: {:?}", action);
+
+    Ok(action)
+}
+
+pub async fn find_by<C>(db: &C, query_params: HashMap<String, String>) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = soft_delete::find::<Entity>();
 
     for (key, value) in query_params {
         match key.as_str() {
             "coaching_session_id" => {
-                let coaching_session_id = uuid_parse_str(&value)?;
+                let coaching_session_id = uuid_parse_str(&key, &value)?;
 
                 query = query.filter(actions::Column::CoachingSessionId.eq(coaching_session_id));
             }
             _ => {
-                return Err(Error {
-                    inner: None,
-                    error_code: EntityApiErrorCode::InvalidQueryTerm,
-                });
+                return Err(unknown_query_param(&key));
             }
         }
     }
@@ -177,7 +343,8 @@ pub async fn find_by(
 #[cfg(feature = "mock")]
 mod tests {
     use super::*;
-    use entity::{actions::Model, Id};
+    use crate::error::EntityApiErrorCode;
+    use entity::{actions::Model, status_transitions, Id};
     use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
 
     #[tokio::test]
@@ -194,10 +361,12 @@ mod tests {
             status: Default::default(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
             .append_query_results(vec![vec![action_model.clone()]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
         let action = create(&db, action_model.clone().into(), Id::new_v4()).await?;
@@ -221,13 +390,22 @@ mod tests {
             status: Default::default(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
             .append_query_results(vec![vec![action_model.clone()], vec![action_model.clone()]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let action = update(&db, action_model.id, action_model.clone()).await?;
+        let action = update(
+            &db,
+            action_model.id,
+            Precondition::Any,
+            action_model.clone(),
+            Id::new_v4(),
+        )
+        .await?;
 
         assert_eq!(action.body, action_model.body);
 
@@ -248,6 +426,7 @@ mod tests {
             status: Default::default(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let updated_action_model = Model {
@@ -260,6 +439,19 @@ mod tests {
             status: Status::Completed,
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let user_id = Id::new_v4();
+
+        let status_transition_model = status_transitions::Model {
+            id: Id::new_v4(),
+            action_id: Some(action_model.id),
+            overarching_goal_id: None,
+            from_status: Some(action_model.status.clone()),
+            to_status: Status::Completed,
+            user_id,
+            created_at: now.into(),
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
@@ -267,12 +459,67 @@ mod tests {
                 vec![action_model.clone()],
                 vec![updated_action_model.clone()],
             ])
+            .append_query_results(vec![vec![status_transition_model.clone()]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let action = update_status(&db, action_model.id, Status::Completed).await?;
+        let action = update_status(
+            &db,
+            action_model.id,
+            Precondition::Any,
+            Status::Completed,
+            user_id,
+        )
+        .await?;
 
         assert_eq!(action.status, Status::Completed);
 
+        // The lookup, the update and the status transition must all be logged as a
+        // single transaction rather than as separate statements
+        assert_eq!(db.into_transaction_log().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_status_rejects_completing_an_action_that_was_never_started() -> Result<(), Error>
+    {
+        let now = chrono::Utc::now();
+
+        let action_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            due_by: Some(now.into()),
+            body: Some("This is a action".to_owned()),
+            user_id: Id::new_v4(),
+            status_changed_at: now.into(),
+            status: Status::NotStarted,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![action_model.clone()]])
+            .into_connection();
+
+        let result = update_status(
+            &db,
+            action_model.id,
+            Precondition::Any,
+            Status::Completed,
+            Id::new_v4(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::InvalidStatusTransition,
+                ..
+            })
+        ));
+
         Ok(())
     }
 
@@ -280,7 +527,14 @@ mod tests {
     async fn update_status_returns_error_when_action_not_found() -> Result<(), Error> {
         let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
 
-        let result = update_status(&db, Id::new_v4(), Status::Completed).await;
+        let result = update_status(
+            &db,
+            Id::new_v4(),
+            Precondition::Any,
+            Status::Completed,
+            Id::new_v4(),
+        )
+        .await;
 
         assert_eq!(result.is_err(), true);
 
@@ -304,11 +558,292 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "actions"."id", "actions"."coaching_session_id", "actions"."user_id", "actions"."body", "actions"."due_by", CAST("actions"."status" AS text), "actions"."status_changed_at", "actions"."created_at", "actions"."updated_at" FROM "refactor_platform"."actions" WHERE "actions"."coaching_session_id" = $1"#,
+                r#"SELECT "actions"."id", "actions"."coaching_session_id", "actions"."user_id", "actions"."body", "actions"."due_by", CAST("actions"."status" AS text), "actions"."status_changed_at", "actions"."created_at", "actions"."updated_at", "actions"."deleted_at" FROM "refactor_platform"."actions" WHERE "actions"."deleted_at" IS NULL AND "actions"."coaching_session_id" = $1"#,
                 [coaching_session_id.into()]
             )]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn find_by_id_returns_not_found_for_a_missing_action() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = find_by_id(&db, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_by_id_returns_not_found_for_a_missing_action() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = delete_by_id(&db, Id::new_v4(), Precondition::Any).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_rejects_a_stale_version_of_the_action() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let action_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            due_by: Some(now.into()),
+            body: Some("This is a action".to_owned()),
+            user_id: Id::new_v4(),
+            status_changed_at: now.into(),
+            status: Default::default(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![action_model.clone()]])
+            .into_connection();
+
+        let stale = Precondition::UpdatedAt(vec![(now - chrono::Duration::minutes(1)).into()]);
+        let result = update(
+            &db,
+            action_model.id,
+            stale,
+            action_model.clone(),
+            Id::new_v4(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_status_rejects_an_action_changed_since_it_was_read() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let action_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            due_by: Some(now.into()),
+            body: Some("This is a action".to_owned()),
+            user_id: Id::new_v4(),
+            status_changed_at: now.into(),
+            status: Status::NotStarted,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        // The conditional UPDATE matches no row, as another write got there first
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![action_model.clone()], Vec::<Model>::new()])
+            .into_connection();
+
+        let result = update_status(
+            &db,
+            action_model.id,
+            Precondition::UpdatedAt(vec![action_model.updated_at]),
+            Status::InProgress,
+            Id::new_v4(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_by_id_hides_only_the_version_that_was_read() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let action_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            due_by: None,
+            body: Some("This is a action".to_owned()),
+            user_id: Id::new_v4(),
+            status_changed_at: now.into(),
+            status: Default::default(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        // The conditional UPDATE matches no row, as another write got there first
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![action_model.clone()], Vec::<Model>::new()])
+            .into_connection();
+
+        let result = delete_by_id(&db, action_model.id, Precondition::Any).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"actions\" SET \"updated_at\" = $1, \"deleted_at\" = $2 WHERE \"actions\".\"id\" = $3 AND \"actions\".\"updated_at\" = $4"#
+        ));
+        assert!(!transaction_log.contains("DELETE"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_returns_not_found_for_an_action_that_was_not_deleted() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let id = Id::new_v4();
+        let result = restore(&db, id).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"SELECT "actions"."id", "actions"."coaching_session_id", "actions"."user_id", "actions"."body", "actions"."due_by", CAST("actions"."status" AS text), "actions"."status_changed_at", "actions"."created_at", "actions"."updated_at", "actions"."deleted_at" FROM "refactor_platform"."actions" WHERE "actions"."id" = $1 AND "actions"."deleted_at" IS NOT NULL LIMIT $2"#,
+                [id.into(), 1u64.into()]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn patch_changes_only_the_fields_it_names() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let action_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            due_by: Some(now.into()),
+            body: Some("This is a action".to_owned()),
+            user_id: Id::new_v4(),
+            status_changed_at: now.into(),
+            status: Default::default(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![
+                vec![action_model.clone()],
+                vec![Model {
+                    due_by: None,
+                    ..action_model.clone()
+                }],
+            ])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let changes = Patch {
+            due_by: Some(None),
+            ..Default::default()
+        };
+        let action = patch(
+            &db,
+            action_model.id,
+            Precondition::Any,
+            changes,
+            Id::new_v4(),
+        )
+        .await?;
+
+        assert_eq!(action.due_by, None);
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(r#"SET \"due_by\" = $1, \"updated_at\" = $2 WHERE"#));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_status_keeps_status_changed_at_when_the_status_stays_the_same(
+    ) -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let action_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            due_by: None,
+            body: Some("This is a action".to_owned()),
+            user_id: Id::new_v4(),
+            status_changed_at: now.into(),
+            status: Status::InProgress,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![action_model.clone()], vec![action_model.clone()]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        update_status(
+            &db,
+            action_model.id,
+            Precondition::Any,
+            Status::InProgress,
+            Id::new_v4(),
+        )
+        .await?;
+
+        // Neither the status nor status_changed_at is written, and no transition is recorded
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(!transaction_log.contains("status_changed_at\" ="));
+        assert!(!transaction_log.contains("status_transitions"));
+
+        Ok(())
+    }
 }
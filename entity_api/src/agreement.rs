@@ -1,21 +1,25 @@
-use super::error::{EntityApiErrorCode, Error};
-use crate::uuid_parse_str;
+use super::error::Error;
+use crate::audit::{self, Within};
+use crate::coaching_session::{self, with_member};
+use crate::precondition::{self, Precondition};
+use crate::revision::{self, Subject};
+use crate::soft_delete;
+use crate::{unknown_query_param, uuid_parse_str};
 use entity::agreements::{self, ActiveModel, Entity, Model};
-use entity::Id;
+use entity::{audit_action::AuditAction, Id};
 use sea_orm::{
     entity::prelude::*,
     ActiveValue::{Set, Unchanged},
-    DatabaseConnection, TryIntoModel,
+    Condition, ConnectionTrait, TransactionTrait, TryIntoModel,
 };
 use std::collections::HashMap;
 
 use log::*;
 
-pub async fn create(
-    db: &DatabaseConnection,
-    agreement_model: Model,
-    user_id: Id,
-) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, agreement_model: Model, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!("New Agreement Model to be inserted: {:?}", agreement_model);
 
     let now = chrono::Utc::now();
@@ -29,102 +33,473 @@ pub async fn create(
         ..Default::default()
     };
 
-    Ok(agreement_active_model.save(db).await?.try_into_model()?)
+    let changed_fields = audit::changed_fields(&agreement_active_model);
+
+    let txn = db.begin().await?;
+    let agreement = agreement_active_model.save(&txn).await?.try_into_model()?;
+
+    revision::create(
+        &txn,
+        Subject::Agreement(agreement.id),
+        agreement.body.clone(),
+        user_id,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        agreement.id,
+        changed_fields,
+        Within::CoachingSession(agreement.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(agreement)
+}
+
+/// Changes to some of an Agreement's fields. A field left as `None` keeps its current value,
+/// while `Some(None)` clears the body.
+#[derive(Debug, Default)]
+pub struct Patch {
+    pub body: Option<Option<String>>,
+}
+
+pub async fn update<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    model: Model,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let changes = Patch {
+        body: Some(model.body),
+    };
+
+    patch(db, id, precondition, changes, user_id).await
 }
 
-pub async fn update(db: &DatabaseConnection, id: Id, model: Model) -> Result<Model, Error> {
-    let result = Entity::find_by_id(id).one(db).await?;
+/// Changes only the fields of an Agreement that `changes` names.
+pub async fn patch<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    changes: Patch,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id).one(&txn).await?;
 
     match result {
         Some(agreement) => {
             debug!("Existing Agreement model to be Updated: {:?}", agreement);
 
-            let active_model: ActiveModel = ActiveModel {
+            precondition.check("Agreement", id, &agreement.updated_at)?;
+
+            let mut active_model: ActiveModel = ActiveModel {
                 id: Unchanged(agreement.id),
-                coaching_session_id: Unchanged(agreement.coaching_session_id),
-                body: Set(model.body),
-                user_id: Unchanged(agreement.user_id),
                 updated_at: Set(chrono::Utc::now().into()),
-                created_at: Unchanged(agreement.created_at),
+                ..Default::default()
             };
 
-            Ok(active_model.update(db).await?.try_into_model()?)
-        }
-        None => {
-            debug!("Agreement with id {} not found", id);
+            let new_body = changes.body.filter(|body| *body != agreement.body);
+            if let Some(body) = &new_body {
+                active_model.body = Set(body.clone());
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
+                // Acknowledgments were given to the old body, not to what it says now
+                clear_acknowledgments(&mut active_model);
+            }
+
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let updated_agreement = precondition::update_unchanged(
+                &txn,
+                "Agreement",
+                id,
+                active_model,
+                agreements::Column::UpdatedAt,
+                agreement.updated_at,
+            )
+            .await?;
+
+            if let Some(body) = new_body {
+                revision::create(&txn, Subject::Agreement(agreement.id), body, user_id).await?;
+            }
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(agreement.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(updated_agreement)
         }
+        None => Err(Error::not_found("Agreement", id)),
     }
 }
 
-pub async fn delete_by_id(db: &DatabaseConnection, id: Id) -> Result<(), Error> {
-    let result = find_by_id(db, id).await?;
+/// Sets the Agreement's body back to that of one of its earlier revisions, recording the
+/// restored body as a new revision so that history is never rewritten.
+pub async fn restore_revision<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    revision_id: Id,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id).one(&txn).await?;
 
     match result {
-        Some(agreement_model) => {
+        Some(agreement) => {
+            let revision =
+                revision::find_by_id(&txn, Subject::Agreement(agreement.id), revision_id).await?;
+
             debug!(
-                "Existing Agreement model to be deleted: {:?}",
-                agreement_model
+                "Existing Agreement model to be restored to revision {}: {:?}",
+                revision.id, agreement
             );
 
-            agreement_model.delete(db).await?;
-            Ok(())
+            precondition.check("Agreement", id, &agreement.updated_at)?;
+
+            let mut active_model: ActiveModel = ActiveModel {
+                id: Unchanged(agreement.id),
+                body: Set(revision.body.clone()),
+                updated_at: Set(chrono::Utc::now().into()),
+                ..Default::default()
+            };
+
+            if revision.body != agreement.body {
+                clear_acknowledgments(&mut active_model);
+            }
+
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let restored_agreement = precondition::update_unchanged(
+                &txn,
+                "Agreement",
+                id,
+                active_model,
+                agreements::Column::UpdatedAt,
+                agreement.updated_at,
+            )
+            .await?;
+
+            revision::create(
+                &txn,
+                Subject::Agreement(agreement.id),
+                revision.body,
+                user_id,
+            )
+            .await?;
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(agreement.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(restored_agreement)
         }
-        None => Err(Error {
-            inner: None,
-            error_code: EntityApiErrorCode::RecordNotFound,
-        }),
+        None => Err(Error::not_found("Agreement", id)),
     }
 }
 
-pub async fn find_by_id(db: &DatabaseConnection, id: Id) -> Result<Option<Model>, Error> {
-    match Entity::find_by_id(id).one(db).await {
-        Ok(Some(agreement)) => {
-            debug!("Agreement found: {:?}", agreement);
+/// Records that `user_id`, the coach or the coachee of the Agreement's coaching relationship,
+/// acknowledges the Agreement's body at the version `precondition` names, along with the
+/// revision of the body that was acknowledged. The acknowledgment is only written while the
+/// Agreement is still at the version it was read at, so it never lands on a body that was
+/// edited in the meantime.
+pub async fn acknowledge<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id).one(&txn).await?;
 
-            Ok(Some(agreement))
-        }
-        Ok(None) => {
-            error!("Agreement with id {} not found", id);
+    match result {
+        Some(agreement) => {
+            let (_, relationship) =
+                coaching_session::find_with_relationship(&txn, agreement.coaching_session_id)
+                    .await?;
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
-        Err(err) => {
-            error!("Error finding Agreement with id {}: {:?}", id, err);
+            let is_coach = relationship.coach_id == user_id;
+            let is_coachee = relationship.coachee_id == user_id;
 
-            Err(Error {
-                inner: Some(err),
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
+            if !is_coach && !is_coachee {
+                debug!("User {} is not a party to Agreement {}", user_id, id);
+
+                return Err(Error::not_found("Agreement", id));
+            }
+
+            debug!(
+                "Existing Agreement model to be acknowledged by {}: {:?}",
+                user_id, agreement
+            );
+
+            precondition.check("Agreement", id, &agreement.updated_at)?;
+
+            let acknowledged_revision =
+                revision::find_head(&txn, Subject::Agreement(agreement.id)).await?;
+
+            let now = chrono::Utc::now();
+            let mut active_model: ActiveModel = ActiveModel {
+                id: Unchanged(agreement.id),
+                acknowledged_revision_id: Set(acknowledged_revision.map(|revision| revision.id)),
+                ..Default::default()
+            };
+
+            if is_coach {
+                active_model.coach_acknowledged_at = Set(Some(now.into()));
+            }
+            if is_coachee {
+                active_model.coachee_acknowledged_at = Set(Some(now.into()));
+            }
+
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let acknowledged_agreement = precondition::update_unchanged(
+                &txn,
+                "Agreement",
+                id,
+                active_model,
+                agreements::Column::UpdatedAt,
+                agreement.updated_at,
+            )
+            .await?;
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(agreement.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(acknowledged_agreement)
         }
+        None => Err(Error::not_found("Agreement", id)),
     }
 }
 
-pub async fn find_by(
-    db: &DatabaseConnection,
-    query_params: HashMap<String, String>,
-) -> Result<Vec<Model>, Error> {
-    let mut query = Entity::find();
+fn clear_acknowledgments(active_model: &mut ActiveModel) {
+    active_model.coach_acknowledged_at = Set(None);
+    active_model.coachee_acknowledged_at = Set(None);
+    active_model.acknowledged_revision_id = Set(None);
+}
+
+/// Deletes an Agreement by hiding it until it is restored or purged.
+pub async fn delete_by_id<C>(db: &C, id: Id, precondition: Precondition) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let agreement_model = find_by_id(db, id).await?;
+    debug!(
+        "Existing Agreement model to be deleted: {:?}",
+        agreement_model
+    );
+
+    precondition.check("Agreement", id, &agreement_model.updated_at)?;
+
+    let now = chrono::Utc::now();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(now.into())),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    precondition::update_unchanged(
+        &txn,
+        "Agreement",
+        id,
+        active_model,
+        agreements::Column::UpdatedAt,
+        agreement_model.updated_at,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::CoachingSession(agreement_model.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Restores a deleted Agreement that hasn't been purged yet, along with its revisions.
+pub async fn restore<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let agreement_model = soft_delete::find_deleted_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Agreement", id))?;
+    debug!(
+        "Deleted Agreement model to be restored: {:?}",
+        agreement_model
+    );
+
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        updated_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    let restored_agreement = active_model.update(&txn).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::CoachingSession(restored_agreement.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(restored_agreement)
+}
+
+pub async fn find_by_id<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let agreement = soft_delete::find_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Agreement", id))?;
+    debug!("Agreement found: {:?}", agreement);
+
+    Ok(agreement)
+}
+
+/// Finds an Agreement that `user_id` takes part in, as either the coach or the coachee of the
+/// relationship its coaching session belongs to. Any other user is told it was not found.
+pub async fn find_by_id_for_member<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let agreement = with_member(soft_delete::find_by_id::<Entity>(id), user_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Agreement", id))?;
+    debug!("Agreement found: {:?}", agreement);
+
+    Ok(agreement)
+}
+
+/// Like `find_by_id_for_member`, for an Agreement that has been deleted.
+pub async fn find_deleted_by_id_for_member<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let agreement = with_member(soft_delete::find_deleted_by_id::<Entity>(id), user_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Agreement", id))?;
+    debug!("Deleted Agreement found: {:?}", agreement);
+
+    Ok(agreement)
+}
+
+pub async fn find_by<C>(db: &C, query_params: HashMap<String, String>) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = soft_delete::find::<Entity>();
 
     for (key, value) in query_params {
         match key.as_str() {
             "coaching_session_id" => {
-                let coaching_session_id = uuid_parse_str(&value)?;
+                let coaching_session_id = uuid_parse_str(&key, &value)?;
 
                 query = query.filter(agreements::Column::CoachingSessionId.eq(coaching_session_id));
             }
+            "acknowledged" => {
+                let condition = match value.as_str() {
+                    "true" => Condition::all()
+                        .add(agreements::Column::CoachAcknowledgedAt.is_not_null())
+                        .add(agreements::Column::CoacheeAcknowledgedAt.is_not_null()),
+                    "false" => Condition::any()
+                        .add(agreements::Column::CoachAcknowledgedAt.is_null())
+                        .add(agreements::Column::CoacheeAcknowledgedAt.is_null()),
+                    _ => {
+                        return Err(Error::invalid_field(
+                            &key,
+                            "invalid_value",
+                            "must be true or false",
+                        ));
+                    }
+                };
+
+                query = query.filter(condition);
+            }
+            "unacknowledged_by" => {
+                let column = match value.as_str() {
+                    "coach" => agreements::Column::CoachAcknowledgedAt,
+                    "coachee" => agreements::Column::CoacheeAcknowledgedAt,
+                    _ => {
+                        return Err(Error::invalid_field(
+                            &key,
+                            "invalid_value",
+                            "must be coach or coachee",
+                        ));
+                    }
+                };
+
+                query = query.filter(column.is_null());
+            }
             _ => {
-                return Err(Error {
-                    inner: None,
-                    error_code: EntityApiErrorCode::InvalidQueryTerm,
-                });
+                return Err(unknown_query_param(&key));
             }
         }
     }
@@ -139,9 +514,54 @@ pub async fn find_by(
 #[cfg(feature = "mock")]
 mod tests {
     use super::*;
-    use entity::{agreements::Model, Id};
+    use crate::error::EntityApiErrorCode;
+    use entity::{agreements::Model, coaching_relationships, coaching_sessions, revisions, Id};
     use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
 
+    fn session_with_relationship(
+        coaching_session_id: Id,
+        coach_id: Id,
+        coachee_id: Id,
+    ) -> (coaching_sessions::Model, coaching_relationships::Model) {
+        let now = chrono::Utc::now();
+        let relationship = coaching_relationships::Model {
+            id: Id::new_v4(),
+            organization_id: Id::new_v4(),
+            coach_id,
+            coachee_id,
+            status: Default::default(),
+            start_date: now.date_naive(),
+            end_date: None,
+            reassigned_to_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let session = coaching_sessions::Model {
+            id: coaching_session_id,
+            coaching_relationship_id: relationship.id,
+            date: now.naive_utc(),
+            timezone: "America/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        (session, relationship)
+    }
+
+    fn revision_of(agreement: &Model, user_id: Id) -> revisions::Model {
+        revisions::Model {
+            id: Id::new_v4(),
+            note_id: None,
+            agreement_id: Some(agreement.id),
+            overarching_goal_id: None,
+            body: agreement.body.clone(),
+            user_id,
+            created_at: chrono::Utc::now().into(),
+        }
+    }
+
     #[tokio::test]
     async fn create_returns_a_new_agreement_model() -> Result<(), Error> {
         let now = chrono::Utc::now();
@@ -151,18 +571,29 @@ mod tests {
             user_id: Id::new_v4(),
             coaching_session_id: Id::new_v4(),
             body: Some("This is a agreement".to_owned()),
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
+        let user_id = Id::new_v4();
+
         let db = MockDatabase::new(DatabaseBackend::Postgres)
             .append_query_results(vec![vec![agreement_model.clone()]])
+            .append_query_results(vec![vec![revision_of(&agreement_model, user_id)]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let agreement = create(&db, agreement_model.clone().into(), Id::new_v4()).await?;
+        let agreement = create(&db, agreement_model.clone(), user_id).await?;
 
         assert_eq!(agreement.id, agreement_model.id);
 
+        // The agreement and its initial revision must be written in a single transaction
+        assert_eq!(db.into_transaction_log().len(), 1);
+
         Ok(())
     }
 
@@ -175,8 +606,12 @@ mod tests {
             coaching_session_id: Id::new_v4(),
             body: Some("This is a agreement".to_owned()),
             user_id: Id::new_v4(),
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
@@ -184,15 +619,413 @@ mod tests {
                 vec![agreement_model.clone()],
                 vec![agreement_model.clone()],
             ])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let agreement = update(&db, agreement_model.id, agreement_model.clone()).await?;
+        let agreement = update(
+            &db,
+            agreement_model.id,
+            Precondition::UpdatedAt(vec![agreement_model.updated_at]),
+            agreement_model.clone(),
+            Id::new_v4(),
+        )
+        .await?;
 
         assert_eq!(agreement.body, agreement_model.body);
 
         Ok(())
     }
 
+    #[tokio::test]
+    async fn update_clears_acknowledgments_when_the_body_changes() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Rotate on-call weekly".to_owned()),
+            user_id: Id::new_v4(),
+            coach_acknowledged_at: Some(now.into()),
+            coachee_acknowledged_at: Some(now.into()),
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let edited_agreement_model = Model {
+            body: Some("Rotate on-call biweekly".to_owned()),
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            ..agreement_model.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![
+                vec![agreement_model.clone()],
+                vec![edited_agreement_model.clone()],
+            ])
+            .append_query_results(vec![vec![revision_of(
+                &edited_agreement_model,
+                agreement_model.user_id,
+            )]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        update(
+            &db,
+            agreement_model.id,
+            Precondition::UpdatedAt(vec![agreement_model.updated_at]),
+            edited_agreement_model.clone(),
+            agreement_model.user_id,
+        )
+        .await?;
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log
+            .contains(r#"\"coach_acknowledged_at\" = $2, \"coachee_acknowledged_at\" = $3"#));
+        assert!(transaction_log
+            .contains("ChronoDateTimeWithTimeZone(None), ChronoDateTimeWithTimeZone(None)"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn patch_keeps_acknowledgments_when_the_body_stays_the_same() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Rotate on-call weekly".to_owned()),
+            user_id: Id::new_v4(),
+            coach_acknowledged_at: Some(now.into()),
+            coachee_acknowledged_at: Some(now.into()),
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![
+                vec![agreement_model.clone()],
+                vec![agreement_model.clone()],
+            ])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let changes = Patch {
+            body: Some(agreement_model.body.clone()),
+        };
+        patch(
+            &db,
+            agreement_model.id,
+            Precondition::Any,
+            changes,
+            agreement_model.user_id,
+        )
+        .await?;
+
+        // Only updated_at is written, and no revision is recorded
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(r#"SET \"updated_at\" = $1 WHERE"#));
+        assert!(!transaction_log.contains("revisions"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn acknowledge_records_the_coachees_acknowledgment() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let coach_id = Id::new_v4();
+        let coachee_id = Id::new_v4();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Rotate on-call weekly".to_owned()),
+            user_id: coach_id,
+            coach_acknowledged_at: Some(now.into()),
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let head_revision = revision_of(&agreement_model, coach_id);
+
+        let acknowledged_agreement_model = Model {
+            coachee_acknowledged_at: Some(now.into()),
+            acknowledged_revision_id: Some(head_revision.id),
+            ..agreement_model.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![agreement_model.clone()]])
+            .append_query_results(vec![vec![session_with_relationship(
+                agreement_model.coaching_session_id,
+                coach_id,
+                coachee_id,
+            )]])
+            .append_query_results(vec![vec![head_revision.clone()]])
+            .append_query_results(vec![vec![acknowledged_agreement_model.clone()]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let agreement = acknowledge(
+            &db,
+            agreement_model.id,
+            Precondition::UpdatedAt(vec![agreement_model.updated_at]),
+            coachee_id,
+        )
+        .await?;
+
+        assert!(agreement.coachee_acknowledged_at.is_some());
+        assert_eq!(agreement.acknowledged_revision_id, Some(head_revision.id));
+
+        // Only the coachee's acknowledgment of the current revision is written, and only over
+        // the version of the Agreement that was read
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"SET \"coachee_acknowledged_at\" = $1, \"acknowledged_revision_id\" = $2 WHERE \"agreements\".\"id\" = $3 AND \"agreements\".\"updated_at\" = $4"#
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn acknowledge_fails_when_the_body_changes_before_it_is_written() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let coach_id = Id::new_v4();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Rotate on-call weekly".to_owned()),
+            user_id: coach_id,
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        // The body is edited between reading the Agreement and writing the acknowledgment, so
+        // the conditional update matches no row
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![agreement_model.clone()]])
+            .append_query_results(vec![vec![session_with_relationship(
+                agreement_model.coaching_session_id,
+                coach_id,
+                Id::new_v4(),
+            )]])
+            .append_query_results(vec![vec![revision_of(&agreement_model, coach_id)]])
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = acknowledge(&db, agreement_model.id, Precondition::Any, coach_id).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn acknowledge_rejects_a_version_other_than_the_current_one() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let coach_id = Id::new_v4();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Rotate on-call weekly".to_owned()),
+            user_id: coach_id,
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![agreement_model.clone()]])
+            .append_query_results(vec![vec![session_with_relationship(
+                agreement_model.coaching_session_id,
+                coach_id,
+                Id::new_v4(),
+            )]])
+            .into_connection();
+
+        let earlier_version = (now - chrono::Duration::minutes(5)).into();
+        let result = acknowledge(
+            &db,
+            agreement_model.id,
+            Precondition::UpdatedAt(vec![earlier_version]),
+            coach_id,
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn acknowledge_rejects_users_outside_the_relationship() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Rotate on-call weekly".to_owned()),
+            user_id: Id::new_v4(),
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![agreement_model.clone()]])
+            .append_query_results(vec![vec![session_with_relationship(
+                agreement_model.coaching_session_id,
+                Id::new_v4(),
+                Id::new_v4(),
+            )]])
+            .into_connection();
+
+        let result = acknowledge(&db, agreement_model.id, Precondition::Any, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_revision_sets_body_to_the_revision_body() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let user_id = Id::new_v4();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("This is an edited agreement".to_owned()),
+            user_id: Id::new_v4(),
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let earlier_revision = revisions::Model {
+            body: Some("This is a agreement".to_owned()),
+            ..revision_of(&agreement_model, user_id)
+        };
+
+        let restored_agreement_model = Model {
+            body: earlier_revision.body.clone(),
+            ..agreement_model.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![agreement_model.clone()]])
+            .append_query_results(vec![vec![earlier_revision.clone()]])
+            .append_query_results(vec![vec![restored_agreement_model.clone()]])
+            .append_query_results(vec![vec![revision_of(&restored_agreement_model, user_id)]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let agreement = restore_revision(
+            &db,
+            agreement_model.id,
+            Precondition::Any,
+            earlier_revision.id,
+            user_id,
+        )
+        .await?;
+
+        assert_eq!(agreement.body, earlier_revision.body);
+        assert_eq!(db.into_transaction_log().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_revision_rejects_a_version_changed_since_it_was_read() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let user_id = Id::new_v4();
+
+        let agreement_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("This is an edited agreement".to_owned()),
+            user_id: Id::new_v4(),
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let earlier_revision = revisions::Model {
+            body: Some("This is a agreement".to_owned()),
+            ..revision_of(&agreement_model, user_id)
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![agreement_model.clone()]])
+            .append_query_results(vec![vec![earlier_revision.clone()]])
+            .into_connection();
+
+        let stale = (now - chrono::Duration::minutes(5)).into();
+        let result = restore_revision(
+            &db,
+            agreement_model.id,
+            Precondition::UpdatedAt(vec![stale]),
+            earlier_revision.id,
+            user_id,
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn find_by_id_returns_agreement_associated_with_id() -> Result<(), Error> {
         let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
@@ -204,7 +1037,7 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "agreements"."id", "agreements"."coaching_session_id", "agreements"."body", "agreements"."user_id", "agreements"."created_at", "agreements"."updated_at" FROM "refactor_platform"."agreements" WHERE "agreements"."id" = $1 LIMIT $2"#,
+                r#"SELECT "agreements"."id", "agreements"."coaching_session_id", "agreements"."body", "agreements"."user_id", "agreements"."coach_acknowledged_at", "agreements"."coachee_acknowledged_at", "agreements"."acknowledged_revision_id", "agreements"."created_at", "agreements"."updated_at", "agreements"."deleted_at" FROM "refactor_platform"."agreements" WHERE "agreements"."id" = $1 AND "agreements"."deleted_at" IS NULL LIMIT $2"#,
                 [agreement_id.into(), sea_orm::Value::BigUnsigned(Some(1))]
             )]
         );
@@ -230,11 +1063,68 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "agreements"."id", "agreements"."coaching_session_id", "agreements"."body", "agreements"."user_id", "agreements"."created_at", "agreements"."updated_at" FROM "refactor_platform"."agreements" WHERE "agreements"."coaching_session_id" = $1"#,
+                r#"SELECT "agreements"."id", "agreements"."coaching_session_id", "agreements"."body", "agreements"."user_id", "agreements"."coach_acknowledged_at", "agreements"."coachee_acknowledged_at", "agreements"."acknowledged_revision_id", "agreements"."created_at", "agreements"."updated_at", "agreements"."deleted_at" FROM "refactor_platform"."agreements" WHERE "agreements"."deleted_at" IS NULL AND "agreements"."coaching_session_id" = $1"#,
                 [coaching_session_id.into()]
             )]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn find_by_returns_agreements_not_yet_acknowledged_by_both_parties() -> Result<(), Error>
+    {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let query_params = HashMap::from([("acknowledged".to_owned(), "false".to_owned())]);
+
+        let _ = find_by(&db, query_params).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"SELECT "agreements"."id", "agreements"."coaching_session_id", "agreements"."body", "agreements"."user_id", "agreements"."coach_acknowledged_at", "agreements"."coachee_acknowledged_at", "agreements"."acknowledged_revision_id", "agreements"."created_at", "agreements"."updated_at", "agreements"."deleted_at" FROM "refactor_platform"."agreements" WHERE "agreements"."deleted_at" IS NULL AND ("agreements"."coach_acknowledged_at" IS NULL OR "agreements"."coachee_acknowledged_at" IS NULL)"#,
+                []
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_rejects_unknown_acknowledgment_filters() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let query_params = HashMap::from([("unacknowledged_by".to_owned(), "hr".to_owned())]);
+
+        let result = find_by(&db, query_params).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::ValidationFailed(_),
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_id_returns_not_found_for_a_missing_agreement() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = find_by_id(&db, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,375 @@
+//! Utilization reports for the sponsors of an organization's coaching. Reports are made up
+//! solely of counts and rates so that they never expose the content of coaching sessions.
+
+use super::error::Error;
+use crate::{naive_date_parse_str, organization, unknown_query_param};
+use chrono::Months;
+use entity::Id;
+use sea_orm::{prelude::Date, ConnectionTrait, DbBackend, FromQueryResult, Statement, Value};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use log::*;
+
+// The longest span a report may cover, which bounds the number of periods it generates
+const MAX_REPORT_YEARS: u32 = 5;
+
+// One row per period between $4 and $5 inclusive, each period being a $2 (as understood by
+// date_trunc) that lasts $3. Only events within the date range are counted, so the first and
+// last periods may be partial. Sessions are counted once they have been held, and actions
+// and overarching goals are counted in the period they were created in, along with the
+// outcome they have had since.
+const REPORT_SQL: &str = r#"WITH "periods" AS (
+  SELECT "period_start", "period_start" + CAST($3 AS interval) AS "period_end"
+  FROM generate_series(date_trunc($2, CAST($4 AS timestamp)), CAST($5 AS timestamp), CAST($3 AS interval)) AS "period_start"
+),
+"relationships" AS (
+  SELECT "id", "start_date", "end_date"
+  FROM "refactor_platform"."coaching_relationships"
+  WHERE "organization_id" = $1 AND "deleted_at" IS NULL
+),
+"sessions" AS (
+  SELECT date_trunc($2, "date") AS "period_start", count(*) AS "sessions_held"
+  FROM "refactor_platform"."coaching_sessions"
+  WHERE "coaching_relationship_id" IN (SELECT "id" FROM "relationships")
+    AND "deleted_at" IS NULL
+    AND "date" >= $4 AND "date" < $5 + 1
+    AND "date" <= now() AT TIME ZONE 'UTC'
+  GROUP BY 1
+),
+"actions" AS (
+  SELECT date_trunc($2, "actions"."created_at" AT TIME ZONE 'UTC') AS "period_start",
+    count(*) AS "actions_created",
+    count(*) FILTER (WHERE "actions"."status" = 'completed') AS "actions_completed"
+  FROM "refactor_platform"."actions"
+  JOIN "refactor_platform"."coaching_sessions" ON "coaching_sessions"."id" = "actions"."coaching_session_id"
+  WHERE "coaching_sessions"."coaching_relationship_id" IN (SELECT "id" FROM "relationships")
+    AND "actions"."deleted_at" IS NULL
+    AND "actions"."created_at" AT TIME ZONE 'UTC' >= $4 AND "actions"."created_at" AT TIME ZONE 'UTC' < $5 + 1
+  GROUP BY 1
+),
+"goals" AS (
+  SELECT date_trunc($2, "overarching_goals"."created_at" AT TIME ZONE 'UTC') AS "period_start",
+    count(*) AS "goals_created",
+    count(*) FILTER (WHERE "overarching_goals"."status" = 'completed') AS "goals_completed",
+    count(*) FILTER (WHERE "overarching_goals"."status" = 'wont_do') AS "goals_wont_do",
+    count(*) FILTER (WHERE "overarching_goals"."status" IN ('not_started', 'in_progress')) AS "goals_open"
+  FROM "refactor_platform"."overarching_goals"
+  JOIN "refactor_platform"."coaching_sessions" ON "coaching_sessions"."id" = "overarching_goals"."coaching_session_id"
+  WHERE "coaching_sessions"."coaching_relationship_id" IN (SELECT "id" FROM "relationships")
+    AND "overarching_goals"."deleted_at" IS NULL
+    AND "overarching_goals"."created_at" AT TIME ZONE 'UTC' >= $4 AND "overarching_goals"."created_at" AT TIME ZONE 'UTC' < $5 + 1
+  GROUP BY 1
+)
+SELECT CAST("periods"."period_start" AS date) AS "period_start",
+  coalesce("sessions"."sessions_held", 0) AS "sessions_held",
+  (
+    SELECT count(*) FROM "relationships"
+    WHERE "relationships"."start_date" < "periods"."period_end"
+      AND ("relationships"."end_date" IS NULL OR "relationships"."end_date" >= "periods"."period_start")
+  ) AS "active_relationships",
+  coalesce("actions"."actions_created", 0) AS "actions_created",
+  coalesce("actions"."actions_completed", 0) AS "actions_completed",
+  CAST("actions"."actions_completed" AS float8) / nullif("actions"."actions_created", 0) AS "action_completion_rate",
+  coalesce("goals"."goals_created", 0) AS "goals_created",
+  coalesce("goals"."goals_completed", 0) AS "goals_completed",
+  coalesce("goals"."goals_wont_do", 0) AS "goals_wont_do",
+  coalesce("goals"."goals_open", 0) AS "goals_open"
+FROM "periods"
+LEFT JOIN "sessions" ON "sessions"."period_start" = "periods"."period_start"
+LEFT JOIN "actions" ON "actions"."period_start" = "periods"."period_start"
+LEFT JOIN "goals" ON "goals"."period_start" = "periods"."period_start"
+ORDER BY "periods"."period_start""#;
+
+/// The length of each period a report is broken down into.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    Week,
+    #[default]
+    Month,
+    Quarter,
+}
+
+impl Grouping {
+    fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "week" => Ok(Grouping::Week),
+            "month" => Ok(Grouping::Month),
+            "quarter" => Ok(Grouping::Quarter),
+            _ => Err(Error::invalid_field(
+                "group_by",
+                "invalid_value",
+                "must be one of week, month or quarter",
+            )),
+        }
+    }
+
+    // The field name date_trunc rounds down to
+    fn field(&self) -> &'static str {
+        match self {
+            Grouping::Week => "week",
+            Grouping::Month => "month",
+            Grouping::Quarter => "quarter",
+        }
+    }
+
+    fn interval(&self) -> &'static str {
+        match self {
+            Grouping::Week => "1 week",
+            Grouping::Month => "1 month",
+            Grouping::Quarter => "3 months",
+        }
+    }
+}
+
+/// An organization's coaching utilization over one period of a report.
+///
+/// `active_relationships` counts the coaching relationships that had started and not yet
+/// ended at some point during the period. Actions and overarching goals are counted in the
+/// period they were created in, broken down by the outcome they have reached since.
+/// `action_completion_rate` is the share of those actions that are completed, or null when
+/// there are none.
+#[derive(Debug, FromQueryResult, Serialize, ToSchema)]
+#[schema(as = entity_api::analytics::PeriodReport)] // OpenAPI schema
+pub struct PeriodReport {
+    #[schema(value_type = String, format = Date)]
+    pub period_start: Date,
+    pub sessions_held: i64,
+    pub active_relationships: i64,
+    pub actions_created: i64,
+    pub actions_completed: i64,
+    pub action_completion_rate: Option<f64>,
+    pub goals_created: i64,
+    pub goals_completed: i64,
+    pub goals_wont_do: i64,
+    pub goals_open: i64,
+}
+
+/// The utilization report of an organization that `user_id` is an admin of, for the
+/// `from_date` and `to_date` (both inclusive) in `query_params`, grouped by `group_by`
+/// (`week`, `month` or `quarter`, `month` by default).
+pub async fn find_by_organization<C>(
+    db: &C,
+    organization_id: Id,
+    user_id: Id,
+    query_params: HashMap<String, String>,
+) -> Result<Vec<PeriodReport>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut from_date: Option<Date> = None;
+    let mut to_date: Option<Date> = None;
+    let mut grouping = Grouping::default();
+
+    for (key, value) in query_params {
+        match key.as_str() {
+            "from_date" => {
+                from_date = Some(naive_date_parse_str(&key, &value)?);
+            }
+            "to_date" => {
+                to_date = Some(naive_date_parse_str(&key, &value)?);
+            }
+            "group_by" => {
+                grouping = Grouping::parse(&value)?;
+            }
+            _ => {
+                return Err(unknown_query_param(&key));
+            }
+        }
+    }
+
+    let from_date =
+        from_date.ok_or_else(|| Error::invalid_field("from_date", "missing", "is required"))?;
+    let to_date =
+        to_date.ok_or_else(|| Error::invalid_field("to_date", "missing", "is required"))?;
+    if from_date > to_date {
+        error!("A report needs a from_date that is on or before its to_date");
+
+        return Err(Error::invalid_field(
+            "to_date",
+            "invalid_value",
+            "must be on or after from_date",
+        ));
+    }
+    if from_date
+        .checked_add_months(Months::new(12 * MAX_REPORT_YEARS))
+        .is_some_and(|latest| to_date > latest)
+    {
+        error!("A report may cover at most {MAX_REPORT_YEARS} years");
+
+        return Err(Error::invalid_field(
+            "to_date",
+            "range_too_large",
+            &format!("must be within {MAX_REPORT_YEARS} years of from_date"),
+        ));
+    }
+
+    organization::find_by_id_for_admin(db, organization_id, user_id).await?;
+
+    debug!(
+        "Reporting on Organization {} from {} to {} by {:?}",
+        organization_id, from_date, to_date, grouping
+    );
+
+    let report = PeriodReport::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        REPORT_SQL,
+        report_values(organization_id, grouping, from_date, to_date),
+    ))
+    .all(db)
+    .await?;
+
+    Ok(report)
+}
+
+fn report_values(
+    organization_id: Id,
+    grouping: Grouping,
+    from_date: Date,
+    to_date: Date,
+) -> [Value; 5] {
+    [
+        organization_id.into(),
+        grouping.field().into(),
+        grouping.interval().into(),
+        from_date.into(),
+        to_date.into(),
+    ]
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use crate::error::EntityApiErrorCode;
+    use chrono::{NaiveDate, Utc};
+    use entity::{organization_admins, organizations};
+    use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
+
+    fn organization() -> organizations::Model {
+        let now = Utc::now();
+
+        organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            logo: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn find_by_organization_reports_by_quarter_for_admins() -> Result<(), Error> {
+        let organization = organization();
+        let user_id = Id::new_v4();
+        let admin = organization_admins::Model {
+            id: Id::new_v4(),
+            organization_id: organization.id,
+            user_id,
+            created_at: Utc::now().into(),
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![organization.clone()]])
+            .append_query_results(vec![vec![admin]])
+            .into_connection();
+
+        let _ = find_by_organization(
+            &db,
+            organization.id,
+            user_id,
+            params(&[
+                ("from_date", "2025-01-01"),
+                ("to_date", "2025-12-31"),
+                ("group_by", "quarter"),
+            ]),
+        )
+        .await;
+
+        let transaction_log = db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 3);
+        assert_eq!(
+            transaction_log[2],
+            Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                REPORT_SQL,
+                report_values(
+                    organization.id,
+                    Grouping::Quarter,
+                    NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+                    NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+                )
+            )
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_organization_rejects_users_that_are_not_admins() -> Result<(), Error> {
+        let organization = organization();
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![organization.clone()]])
+            .append_query_results(vec![Vec::<organization_admins::Model>::new()])
+            .into_connection();
+
+        let result = find_by_organization(
+            &db,
+            organization.id,
+            Id::new_v4(),
+            params(&[("from_date", "2025-01-01"), ("to_date", "2025-12-31")]),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_organization_returns_error_for_invalid_parameters() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+
+        for query_params in [
+            params(&[("from_date", "2025-12-31"), ("to_date", "2025-01-01")]),
+            params(&[("from_date", "2025-01-01")]),
+            params(&[
+                ("from_date", "2025-01-01"),
+                ("to_date", "2025-12-31"),
+                ("group_by", "day"),
+            ]),
+            params(&[("from_date", "2020-01-01"), ("to_date", "2025-01-02")]),
+        ] {
+            let result = find_by_organization(&db, Id::new_v4(), Id::new_v4(), query_params).await;
+
+            assert!(matches!(
+                result,
+                Err(Error {
+                    error_code: EntityApiErrorCode::ValidationFailed(_),
+                    ..
+                })
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,619 @@
+use super::error::{EntityApiErrorCode, Error};
+use crate::audit::{self, Within};
+use crate::{coaching_session, soft_delete};
+use entity::attachments::{self, ActiveModel, Entity, Model};
+use entity::{audit_action::AuditAction, Id};
+use sea_orm::{
+    entity::prelude::*, ActiveValue::Unchanged, ConnectionTrait, QueryOrder, Set, TransactionTrait,
+};
+use service::{config::Config, storage::Storage};
+
+use log::*;
+
+/// The size and MIME type restrictions that every upload must satisfy.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits<'a> {
+    pub max_bytes: usize,
+    pub allowed_types: &'a [String],
+}
+
+impl<'a> From<&'a Config> for Limits<'a> {
+    fn from(config: &'a Config) -> Self {
+        Self {
+            max_bytes: config.attachment_max_bytes,
+            allowed_types: &config.attachment_allowed_types,
+        }
+    }
+}
+
+impl Limits<'_> {
+    /// Checks an upload against these limits, returning its normalized MIME type.
+    pub fn check(&self, content_type: &str, size: usize) -> Result<String, Error> {
+        if size > self.max_bytes {
+            warn!(
+                "Rejecting upload of {} bytes, the limit is {} bytes",
+                size, self.max_bytes
+            );
+
+            return Err(Error {
+                inner: None,
+                error_code: EntityApiErrorCode::PayloadTooLarge,
+                detail: None,
+            });
+        }
+
+        // Parameters like "; charset=utf-8" don't change what kind of file this is
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        if self
+            .allowed_types
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(&essence))
+        {
+            Ok(essence)
+        } else {
+            warn!(
+                "Rejecting upload with disallowed MIME type {}",
+                content_type
+            );
+
+            Err(Error {
+                inner: None,
+                error_code: EntityApiErrorCode::UnsupportedMediaType,
+                detail: None,
+            })
+        }
+    }
+}
+
+/// A file being uploaded to a coaching session.
+#[derive(Debug)]
+pub struct Upload {
+    pub filename: String,
+    pub content_type: String,
+    pub contents: Vec<u8>,
+}
+
+/// Stores an uploaded file and records it against the coaching session, provided `user_id`
+/// is the coach or coachee of the session's relationship.
+pub async fn create<C>(
+    db: &C,
+    storage: &dyn Storage,
+    limits: Limits<'_>,
+    coaching_session_id: Id,
+    upload: Upload,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    coaching_session::find_by_id_for_member(db, coaching_session_id, user_id).await?;
+
+    let content_type = limits.check(&upload.content_type, upload.contents.len())?;
+
+    let id = Id::new_v4();
+    let storage_key = format!("coaching_sessions/{coaching_session_id}/{id}");
+    let size_bytes = upload.contents.len() as i64;
+
+    debug!(
+        "New Attachment {} of {} bytes to be stored under {}",
+        upload.filename, size_bytes, storage_key
+    );
+
+    storage.put(&storage_key, upload.contents).await?;
+
+    let attachment_active_model: ActiveModel = ActiveModel {
+        id: Set(id),
+        coaching_session_id: Set(coaching_session_id),
+        user_id: Set(user_id),
+        filename: Set(sanitize_filename(&upload.filename)),
+        content_type: Set(content_type),
+        size_bytes: Set(size_bytes),
+        storage_key: Set(storage_key.clone()),
+        created_at: Set(chrono::Utc::now().into()),
+        deleted_at: Set(None),
+    };
+
+    let changed_fields = audit::changed_fields(&attachment_active_model);
+
+    let inserted: Result<Model, Error> = async {
+        let txn = db.begin().await?;
+        let attachment = attachment_active_model.insert(&txn).await?;
+
+        audit::record(
+            &txn,
+            Entity,
+            AuditAction::Create,
+            attachment.id,
+            changed_fields,
+            Within::CoachingSession(coaching_session_id),
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(attachment)
+    }
+    .await;
+
+    match inserted {
+        Ok(attachment) => Ok(attachment),
+        Err(err) => {
+            // Don't leave contents behind that no attachment refers to
+            if let Err(storage_err) = storage.delete(&storage_key).await {
+                error!(
+                    "Failed to remove orphaned attachment contents {}: {}",
+                    storage_key, storage_err
+                );
+            }
+
+            Err(err)
+        }
+    }
+}
+
+/// Returns the attachments of a coaching session that `user_id` is a member of, oldest first.
+pub async fn find_by_coaching_session<C>(
+    db: &C,
+    coaching_session_id: Id,
+    user_id: Id,
+) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    coaching_session::find_by_id_for_member(db, coaching_session_id, user_id).await?;
+
+    Ok(soft_delete::find::<Entity>()
+        .filter(attachments::Column::CoachingSessionId.eq(coaching_session_id))
+        .order_by_asc(attachments::Column::CreatedAt)
+        .all(db)
+        .await?)
+}
+
+/// Finds an attachment of the given coaching session, provided `user_id` is a member of it.
+pub async fn find_by_id<C>(
+    db: &C,
+    coaching_session_id: Id,
+    id: Id,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    coaching_session::find_by_id_for_member(db, coaching_session_id, user_id).await?;
+
+    match soft_delete::find_by_id::<Entity>(id)
+        .filter(attachments::Column::CoachingSessionId.eq(coaching_session_id))
+        .one(db)
+        .await?
+    {
+        Some(attachment) => Ok(attachment),
+        None => {
+            error!(
+                "Attachment with id {} not found for Coaching Session {}",
+                id, coaching_session_id
+            );
+
+            Err(Error {
+                inner: None,
+                error_code: EntityApiErrorCode::RecordNotFound,
+                detail: None,
+            })
+        }
+    }
+}
+
+/// Returns an attachment along with its contents, provided `user_id` is a member of the
+/// attachment's coaching session.
+pub async fn download<C>(
+    db: &C,
+    storage: &dyn Storage,
+    coaching_session_id: Id,
+    id: Id,
+    user_id: Id,
+) -> Result<(Model, Vec<u8>), Error>
+where
+    C: ConnectionTrait,
+{
+    let attachment = find_by_id(db, coaching_session_id, id, user_id).await?;
+    let contents = storage.get(&attachment.storage_key).await?;
+
+    Ok((attachment, contents))
+}
+
+/// Deletes an attachment, keeping its contents until it is restored or purged. Only the user
+/// that uploaded it may do so.
+pub async fn delete_by_id<C>(
+    db: &C,
+    coaching_session_id: Id,
+    id: Id,
+    user_id: Id,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let attachment = find_by_id(db, coaching_session_id, id, user_id).await?;
+    ensure_uploader(&attachment, user_id)?;
+
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(chrono::Utc::now().into())),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    active_model.update(&txn).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::CoachingSession(coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Restores a deleted attachment that hasn't been purged yet. Only the user that uploaded it
+/// may do so, provided they are still a member of its coaching session.
+pub async fn restore<C>(
+    db: &C,
+    coaching_session_id: Id,
+    id: Id,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    coaching_session::find_by_id_for_member(db, coaching_session_id, user_id).await?;
+
+    let attachment = soft_delete::find_deleted_by_id::<Entity>(id)
+        .filter(attachments::Column::CoachingSessionId.eq(coaching_session_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Attachment", id))?;
+    ensure_uploader(&attachment, user_id)?;
+    debug!("Deleted Attachment model to be restored: {:?}", attachment);
+
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    let restored_attachment = active_model.update(&txn).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::CoachingSession(coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(restored_attachment)
+}
+
+// Only the user that uploaded an attachment may delete or restore it.
+fn ensure_uploader(attachment: &Model, user_id: Id) -> Result<(), Error> {
+    if attachment.user_id == user_id {
+        return Ok(());
+    }
+
+    error!(
+        "User {} may not change Attachment {} uploaded by {}",
+        user_id, attachment.id, attachment.user_id
+    );
+
+    Err(Error {
+        inner: None,
+        error_code: EntityApiErrorCode::RecordUnauthorized,
+        detail: None,
+    })
+}
+
+// Keeps only the final path segment of a client supplied file name, without control
+// characters, so it can be safely echoed back in a Content-Disposition header.
+fn sanitize_filename(filename: &str) -> String {
+    let name = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .collect::<String>();
+
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        "attachment".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use entity::{coaching_relationships, coaching_sessions};
+    use sea_orm::{DatabaseBackend, MockDatabase};
+    use service::storage::LocalStorage;
+
+    fn allowed_types() -> Vec<String> {
+        vec!["application/pdf".to_owned(), "image/png".to_owned()]
+    }
+
+    fn session_with_relationship(
+        coach_id: Id,
+        coachee_id: Id,
+    ) -> (coaching_sessions::Model, coaching_relationships::Model) {
+        let now = chrono::Utc::now();
+        let relationship = coaching_relationships::Model {
+            id: Id::new_v4(),
+            organization_id: Id::new_v4(),
+            coach_id,
+            coachee_id,
+            status: Default::default(),
+            start_date: now.date_naive(),
+            end_date: None,
+            reassigned_to_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let session = coaching_sessions::Model {
+            id: Id::new_v4(),
+            coaching_relationship_id: relationship.id,
+            date: now.naive_utc(),
+            timezone: "America/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        (session, relationship)
+    }
+
+    fn attachment(coaching_session_id: Id, user_id: Id) -> Model {
+        let id = Id::new_v4();
+        Model {
+            id,
+            coaching_session_id,
+            user_id,
+            filename: "design.pdf".to_owned(),
+            content_type: "application/pdf".to_owned(),
+            size_bytes: 10,
+            storage_key: format!("coaching_sessions/{coaching_session_id}/{id}"),
+            created_at: chrono::Utc::now().into(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn limits_check_normalizes_allowed_types_and_rejects_others() {
+        let allowed_types = allowed_types();
+        let limits = Limits {
+            max_bytes: 10,
+            allowed_types: &allowed_types,
+        };
+
+        assert_eq!(
+            limits
+                .check("Application/PDF; name=design.pdf", 10)
+                .unwrap(),
+            "application/pdf"
+        );
+        assert!(matches!(
+            limits.check("application/pdf", 11),
+            Err(Error {
+                error_code: EntityApiErrorCode::PayloadTooLarge,
+                ..
+            })
+        ));
+        assert!(matches!(
+            limits.check("application/x-msdownload", 1),
+            Err(Error {
+                error_code: EntityApiErrorCode::UnsupportedMediaType,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn sanitize_filename_drops_directories_and_quotes() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("C:\\Users\\me\\review.pdf"), "review.pdf");
+        assert_eq!(sanitize_filename("a\"b\r\n.png"), "ab.png");
+        assert_eq!(sanitize_filename(".."), "attachment");
+    }
+
+    #[tokio::test]
+    async fn create_stores_contents_and_records_the_attachment() -> Result<(), Error> {
+        let coachee_id = Id::new_v4();
+        let (session, relationship) = session_with_relationship(Id::new_v4(), coachee_id);
+        let attachment_model = attachment(session.id, coachee_id);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(session.clone(), relationship)]])
+            .append_query_results(vec![vec![attachment_model.clone()]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let root = std::env::temp_dir().join(format!("attachment-create-{}", Id::new_v4()));
+        let storage = LocalStorage::new(&root);
+        let allowed_types = allowed_types();
+        let limits = Limits {
+            max_bytes: 1024,
+            allowed_types: &allowed_types,
+        };
+
+        let upload = Upload {
+            filename: "design.pdf".to_owned(),
+            content_type: "application/pdf".to_owned(),
+            contents: b"%PDF-1.7".to_vec(),
+        };
+
+        let created = create(&db, &storage, limits, session.id, upload, coachee_id).await?;
+
+        assert_eq!(created.id, attachment_model.id);
+
+        // The contents were written under the session before the row was inserted
+        let stored = std::fs::read_dir(root.join(format!("coaching_sessions/{}", session.id)))
+            .unwrap()
+            .count();
+        assert_eq!(stored, 1);
+
+        let _ = std::fs::remove_dir_all(root);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_rejects_users_outside_the_relationship() -> Result<(), Error> {
+        let (session, relationship) = session_with_relationship(Id::new_v4(), Id::new_v4());
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(session.clone(), relationship)]])
+            .into_connection();
+
+        let storage = LocalStorage::new(std::env::temp_dir().join("attachment-unauthorized"));
+        let allowed_types = allowed_types();
+        let limits = Limits {
+            max_bytes: 1024,
+            allowed_types: &allowed_types,
+        };
+
+        let upload = Upload {
+            filename: "design.pdf".to_owned(),
+            content_type: "application/pdf".to_owned(),
+            contents: b"%PDF-1.7".to_vec(),
+        };
+
+        let result = create(&db, &storage, limits, session.id, upload, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_returns_contents_to_relationship_members() -> Result<(), Error> {
+        let coach_id = Id::new_v4();
+        let coachee_id = Id::new_v4();
+        let (session, relationship) = session_with_relationship(coach_id, coachee_id);
+        let attachment_model = attachment(session.id, coachee_id);
+
+        let root = std::env::temp_dir().join(format!("attachment-download-{}", Id::new_v4()));
+        let storage = LocalStorage::new(&root);
+        storage
+            .put(&attachment_model.storage_key, b"%PDF-1.7".to_vec())
+            .await?;
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(session.clone(), relationship)]])
+            .append_query_results(vec![vec![attachment_model.clone()]])
+            .into_connection();
+
+        let (found, contents) =
+            download(&db, &storage, session.id, attachment_model.id, coach_id).await?;
+
+        assert_eq!(found, attachment_model);
+        assert_eq!(contents, b"%PDF-1.7");
+
+        let _ = std::fs::remove_dir_all(root);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_by_id_keeps_the_contents_until_the_attachment_is_purged() -> Result<(), Error> {
+        let coachee_id = Id::new_v4();
+        let (session, relationship) = session_with_relationship(Id::new_v4(), coachee_id);
+        let attachment_model = attachment(session.id, coachee_id);
+        let deleted = Model {
+            deleted_at: Some(chrono::Utc::now().into()),
+            ..attachment_model.clone()
+        };
+
+        let root = std::env::temp_dir().join(format!("attachment-delete-{}", Id::new_v4()));
+        let storage = LocalStorage::new(&root);
+        storage
+            .put(&attachment_model.storage_key, b"%PDF-1.7".to_vec())
+            .await?;
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(session.clone(), relationship)]])
+            .append_query_results(vec![vec![attachment_model.clone()]])
+            .append_query_results(vec![vec![deleted]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        delete_by_id(&db, session.id, attachment_model.id, coachee_id).await?;
+
+        assert_eq!(
+            storage.get(&attachment_model.storage_key).await?,
+            b"%PDF-1.7"
+        );
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"attachments\" SET \"deleted_at\" = $1 WHERE \"attachments\".\"id\" = $2"#
+        ));
+
+        let _ = std::fs::remove_dir_all(root);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_by_id_rejects_members_that_did_not_upload_the_attachment() -> Result<(), Error>
+    {
+        let coach_id = Id::new_v4();
+        let coachee_id = Id::new_v4();
+        let (session, relationship) = session_with_relationship(coach_id, coachee_id);
+        let attachment_model = attachment(session.id, coachee_id);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(session.clone(), relationship)]])
+            .append_query_results(vec![vec![attachment_model.clone()]])
+            .into_connection();
+
+        let result = delete_by_id(&db, session.id, attachment_model.id, coach_id).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordUnauthorized,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+}
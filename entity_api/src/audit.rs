@@ -0,0 +1,316 @@
+//! The audit log, which records who created, changed or deleted which record and when.
+//!
+//! Every write function records an Audit Event in the same database transaction as the write
+//! itself, so a write is never committed without its event and an event never outlives a write
+//! that was rolled back. Who made the write, and in which request, comes from the `Context`
+//! that the caller runs within, rather than being passed to every write function.
+
+use super::error::Error;
+use crate::{naive_date_parse_str, organization, unknown_query_param, uuid_parse_str};
+use chrono::Days;
+use entity::audit_action::AuditAction;
+use entity::audit_events::{self, Entity, Model};
+use entity::{coaching_relationships, coaching_sessions, Id};
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{Query, SelectStatement, SimpleExpr},
+    ConnectionTrait, Iterable, JsonValue, QueryOrder, QuerySelect, QueryTrait,
+};
+use std::collections::HashMap;
+
+use log::*;
+
+/// The most Audit Events a query returns, newest first.
+const AUDIT_EVENT_LIMIT: u64 = 1000;
+
+/// Fields that every write sets, which would only add noise to an event's changed fields.
+const UNAUDITED_FIELDS: [&str; 3] = ["id", "created_at", "updated_at"];
+
+tokio::task_local! {
+    static CONTEXT: Context;
+}
+
+/// Who is making writes, and in which request.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub actor_id: Option<Id>,
+    pub request_id: Option<String>,
+}
+
+/// Runs `fut` with every write it makes recorded as made within `context`. Writes made outside
+/// of any context are recorded without an actor or request, as made by the server itself.
+pub async fn scope<F: std::future::Future>(context: Context, fut: F) -> F::Output {
+    CONTEXT.scope(context, fut).await
+}
+
+fn current() -> Context {
+    CONTEXT.try_with(Context::clone).unwrap_or_default()
+}
+
+/// Where the record that was written sits, which is how its event is scoped to an
+/// organization.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Within {
+    Organization(Id),
+    CoachingSession(Id),
+    Nothing,
+}
+
+impl Within {
+    // The organization's id, looked up by the INSERT itself for records beneath it
+    fn organization_id(self) -> SimpleExpr {
+        match self {
+            Within::Organization(id) => Expr::value(id),
+            Within::CoachingSession(id) => subquery(
+                coaching_sessions::Entity::find_by_id(id)
+                    .select_only()
+                    .column(coaching_relationships::Column::OrganizationId)
+                    .inner_join(coaching_relationships::Entity)
+                    .into_query(),
+            ),
+            Within::Nothing => Expr::value(Option::<Id>::None),
+        }
+    }
+}
+
+fn subquery(select: SelectStatement) -> SimpleExpr {
+    SimpleExpr::SubQuery(None, Box::new(select.into_sub_query_statement()))
+}
+
+/// The names of the fields that `active_model` sets.
+pub(crate) fn changed_fields<A: ActiveModelTrait>(active_model: &A) -> Vec<String> {
+    <A::Entity as EntityTrait>::Column::iter()
+        .filter(|column| active_model.get(*column).is_set())
+        .map(|column| column.as_str().to_owned())
+        .filter(|field| !UNAUDITED_FIELDS.contains(&field.as_str()))
+        .collect()
+}
+
+/// Records an `action` on the `entity` record with `entity_id`, which set `changed_fields`.
+/// This takes any connection so that callers can record the event in the same database
+/// transaction as the write itself.
+pub(crate) async fn record<C, E>(
+    db: &C,
+    entity: E,
+    action: AuditAction,
+    entity_id: Id,
+    changed_fields: Vec<String>,
+    within: Within,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+{
+    let context = current();
+    debug!(
+        "Recording {:?} of {} {} by {:?}",
+        action,
+        entity.table_name(),
+        entity_id,
+        context.actor_id
+    );
+
+    // Built by hand rather than from an ActiveModel so that the organization can be looked up
+    // by a subquery
+    let insert = Query::insert()
+        .into_table(Entity.table_ref())
+        .columns([
+            audit_events::Column::OrganizationId,
+            audit_events::Column::ActorId,
+            audit_events::Column::Action,
+            audit_events::Column::EntityType,
+            audit_events::Column::EntityId,
+            audit_events::Column::ChangedFields,
+            audit_events::Column::RequestId,
+        ])
+        .values_panic([
+            within.organization_id(),
+            context.actor_id.into(),
+            action.as_enum(),
+            entity.table_name().into(),
+            entity_id.into(),
+            JsonValue::from(changed_fields).into(),
+            context.request_id.into(),
+        ])
+        .to_owned();
+
+    db.execute(db.get_database_backend().build(&insert)).await?;
+
+    Ok(())
+}
+
+/// The Audit Events of an organization that `user_id` is an admin of, newest first, narrowed
+/// down by the `actor_id`, `entity_type` (a table name, like `actions`), `entity_id`, `action`,
+/// `request_id`, `from_date` and `to_date` (both inclusive) in `query_params`.
+pub async fn find_by_organization<C>(
+    db: &C,
+    organization_id: Id,
+    user_id: Id,
+    query_params: HashMap<String, String>,
+) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = Entity::find().filter(audit_events::Column::OrganizationId.eq(organization_id));
+
+    for (key, value) in query_params {
+        match key.as_str() {
+            "actor_id" => {
+                let actor_id = uuid_parse_str(&key, &value)?;
+
+                query = query.filter(audit_events::Column::ActorId.eq(actor_id));
+            }
+            "entity_type" => {
+                query = query.filter(audit_events::Column::EntityType.eq(value));
+            }
+            "entity_id" => {
+                let entity_id = uuid_parse_str(&key, &value)?;
+
+                query = query.filter(audit_events::Column::EntityId.eq(entity_id));
+            }
+            "action" => {
+                let action = AuditAction::try_from_value(&value).map_err(|_| {
+                    Error::invalid_field(
+                        &key,
+                        "invalid_value",
+                        "must be one of create, update, delete or restore",
+                    )
+                })?;
+
+                query = query.filter(audit_events::Column::Action.eq(action));
+            }
+            "request_id" => {
+                query = query.filter(audit_events::Column::RequestId.eq(value));
+            }
+            "from_date" => {
+                let from_date = naive_date_parse_str(&key, &value)?;
+
+                query = query.filter(
+                    audit_events::Column::CreatedAt
+                        .gte(from_date.and_time(Default::default()).and_utc()),
+                );
+            }
+            "to_date" => {
+                let to_date = naive_date_parse_str(&key, &value)? + Days::new(1);
+
+                query = query.filter(
+                    audit_events::Column::CreatedAt
+                        .lt(to_date.and_time(Default::default()).and_utc()),
+                );
+            }
+            _ => {
+                return Err(unknown_query_param(&key));
+            }
+        }
+    }
+
+    organization::find_by_id_for_admin(db, organization_id, user_id).await?;
+
+    Ok(query
+        .order_by_desc(audit_events::Column::CreatedAt)
+        .limit(AUDIT_EVENT_LIMIT)
+        .all(db)
+        .await?)
+}
+
+/// The result of recording an event, for the mock databases of tests that write.
+#[cfg(test)]
+#[cfg(feature = "mock")]
+pub(crate) fn recorded() -> sea_orm::MockExecResult {
+    sea_orm::MockExecResult {
+        last_insert_id: 0,
+        rows_affected: 1,
+    }
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use crate::action;
+    use crate::error::EntityApiErrorCode;
+    use entity::actions;
+    use sea_orm::{ActiveValue::Unchanged, DatabaseBackend, MockDatabase, Set};
+
+    #[test]
+    fn changed_fields_names_the_fields_a_write_sets() {
+        let active_model = actions::ActiveModel {
+            id: Unchanged(Id::new_v4()),
+            body: Set(Some("Follow up".to_owned())),
+            status_changed_at: Set(chrono::Utc::now().into()),
+            updated_at: Set(chrono::Utc::now().into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            changed_fields(&active_model),
+            vec!["body".to_owned(), "status_changed_at".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn create_records_the_event_in_the_same_transaction_as_the_write() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let action_model = actions::Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            user_id: Id::new_v4(),
+            body: Some("Follow up".to_owned()),
+            due_by: None,
+            status: Default::default(),
+            status_changed_at: now.into(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let actor_id = Id::new_v4();
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![action_model.clone()]])
+            .append_exec_results([recorded()])
+            .into_connection();
+
+        let context = Context {
+            actor_id: Some(actor_id),
+            request_id: Some("req-1".to_owned()),
+        };
+        scope(
+            context,
+            action::create(&db, action_model.clone(), action_model.user_id),
+        )
+        .await?;
+
+        let transaction_log = db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 1);
+
+        let transaction_log = format!("{:?}", transaction_log);
+        assert!(transaction_log.contains(
+            r#"INSERT INTO \"refactor_platform\".\"audit_events\" (\"organization_id\", \"actor_id\", \"action\", \"entity_type\", \"entity_id\", \"changed_fields\", \"request_id\") VALUES ((SELECT \"coaching_relationships\".\"organization_id\" FROM \"refactor_platform\".\"coaching_sessions\" INNER JOIN \"refactor_platform\".\"coaching_relationships\" ON \"coaching_sessions\".\"coaching_relationship_id\" = \"coaching_relationships\".\"id\" WHERE \"coaching_sessions\".\"id\" = $1), $2, CAST($3 AS audit_action), $4, $5, $6, $7)"#
+        ));
+        assert!(transaction_log.contains(&format!("{actor_id}")));
+        assert!(transaction_log.contains(r#"String(Some("req-1"))"#));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_organization_rejects_an_unknown_action() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let query_params = HashMap::from([("action".to_owned(), "archive".to_owned())]);
+
+        let result = find_by_organization(&db, Id::new_v4(), Id::new_v4(), query_params).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::ValidationFailed(_),
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+}
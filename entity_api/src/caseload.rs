@@ -0,0 +1,322 @@
+use super::error::Error;
+use crate::unknown_query_param;
+use entity::{relationship_status::RelationshipStatus, Id};
+use sea_orm::{
+    prelude::{Date, DateTime},
+    ConnectionTrait, DbBackend, FromQueryResult, Statement,
+};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use log::*;
+
+// One row per coaching relationship of the coach that has not ended, leaving out those of
+// deleted organizations. Sessions, actions and
+// overarching goals are each aggregated per relationship before being joined so that the
+// counts don't multiply each other. Session dates have no time zone of their own and are
+// compared against the current time in UTC, like the date filters on coaching sessions.
+const CASELOAD_SQL: &str = r#"WITH "relationships" AS (
+  SELECT "id", "organization_id", "coachee_id", "status", "start_date"
+  FROM "refactor_platform"."coaching_relationships"
+  WHERE "coach_id" = $1 AND "status" <> 'ended' AND "deleted_at" IS NULL
+    AND "organization_id" IN (SELECT "id" FROM "refactor_platform"."organizations" WHERE "deleted_at" IS NULL)
+),
+"session_dates" AS (
+  SELECT "coaching_relationship_id",
+    max("date") FILTER (WHERE "date" <= now() AT TIME ZONE 'UTC') AS "last_session_date",
+    min("date") FILTER (WHERE "date" > now() AT TIME ZONE 'UTC') AS "next_session_date"
+  FROM "refactor_platform"."coaching_sessions"
+  WHERE "coaching_relationship_id" IN (SELECT "id" FROM "relationships")
+    AND "deleted_at" IS NULL
+  GROUP BY "coaching_relationship_id"
+),
+"action_counts" AS (
+  SELECT "coaching_sessions"."coaching_relationship_id",
+    count(*) AS "open_action_count",
+    count(*) FILTER (WHERE "actions"."due_by" < now()) AS "overdue_action_count"
+  FROM "refactor_platform"."actions"
+  JOIN "refactor_platform"."coaching_sessions" ON "coaching_sessions"."id" = "actions"."coaching_session_id"
+  WHERE "coaching_sessions"."coaching_relationship_id" IN (SELECT "id" FROM "relationships")
+    AND "actions"."status" IN ('not_started', 'in_progress')
+    AND "actions"."deleted_at" IS NULL
+  GROUP BY "coaching_sessions"."coaching_relationship_id"
+),
+"goal_counts" AS (
+  SELECT "coaching_sessions"."coaching_relationship_id",
+    count(*) AS "active_goal_count"
+  FROM "refactor_platform"."overarching_goals"
+  JOIN "refactor_platform"."coaching_sessions" ON "coaching_sessions"."id" = "overarching_goals"."coaching_session_id"
+  WHERE "coaching_sessions"."coaching_relationship_id" IN (SELECT "id" FROM "relationships")
+    AND "overarching_goals"."status" IN ('not_started', 'in_progress')
+    AND "overarching_goals"."deleted_at" IS NULL
+  GROUP BY "coaching_sessions"."coaching_relationship_id"
+)
+SELECT "relationships"."id" AS "coaching_relationship_id",
+  "relationships"."organization_id",
+  "relationships"."coachee_id",
+  "coachees"."first_name" AS "coachee_first_name",
+  "coachees"."last_name" AS "coachee_last_name",
+  CAST("relationships"."status" AS text) AS "status",
+  "relationships"."start_date",
+  "session_dates"."last_session_date",
+  "session_dates"."next_session_date",
+  coalesce("action_counts"."open_action_count", 0) AS "open_action_count",
+  coalesce("action_counts"."overdue_action_count", 0) AS "overdue_action_count",
+  coalesce("goal_counts"."active_goal_count", 0) AS "active_goal_count",
+  (coalesce("action_counts"."overdue_action_count", 0) > 0 OR "session_dates"."next_session_date" IS NULL) AS "needs_attention"
+FROM "relationships"
+JOIN "refactor_platform"."users" AS "coachees" ON "coachees"."id" = "relationships"."coachee_id"
+LEFT JOIN "session_dates" ON "session_dates"."coaching_relationship_id" = "relationships"."id"
+LEFT JOIN "action_counts" ON "action_counts"."coaching_relationship_id" = "relationships"."id"
+LEFT JOIN "goal_counts" ON "goal_counts"."coaching_relationship_id" = "relationships"."id""#;
+
+const COACHEE_ORDER: &str =
+    r#"ORDER BY "coachee_last_name" NULLS LAST, "coachee_first_name" NULLS LAST"#;
+
+/// A coach's view of one of their coaching relationships that has not ended.
+///
+/// Open actions and active overarching goals are those not yet completed or won't do. An
+/// action is overdue when it is open and its `due_by` has passed. A relationship
+/// `needs_attention` when it has overdue actions or no upcoming session.
+#[derive(Debug, FromQueryResult, Serialize, ToSchema)]
+#[schema(as = entity_api::caseload::CaseloadEntry)] // OpenAPI schema
+pub struct CaseloadEntry {
+    pub coaching_relationship_id: Id,
+    pub organization_id: Id,
+    pub coachee_id: Id,
+    pub coachee_first_name: Option<String>,
+    pub coachee_last_name: Option<String>,
+    #[schema(value_type = String)]
+    pub status: RelationshipStatus,
+    #[schema(value_type = String, format = Date)]
+    pub start_date: Date,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub last_session_date: Option<DateTime>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub next_session_date: Option<DateTime>,
+    pub open_action_count: i64,
+    pub overdue_action_count: i64,
+    pub active_goal_count: i64,
+    pub needs_attention: bool,
+}
+
+/// The caseload of the coach with `coach_id`, ordered by coachee name by default or with
+/// `sort=needs_attention` by how much attention each relationship needs.
+pub async fn find_by_coach<C>(
+    db: &C,
+    coach_id: Id,
+    query_params: HashMap<String, String>,
+) -> Result<Vec<CaseloadEntry>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut by_needs_attention = false;
+
+    for (key, value) in query_params {
+        match (key.as_str(), value.as_str()) {
+            ("sort", "needs_attention") => {
+                by_needs_attention = true;
+            }
+            ("sort", "coachee") => {
+                by_needs_attention = false;
+            }
+            ("sort", _) => {
+                return Err(Error::invalid_field(
+                    &key,
+                    "invalid_value",
+                    "must be needs_attention or coachee",
+                ));
+            }
+            _ => {
+                return Err(unknown_query_param(&key));
+            }
+        }
+    }
+
+    debug!("Finding the caseload of coach {}", coach_id);
+
+    let mut caseload = CaseloadEntry::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!("{CASELOAD_SQL}\n{COACHEE_ORDER}"),
+        [coach_id.into()],
+    ))
+    .all(db)
+    .await?;
+
+    if by_needs_attention {
+        // A stable sort, so coachees that need as much attention as each other stay in name order
+        caseload.sort_by(needs_attention_order);
+    }
+
+    Ok(caseload)
+}
+
+// Coachees with the most overdue actions first, then those without an upcoming session, then
+// whoever has gone the longest without a session.
+fn needs_attention_order(a: &CaseloadEntry, b: &CaseloadEntry) -> Ordering {
+    b.overdue_action_count
+        .cmp(&a.overdue_action_count)
+        .then_with(|| {
+            a.next_session_date
+                .is_some()
+                .cmp(&b.next_session_date.is_some())
+        })
+        .then_with(|| a.last_session_date.cmp(&b.last_session_date))
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use chrono::{Days, Utc};
+    use sea_orm::{DatabaseBackend, MockDatabase, Transaction, Value};
+    use std::collections::BTreeMap;
+
+    fn row(
+        last_name: &str,
+        last_session_date: Option<DateTime>,
+        next_session_date: Option<DateTime>,
+        overdue_action_count: i64,
+    ) -> BTreeMap<&'static str, Value> {
+        BTreeMap::from([
+            ("coaching_relationship_id", Id::new_v4().into()),
+            ("organization_id", Id::new_v4().into()),
+            ("coachee_id", Id::new_v4().into()),
+            ("coachee_first_name", Some("Jo".to_owned()).into()),
+            ("coachee_last_name", Some(last_name.to_owned()).into()),
+            ("status", "active".into()),
+            ("start_date", Utc::now().date_naive().into()),
+            ("last_session_date", last_session_date.into()),
+            ("next_session_date", next_session_date.into()),
+            ("open_action_count", (overdue_action_count + 1).into()),
+            ("overdue_action_count", overdue_action_count.into()),
+            ("active_goal_count", 2_i64.into()),
+            (
+                "needs_attention",
+                (overdue_action_count > 0 || next_session_date.is_none()).into(),
+            ),
+        ])
+    }
+
+    #[tokio::test]
+    async fn find_by_coach_queries_the_caseload_of_the_coach_in_coachee_order() -> Result<(), Error>
+    {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let coach_id = Id::new_v4();
+
+        let _ = find_by_coach(&db, coach_id, HashMap::new()).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                format!("{CASELOAD_SQL}\n{COACHEE_ORDER}"),
+                [coach_id.into()]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_coach_leaves_out_relationships_of_deleted_organizations() -> Result<(), Error>
+    {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+
+        let _ = find_by_coach(&db, Id::new_v4(), HashMap::new()).await;
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"AND \"organization_id\" IN (SELECT \"id\" FROM \"refactor_platform\".\"organizations\" WHERE \"deleted_at\" IS NULL)"#
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_coach_maps_the_aggregates_of_each_relationship() -> Result<(), Error> {
+        let now = Utc::now().naive_utc();
+        let last_session_date = now - Days::new(14);
+        let next_session_date = now + Days::new(7);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![row(
+                "Rivera",
+                Some(last_session_date),
+                Some(next_session_date),
+                3,
+            )]])
+            .into_connection();
+
+        let caseload = find_by_coach(&db, Id::new_v4(), HashMap::new()).await?;
+
+        assert_eq!(caseload.len(), 1);
+        assert_eq!(caseload[0].coachee_last_name.as_deref(), Some("Rivera"));
+        assert_eq!(caseload[0].status, RelationshipStatus::Active);
+        assert_eq!(caseload[0].last_session_date, Some(last_session_date));
+        assert_eq!(caseload[0].next_session_date, Some(next_session_date));
+        assert_eq!(caseload[0].open_action_count, 4);
+        assert_eq!(caseload[0].overdue_action_count, 3);
+        assert_eq!(caseload[0].active_goal_count, 2);
+        assert!(caseload[0].needs_attention);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_coach_puts_coachees_with_the_most_overdue_actions_first() -> Result<(), Error>
+    {
+        let now = Utc::now().naive_utc();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![
+                row(
+                    "Adams",
+                    Some(now - Days::new(7)),
+                    Some(now + Days::new(7)),
+                    0,
+                ),
+                row(
+                    "Baker",
+                    Some(now - Days::new(3)),
+                    Some(now + Days::new(7)),
+                    1,
+                ),
+                row("Chen", Some(now - Days::new(30)), None, 0),
+                row("Diaz", None, Some(now + Days::new(7)), 5),
+                row(
+                    "Evans",
+                    Some(now - Days::new(60)),
+                    Some(now + Days::new(7)),
+                    0,
+                ),
+            ]])
+            .into_connection();
+        let query_params = HashMap::from([("sort".to_owned(), "needs_attention".to_owned())]);
+
+        let caseload = find_by_coach(&db, Id::new_v4(), query_params).await?;
+
+        let last_names: Vec<_> = caseload
+            .iter()
+            .map(|entry| entry.coachee_last_name.as_deref().unwrap())
+            .collect();
+        assert_eq!(last_names, ["Diaz", "Baker", "Chen", "Evans", "Adams"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_coach_returns_error_for_unknown_sort() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let query_params = HashMap::from([("sort".to_owned(), "random".to_owned())]);
+
+        let result = find_by_coach(&db, Id::new_v4(), query_params).await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
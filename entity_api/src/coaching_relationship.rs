@@ -1,43 +1,535 @@
 use super::error::{EntityApiErrorCode, Error};
-use crate::uuid_parse_str;
+use crate::audit::{self, Within};
+use crate::precondition::{self, Precondition};
+use crate::{soft_delete, unknown_query_param, uuid_parse_str};
 use chrono::Utc;
 use entity::{
+    audit_action::AuditAction,
     coachees, coaches,
-    coaching_relationships::{self, ActiveModel, Entity, Model},
+    coaching_relationships::{self, today, ActiveModel, Entity, Model},
+    organizations,
+    relationship_status::RelationshipStatus,
     Id,
 };
 use sea_orm::{
-    entity::prelude::*, sea_query::Alias, Condition, DatabaseConnection, FromQueryResult, JoinType,
-    QuerySelect, QueryTrait, Set,
+    entity::prelude::*,
+    sea_query::Alias,
+    ActiveValue::{Set, Unchanged},
+    Condition, ConnectionTrait, FromQueryResult, JoinType, QuerySelect, QueryTrait, SqlErr,
+    TransactionTrait,
 };
 use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde::Deserialize;
+use utoipa::ToSchema;
 
 use log::*;
 
-pub async fn create(
-    db: &DatabaseConnection,
-    coaching_relationship_model: Model,
-) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, coaching_relationship_model: Model) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!(
         "New Coaching Relationship Model to be inserted: {:?}",
         coaching_relationship_model
     );
 
+    if coaching_relationship_model.coach_id == coaching_relationship_model.coachee_id {
+        debug!(
+            "A Coaching Relationship's coach and coachee must be different users, got {} for both",
+            coaching_relationship_model.coach_id
+        );
+
+        return Err(Error::invalid_field(
+            "coachee_id",
+            "same_as_coach",
+            "must be a different user than coach_id",
+        ));
+    }
+
+    let txn = db.begin().await?;
+
+    ensure_pairing_available(
+        &txn,
+        coaching_relationship_model.organization_id,
+        coaching_relationship_model.coach_id,
+        coaching_relationship_model.coachee_id,
+    )
+    .await?;
+
     let now = Utc::now();
 
     let coaching_relationship_active_model: ActiveModel = ActiveModel {
         organization_id: Set(coaching_relationship_model.organization_id),
         coach_id: Set(coaching_relationship_model.coach_id),
         coachee_id: Set(coaching_relationship_model.coachee_id),
+        status: Set(RelationshipStatus::Active),
+        start_date: Set(coaching_relationship_model.start_date),
+        end_date: Set(None),
+        reassigned_to_id: Set(None),
         created_at: Set(now.into()),
         updated_at: Set(now.into()),
         ..Default::default()
     };
 
-    Ok(coaching_relationship_active_model.insert(db).await?)
+    let changed_fields = audit::changed_fields(&coaching_relationship_active_model);
+
+    let coaching_relationship = coaching_relationship_active_model
+        .insert(&txn)
+        .await
+        .map_err(pairing_conflict)?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        coaching_relationship.id,
+        changed_fields,
+        Within::Organization(coaching_relationship.organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(coaching_relationship)
+}
+
+/// Updates the status and dates of a Coaching Relationship. Its organization, coach and
+/// coachee never change, a coachee moves to another coach through `reassign` instead.
+pub async fn update<C>(
+    db: &C,
+    organization_id: Id,
+    id: Id,
+    precondition: Precondition,
+    model: Model,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let relationship = find_in_organization(&txn, organization_id, id).await?;
+
+    debug!(
+        "Existing Coaching Relationship model to be Updated: {:?}",
+        relationship
+    );
+
+    precondition.check("Coaching Relationship", id, &relationship.updated_at)?;
+
+    if !relationship.status.can_transition_to(&model.status) {
+        error!(
+            "Coaching Relationship status change from {:?} to {:?} is not permitted",
+            relationship.status, model.status
+        );
+
+        return Err(Error {
+            inner: None,
+            error_code: EntityApiErrorCode::InvalidStatusTransition,
+            detail: Some(format!(
+                "Coaching Relationship {id} cannot change from {:?} to {:?}.",
+                relationship.status, model.status
+            )),
+        });
+    }
+
+    // Only a relationship that is over has an end date, which defaults to the day it ended
+    let end_date = match model.status {
+        RelationshipStatus::Ended => Some(model.end_date.unwrap_or_else(today)),
+        _ => None,
+    };
+
+    if end_date.is_some_and(|end_date| end_date < model.start_date) {
+        debug!(
+            "Coaching Relationship {} cannot end before it starts on {}",
+            id, model.start_date
+        );
+
+        return Err(Error::invalid_field(
+            "end_date",
+            "before_start_date",
+            "must not be before start_date",
+        ));
+    }
+
+    let active_model: ActiveModel = ActiveModel {
+        id: Unchanged(relationship.id),
+        status: Set(model.status),
+        start_date: Set(model.start_date),
+        end_date: Set(end_date),
+        updated_at: Set(Utc::now().into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let updated_relationship = precondition::update_unchanged(
+        &txn,
+        "Coaching Relationship",
+        id,
+        active_model,
+        coaching_relationships::Column::UpdatedAt,
+        relationship.updated_at,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Update,
+        id,
+        changed_fields,
+        Within::Organization(organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(updated_relationship)
+}
+
+/// The coach to hand a Coaching Relationship's coachee to.
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = entity_api::coaching_relationship::Reassignment)] // OpenAPI schema
+pub struct Reassignment {
+    pub coach_id: Id,
+}
+
+/// Hands the coachee of a Coaching Relationship to another coach. This starts a new active
+/// relationship and ends the existing one, which stays readable along with its sessions and
+/// points to its successor through `reassigned_to_id`. Returns the new relationship.
+pub async fn reassign<C>(
+    db: &C,
+    organization_id: Id,
+    id: Id,
+    reassignment: Reassignment,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let relationship = find_in_organization(&txn, organization_id, id).await?;
+
+    if relationship.status == RelationshipStatus::Ended {
+        return Err(Error::conflict(
+            "Coaching Relationship",
+            id,
+            "has ended and cannot be reassigned",
+        ));
+    }
+
+    if reassignment.coach_id == relationship.coach_id
+        || reassignment.coach_id == relationship.coachee_id
+    {
+        debug!(
+            "Cannot reassign Coaching Relationship {} to coach {}",
+            id, reassignment.coach_id
+        );
+
+        return Err(Error::invalid_field(
+            "coach_id",
+            "already_in_relationship",
+            "must be a different user than the relationship's coach and coachee",
+        ));
+    }
+
+    debug!(
+        "Existing Coaching Relationship to be reassigned to coach {}: {:?}",
+        reassignment.coach_id, relationship
+    );
+
+    ensure_pairing_available(
+        &txn,
+        relationship.organization_id,
+        reassignment.coach_id,
+        relationship.coachee_id,
+    )
+    .await?;
+
+    let now = Utc::now();
+
+    let successor_active_model = ActiveModel {
+        organization_id: Set(relationship.organization_id),
+        coach_id: Set(reassignment.coach_id),
+        coachee_id: Set(relationship.coachee_id),
+        status: Set(RelationshipStatus::Active),
+        start_date: Set(now.date_naive()),
+        end_date: Set(None),
+        reassigned_to_id: Set(None),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let successor_changed_fields = audit::changed_fields(&successor_active_model);
+
+    let successor: Model = successor_active_model
+        .insert(&txn)
+        .await
+        .map_err(pairing_conflict)?;
+
+    let ended_active_model = ActiveModel {
+        id: Unchanged(relationship.id),
+        status: Set(RelationshipStatus::Ended),
+        end_date: Set(Some(now.date_naive())),
+        reassigned_to_id: Set(Some(successor.id)),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let ended_changed_fields = audit::changed_fields(&ended_active_model);
+
+    ended_active_model.update(&txn).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        successor.id,
+        successor_changed_fields,
+        Within::Organization(organization_id),
+    )
+    .await?;
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Update,
+        id,
+        ended_changed_fields,
+        Within::Organization(organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(successor)
+}
+
+/// Deletes a Coaching Relationship along with its sessions and everything in them, hiding them
+/// until the relationship is restored or purged after the retention period. Its pairing is
+/// free to be coached again in a new relationship in the meantime.
+pub async fn delete_by_id<C>(
+    db: &C,
+    organization_id: Id,
+    id: Id,
+    precondition: Precondition,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let relationship = find_in_organization(&txn, organization_id, id).await?;
+    debug!(
+        "Existing Coaching Relationship model to be deleted: {:?}",
+        relationship
+    );
+
+    precondition.check("Coaching Relationship", id, &relationship.updated_at)?;
+
+    let now: DateTimeWithTimeZone = Utc::now().into();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(now)),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    precondition::update_unchanged(
+        &txn,
+        "Coaching Relationship",
+        id,
+        active_model,
+        coaching_relationships::Column::UpdatedAt,
+        relationship.updated_at,
+    )
+    .await?;
+
+    soft_delete::delete_relationships(&txn, coaching_relationships::Column::Id.eq(id), now).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::Organization(organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Restores a deleted Coaching Relationship that hasn't been purged yet, along with the
+/// sessions and content that were deleted with it. A relationship that hasn't ended can only
+/// be restored while its coach and coachee have no other such relationship.
+pub async fn restore<C>(db: &C, organization_id: Id, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+
+    let relationship = soft_delete::find_deleted_by_id::<Entity>(id)
+        .filter(coaching_relationships::Column::OrganizationId.eq(organization_id))
+        .one(&txn)
+        .await?
+        .ok_or_else(|| Error::not_found("Coaching Relationship", id))?;
+    debug!(
+        "Deleted Coaching Relationship model to be restored: {:?}",
+        relationship
+    );
+
+    if relationship.status != RelationshipStatus::Ended {
+        ensure_pairing_available(
+            &txn,
+            organization_id,
+            relationship.coach_id,
+            relationship.coachee_id,
+        )
+        .await?;
+    }
+
+    let now: DateTimeWithTimeZone = Utc::now().into();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+    let restored_relationship = active_model.update(&txn).await.map_err(pairing_conflict)?;
+
+    // Sessions and content deleted on their own before the relationship was stay deleted
+    if let Some(deleted_at) = relationship.deleted_at {
+        soft_delete::restore_relationships(
+            &txn,
+            coaching_relationships::Column::Id.eq(id),
+            deleted_at,
+            now,
+        )
+        .await?;
+    }
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::Organization(organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(restored_relationship)
+}
+
+async fn find_in_organization<C>(db: &C, organization_id: Id, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    match Entity::find_by_id(id)
+        .filter(coaching_relationships::Column::OrganizationId.eq(organization_id))
+        .filter(visible())
+        .one(db)
+        .await?
+    {
+        Some(relationship) => Ok(relationship),
+        None => {
+            error!(
+                "Coaching Relationship with id {} not found in Organization {}",
+                id, organization_id
+            );
+
+            Err(Error {
+                inner: None,
+                error_code: EntityApiErrorCode::RecordNotFound,
+                detail: None,
+            })
+        }
+    }
+}
+
+/// Finds a coaching relationship in an organization that `user_id` is the coach or coachee of.
+/// Any other user is told it was not found.
+pub async fn find_in_organization_for_member<C>(
+    db: &C,
+    organization_id: Id,
+    id: Id,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let relationship = find_in_organization(db, organization_id, id).await?;
+
+    if relationship.coach_id == user_id || relationship.coachee_id == user_id {
+        Ok(relationship)
+    } else {
+        debug!(
+            "User {} is not a member of Coaching Relationship {}",
+            user_id, id
+        );
+
+        Err(Error::not_found("Coaching Relationship", id))
+    }
+}
+
+// A coach and coachee may only have one relationship in an organization that has not ended
+// or been deleted.
+async fn ensure_pairing_available<C>(
+    db: &C,
+    organization_id: Id,
+    coach_id: Id,
+    coachee_id: Id,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    let existing = Entity::find()
+        .filter(coaching_relationships::Column::OrganizationId.eq(organization_id))
+        .filter(coaching_relationships::Column::CoachId.eq(coach_id))
+        .filter(coaching_relationships::Column::CoacheeId.eq(coachee_id))
+        .filter(coaching_relationships::Column::Status.ne(RelationshipStatus::Ended))
+        .filter(coaching_relationships::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+
+    match existing {
+        Some(existing) => {
+            debug!(
+                "Coach {} and coachee {} already have a Coaching Relationship in Organization {}",
+                coach_id, coachee_id, organization_id
+            );
+
+            Err(Error::conflict(
+                "Coaching Relationship",
+                existing.id,
+                "already pairs the coach and coachee and has not ended",
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+// A concurrent request can create the same pairing between the check above and the insert,
+// in which case the database's unique index rejects the second one.
+fn pairing_conflict(err: DbErr) -> Error {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => Error {
+            inner: Some(err),
+            error_code: EntityApiErrorCode::RecordConflict,
+            detail: Some(
+                "The coach and coachee already have a Coaching Relationship that has not ended."
+                    .to_owned(),
+            ),
+        },
+        _ => err.into(),
+    }
 }
 
-pub async fn find_by_user(db: &DatabaseConnection, user_id: Id) -> Result<Vec<Model>, Error> {
+pub async fn find_by_user<C>(db: &C, user_id: Id) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
     let coaching_relationships: Vec<coaching_relationships::Model> =
         coaching_relationships::Entity::find()
             .filter(
@@ -45,30 +537,34 @@ pub async fn find_by_user(db: &DatabaseConnection, user_id: Id) -> Result<Vec<Mo
                     .add(coaching_relationships::Column::CoachId.eq(user_id))
                     .add(coaching_relationships::Column::CoacheeId.eq(user_id)),
             )
+            .filter(visible())
             .all(db)
             .await?;
 
     Ok(coaching_relationships)
 }
 
-pub async fn find_by_organization(
-    db: &DatabaseConnection,
-    organization_id: Id,
-) -> Result<Vec<Model>, Error> {
-    let query = by_organization(coaching_relationships::Entity::find(), organization_id).await;
+pub async fn find_by_organization<C>(db: &C, organization_id: Id) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let query = by_organization(soft_delete::find::<Entity>(), organization_id).await;
 
     Ok(query.all(db).await?)
 }
 
-pub async fn find_by_organization_with_user_names(
-    db: &DatabaseConnection,
+pub async fn find_by_organization_with_user_names<C>(
+    db: &C,
     organization_id: Id,
     user_id: Id,
-) -> Result<Vec<CoachingRelationshipWithUserNames>, Error> {
+) -> Result<Vec<CoachingRelationshipWithUserNames>, Error>
+where
+    C: ConnectionTrait,
+{
     let coaches = Alias::new("coaches");
     let coachees = Alias::new("coachees");
 
-    let query = by_organization(coaching_relationships::Entity::find(), organization_id)
+    let query = by_organization(soft_delete::find::<Entity>(), organization_id)
         .await
         .join_as(
             JoinType::Join,
@@ -90,6 +586,10 @@ pub async fn find_by_organization_with_user_names(
         .column(coaching_relationships::Column::OrganizationId)
         .column(coaching_relationships::Column::CoachId)
         .column(coaching_relationships::Column::CoacheeId)
+        .column(coaching_relationships::Column::Status)
+        .column(coaching_relationships::Column::StartDate)
+        .column(coaching_relationships::Column::EndDate)
+        .column(coaching_relationships::Column::ReassignedToId)
         .column(coaching_relationships::Column::CreatedAt)
         .column(coaching_relationships::Column::UpdatedAt)
         .column_as(Expr::cust("coaches.first_name"), "coach_first_name")
@@ -101,10 +601,13 @@ pub async fn find_by_organization_with_user_names(
     Ok(query.all(db).await?)
 }
 
-pub async fn get_relationship_with_user_names(
-    db: &DatabaseConnection,
+pub async fn get_relationship_with_user_names<C>(
+    db: &C,
     relationship_id: Id,
-) -> Result<Option<CoachingRelationshipWithUserNames>, Error> {
+) -> Result<Option<CoachingRelationshipWithUserNames>, Error>
+where
+    C: ConnectionTrait,
+{
     let coaches = Alias::new("coaches");
     let coachees = Alias::new("coachees");
 
@@ -125,6 +628,10 @@ pub async fn get_relationship_with_user_names(
         .column(coaching_relationships::Column::OrganizationId)
         .column(coaching_relationships::Column::CoachId)
         .column(coaching_relationships::Column::CoacheeId)
+        .column(coaching_relationships::Column::Status)
+        .column(coaching_relationships::Column::StartDate)
+        .column(coaching_relationships::Column::EndDate)
+        .column(coaching_relationships::Column::ReassignedToId)
         .column(coaching_relationships::Column::CreatedAt)
         .column(coaching_relationships::Column::UpdatedAt)
         .column_as(Expr::cust("coaches.first_name"), "coach_first_name")
@@ -136,22 +643,22 @@ pub async fn get_relationship_with_user_names(
     Ok(query.one(db).await?)
 }
 
-pub async fn find_by(
-    db: &DatabaseConnection,
+pub async fn find_by<C>(
+    db: &C,
     params: std::collections::HashMap<String, String>,
-) -> Result<Vec<Model>, Error> {
-    let mut query = coaching_relationships::Entity::find();
+) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = coaching_relationships::Entity::find().filter(visible());
 
     for (key, value) in params.iter() {
         match key.as_str() {
             "organization_id" => {
-                query = by_organization(query, uuid_parse_str(value)?).await;
+                query = by_organization(query, uuid_parse_str(key, value)?).await;
             }
             _ => {
-                return Err(Error {
-                    inner: None,
-                    error_code: EntityApiErrorCode::InvalidQueryTerm,
-                });
+                return Err(unknown_query_param(key));
             }
         }
     }
@@ -167,16 +674,32 @@ pub async fn by_coaching_relationship(
         .select_only()
         .column(entity::coaching_relationships::Column::Id)
         .filter(entity::coaching_relationships::Column::Id.eq(id))
+        .filter(visible())
         .into_query();
 
     query.filter(coaching_relationships::Column::Id.in_subquery(relationship_subsquery.to_owned()))
 }
 
+/// Keeps only coaching relationships that haven't been deleted, either on their own or along
+/// with their organization, as those are hidden until they are restored.
+pub(crate) fn visible() -> Condition {
+    Condition::all()
+        .add(coaching_relationships::Column::DeletedAt.is_null())
+        .add(
+            coaching_relationships::Column::OrganizationId.in_subquery(
+                soft_delete::find::<organizations::Entity>()
+                    .select_only()
+                    .column(organizations::Column::Id)
+                    .into_query(),
+            ),
+        )
+}
+
 async fn by_organization(
     query: Select<coaching_relationships::Entity>,
     organization_id: Id,
 ) -> Select<coaching_relationships::Entity> {
-    let organization_subquery = entity::organizations::Entity::find()
+    let organization_subquery = soft_delete::find::<entity::organizations::Entity>()
         .select_only()
         .column(entity::organizations::Column::Id)
         .filter(entity::organizations::Column::Id.eq(organization_id))
@@ -199,6 +722,10 @@ pub struct CoachingRelationshipWithUserNames {
     pub coach_last_name: String,
     pub coachee_first_name: String,
     pub coachee_last_name: String,
+    pub status: RelationshipStatus,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: Option<chrono::NaiveDate>,
+    pub reassigned_to_id: Option<Id>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -210,7 +737,7 @@ impl Serialize for CoachingRelationshipWithUserNames {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("CoachingRelationship", 7)?;
+        let mut state = serializer.serialize_struct("CoachingRelationship", 13)?;
         state.serialize_field("id", &self.id)?;
         state.serialize_field("coach_id", &self.coach_id)?;
         state.serialize_field("coachee_id", &self.coachee_id)?;
@@ -218,6 +745,10 @@ impl Serialize for CoachingRelationshipWithUserNames {
         state.serialize_field("coach_last_name", &self.coach_last_name)?;
         state.serialize_field("coachee_first_name", &self.coachee_first_name)?;
         state.serialize_field("coachee_last_name", &self.coachee_last_name)?;
+        state.serialize_field("status", &self.status)?;
+        state.serialize_field("start_date", &self.start_date)?;
+        state.serialize_field("end_date", &self.end_date)?;
+        state.serialize_field("reassigned_to_id", &self.reassigned_to_id)?;
         state.serialize_field("created_at", &self.created_at)?;
         state.serialize_field("updated_at", &self.updated_at)?;
         state.end()
@@ -244,7 +775,7 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "coaching_relationships"."id", "coaching_relationships"."organization_id", "coaching_relationships"."coach_id", "coaching_relationships"."coachee_id", "coaching_relationships"."created_at", "coaching_relationships"."updated_at" FROM "refactor_platform"."coaching_relationships" WHERE "coaching_relationships"."coach_id" = $1 OR "coaching_relationships"."coachee_id" = $2"#,
+                r#"SELECT "coaching_relationships"."id", "coaching_relationships"."organization_id", "coaching_relationships"."coach_id", "coaching_relationships"."coachee_id", CAST("coaching_relationships"."status" AS text), "coaching_relationships"."start_date", "coaching_relationships"."end_date", "coaching_relationships"."reassigned_to_id", "coaching_relationships"."created_at", "coaching_relationships"."updated_at", "coaching_relationships"."deleted_at" FROM "refactor_platform"."coaching_relationships" WHERE ("coaching_relationships"."coach_id" = $1 OR "coaching_relationships"."coachee_id" = $2) AND ("coaching_relationships"."deleted_at" IS NULL AND "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."deleted_at" IS NULL))"#,
                 [user_id.into(), user_id.into()]
             )]
         );
@@ -263,7 +794,7 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "coaching_relationships"."id", "coaching_relationships"."organization_id", "coaching_relationships"."coach_id", "coaching_relationships"."coachee_id", "coaching_relationships"."created_at", "coaching_relationships"."updated_at" FROM "refactor_platform"."coaching_relationships" WHERE "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."id" = $1)"#,
+                r#"SELECT "coaching_relationships"."id", "coaching_relationships"."organization_id", "coaching_relationships"."coach_id", "coaching_relationships"."coachee_id", CAST("coaching_relationships"."status" AS text), "coaching_relationships"."start_date", "coaching_relationships"."end_date", "coaching_relationships"."reassigned_to_id", "coaching_relationships"."created_at", "coaching_relationships"."updated_at", "coaching_relationships"."deleted_at" FROM "refactor_platform"."coaching_relationships" WHERE "coaching_relationships"."deleted_at" IS NULL AND "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."deleted_at" IS NULL AND "organizations"."id" = $1)"#,
                 [organization_id.clone().into()]
             )]
         );
@@ -284,7 +815,7 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "coaching_relationships"."id", "coaching_relationships"."organization_id", "coaching_relationships"."coach_id", "coaching_relationships"."coachee_id", "coaching_relationships"."created_at", "coaching_relationships"."updated_at", coaches.first_name AS "coach_first_name", coaches.last_name AS "coach_last_name", coachees.first_name AS "coachee_first_name", coachees.last_name AS "coachee_last_name" FROM "refactor_platform"."coaching_relationships" JOIN "refactor_platform"."users" AS "coaches" ON "coaching_relationships"."coach_id" = "coaches"."id" JOIN "refactor_platform"."users" AS "coachees" ON "coaching_relationships"."coachee_id" = "coachees"."id" WHERE "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."id" = $1) AND ("coaching_relationships"."coach_id" = $2 OR "coaching_relationships"."coachee_id" = $3)"#,
+                r#"SELECT "coaching_relationships"."id", "coaching_relationships"."organization_id", "coaching_relationships"."coach_id", "coaching_relationships"."coachee_id", CAST("coaching_relationships"."status" AS text), "coaching_relationships"."start_date", "coaching_relationships"."end_date", "coaching_relationships"."reassigned_to_id", "coaching_relationships"."created_at", "coaching_relationships"."updated_at", coaches.first_name AS "coach_first_name", coaches.last_name AS "coach_last_name", coachees.first_name AS "coachee_first_name", coachees.last_name AS "coachee_last_name" FROM "refactor_platform"."coaching_relationships" JOIN "refactor_platform"."users" AS "coaches" ON "coaching_relationships"."coach_id" = "coaches"."id" JOIN "refactor_platform"."users" AS "coachees" ON "coaching_relationships"."coachee_id" = "coachees"."id" WHERE "coaching_relationships"."deleted_at" IS NULL AND "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."deleted_at" IS NULL AND "organizations"."id" = $1) AND ("coaching_relationships"."coach_id" = $2 OR "coaching_relationships"."coachee_id" = $3)"#,
                 [
                     organization_id.clone().into(),
                     user_id.clone().into(),
@@ -295,4 +826,368 @@ mod tests {
 
         Ok(())
     }
+
+    fn relationship(status: RelationshipStatus) -> Model {
+        let now = Utc::now();
+
+        Model {
+            id: Id::new_v4(),
+            organization_id: Id::new_v4(),
+            coach_id: Id::new_v4(),
+            coachee_id: Id::new_v4(),
+            status,
+            start_date: now.date_naive() - chrono::Days::new(90),
+            end_date: None,
+            reassigned_to_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn update_ends_a_relationship_as_of_today_by_default() -> Result<(), Error> {
+        let active = relationship(RelationshipStatus::Active);
+        let ended = Model {
+            status: RelationshipStatus::Ended,
+            end_date: Some(today()),
+            ..active.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![active.clone()], vec![ended.clone()]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let updated = update(
+            &db,
+            active.organization_id,
+            active.id,
+            Precondition::Any,
+            Model {
+                status: RelationshipStatus::Ended,
+                ..active.clone()
+            },
+        )
+        .await?;
+
+        assert_eq!(updated.end_date, Some(today()));
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(&format!("ChronoDate(Some({}))", today())));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_rejects_resuming_an_ended_relationship() -> Result<(), Error> {
+        let ended = relationship(RelationshipStatus::Ended);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![ended.clone()]])
+            .into_connection();
+
+        let result = update(
+            &db,
+            ended.organization_id,
+            ended.id,
+            Precondition::Any,
+            Model {
+                status: RelationshipStatus::Active,
+                ..ended.clone()
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::InvalidStatusTransition,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_rejects_ending_a_relationship_before_it_starts() -> Result<(), Error> {
+        let active = relationship(RelationshipStatus::Active);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![active.clone()]])
+            .into_connection();
+
+        let result = update(
+            &db,
+            active.organization_id,
+            active.id,
+            Precondition::Any,
+            Model {
+                status: RelationshipStatus::Ended,
+                end_date: active.start_date.pred_opt(),
+                ..active.clone()
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::ValidationFailed(ref errors),
+                ..
+            }) if errors[0].field == "end_date" && errors[0].code == "before_start_date"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reassign_starts_a_new_relationship_and_ends_the_old_one() -> Result<(), Error> {
+        let active = relationship(RelationshipStatus::Active);
+        let new_coach_id = Id::new_v4();
+
+        let successor = Model {
+            id: Id::new_v4(),
+            coach_id: new_coach_id,
+            start_date: today(),
+            ..active.clone()
+        };
+        let ended = Model {
+            status: RelationshipStatus::Ended,
+            end_date: Some(today()),
+            reassigned_to_id: Some(successor.id),
+            ..active.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![active.clone()]])
+            .append_query_results(vec![Vec::<Model>::new()])
+            .append_query_results(vec![vec![successor.clone()], vec![ended.clone()]])
+            .append_exec_results([audit::recorded(), audit::recorded()])
+            .into_connection();
+
+        let reassigned = reassign(
+            &db,
+            active.organization_id,
+            active.id,
+            Reassignment {
+                coach_id: new_coach_id,
+            },
+        )
+        .await?;
+
+        assert_eq!(reassigned.id, successor.id);
+        assert_eq!(reassigned.coachee_id, active.coachee_id);
+
+        // Creating the successor and ending the old relationship happen together or not at all
+        assert_eq!(db.into_transaction_log().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reassign_rejects_the_relationships_own_coach_or_coachee() -> Result<(), Error> {
+        let active = relationship(RelationshipStatus::Active);
+
+        for coach_id in [active.coach_id, active.coachee_id] {
+            let db = MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results(vec![vec![active.clone()]])
+                .into_connection();
+
+            let result = reassign(
+                &db,
+                active.organization_id,
+                active.id,
+                Reassignment { coach_id },
+            )
+            .await;
+
+            assert!(matches!(
+                result,
+                Err(Error {
+                    error_code: EntityApiErrorCode::ValidationFailed(ref errors),
+                    ..
+                }) if errors[0].field == "coach_id" && errors[0].code == "already_in_relationship"
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reassign_rejects_an_ended_relationship() -> Result<(), Error> {
+        let ended = relationship(RelationshipStatus::Ended);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![ended.clone()]])
+            .into_connection();
+
+        let result = reassign(
+            &db,
+            ended.organization_id,
+            ended.id,
+            Reassignment {
+                coach_id: Id::new_v4(),
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordConflict,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_rejects_a_coach_coaching_themselves() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let user_id = Id::new_v4();
+
+        let result = create(
+            &db,
+            Model {
+                coach_id: user_id,
+                coachee_id: user_id,
+                ..relationship(RelationshipStatus::Active)
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::ValidationFailed(ref errors),
+                ..
+            }) if errors[0].field == "coachee_id" && errors[0].code == "same_as_coach"
+        ));
+        assert!(db.into_transaction_log().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_rejects_a_relationship_whose_pairing_was_taken_since() -> Result<(), Error> {
+        let deleted = Model {
+            deleted_at: Some(Utc::now().into()),
+            ..relationship(RelationshipStatus::Active)
+        };
+        let successor = Model {
+            id: Id::new_v4(),
+            deleted_at: None,
+            ..deleted.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![deleted.clone()], vec![successor]])
+            .into_connection();
+
+        let result = restore(&db, deleted.organization_id, deleted.id).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordConflict,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_rejects_a_pairing_that_has_not_ended() -> Result<(), Error> {
+        let paused = relationship(RelationshipStatus::Paused);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![paused.clone()]])
+            .into_connection();
+
+        let result = create(
+            &db,
+            Model {
+                id: Id::new_v4(),
+                ..paused.clone()
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordConflict,
+                ..
+            })
+        ));
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"\"coaching_relationships\".\"status\" <> (CAST($4 AS relationship_status))"#
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reassign_rejects_a_coach_already_paired_with_the_coachee() -> Result<(), Error> {
+        let active = relationship(RelationshipStatus::Active);
+        let other = Model {
+            id: Id::new_v4(),
+            coach_id: Id::new_v4(),
+            ..active.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![active.clone()], vec![other.clone()]])
+            .into_connection();
+
+        let result = reassign(
+            &db,
+            active.organization_id,
+            active.id,
+            Reassignment {
+                coach_id: other.coach_id,
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordConflict,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_in_organization_for_member_reports_another_users_relationship_as_not_found(
+    ) -> Result<(), Error> {
+        let active = relationship(RelationshipStatus::Active);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![active.clone()]])
+            .into_connection();
+
+        let result =
+            find_in_organization_for_member(&db, active.organization_id, active.id, Id::new_v4())
+                .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
 }
@@ -1,19 +1,62 @@
 use super::error::{EntityApiErrorCode, Error};
-use crate::{naive_date_parse_str, uuid_parse_str};
+use crate::audit::{self, Within};
+use crate::coaching_relationship;
+use crate::precondition::{self, Precondition};
+use crate::{naive_date_parse_str, soft_delete, unknown_query_param, uuid_parse_str};
 use entity::coaching_sessions::{self, ActiveModel, Entity, Model};
-use log::debug;
-use sea_orm::{entity::prelude::*, DatabaseConnection, Set, TryIntoModel};
+use entity::{
+    audit_action::AuditAction, coaching_relationships, relationship_status::RelationshipStatus, Id,
+};
+use log::{debug, error};
+use sea_orm::{
+    entity::prelude::*, ActiveValue::Unchanged, Condition, ConnectionTrait, JoinType, QuerySelect,
+    Select, Set, TransactionTrait, TryIntoModel,
+};
 use std::collections::HashMap;
 
-pub async fn create(
-    db: &DatabaseConnection,
-    coaching_session_model: Model,
-) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, coaching_session_model: Model) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!(
         "New Coaching Session Model to be inserted: {:?}",
         coaching_session_model
     );
 
+    let txn = db.begin().await?;
+
+    // Holding a share lock on the relationship until the session is written keeps it from
+    // being ended between the check below and the insert
+    let relationship =
+        coaching_relationships::Entity::find_by_id(coaching_session_model.coaching_relationship_id)
+            .filter(coaching_relationship::visible())
+            .lock_shared()
+            .one(&txn)
+            .await?;
+
+    let relationship = match relationship {
+        Some(relationship) if relationship.status == RelationshipStatus::Ended => {
+            return Err(Error::conflict(
+                "Coaching Relationship",
+                relationship.id,
+                "has ended and takes no new sessions",
+            ));
+        }
+        Some(relationship) => relationship,
+        None => {
+            error!(
+                "Coaching Relationship with id {} not found",
+                coaching_session_model.coaching_relationship_id
+            );
+
+            return Err(Error {
+                inner: None,
+                error_code: EntityApiErrorCode::RecordNotFound,
+                detail: None,
+            });
+        }
+    };
+
     let now = chrono::Utc::now();
 
     let coaching_session_active_model: ActiveModel = ActiveModel {
@@ -25,39 +68,333 @@ pub async fn create(
         ..Default::default()
     };
 
-    Ok(coaching_session_active_model
-        .save(db)
+    let changed_fields = audit::changed_fields(&coaching_session_active_model);
+
+    let coaching_session = coaching_session_active_model
+        .save(&txn)
+        .await?
+        .try_into_model()?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        coaching_session.id,
+        changed_fields,
+        Within::Organization(relationship.organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(coaching_session)
+}
+
+/// Changes to some of a Coaching Session's fields. A field left as `None` keeps its current
+/// value.
+#[derive(Debug, Default)]
+pub struct Patch {
+    pub date: Option<DateTime>,
+    pub timezone: Option<String>,
+}
+
+/// Changes only the fields of a Coaching Session that `changes` names, for a `user_id` who
+/// takes part in it.
+pub async fn patch<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    changes: Patch,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let (coaching_session, relationship) =
+        find_with_relationship_for_member(db, id, user_id).await?;
+    debug!(
+        "Existing Coaching Session model to be Updated: {:?}",
+        coaching_session
+    );
+
+    precondition.check("Coaching Session", id, &coaching_session.updated_at)?;
+
+    let mut active_model: ActiveModel = ActiveModel {
+        id: Unchanged(coaching_session.id),
+        updated_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    };
+
+    if let Some(date) = changes.date {
+        active_model.date = Set(date);
+    }
+    if let Some(timezone) = changes.timezone {
+        active_model.timezone = Set(timezone);
+    }
+
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    let updated_coaching_session = precondition::update_unchanged(
+        &txn,
+        "Coaching Session",
+        id,
+        active_model,
+        coaching_sessions::Column::UpdatedAt,
+        coaching_session.updated_at,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Update,
+        id,
+        changed_fields,
+        Within::Organization(relationship.organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(updated_coaching_session)
+}
+
+/// Deletes a Coaching Session that `user_id` takes part in, along with its notes, agreements,
+/// actions, overarching goals and attachments, hiding them until the session is restored or
+/// purged after the retention period.
+pub async fn delete_by_id<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    user_id: Id,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let (coaching_session, relationship) =
+        find_with_relationship_for_member(db, id, user_id).await?;
+    debug!(
+        "Existing Coaching Session model to be deleted: {:?}",
+        coaching_session
+    );
+
+    precondition.check("Coaching Session", id, &coaching_session.updated_at)?;
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(now)),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    precondition::update_unchanged(
+        &txn,
+        "Coaching Session",
+        id,
+        active_model,
+        coaching_sessions::Column::UpdatedAt,
+        coaching_session.updated_at,
+    )
+    .await?;
+
+    soft_delete::delete_sessions(&txn, coaching_sessions::Column::Id.eq(id), now).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::Organization(relationship.organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Restores a deleted Coaching Session that hasn't been purged yet, for a `user_id` who takes
+/// part in it, along with the content that was deleted with it.
+pub async fn restore<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+
+    let (coaching_session, relationship) = match soft_delete::find_deleted_by_id::<Entity>(id)
+        .find_also_related(coaching_relationships::Entity)
+        .filter(coaching_relationship::visible())
+        .one(&txn)
+        .await?
+    {
+        Some((coaching_session, Some(relationship)))
+            if relationship.coach_id == user_id || relationship.coachee_id == user_id =>
+        {
+            (coaching_session, relationship)
+        }
+        _ => return Err(Error::not_found("Coaching Session", id)),
+    };
+    debug!(
+        "Deleted Coaching Session model to be restored: {:?}",
+        coaching_session
+    );
+
+    let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+    let restored_coaching_session = active_model.update(&txn).await?;
+
+    // Content deleted on its own before the Coaching Session was stays deleted
+    if let Some(deleted_at) = coaching_session.deleted_at {
+        soft_delete::restore_sessions(&txn, coaching_sessions::Column::Id.eq(id), deleted_at, now)
+            .await?;
+    }
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::Organization(relationship.organization_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(restored_coaching_session)
+}
+
+/// Finds a coaching session along with the coaching relationship it belongs to.
+pub(crate) async fn find_with_relationship<C>(
+    db: &C,
+    id: Id,
+) -> Result<(Model, coaching_relationships::Model), Error>
+where
+    C: ConnectionTrait,
+{
+    match Entity::find_by_id(id)
+        .find_also_related(coaching_relationships::Entity)
+        .filter(visible())
+        .one(db)
         .await?
-        .try_into_model()?)
+    {
+        Some((coaching_session, Some(relationship))) => Ok((coaching_session, relationship)),
+        _ => Err(Error::not_found("Coaching Session", id)),
+    }
 }
 
-pub async fn find_by(
-    db: &DatabaseConnection,
-    params: HashMap<String, String>,
-) -> Result<Vec<Model>, Error> {
-    let mut query = Entity::find();
+/// Finds a coaching session that `user_id` takes part in, as either the coach or the coachee
+/// of its coaching relationship. Any other user is told it was not found.
+pub async fn find_by_id_for_member<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let (coaching_session, _) = find_with_relationship_for_member(db, id, user_id).await?;
+
+    Ok(coaching_session)
+}
+
+/// Like `find_by_id_for_member`, also returning the coaching relationship the session
+/// belongs to.
+pub(crate) async fn find_with_relationship_for_member<C>(
+    db: &C,
+    id: Id,
+    user_id: Id,
+) -> Result<(Model, coaching_relationships::Model), Error>
+where
+    C: ConnectionTrait,
+{
+    let (coaching_session, relationship) = find_with_relationship(db, id).await?;
+
+    if relationship.coach_id == user_id || relationship.coachee_id == user_id {
+        Ok((coaching_session, relationship))
+    } else {
+        debug!(
+            "User {} is not a member of the relationship of Coaching Session {}",
+            user_id, id
+        );
+
+        Err(Error::not_found("Coaching Session", id))
+    }
+}
+
+/// Narrows a query of records belonging to coaching sessions to those of one relationship
+/// whose sessions haven't been deleted.
+pub(crate) fn in_relationship<E>(query: Select<E>, relationship_id: Id) -> Select<E>
+where
+    E: EntityTrait + Related<Entity>,
+{
+    query
+        .join(JoinType::InnerJoin, E::to())
+        .filter(coaching_sessions::Column::CoachingRelationshipId.eq(relationship_id))
+        .filter(coaching_sessions::Column::DeletedAt.is_null())
+}
+
+/// Narrows a query of records belonging to coaching sessions to those that `user_id` takes part
+/// in, as either the coach or the coachee of the session's coaching relationship.
+pub(crate) fn with_member<E>(query: Select<E>, user_id: Id) -> Select<E>
+where
+    E: EntityTrait + Related<Entity>,
+{
+    query
+        .join(JoinType::InnerJoin, E::to())
+        .join(
+            JoinType::InnerJoin,
+            coaching_sessions::Relation::CoachingRelationships.def(),
+        )
+        .filter(
+            Condition::any()
+                .add(coaching_relationships::Column::CoachId.eq(user_id))
+                .add(coaching_relationships::Column::CoacheeId.eq(user_id)),
+        )
+        .filter(visible())
+}
+
+/// Keeps only coaching sessions that haven't been deleted and whose coaching relationship is
+/// visible, in a query joined with the relationship.
+pub(crate) fn visible() -> Condition {
+    Condition::all()
+        .add(coaching_sessions::Column::DeletedAt.is_null())
+        .add(coaching_relationship::visible())
+}
+
+pub async fn find_by<C>(db: &C, params: HashMap<String, String>) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = Entity::find()
+        .inner_join(coaching_relationships::Entity)
+        .filter(visible());
 
     for (key, value) in params {
         match key.as_str() {
             "coaching_relationship_id" => {
-                let coaching_relationship_id = uuid_parse_str(&value)?;
+                let coaching_relationship_id = uuid_parse_str(&key, &value)?;
                 query = query.filter(
                     coaching_sessions::Column::CoachingRelationshipId.eq(coaching_relationship_id),
                 )
             }
             "from_date" => {
-                let from_date = naive_date_parse_str(&value)?;
+                let from_date = naive_date_parse_str(&key, &value)?;
                 query = query.filter(coaching_sessions::Column::Date.gt(from_date));
             }
             "to_date" => {
-                let to_date = naive_date_parse_str(&value)?;
+                let to_date = naive_date_parse_str(&key, &value)?;
                 query = query.filter(coaching_sessions::Column::Date.lt(to_date));
             }
             _ => {
-                return Err(Error {
-                    inner: None,
-                    error_code: EntityApiErrorCode::InvalidQueryTerm,
-                });
+                return Err(unknown_query_param(&key));
             }
         }
     }
@@ -74,7 +411,25 @@ mod tests {
     use super::*;
     use chrono::NaiveDate;
     use entity::Id;
-    use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
+
+    fn relationship(id: Id, status: RelationshipStatus) -> coaching_relationships::Model {
+        let now = chrono::Utc::now();
+
+        coaching_relationships::Model {
+            id,
+            organization_id: Id::new_v4(),
+            coach_id: Id::new_v4(),
+            coachee_id: Id::new_v4(),
+            status,
+            start_date: now.date_naive(),
+            end_date: None,
+            reassigned_to_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Transaction};
 
     #[tokio::test]
     async fn create_returns_a_new_coaching_session_model() -> Result<(), Error> {
@@ -87,10 +442,16 @@ mod tests {
             timezone: "Americas/Chicago".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![relationship(
+                coaching_session_model.coaching_relationship_id,
+                RelationshipStatus::Active,
+            )]])
             .append_query_results(vec![vec![coaching_session_model.clone()]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
         let coaching_session = create(&db, coaching_session_model.clone().into()).await?;
@@ -100,6 +461,41 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn create_rejects_sessions_for_an_ended_relationship() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let coaching_session_model = Model {
+            id: Id::new_v4(),
+            coaching_relationship_id: Id::new_v4(),
+            date: chrono::Local::now().naive_utc(),
+            timezone: "Americas/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![relationship(
+                coaching_session_model.coaching_relationship_id,
+                RelationshipStatus::Ended,
+            )]])
+            .into_connection();
+
+        let result = create(&db, coaching_session_model).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordConflict,
+                ..
+            })
+        ));
+        assert!(format!("{:?}", db.into_transaction_log()).contains("FOR SHARE"));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn find_by_coaching_relationships_returns_all_records_associated_with_coaching_relationship(
     ) -> Result<(), Error> {
@@ -118,7 +514,7 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "coaching_sessions"."id", "coaching_sessions"."coaching_relationship_id", "coaching_sessions"."date", "coaching_sessions"."timezone", "coaching_sessions"."created_at", "coaching_sessions"."updated_at" FROM "refactor_platform"."coaching_sessions" WHERE "coaching_sessions"."coaching_relationship_id" = $1"#,
+                r#"SELECT "coaching_sessions"."id", "coaching_sessions"."coaching_relationship_id", "coaching_sessions"."date", "coaching_sessions"."timezone", "coaching_sessions"."created_at", "coaching_sessions"."updated_at", "coaching_sessions"."deleted_at" FROM "refactor_platform"."coaching_sessions" INNER JOIN "refactor_platform"."coaching_relationships" ON "coaching_sessions"."coaching_relationship_id" = "coaching_relationships"."id" WHERE "coaching_sessions"."deleted_at" IS NULL AND ("coaching_relationships"."deleted_at" IS NULL AND "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."deleted_at" IS NULL)) AND "coaching_sessions"."coaching_relationship_id" = $1"#,
                 [coaching_relationship_id.into()]
             )]
         );
@@ -140,7 +536,7 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "coaching_sessions"."id", "coaching_sessions"."coaching_relationship_id", "coaching_sessions"."date", "coaching_sessions"."timezone", "coaching_sessions"."created_at", "coaching_sessions"."updated_at" FROM "refactor_platform"."coaching_sessions" WHERE "coaching_sessions"."date" > $1"#,
+                r#"SELECT "coaching_sessions"."id", "coaching_sessions"."coaching_relationship_id", "coaching_sessions"."date", "coaching_sessions"."timezone", "coaching_sessions"."created_at", "coaching_sessions"."updated_at", "coaching_sessions"."deleted_at" FROM "refactor_platform"."coaching_sessions" INNER JOIN "refactor_platform"."coaching_relationships" ON "coaching_sessions"."coaching_relationship_id" = "coaching_relationships"."id" WHERE "coaching_sessions"."deleted_at" IS NULL AND ("coaching_relationships"."deleted_at" IS NULL AND "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."deleted_at" IS NULL)) AND "coaching_sessions"."date" > $1"#,
                 [from_date.into()]
             )]
         );
@@ -162,11 +558,122 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "coaching_sessions"."id", "coaching_sessions"."coaching_relationship_id", "coaching_sessions"."date", "coaching_sessions"."timezone", "coaching_sessions"."created_at", "coaching_sessions"."updated_at" FROM "refactor_platform"."coaching_sessions" WHERE "coaching_sessions"."date" < $1"#,
+                r#"SELECT "coaching_sessions"."id", "coaching_sessions"."coaching_relationship_id", "coaching_sessions"."date", "coaching_sessions"."timezone", "coaching_sessions"."created_at", "coaching_sessions"."updated_at", "coaching_sessions"."deleted_at" FROM "refactor_platform"."coaching_sessions" INNER JOIN "refactor_platform"."coaching_relationships" ON "coaching_sessions"."coaching_relationship_id" = "coaching_relationships"."id" WHERE "coaching_sessions"."deleted_at" IS NULL AND ("coaching_relationships"."deleted_at" IS NULL AND "coaching_relationships"."organization_id" IN (SELECT "organizations"."id" FROM "refactor_platform"."organizations" WHERE "organizations"."deleted_at" IS NULL)) AND "coaching_sessions"."date" < $1"#,
                 [to_date.into()]
             )]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn delete_by_id_deletes_the_sessions_content_with_it() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let relationship = relationship(Id::new_v4(), RelationshipStatus::Active);
+        let coaching_session = Model {
+            id: Id::new_v4(),
+            coaching_relationship_id: relationship.id,
+            date: now.naive_utc(),
+            timezone: "America/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let deleted = Model {
+            deleted_at: Some(now.into()),
+            ..coaching_session.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(coaching_session.clone(), relationship.clone())]])
+            .append_query_results(vec![vec![deleted]])
+            // Notes, agreements, actions, overarching goals, attachments and then the session
+            .append_exec_results((0..6).map(|_| MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }))
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        delete_by_id(
+            &db,
+            coaching_session.id,
+            Precondition::Any,
+            relationship.coachee_id,
+        )
+        .await?;
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"notes\" SET \"deleted_at\" = $1, \"updated_at\" = $2 WHERE \"notes\".\"deleted_at\" IS NULL AND \"notes\".\"coaching_session_id\" IN (SELECT \"coaching_sessions\".\"id\" FROM \"refactor_platform\".\"coaching_sessions\" WHERE \"coaching_sessions\".\"id\" = $3)"#
+        ));
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"attachments\" SET \"deleted_at\" = $1 WHERE"#
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_reports_another_relationships_session_as_not_found() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let relationship = relationship(Id::new_v4(), RelationshipStatus::Active);
+        let coaching_session = Model {
+            id: Id::new_v4(),
+            coaching_relationship_id: relationship.id,
+            date: now.naive_utc(),
+            timezone: "America/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: Some(now.into()),
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(coaching_session.clone(), relationship)]])
+            .into_connection();
+
+        let result = restore(&db, coaching_session.id, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_id_for_member_reports_another_relationships_session_as_not_found(
+    ) -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let relationship = relationship(Id::new_v4(), RelationshipStatus::Active);
+        let coaching_session = Model {
+            id: Id::new_v4(),
+            coaching_relationship_id: relationship.id,
+            date: now.naive_utc(),
+            timezone: "America/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(coaching_session.clone(), relationship)]])
+            .into_connection();
+
+        let result = find_by_id_for_member(&db, coaching_session.id, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
 }
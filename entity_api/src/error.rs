@@ -2,8 +2,13 @@ use std::error::Error as StdError;
 use std::fmt;
 
 use serde::Serialize;
+use utoipa::ToSchema;
 
+use entity::{status::UnknownStatus, Id};
 use sea_orm::error::DbErr;
+use service::storage::Error as StorageError;
+
+use log::*;
 
 /// Errors while executing operations related to entities.
 /// The intent is to categorize errors into two major types:
@@ -15,22 +20,107 @@ pub struct Error {
     pub inner: Option<DbErr>,
     // Enum representing which category of error
     pub error_code: EntityApiErrorCode,
+    // A human-readable explanation naming the record involved, when the error is about one
+    pub detail: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub enum EntityApiErrorCode {
     // Invalid search term
     InvalidQueryTerm,
-    // Record not found
+    // Record does not exist, or exists but the authenticated user may not see it
     RecordNotFound,
     // Record not updated
     RecordNotUpdated,
     // Record not authenticated
     RecordUnauthenticated,
+    // Record is visible to the authenticated user, who may not perform the operation on it
+    RecordUnauthorized,
+    // Uploaded content is larger than allowed
+    PayloadTooLarge,
+    // Uploaded content is of a type that is not allowed
+    UnsupportedMediaType,
+    // Status change not permitted from the record's current status
+    InvalidStatusTransition,
+    // Operation conflicts with the current state of the record or of related records
+    RecordConflict,
+    // Record has changed since the version the operation was requested against
+    PreconditionFailed,
+    // One or more fields of the request, such as query parameters, are invalid
+    ValidationFailed(Vec<FieldError>),
     // Errors related to interactions with the database itself. Ex DbError::Conn
     SystemError,
 }
 
+/// What is wrong with one field of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[schema(as = entity_api::error::FieldError)] // OpenAPI schema
+pub struct FieldError {
+    /// The name of the field or query parameter, with nested fields separated by `.`
+    pub field: String,
+    /// A stable, machine-readable code such as `invalid_uuid`, `invalid_date` or `missing`
+    pub code: String,
+    /// A human-readable description of the problem
+    pub message: String,
+}
+
+/// Lookups of a single record return the record itself rather than an `Option`. A record that
+/// does not exist and one that the user may not see are both `RecordNotFound`, so that the
+/// response doesn't reveal whether another user's record exists. `RecordUnauthorized` is kept
+/// for a record the user can see but may not act on, such as an attachment another member of
+/// the coaching session uploaded.
+impl Error {
+    /// The error for a `kind` of record with `id` that does not exist or may not be seen.
+    pub fn not_found(kind: &str, id: Id) -> Self {
+        debug!("{} with id {} not found", kind, id);
+
+        Error {
+            inner: None,
+            error_code: EntityApiErrorCode::RecordNotFound,
+            detail: Some(format!("{kind} {id} was not found.")),
+        }
+    }
+
+    /// The error for a change to a `kind` of record with `id` that is no longer at the
+    /// version the change was requested against.
+    pub fn precondition_failed(kind: &str, id: Id) -> Self {
+        debug!("{} with id {} is not at the requested version", kind, id);
+
+        Error {
+            inner: None,
+            error_code: EntityApiErrorCode::PreconditionFailed,
+            detail: Some(format!(
+                "{kind} {id} has changed since the version named by `If-Match`."
+            )),
+        }
+    }
+
+    /// The error for a change that conflicts with the current state of the `kind` of record
+    /// with `id`, for the `reason` given, such as "has ended and cannot be reassigned".
+    pub fn conflict(kind: &str, id: Id, reason: &str) -> Self {
+        debug!("{} with id {} {}", kind, id, reason);
+
+        Error {
+            inner: None,
+            error_code: EntityApiErrorCode::RecordConflict,
+            detail: Some(format!("{kind} {id} {reason}.")),
+        }
+    }
+
+    /// A validation error for a single invalid `field`.
+    pub fn invalid_field(field: &str, code: &str, message: &str) -> Self {
+        Error {
+            inner: None,
+            error_code: EntityApiErrorCode::ValidationFailed(vec![FieldError {
+                field: field.to_owned(),
+                code: code.to_owned(),
+                message: message.to_owned(),
+            }]),
+            detail: Some(format!("{field} {message}.")),
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Entity API Error: {:?}", self)
@@ -45,26 +135,59 @@ impl From<DbErr> for Error {
             DbErr::RecordNotFound(_) => Error {
                 inner: Some(err),
                 error_code: EntityApiErrorCode::RecordNotFound,
+                detail: None,
             },
             DbErr::RecordNotUpdated => Error {
                 inner: Some(err),
                 error_code: EntityApiErrorCode::RecordNotUpdated,
+                detail: None,
             },
             DbErr::ConnectionAcquire(_) => Error {
                 inner: Some(err),
                 error_code: EntityApiErrorCode::SystemError,
+                detail: None,
             },
             DbErr::Conn(_) => Error {
                 inner: Some(err),
                 error_code: EntityApiErrorCode::SystemError,
+                detail: None,
             },
             DbErr::Exec(_) => Error {
                 inner: Some(err),
                 error_code: EntityApiErrorCode::SystemError,
+                detail: None,
             },
             _ => Error {
                 inner: Some(err),
                 error_code: EntityApiErrorCode::SystemError,
+                detail: None,
+            },
+        }
+    }
+}
+
+impl From<UnknownStatus> for Error {
+    fn from(_err: UnknownStatus) -> Self {
+        Error {
+            inner: None,
+            error_code: EntityApiErrorCode::InvalidQueryTerm,
+            detail: None,
+        }
+    }
+}
+
+impl From<StorageError> for Error {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::NotFound(_) => Error {
+                inner: None,
+                error_code: EntityApiErrorCode::RecordNotFound,
+                detail: None,
+            },
+            _ => Error {
+                inner: Some(DbErr::Custom(err.to_string())),
+                error_code: EntityApiErrorCode::SystemError,
+                detail: None,
             },
         }
     }
@@ -0,0 +1,336 @@
+//! Exports of a coaching relationship's full history as a ZIP archive, so that a coachee can
+//! take it with them once the engagement ends. Each kind of record is written as both a JSON
+//! array and a CSV file.
+//!
+//! Records are streamed from the database straight into the archive one at a time and the
+//! archive is written to any `AsyncWrite`, so an export never holds a whole history in memory.
+
+use super::error::{EntityApiErrorCode, Error};
+use crate::{coaching_session::in_relationship, note, soft_delete};
+use async_zip::{tokio::write::ZipFileWriter, Compression, ZipDateTime, ZipEntryBuilder};
+use chrono::Utc;
+use entity::{
+    actions, agreements, coaching_relationships, coaching_sessions, notes, overarching_goals, Id,
+};
+use futures::io::AsyncWriteExt;
+use futures::TryStreamExt;
+use sea_orm::{entity::prelude::*, ConnectionTrait, QueryOrder, Select, StreamTrait};
+use serde::Serialize;
+use std::fmt::Display;
+use tokio::io::AsyncWrite;
+
+use log::*;
+
+/// The file name to offer an export of `relationship` for download under.
+pub fn archive_filename(relationship: &coaching_relationships::Model) -> String {
+    format!("coaching-relationship-{}.zip", relationship.id)
+}
+
+/// Writes a ZIP archive of everything in `relationship` that `user_id` can see to `writer`:
+/// the relationship itself along with its coaching sessions and their notes, agreements,
+/// actions and overarching goals. Other users' private notes are left out.
+pub async fn write_archive<C, W>(
+    db: &C,
+    relationship: &coaching_relationships::Model,
+    user_id: Id,
+    writer: W,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait + StreamTrait,
+    W: AsyncWrite + Unpin,
+{
+    debug!(
+        "Exporting Coaching Relationship {} for user {}",
+        relationship.id, user_id
+    );
+
+    let mut archive = Archive {
+        zip: ZipFileWriter::with_tokio(writer),
+        modified: ZipDateTime::from_chrono(&Utc::now()),
+    };
+
+    let relationship_json = serde_json::to_vec_pretty(relationship).map_err(archive_error)?;
+    archive
+        .zip
+        .write_entry_whole(archive.entry("relationship.json"), &relationship_json)
+        .await
+        .map_err(archive_error)?;
+
+    archive
+        .write_records(
+            db,
+            "coaching_sessions",
+            soft_delete::find::<coaching_sessions::Entity>()
+                .filter(coaching_sessions::Column::CoachingRelationshipId.eq(relationship.id))
+                .order_by_asc(coaching_sessions::Column::Date),
+        )
+        .await?;
+    archive
+        .write_records(
+            db,
+            "notes",
+            in_relationship(soft_delete::find::<notes::Entity>(), relationship.id)
+                .filter(note::visible_to(user_id))
+                .order_by_asc(notes::Column::CreatedAt),
+        )
+        .await?;
+    archive
+        .write_records(
+            db,
+            "agreements",
+            in_relationship(soft_delete::find::<agreements::Entity>(), relationship.id)
+                .order_by_asc(agreements::Column::CreatedAt),
+        )
+        .await?;
+    archive
+        .write_records(
+            db,
+            "actions",
+            in_relationship(soft_delete::find::<actions::Entity>(), relationship.id)
+                .order_by_asc(actions::Column::CreatedAt),
+        )
+        .await?;
+    archive
+        .write_records(
+            db,
+            "overarching_goals",
+            in_relationship(
+                soft_delete::find::<overarching_goals::Entity>(),
+                relationship.id,
+            )
+            .order_by_asc(overarching_goals::Column::CreatedAt),
+        )
+        .await?;
+
+    archive.zip.close().await.map_err(archive_error)?;
+
+    Ok(())
+}
+
+struct Archive<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    zip: ZipFileWriter<W>,
+    modified: ZipDateTime,
+}
+
+impl<W> Archive<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn entry(&self, filename: &str) -> ZipEntryBuilder {
+        ZipEntryBuilder::new(filename.to_owned().into(), Compression::Deflate)
+            .last_modification_date(self.modified)
+    }
+
+    // Writes `{name}.json` and `{name}.csv`, each from its own pass over the query's results
+    async fn write_records<C, E>(
+        &mut self,
+        db: &C,
+        name: &str,
+        query: Select<E>,
+    ) -> Result<(), Error>
+    where
+        C: ConnectionTrait + StreamTrait,
+        E: EntityTrait,
+        E::Model: Serialize + Send + Sync,
+    {
+        let json_entry = self.entry(&format!("{name}.json"));
+        let mut json = self
+            .zip
+            .write_entry_stream(json_entry)
+            .await
+            .map_err(archive_error)?;
+        let mut records = query.clone().stream(db).await?;
+        let mut count = 0;
+
+        json.write_all(b"[").await.map_err(archive_error)?;
+        while let Some(record) = records.try_next().await? {
+            let separator: &[u8] = if count == 0 { b"\n" } else { b",\n" };
+            json.write_all(separator).await.map_err(archive_error)?;
+            json.write_all(&serde_json::to_vec(&record).map_err(archive_error)?)
+                .await
+                .map_err(archive_error)?;
+            count += 1;
+        }
+        json.write_all(b"\n]\n").await.map_err(archive_error)?;
+        json.close().await.map_err(archive_error)?;
+        drop(records);
+
+        let csv_entry = self.entry(&format!("{name}.csv"));
+        let mut csv = self
+            .zip
+            .write_entry_stream(csv_entry)
+            .await
+            .map_err(archive_error)?;
+        let mut records = query.stream(db).await?;
+        let mut has_header = false;
+
+        while let Some(record) = records.try_next().await? {
+            csv.write_all(&csv_row(&record, !has_header)?)
+                .await
+                .map_err(archive_error)?;
+            has_header = true;
+        }
+        csv.close().await.map_err(archive_error)?;
+
+        debug!("Exported {} {}", count, name);
+
+        Ok(())
+    }
+}
+
+// A single CSV record, preceded by a header row of the record's field names if requested
+fn csv_row<T: Serialize>(record: &T, with_header: bool) -> Result<Vec<u8>, Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(with_header)
+        .from_writer(Vec::new());
+
+    writer.serialize(record).map_err(archive_error)?;
+    writer.into_inner().map_err(archive_error)
+}
+
+fn archive_error(err: impl Display) -> Error {
+    error!("Failed to write export archive: {}", err);
+
+    Error {
+        inner: Some(DbErr::Custom(err.to_string())),
+        error_code: EntityApiErrorCode::SystemError,
+        detail: None,
+    }
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use async_zip::base::read::mem::ZipFileReader;
+    use entity::{relationship_status::RelationshipStatus, status::Status, visibility::Visibility};
+    use sea_orm::{DatabaseBackend, MockDatabase};
+
+    async fn read_entries(archive: Vec<u8>) -> Vec<(String, String)> {
+        let reader = ZipFileReader::new(archive).await.unwrap();
+        let mut entries = Vec::new();
+
+        for index in 0..reader.file().entries().len() {
+            let filename = reader.file().entries()[index]
+                .filename()
+                .as_str()
+                .unwrap()
+                .to_owned();
+            let mut contents = String::new();
+            reader
+                .reader_with_entry(index)
+                .await
+                .unwrap()
+                .read_to_string_checked(&mut contents)
+                .await
+                .unwrap();
+            entries.push((filename, contents));
+        }
+
+        entries
+    }
+
+    #[tokio::test]
+    async fn write_archive_bundles_each_kind_of_record_as_json_and_csv() -> Result<(), Error> {
+        let now = Utc::now();
+        let relationship = coaching_relationships::Model {
+            id: Id::new_v4(),
+            organization_id: Id::new_v4(),
+            coach_id: Id::new_v4(),
+            coachee_id: Id::new_v4(),
+            status: RelationshipStatus::Ended,
+            start_date: now.date_naive(),
+            end_date: Some(now.date_naive()),
+            reassigned_to_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let session = coaching_sessions::Model {
+            id: Id::new_v4(),
+            coaching_relationship_id: relationship.id,
+            date: now.naive_utc(),
+            timezone: "America/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let note = notes::Model {
+            id: Id::new_v4(),
+            coaching_session_id: session.id,
+            body: Some("Talked about \"delegation\", again".to_owned()),
+            user_id: relationship.coachee_id,
+            visibility: Visibility::Shared,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let action = actions::Model {
+            id: Id::new_v4(),
+            coaching_session_id: session.id,
+            user_id: relationship.coachee_id,
+            body: Some("Hand off the release".to_owned()),
+            due_by: None,
+            status: Status::Completed,
+            status_changed_at: now.into(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![session.clone()], vec![session.clone()]])
+            .append_query_results(vec![vec![note.clone()], vec![note.clone()]])
+            .append_query_results(vec![
+                Vec::<agreements::Model>::new(),
+                Vec::<agreements::Model>::new(),
+            ])
+            .append_query_results(vec![vec![action.clone()], vec![action.clone()]])
+            .append_query_results(vec![
+                Vec::<overarching_goals::Model>::new(),
+                Vec::<overarching_goals::Model>::new(),
+            ])
+            .into_connection();
+
+        let mut archive = Vec::new();
+        write_archive(&db, &relationship, relationship.coachee_id, &mut archive).await?;
+
+        let entries = read_entries(archive).await;
+        let filenames: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            filenames,
+            [
+                "relationship.json",
+                "coaching_sessions.json",
+                "coaching_sessions.csv",
+                "notes.json",
+                "notes.csv",
+                "agreements.json",
+                "agreements.csv",
+                "actions.json",
+                "actions.csv",
+                "overarching_goals.json",
+                "overarching_goals.csv",
+            ]
+        );
+
+        let notes_json: serde_json::Value = serde_json::from_str(&entries[3].1).unwrap();
+        assert_eq!(notes_json, serde_json::json!([note]));
+
+        let notes_csv = &entries[4].1;
+        assert!(notes_csv.starts_with("id,coaching_session_id,body,user_id,visibility"));
+        assert!(notes_csv.contains(r#""Talked about ""delegation"", again""#));
+
+        assert_eq!(entries[5].1, "[\n]\n");
+        assert_eq!(entries[6].1, "");
+        assert!(entries[8].1.contains(",Completed,"));
+
+        Ok(())
+    }
+}
@@ -6,28 +6,53 @@ use entity::{coaching_relationships, coaching_sessions, organizations, users, Id
 
 pub mod action;
 pub mod agreement;
+pub mod analytics;
+pub mod attachment;
+pub mod audit;
+pub mod caseload;
 pub mod coaching_relationship;
 pub mod coaching_session;
 pub mod error;
+pub mod export;
 pub mod note;
 pub mod organization;
 pub mod overarching_goal;
+pub mod precondition;
+pub mod revision;
+pub mod search;
+pub mod session_summary;
+pub mod soft_delete;
+pub mod status_transition;
+pub mod unit_of_work;
 pub mod user;
 
-pub(crate) fn uuid_parse_str(uuid_str: &str) -> Result<Id, error::Error> {
-    Id::parse_str(uuid_str).map_err(|_| error::Error {
-        inner: None,
-        error_code: error::EntityApiErrorCode::InvalidQueryTerm,
-    })
+pub(crate) fn uuid_parse_str(field: &str, uuid_str: &str) -> Result<Id, error::Error> {
+    Id::parse_str(uuid_str)
+        .map_err(|_| error::Error::invalid_field(field, "invalid_uuid", "must be a UUID"))
 }
 
-pub(crate) fn naive_date_parse_str(date_str: &str) -> Result<chrono::NaiveDate, error::Error> {
-    chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_| error::Error {
-        inner: None,
-        error_code: error::EntityApiErrorCode::InvalidQueryTerm,
+pub(crate) fn naive_date_parse_str(
+    field: &str,
+    date_str: &str,
+) -> Result<chrono::NaiveDate, error::Error> {
+    chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_| {
+        error::Error::invalid_field(
+            field,
+            "invalid_date",
+            "must be a date formatted as YYYY-MM-DD",
+        )
     })
 }
 
+/// The error for a query parameter that an endpoint does not take.
+pub(crate) fn unknown_query_param(field: &str) -> error::Error {
+    error::Error::invalid_field(
+        field,
+        "unknown_parameter",
+        "is not a supported query parameter",
+    )
+}
+
 pub async fn seed_database(db: &DatabaseConnection) {
     let now = Utc::now();
 
@@ -259,28 +284,40 @@ mod tests {
     #[tokio::test]
     async fn uuid_parse_str_parses_valid_uuid() {
         let uuid_str = "a98c3295-0933-44cb-89db-7db0f7250fb1";
-        let uuid = uuid_parse_str(uuid_str).unwrap();
+        let uuid = uuid_parse_str("id", uuid_str).unwrap();
         assert_eq!(uuid.to_string(), uuid_str);
     }
 
     #[tokio::test]
     async fn uuid_parse_str_returns_error_for_invalid_uuid() {
         let uuid_str = "invalid";
-        let result = uuid_parse_str(uuid_str);
-        assert!(result.is_err());
+        let result = uuid_parse_str("coaching_session_id", uuid_str);
+        assert!(matches!(
+            result,
+            Err(error::Error {
+                error_code: error::EntityApiErrorCode::ValidationFailed(fields),
+                ..
+            }) if fields[0].field == "coaching_session_id" && fields[0].code == "invalid_uuid"
+        ));
     }
 
     #[tokio::test]
     async fn naive_date_parse_str_parses_valid_date() {
         let date_str = "2021-08-01";
-        let date = naive_date_parse_str(date_str).unwrap();
+        let date = naive_date_parse_str("from_date", date_str).unwrap();
         assert_eq!(date.to_string(), date_str);
     }
 
     #[tokio::test]
     async fn naive_date_parse_str_returns_error_for_invalid_date() {
         let date_str = "invalid";
-        let result = naive_date_parse_str(date_str);
-        assert!(result.is_err());
+        let result = naive_date_parse_str("from_date", date_str);
+        assert!(matches!(
+            result,
+            Err(error::Error {
+                error_code: error::EntityApiErrorCode::ValidationFailed(fields),
+                ..
+            }) if fields[0].field == "from_date" && fields[0].code == "invalid_date"
+        ));
     }
 }
@@ -1,21 +1,24 @@
-use super::error::{EntityApiErrorCode, Error};
-use crate::uuid_parse_str;
+use super::error::Error;
+use crate::audit::{self, Within};
+use crate::coaching_session::with_member;
+use crate::precondition::{self, Precondition};
+use crate::revision::{self, Subject};
+use crate::{soft_delete, unknown_query_param, uuid_parse_str};
 use entity::notes::{self, ActiveModel, Entity, Model};
-use entity::Id;
+use entity::{audit_action::AuditAction, visibility::Visibility, Id};
 use sea_orm::{
     entity::prelude::*,
     ActiveValue::{Set, Unchanged},
-    DatabaseConnection, TryIntoModel,
+    Condition, ConnectionTrait, TransactionTrait, TryIntoModel,
 };
 use std::collections::HashMap;
 
 use log::*;
 
-pub async fn create(
-    db: &DatabaseConnection,
-    note_model: Model,
-    user_id: Id,
-) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, note_model: Model, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!("New Note Model to be inserted: {:?}", note_model);
 
     let now = chrono::Utc::now();
@@ -24,86 +27,338 @@ pub async fn create(
         coaching_session_id: Set(note_model.coaching_session_id),
         body: Set(note_model.body),
         user_id: Set(user_id),
+        visibility: Set(note_model.visibility),
         created_at: Set(now.into()),
         updated_at: Set(now.into()),
         ..Default::default()
     };
 
-    Ok(note_active_model.save(db).await?.try_into_model()?)
+    let changed_fields = audit::changed_fields(&note_active_model);
+
+    let txn = db.begin().await?;
+    let note = note_active_model.save(&txn).await?.try_into_model()?;
+
+    revision::create(&txn, Subject::Note(note.id), note.body.clone(), user_id).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        note.id,
+        changed_fields,
+        Within::CoachingSession(note.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(note)
+}
+
+/// Updates a Note that `user_id` can see. Only the Note's author may change its visibility;
+/// a visibility sent by anyone else is ignored.
+/// Changes to some of a Note's fields. A field left as `None` keeps its current value, while
+/// `Some(None)` clears the body.
+#[derive(Debug, Default)]
+pub struct Patch {
+    pub body: Option<Option<String>>,
+    pub visibility: Option<Visibility>,
+}
+
+pub async fn update<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    model: Model,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let changes = Patch {
+        body: Some(model.body),
+        visibility: Some(model.visibility),
+    };
+
+    patch(db, id, precondition, changes, user_id).await
 }
 
-pub async fn update(db: &DatabaseConnection, id: Id, model: Model) -> Result<Model, Error> {
-    let result = Entity::find_by_id(id).one(db).await?;
+/// Changes only the fields of a Note that `changes` names. Only the Note's author may change
+/// its visibility, so a visibility sent by anyone else is ignored.
+pub async fn patch<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    changes: Patch,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id)
+        .filter(visible_to(user_id))
+        .one(&txn)
+        .await?;
 
     match result {
         Some(note) => {
             debug!("Existing Note model to be Updated: {:?}", note);
 
-            let active_model: ActiveModel = ActiveModel {
+            precondition.check("Note", id, &note.updated_at)?;
+
+            let mut active_model: ActiveModel = ActiveModel {
                 id: Unchanged(note.id),
-                coaching_session_id: Unchanged(note.coaching_session_id),
-                body: Set(model.body),
-                user_id: Unchanged(note.user_id),
                 updated_at: Set(chrono::Utc::now().into()),
-                created_at: Unchanged(note.created_at),
+                ..Default::default()
             };
 
-            Ok(active_model.update(db).await?.try_into_model()?)
-        }
-        None => {
-            error!("Note with id {} not found", id);
+            let new_body = changes.body.filter(|body| *body != note.body);
+            if let Some(body) = &new_body {
+                active_model.body = Set(body.clone());
+            }
+            if let Some(visibility) = changes.visibility {
+                if note.user_id == user_id {
+                    active_model.visibility = Set(visibility);
+                }
+            }
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let updated_note = precondition::update_unchanged(
+                &txn,
+                "Note",
+                id,
+                active_model,
+                notes::Column::UpdatedAt,
+                note.updated_at,
+            )
+            .await?;
+
+            if let Some(body) = new_body {
+                revision::create(&txn, Subject::Note(note.id), body, user_id).await?;
+            }
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(note.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(updated_note)
         }
+        None => Err(Error::not_found("Note", id)),
     }
 }
 
-pub async fn find_by_id(db: &DatabaseConnection, id: Id) -> Result<Option<Model>, Error> {
-    match Entity::find_by_id(id).one(db).await {
-        Ok(Some(note)) => {
-            debug!("Note found: {:?}", note);
+/// Sets the Note's body back to that of one of its earlier revisions, recording the
+/// restored body as a new revision so that history is never rewritten.
+pub async fn restore_revision<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    revision_id: Id,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id)
+        .filter(visible_to(user_id))
+        .one(&txn)
+        .await?;
 
-            Ok(Some(note))
-        }
-        Ok(None) => {
-            error!("Note with id {} not found", id);
+    match result {
+        Some(note) => {
+            let revision = revision::find_by_id(&txn, Subject::Note(note.id), revision_id).await?;
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
-        Err(err) => {
-            error!("Note with id {} not found and returned error {}", id, err);
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
+            debug!(
+                "Existing Note model to be restored to revision {}: {:?}",
+                revision.id, note
+            );
+
+            precondition.check("Note", id, &note.updated_at)?;
+
+            let active_model: ActiveModel = ActiveModel {
+                id: Unchanged(note.id),
+                body: Set(revision.body.clone()),
+                updated_at: Set(chrono::Utc::now().into()),
+                ..Default::default()
+            };
+
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let restored_note = precondition::update_unchanged(
+                &txn,
+                "Note",
+                id,
+                active_model,
+                notes::Column::UpdatedAt,
+                note.updated_at,
+            )
+            .await?;
+
+            revision::create(&txn, Subject::Note(note.id), revision.body, user_id).await?;
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(note.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(restored_note)
         }
+        None => Err(Error::not_found("Note", id)),
     }
 }
 
-pub async fn find_by(
-    db: &DatabaseConnection,
+/// Deletes a Note that `user_id` can see in a coaching session they take part in, hiding it
+/// until it is restored or purged after the retention period.
+pub async fn delete_by_id<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    user_id: Id,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let note = with_member(
+        soft_delete::find_by_id::<Entity>(id).filter(visible_to(user_id)),
+        user_id,
+    )
+    .one(&txn)
+    .await?
+    .ok_or_else(|| Error::not_found("Note", id))?;
+    debug!("Existing Note model to be deleted: {:?}", note);
+
+    precondition.check("Note", id, &note.updated_at)?;
+
+    let now = chrono::Utc::now();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(now.into())),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    precondition::update_unchanged(
+        &txn,
+        "Note",
+        id,
+        active_model,
+        notes::Column::UpdatedAt,
+        note.updated_at,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::CoachingSession(note.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Restores a deleted Note that hasn't been purged yet, for a `user_id` who could see it in a
+/// coaching session they take part in.
+pub async fn restore<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let note = with_member(
+        soft_delete::find_deleted_by_id::<Entity>(id).filter(visible_to(user_id)),
+        user_id,
+    )
+    .one(&txn)
+    .await?
+    .ok_or_else(|| Error::not_found("Note", id))?;
+    debug!("Deleted Note model to be restored: {:?}", note);
+
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        updated_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let restored_note = active_model.update(&txn).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::CoachingSession(note.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(restored_note)
+}
+
+/// Finds a Note by its id on behalf of `user_id`. Another user's private Note is reported
+/// as not found so that its existence isn't revealed.
+pub async fn find_by_id<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let note = soft_delete::find_by_id::<Entity>(id)
+        .filter(visible_to(user_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Note", id))?;
+    debug!("Note found: {:?}", note);
+
+    Ok(note)
+}
+
+/// Finds the Notes matching `query_params` that `user_id` can see.
+pub async fn find_by<C>(
+    db: &C,
     query_params: HashMap<String, String>,
-) -> Result<Vec<Model>, Error> {
-    let mut query = Entity::find();
+    user_id: Id,
+) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = soft_delete::find::<Entity>().filter(visible_to(user_id));
 
     for (key, value) in query_params {
         match key.as_str() {
             "coaching_session_id" => {
-                let coaching_session_id = uuid_parse_str(&value)?;
+                let coaching_session_id = uuid_parse_str(&key, &value)?;
 
                 query = query.filter(notes::Column::CoachingSessionId.eq(coaching_session_id));
             }
             _ => {
-                return Err(Error {
-                    inner: None,
-                    error_code: EntityApiErrorCode::InvalidQueryTerm,
-                });
+                return Err(unknown_query_param(&key));
             }
         }
     }
@@ -111,6 +366,14 @@ pub async fn find_by(
     Ok(query.all(db).await?)
 }
 
+// Shared Notes are visible to everyone who can reach the coaching session, private Notes
+// only to their author.
+pub(crate) fn visible_to(user_id: Id) -> Condition {
+    Condition::any()
+        .add(notes::Column::Visibility.eq(Visibility::Shared))
+        .add(notes::Column::UserId.eq(user_id))
+}
+
 #[cfg(test)]
 // We need to gate seaORM's mock feature behind conditional compilation because
 // the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
@@ -118,9 +381,22 @@ pub async fn find_by(
 #[cfg(feature = "mock")]
 mod tests {
     use super::*;
-    use entity::{notes::Model, Id};
+    use crate::error::EntityApiErrorCode;
+    use entity::{notes::Model, revisions, Id};
     use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
 
+    fn revision_of(note: &Model, user_id: Id) -> revisions::Model {
+        revisions::Model {
+            id: Id::new_v4(),
+            note_id: Some(note.id),
+            agreement_id: None,
+            overarching_goal_id: None,
+            body: note.body.clone(),
+            user_id,
+            created_at: chrono::Utc::now().into(),
+        }
+    }
+
     #[tokio::test]
     async fn create_returns_a_new_note_model() -> Result<(), Error> {
         let now = chrono::Utc::now();
@@ -130,18 +406,27 @@ mod tests {
             user_id: Id::new_v4(),
             coaching_session_id: Id::new_v4(),
             body: Some("This is a note".to_owned()),
+            visibility: Visibility::Shared,
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
+        let user_id = Id::new_v4();
+
         let db = MockDatabase::new(DatabaseBackend::Postgres)
             .append_query_results(vec![vec![note_model.clone()]])
+            .append_query_results(vec![vec![revision_of(&note_model, user_id)]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let note = create(&db, note_model.clone().into(), Id::new_v4()).await?;
+        let note = create(&db, note_model.clone(), user_id).await?;
 
         assert_eq!(note.id, note_model.id);
 
+        // The note and its initial revision must be written in a single transaction
+        assert_eq!(db.into_transaction_log().len(), 1);
+
         Ok(())
     }
 
@@ -154,43 +439,340 @@ mod tests {
             coaching_session_id: Id::new_v4(),
             body: Some("This is a note".to_owned()),
             user_id: Id::new_v4(),
+            visibility: Visibility::Shared,
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
             .append_query_results(vec![vec![note_model.clone()], vec![note_model.clone()]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let note = update(&db, note_model.id, note_model.clone()).await?;
+        let note = update(
+            &db,
+            note_model.id,
+            Precondition::Any,
+            note_model.clone(),
+            Id::new_v4(),
+        )
+        .await?;
 
         assert_eq!(note.body, note_model.body);
 
         Ok(())
     }
 
+    #[tokio::test]
+    async fn update_records_a_revision_when_the_body_changes() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let user_id = Id::new_v4();
+
+        let note_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("This is a note".to_owned()),
+            user_id: Id::new_v4(),
+            visibility: Visibility::Shared,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let updated_note_model = Model {
+            body: Some("This is an edited note".to_owned()),
+            ..note_model.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![
+                vec![note_model.clone()],
+                vec![updated_note_model.clone()],
+            ])
+            .append_query_results(vec![vec![revision_of(&updated_note_model, user_id)]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let note = update(
+            &db,
+            note_model.id,
+            Precondition::Any,
+            updated_note_model.clone(),
+            user_id,
+        )
+        .await?;
+
+        assert_eq!(note.body, updated_note_model.body);
+
+        let transaction_log = db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 1);
+        // Only the revision insert touches the revisions table
+        assert!(format!("{:?}", transaction_log[0]).contains("revisions"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_revision_sets_body_to_the_revision_body() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let user_id = Id::new_v4();
+
+        let note_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("This is an edited note".to_owned()),
+            user_id: Id::new_v4(),
+            visibility: Visibility::Shared,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let earlier_revision = revisions::Model {
+            body: Some("This is a note".to_owned()),
+            ..revision_of(&note_model, user_id)
+        };
+
+        let restored_note_model = Model {
+            body: earlier_revision.body.clone(),
+            ..note_model.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![note_model.clone()]])
+            .append_query_results(vec![vec![earlier_revision.clone()]])
+            .append_query_results(vec![vec![restored_note_model.clone()]])
+            .append_query_results(vec![vec![revision_of(&restored_note_model, user_id)]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let note = restore_revision(
+            &db,
+            note_model.id,
+            Precondition::Any,
+            earlier_revision.id,
+            user_id,
+        )
+        .await?;
+
+        assert_eq!(note.body, earlier_revision.body);
+        assert_eq!(db.into_transaction_log().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_revision_returns_error_for_revision_of_another_note() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let note_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("This is a note".to_owned()),
+            user_id: Id::new_v4(),
+            visibility: Visibility::Shared,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![note_model.clone()]])
+            .append_query_results(vec![Vec::<revisions::Model>::new()])
+            .into_connection();
+
+        let result = restore_revision(
+            &db,
+            note_model.id,
+            Precondition::Any,
+            Id::new_v4(),
+            Id::new_v4(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn find_by_returns_all_notes_associated_with_coaching_session() -> Result<(), Error> {
         let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
         let mut query_params = HashMap::new();
         let coaching_session_id = Id::new_v4();
+        let user_id = Id::new_v4();
 
         query_params.insert(
             "coaching_session_id".to_owned(),
             coaching_session_id.to_string(),
         );
 
-        let _ = find_by(&db, query_params).await;
+        let _ = find_by(&db, query_params, user_id).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"SELECT "notes"."id", "notes"."coaching_session_id", "notes"."body", "notes"."user_id", CAST("notes"."visibility" AS text), "notes"."created_at", "notes"."updated_at", "notes"."deleted_at" FROM "refactor_platform"."notes" WHERE "notes"."deleted_at" IS NULL AND ("notes"."visibility" = (CAST($1 AS visibility)) OR "notes"."user_id" = $2) AND "notes"."coaching_session_id" = $3"#,
+                ["shared".into(), user_id.into(), coaching_session_id.into()]
+            )]
+        );
+
+        Ok(())
+    }
+
+    // A coachee listing a session's notes must only be sent shared notes and their own, never
+    // a coach's private ones
+    #[tokio::test]
+    async fn find_by_excludes_other_users_private_notes() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let coachee_id = Id::new_v4();
+
+        let _ = find_by(&db, HashMap::new(), coachee_id).await;
 
         assert_eq!(
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "notes"."id", "notes"."coaching_session_id", "notes"."body", "notes"."user_id", "notes"."created_at", "notes"."updated_at" FROM "refactor_platform"."notes" WHERE "notes"."coaching_session_id" = $1"#,
-                [coaching_session_id.into()]
+                r#"SELECT "notes"."id", "notes"."coaching_session_id", "notes"."body", "notes"."user_id", CAST("notes"."visibility" AS text), "notes"."created_at", "notes"."updated_at", "notes"."deleted_at" FROM "refactor_platform"."notes" WHERE "notes"."deleted_at" IS NULL AND ("notes"."visibility" = (CAST($1 AS visibility)) OR "notes"."user_id" = $2)"#,
+                ["shared".into(), coachee_id.into()]
             )]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn find_by_id_excludes_other_users_private_notes() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let note_id = Id::new_v4();
+        let coachee_id = Id::new_v4();
+
+        let _ = find_by_id(&db, note_id, coachee_id).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"SELECT "notes"."id", "notes"."coaching_session_id", "notes"."body", "notes"."user_id", CAST("notes"."visibility" AS text), "notes"."created_at", "notes"."updated_at", "notes"."deleted_at" FROM "refactor_platform"."notes" WHERE "notes"."id" = $1 AND "notes"."deleted_at" IS NULL AND ("notes"."visibility" = (CAST($2 AS visibility)) OR "notes"."user_id" = $3) LIMIT $4"#,
+                [
+                    note_id.into(),
+                    "shared".into(),
+                    coachee_id.into(),
+                    1u64.into()
+                ]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_id_reports_another_users_private_note_as_not_found() -> Result<(), Error> {
+        // The visibility filter leaves nothing for the database to return
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = find_by_id(&db, Id::new_v4(), Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    // Writing through update or restore_revision must not be a way to reach, and so read
+    // back, another user's private note
+    #[tokio::test]
+    async fn update_and_restore_revision_exclude_other_users_private_notes() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+        let coachee_id = Id::new_v4();
+
+        let note_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Coachee seems disengaged".to_owned()),
+            user_id: Id::new_v4(),
+            visibility: Visibility::Private,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new(), Vec::<Model>::new()])
+            .into_connection();
+
+        let updated = update(
+            &db,
+            note_model.id,
+            Precondition::Any,
+            note_model.clone(),
+            coachee_id,
+        )
+        .await;
+        let restored = restore_revision(
+            &db,
+            note_model.id,
+            Precondition::Any,
+            Id::new_v4(),
+            coachee_id,
+        )
+        .await;
+
+        assert!(matches!(
+            updated,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+        assert!(matches!(
+            restored,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        for transaction in db.into_transaction_log() {
+            assert!(format!("{:?}", transaction).contains(
+                r#"(\"notes\".\"visibility\" = (CAST($2 AS visibility)) OR \"notes\".\"user_id\" = $3)"#
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_id_returns_not_found_for_a_missing_note() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = find_by_id(&db, Id::new_v4(), Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
 }
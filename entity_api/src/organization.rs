@@ -1,16 +1,31 @@
-use super::error::{EntityApiErrorCode, Error};
-use crate::{organization::Entity, uuid_parse_str};
+use super::error::Error;
+use crate::audit::{self, Within};
+use crate::precondition::{self, Precondition};
+use crate::soft_delete;
+use crate::{organization::Entity, unknown_query_param, uuid_parse_str};
 use chrono::Utc;
-use entity::{coaching_relationships, organizations::*, prelude::Organizations, Id};
+use entity::{
+    actions, agreements, attachments, audit_action::AuditAction, coaching_relationships,
+    coaching_sessions, notes, organization_admins, organizations::*, overarching_goals,
+    prelude::Organizations, Id,
+};
 use sea_orm::{
-    entity::prelude::*, sea_query, ActiveValue::Set, ActiveValue::Unchanged, DatabaseConnection,
-    JoinType, QuerySelect, TryIntoModel,
+    entity::prelude::*,
+    sea_query::{self, SelectStatement, SimpleExpr},
+    ActiveValue::Set,
+    ActiveValue::Unchanged,
+    ConnectionTrait, JoinType, QuerySelect, QueryTrait, TransactionTrait,
 };
+use serde::Serialize;
 use std::collections::HashMap;
+use utoipa::ToSchema;
 
 use log::*;
 
-pub async fn create(db: &DatabaseConnection, organization_model: Model) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, organization_model: Model) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!(
         "New Organization Model to be inserted: {:?}",
         organization_model
@@ -26,83 +41,398 @@ pub async fn create(db: &DatabaseConnection, organization_model: Model) -> Resul
         ..Default::default()
     };
 
-    Ok(organization_active_model.insert(db).await?)
+    let changed_fields = audit::changed_fields(&organization_active_model);
+
+    let txn = db.begin().await?;
+    let organization = organization_active_model.insert(&txn).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        organization.id,
+        changed_fields,
+        Within::Organization(organization.id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(organization)
 }
 
-pub async fn update(db: &DatabaseConnection, id: Id, model: Model) -> Result<Model, Error> {
-    let result = find_by_id(db, id).await?;
+/// Changes to some of an Organization's fields. A field left as `None` keeps its current
+/// value, while `Some(None)` clears the logo.
+#[derive(Debug, Default)]
+pub struct Patch {
+    pub name: Option<String>,
+    pub logo: Option<Option<String>>,
+}
 
-    match result {
-        Some(organization) => {
-            debug!(
-                "Existing Organization model to be Updated: {:?}",
-                organization
-            );
+pub async fn update<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    model: Model,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let changes = Patch {
+        name: Some(model.name),
+        logo: Some(model.logo),
+    };
 
-            let active_model: ActiveModel = ActiveModel {
-                id: Unchanged(organization.id),
-                logo: Set(model.logo),
-                name: Set(model.name),
-                updated_at: Unchanged(organization.updated_at),
-                created_at: Unchanged(organization.created_at),
-            };
-            Ok(active_model.update(db).await?.try_into_model()?)
-        }
-        None => Err(Error {
-            inner: None,
-            error_code: EntityApiErrorCode::RecordNotFound,
-        }),
+    patch(db, id, precondition, changes).await
+}
+
+/// Changes only the fields of an Organization that `changes` names.
+pub async fn patch<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    changes: Patch,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let organization = find_by_id(db, id).await?;
+    debug!(
+        "Existing Organization model to be Updated: {:?}",
+        organization
+    );
+
+    precondition.check("Organization", id, &organization.updated_at)?;
+
+    let mut active_model: ActiveModel = ActiveModel {
+        id: Unchanged(organization.id),
+        updated_at: Set(Utc::now().into()),
+        ..Default::default()
+    };
+
+    if let Some(name) = changes.name {
+        active_model.name = Set(name);
+    }
+    if let Some(logo) = changes.logo {
+        active_model.logo = Set(logo);
     }
+
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    let updated_organization = precondition::update_unchanged(
+        &txn,
+        "Organization",
+        id,
+        active_model,
+        Column::UpdatedAt,
+        organization.updated_at,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Update,
+        id,
+        changed_fields,
+        Within::Organization(id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(updated_organization)
 }
 
-pub async fn delete_by_id(db: &DatabaseConnection, id: Id) -> Result<(), Error> {
-    let result = find_by_id(db, id).await?;
+/// What deleting an Organization takes with it, counted so that an admin can see the impact
+/// before confirming.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DeletionImpact {
+    pub coaching_relationships: u64,
+    pub coaching_sessions: u64,
+    pub notes: u64,
+    pub agreements: u64,
+    pub actions: u64,
+    pub overarching_goals: u64,
+    pub attachments: u64,
+}
 
-    match result {
-        Some(organization_model) => {
-            debug!(
-                "Existing Organization model to be deleted: {:?}",
-                organization_model
-            );
+/// Counts everything that deleting the Organization with `id` would affect, without changing
+/// anything.
+pub async fn deletion_impact<C>(db: &C, id: Id) -> Result<DeletionImpact, Error>
+where
+    C: ConnectionTrait,
+{
+    let organization = find_by_id(db, id).await?;
+    let in_organization = coaching_relationships::Column::OrganizationId.eq(organization.id);
 
-            organization_model.delete(db).await?;
-            Ok(())
-        }
-        None => Err(Error {
-            inner: None,
-            error_code: EntityApiErrorCode::RecordNotFound,
-        }),
+    Ok(DeletionImpact {
+        coaching_relationships: soft_delete::find::<coaching_relationships::Entity>()
+            .filter(in_organization.clone())
+            .count(db)
+            .await?,
+        coaching_sessions: soft_delete::find::<coaching_sessions::Entity>()
+            .filter(
+                coaching_sessions::Column::CoachingRelationshipId
+                    .in_subquery(relationship_ids(in_organization.clone())),
+            )
+            .count(db)
+            .await?,
+        notes: soft_delete::find::<notes::Entity>()
+            .filter(
+                notes::Column::CoachingSessionId.in_subquery(session_ids(in_organization.clone())),
+            )
+            .count(db)
+            .await?,
+        agreements: soft_delete::find::<agreements::Entity>()
+            .filter(
+                agreements::Column::CoachingSessionId
+                    .in_subquery(session_ids(in_organization.clone())),
+            )
+            .count(db)
+            .await?,
+        actions: soft_delete::find::<actions::Entity>()
+            .filter(
+                actions::Column::CoachingSessionId
+                    .in_subquery(session_ids(in_organization.clone())),
+            )
+            .count(db)
+            .await?,
+        overarching_goals: soft_delete::find::<overarching_goals::Entity>()
+            .filter(
+                overarching_goals::Column::CoachingSessionId
+                    .in_subquery(session_ids(in_organization.clone())),
+            )
+            .count(db)
+            .await?,
+        attachments: soft_delete::find::<attachments::Entity>()
+            .filter(
+                attachments::Column::CoachingSessionId.in_subquery(session_ids(in_organization)),
+            )
+            .count(db)
+            .await?,
+    })
+}
+
+/// Deletes an Organization by hiding it until it is restored or purged. Its coaching
+/// relationships, their sessions and everything in those sessions are deleted alongside it in
+/// the same transaction, so that restoring the Organization brings back exactly what it took
+/// with it.
+pub async fn delete_by_id<C>(db: &C, id: Id, precondition: Precondition) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+
+    let organization_model = soft_delete::find_by_id::<Entity>(id)
+        .one(&txn)
+        .await?
+        .ok_or_else(|| Error::not_found("Organization", id))?;
+    debug!(
+        "Existing Organization model to be deleted: {:?}",
+        organization_model
+    );
+
+    precondition.check("Organization", id, &organization_model.updated_at)?;
+
+    let now: DateTimeWithTimeZone = Utc::now().into();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(now)),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    precondition::update_unchanged(
+        &txn,
+        "Organization",
+        id,
+        active_model,
+        Column::UpdatedAt,
+        organization_model.updated_at,
+    )
+    .await?;
+
+    soft_delete::delete_relationships(
+        &txn,
+        coaching_relationships::Column::OrganizationId.eq(id),
+        now,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::Organization(id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Restores a deleted Organization that hasn't been purged yet, along with everything that was
+/// deleted with it.
+pub async fn restore<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+
+    let organization_model = soft_delete::find_deleted_by_id::<Entity>(id)
+        .one(&txn)
+        .await?
+        .ok_or_else(|| Error::not_found("Organization", id))?;
+    debug!(
+        "Deleted Organization model to be restored: {:?}",
+        organization_model
+    );
+
+    let now: DateTimeWithTimeZone = Utc::now().into();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+    let organization = active_model.update(&txn).await?;
+
+    // Content deleted on its own before the Organization was stays deleted
+    if let Some(deleted_at) = organization_model.deleted_at {
+        soft_delete::restore_relationships(
+            &txn,
+            coaching_relationships::Column::OrganizationId.eq(id),
+            deleted_at,
+            now,
+        )
+        .await?;
     }
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::Organization(id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(organization)
 }
 
-pub async fn find_all(db: &DatabaseConnection) -> Result<Vec<Model>, Error> {
-    Ok(Entity::find().all(db).await?)
+// The ids of the coaching relationships that `in_organizations` selects by their organization.
+fn relationship_ids(in_organizations: SimpleExpr) -> SelectStatement {
+    coaching_relationships::Entity::find()
+        .select_only()
+        .column(coaching_relationships::Column::Id)
+        .filter(in_organizations)
+        .into_query()
 }
 
-pub async fn find_by_id(db: &DatabaseConnection, id: Id) -> Result<Option<Model>, Error> {
-    let organization = Entity::find_by_id(id).one(db).await?;
+// The ids of the coaching sessions of the relationships that `in_organizations` selects.
+fn session_ids(in_organizations: SimpleExpr) -> SelectStatement {
+    coaching_sessions::Entity::find()
+        .select_only()
+        .column(coaching_sessions::Column::Id)
+        .filter(
+            coaching_sessions::Column::CoachingRelationshipId
+                .in_subquery(relationship_ids(in_organizations)),
+        )
+        .into_query()
+}
+
+pub async fn find_all<C>(db: &C) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    Ok(soft_delete::find::<Entity>().all(db).await?)
+}
+
+pub async fn find_by_id<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let organization = soft_delete::find_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Organization", id))?;
     debug!("Organization found: {:?}", organization);
 
     Ok(organization)
 }
 
-pub async fn find_by(
-    db: &DatabaseConnection,
-    params: HashMap<String, String>,
-) -> Result<Vec<Model>, Error> {
-    let mut query = Entity::find();
+/// Finds an organization that `user_id` is an admin of. To anyone else the organization is
+/// not found, so that its existence isn't revealed.
+pub async fn find_by_id_for_admin<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let organization = find_by_id(db, id).await?;
+
+    for_admin(db, organization, user_id).await
+}
+
+/// Like `find_by_id_for_admin`, for an organization that has been deleted.
+pub async fn find_deleted_by_id_for_admin<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let organization = soft_delete::find_deleted_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Organization", id))?;
+
+    for_admin(db, organization, user_id).await
+}
+
+// Returns `organization` only if `user_id` is one of its admins
+async fn for_admin<C>(db: &C, organization: Model, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let admin = organization_admins::Entity::find()
+        .filter(organization_admins::Column::OrganizationId.eq(organization.id))
+        .filter(organization_admins::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+
+    match admin {
+        Some(_) => Ok(organization),
+        None => {
+            debug!(
+                "User {} is not an admin of Organization {}",
+                user_id, organization.id
+            );
+
+            Err(Error::not_found("Organization", organization.id))
+        }
+    }
+}
+
+pub async fn find_by<C>(db: &C, params: HashMap<String, String>) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = soft_delete::find::<Entity>();
 
     for (key, value) in params {
         match key.as_str() {
             "user_id" => {
-                let user_uuid = uuid_parse_str(&value)?;
+                let user_uuid = uuid_parse_str(&key, &value)?;
                 query = by_user(query, user_uuid).await;
             }
             _ => {
-                return Err(Error {
-                    inner: None,
-                    error_code: EntityApiErrorCode::InvalidQueryTerm,
-                });
+                return Err(unknown_query_param(&key));
             }
         }
     }
@@ -110,8 +440,14 @@ pub async fn find_by(
     Ok(query.distinct().all(db).await?)
 }
 
-pub async fn find_by_user(db: &DatabaseConnection, user_id: Id) -> Result<Vec<Model>, Error> {
-    let organizations = by_user(Entity::find(), user_id).await.all(db).await?;
+pub async fn find_by_user<C>(db: &C, user_id: Id) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let organizations = by_user(soft_delete::find::<Entity>(), user_id)
+        .await
+        .all(db)
+        .await?;
 
     Ok(organizations)
 }
@@ -134,8 +470,10 @@ async fn by_user(query: Select<Organizations>, user_id: Id) -> Select<Organizati
 #[cfg(feature = "mock")]
 mod tests {
     use super::*;
+    use crate::error::EntityApiErrorCode;
     use entity::{organizations, Id};
-    use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Transaction};
+    use std::collections::BTreeMap;
 
     #[tokio::test]
     async fn find_all_returns_a_list_of_records_when_present() -> Result<(), Error> {
@@ -146,6 +484,7 @@ mod tests {
                 name: "Organization One".to_owned(),
                 created_at: now.into(),
                 updated_at: now.into(),
+                deleted_at: None,
                 logo: None,
             },
             organizations::Model {
@@ -153,6 +492,7 @@ mod tests {
                 name: "Organization One".to_owned(),
                 created_at: now.into(),
                 updated_at: now.into(),
+                deleted_at: None,
                 logo: None,
             },
         ]];
@@ -176,11 +516,312 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT DISTINCT "organizations"."id", "organizations"."name", "organizations"."logo", "organizations"."created_at", "organizations"."updated_at" FROM "refactor_platform"."organizations" INNER JOIN "refactor_platform"."coaching_relationships" ON "organizations"."id" = "coaching_relationships"."organization_id" WHERE "coaching_relationships"."coach_id" = $1 OR "coaching_relationships"."coachee_id" = $2"#,
+                r#"SELECT DISTINCT "organizations"."id", "organizations"."name", "organizations"."logo", "organizations"."created_at", "organizations"."updated_at", "organizations"."deleted_at" FROM "refactor_platform"."organizations" INNER JOIN "refactor_platform"."coaching_relationships" ON "organizations"."id" = "coaching_relationships"."organization_id" WHERE "organizations"."deleted_at" IS NULL AND ("coaching_relationships"."coach_id" = $1 OR "coaching_relationships"."coachee_id" = $2)"#,
                 [user_id.clone().into(), user_id.into()]
             )]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn find_by_id_for_admin_returns_not_found_for_users_that_are_not_admins(
+    ) -> Result<(), Error> {
+        let now = Utc::now();
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+            logo: None,
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![organization.clone()]])
+            .append_query_results(vec![Vec::<organization_admins::Model>::new()])
+            .into_connection();
+
+        let result = find_by_id_for_admin(&db, organization.id, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_id_for_admin_returns_not_found_for_a_missing_organization() -> Result<(), Error>
+    {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<organizations::Model>::new()])
+            .into_connection();
+
+        let result = find_by_id_for_admin(&db, Id::new_v4(), Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_id_returns_not_found_for_a_missing_organization() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<organizations::Model>::new()])
+            .into_connection();
+
+        let result = find_by_id(&db, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_returns_not_found_for_a_missing_organization() -> Result<(), Error> {
+        let now = Utc::now();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<organizations::Model>::new()])
+            .into_connection();
+
+        let result = update(
+            &db,
+            Id::new_v4(),
+            Precondition::Any,
+            organizations::Model {
+                id: Id::new_v4(),
+                name: "Organization One".to_owned(),
+                logo: None,
+                created_at: now.into(),
+                updated_at: now.into(),
+                deleted_at: None,
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_changes_only_the_version_that_was_read() -> Result<(), Error> {
+        let now = Utc::now();
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            logo: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![organization.clone()], vec![organization.clone()]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        update(
+            &db,
+            organization.id,
+            Precondition::UpdatedAt(vec![organization.updated_at]),
+            organizations::Model {
+                name: "Organization Two".to_owned(),
+                ..organization.clone()
+            },
+        )
+        .await?;
+
+        let transaction_log = format!("{:?}", db.into_transaction_log()[1]);
+        assert!(transaction_log.contains(
+            r#"WHERE \"organizations\".\"id\" = $4 AND \"organizations\".\"updated_at\" = $5"#
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_rejects_a_stale_version_of_the_organization() -> Result<(), Error> {
+        let now = Utc::now();
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            logo: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![organization.clone()]])
+            .into_connection();
+
+        let result = update(
+            &db,
+            organization.id,
+            Precondition::UpdatedAt(vec![(now - chrono::Duration::seconds(5)).into()]),
+            organization.clone(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deletion_impact_counts_everything_beneath_the_organization() -> Result<(), Error> {
+        let now = Utc::now();
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            logo: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+        let count = |n: i64| vec![BTreeMap::from([("num_items", Value::BigInt(Some(n)))])];
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![organization.clone()]])
+            .append_query_results([
+                count(2),
+                count(5),
+                count(7),
+                count(3),
+                count(4),
+                count(1),
+                count(0),
+            ])
+            .into_connection();
+
+        assert_eq!(
+            deletion_impact(&db, organization.id).await?,
+            DeletionImpact {
+                coaching_relationships: 2,
+                coaching_sessions: 5,
+                notes: 7,
+                agreements: 3,
+                actions: 4,
+                overarching_goals: 1,
+                attachments: 0,
+            }
+        );
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(!transaction_log.contains("UPDATE"));
+        assert!(!transaction_log.contains("DELETE"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_by_id_deletes_everything_in_the_organization_with_it() -> Result<(), Error> {
+        let now = Utc::now();
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            logo: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![organization.clone()], vec![organization.clone()]])
+            // Notes, agreements, actions, overarching goals, attachments, sessions and relationships
+            .append_exec_results((0..7).map(|_| MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 2,
+            }))
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        delete_by_id(&db, organization.id, Precondition::Any).await?;
+
+        let transaction_log = db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 1, "runs in a single transaction");
+
+        let transaction_log = format!("{:?}", transaction_log);
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"agreements\" SET \"deleted_at\" = $1, \"updated_at\" = $2 WHERE \"agreements\".\"deleted_at\" IS NULL AND \"agreements\".\"coaching_session_id\" IN (SELECT \"coaching_sessions\".\"id\" FROM \"refactor_platform\".\"coaching_sessions\" WHERE \"coaching_sessions\".\"coaching_relationship_id\" IN (SELECT \"coaching_relationships\".\"id\" FROM \"refactor_platform\".\"coaching_relationships\" WHERE \"coaching_relationships\".\"organization_id\" = $3))"#
+        ));
+        assert!(transaction_log.contains(r#"UPDATE \"refactor_platform\".\"actions\""#));
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"coaching_relationships\" SET \"deleted_at\" = $1, \"updated_at\" = $2 WHERE \"coaching_relationships\".\"deleted_at\" IS NULL AND \"coaching_relationships\".\"organization_id\" = $3"#
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_brings_back_only_the_content_deleted_with_the_organization(
+    ) -> Result<(), Error> {
+        let now = Utc::now();
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            logo: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: Some(now.into()),
+        };
+        let restored = organizations::Model {
+            deleted_at: None,
+            ..organization.clone()
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![organization.clone()], vec![restored.clone()]])
+            // Notes, agreements, actions, overarching goals, attachments, sessions and relationships
+            .append_exec_results((0..7).map(|_| MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 2,
+            }))
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        assert_eq!(restore(&db, organization.id).await?, restored);
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"organizations\" SET \"updated_at\" = $1, \"deleted_at\" = $2 WHERE \"organizations\".\"id\" = $3"#
+        ));
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"actions\" SET \"deleted_at\" = $1, \"updated_at\" = $2 WHERE \"actions\".\"deleted_at\" = $3"#
+        ));
+        assert!(transaction_log.contains(
+            r#"UPDATE \"refactor_platform\".\"coaching_sessions\" SET \"deleted_at\" = $1, \"updated_at\" = $2 WHERE \"coaching_sessions\".\"deleted_at\" = $3"#
+        ));
+
+        Ok(())
+    }
 }
@@ -1,23 +1,30 @@
-use super::error::{EntityApiErrorCode, Error};
-use crate::uuid_parse_str;
+use super::error::Error;
+use crate::audit::{self, Within};
+use crate::coaching_session::with_member;
+use crate::precondition::{self, Precondition};
+use crate::revision;
+use crate::status_transition::{self, Subject};
+use crate::{soft_delete, unknown_query_param, uuid_parse_str};
 use entity::overarching_goals::{self, ActiveModel, Entity, Model};
-use entity::{status::Status, Id};
-use sea_orm::ActiveValue;
+use entity::{
+    audit_action::AuditAction,
+    status::{Status, OVERARCHING_GOAL_TRANSITION_POLICY},
+    Id,
+};
 use sea_orm::{
     entity::prelude::*,
     ActiveModelTrait,
     ActiveValue::{Set, Unchanged},
-    DatabaseConnection, TryIntoModel,
+    ConnectionTrait, TransactionTrait, TryIntoModel,
 };
 use std::collections::HashMap;
 
 use log::*;
 
-pub async fn create(
-    db: &DatabaseConnection,
-    overarching_goal_model: Model,
-    user_id: Id,
-) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, overarching_goal_model: Model, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!(
         "New Overarching Goal Model to be inserted: {:?}",
         overarching_goal_model
@@ -30,22 +37,109 @@ pub async fn create(
         user_id: Set(user_id),
         title: Set(overarching_goal_model.title),
         body: Set(overarching_goal_model.body),
+        completed_at: Set(completed_at(
+            None,
+            &overarching_goal_model.status,
+            &overarching_goal_model.completed_at,
+        )),
         status: Set(overarching_goal_model.status),
         status_changed_at: Set(Some(now.into())),
-        completed_at: Unchanged(overarching_goal_model.completed_at),
         created_at: Set(now.into()),
         updated_at: Set(now.into()),
         ..Default::default()
     };
 
-    Ok(overarching_goal_active_model
-        .save(db)
+    let changed_fields = audit::changed_fields(&overarching_goal_active_model);
+
+    let txn = db.begin().await?;
+    let overarching_goal = overarching_goal_active_model
+        .save(&txn)
         .await?
-        .try_into_model()?)
+        .try_into_model()?;
+
+    revision::create(
+        &txn,
+        revision::Subject::OverarchingGoal(overarching_goal.id),
+        overarching_goal.body.clone(),
+        user_id,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        overarching_goal.id,
+        changed_fields,
+        Within::CoachingSession(overarching_goal.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(overarching_goal)
+}
+
+/// Changes to some of an Overarching Goal's fields. A field left as `None` keeps its current
+/// value, while `Some(None)` clears a field that may be empty.
+#[derive(Debug, Default)]
+pub struct Patch {
+    pub title: Option<Option<String>>,
+    pub body: Option<Option<String>>,
+    pub status: Option<Status>,
+}
+
+pub async fn update<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    model: Model,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let changes = Patch {
+        title: Some(model.title),
+        body: Some(model.body),
+        status: Some(model.status),
+    };
+
+    patch(db, id, precondition, changes, user_id).await
 }
 
-pub async fn update(db: &DatabaseConnection, id: Id, model: Model) -> Result<Model, Error> {
-    let result = Entity::find_by_id(id).one(db).await?;
+pub async fn update_status<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    status: Status,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let changes = Patch {
+        status: Some(status),
+        ..Default::default()
+    };
+
+    patch(db, id, precondition, changes, user_id).await
+}
+
+/// Changes only the fields of an Overarching Goal that `changes` names. Its
+/// `status_changed_at` and `completed_at` move only when its status actually changes.
+pub async fn patch<C>(
+    db: &C,
+    id: Id,
+    precondition: Precondition,
+    changes: Patch,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id).one(&txn).await?;
 
     match result {
         Some(overarching_goal) => {
@@ -54,128 +148,324 @@ pub async fn update(db: &DatabaseConnection, id: Id, model: Model) -> Result<Mod
                 overarching_goal
             );
 
-            // Automatically update status_changed_at if the last status and new status differ:
-            let av_status_changed_at: ActiveValue<Option<DateTimeWithTimeZone>> =
-                if model.status != overarching_goal.status {
-                    debug!("Updating status_changed_at for Overarching Goal to now");
-                    Set(Some(chrono::Utc::now().into()))
-                } else {
-                    Unchanged(model.status_changed_at)
-                };
+            precondition.check("Overarching Goal", id, &overarching_goal.updated_at)?;
 
-            let active_model: ActiveModel = ActiveModel {
+            let now = chrono::Utc::now();
+            let mut active_model: ActiveModel = ActiveModel {
                 id: Unchanged(overarching_goal.id),
-                coaching_session_id: Unchanged(overarching_goal.coaching_session_id),
-                user_id: Unchanged(overarching_goal.user_id),
-                body: Set(model.body),
-                title: Set(model.title),
-                status: Set(model.status),
-                status_changed_at: av_status_changed_at,
-                completed_at: Set(model.completed_at),
-                updated_at: Set(chrono::Utc::now().into()),
-                created_at: Unchanged(overarching_goal.created_at),
+                updated_at: Set(now.into()),
+                ..Default::default()
             };
 
-            Ok(active_model.update(db).await?.try_into_model()?)
-        }
-        None => {
-            error!("Overarching Goal with id {} not found", id);
+            if let Some(title) = changes.title {
+                active_model.title = Set(title);
+            }
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
+            let new_body = changes.body.filter(|body| *body != overarching_goal.body);
+            if let Some(body) = &new_body {
+                active_model.body = Set(body.clone());
+            }
+
+            let new_status = changes
+                .status
+                .filter(|status| *status != overarching_goal.status);
+            if let Some(status) = &new_status {
+                status_transition::check(
+                    &overarching_goal.status,
+                    status,
+                    &OVERARCHING_GOAL_TRANSITION_POLICY,
+                )?;
+
+                debug!("Updating status_changed_at for Overarching Goal to now");
+                active_model.status = Set(status.clone());
+                active_model.status_changed_at = Set(Some(now.into()));
+                active_model.completed_at = Set(completed_at(
+                    Some(&overarching_goal.status),
+                    status,
+                    &overarching_goal.completed_at,
+                ));
+            }
+
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let updated_overarching_goal = precondition::update_unchanged(
+                &txn,
+                "Overarching Goal",
+                id,
+                active_model,
+                overarching_goals::Column::UpdatedAt,
+                overarching_goal.updated_at,
+            )
+            .await?;
+
+            if let Some(status) = new_status {
+                status_transition::create(
+                    &txn,
+                    Subject::OverarchingGoal(overarching_goal.id),
+                    Some(overarching_goal.status),
+                    status,
+                    user_id,
+                )
+                .await?;
+            }
+
+            if let Some(body) = new_body {
+                revision::create(
+                    &txn,
+                    revision::Subject::OverarchingGoal(overarching_goal.id),
+                    body,
+                    user_id,
+                )
+                .await?;
+            }
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(overarching_goal.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(updated_overarching_goal)
         }
+        None => Err(Error::not_found("Overarching Goal", id)),
     }
 }
 
-pub async fn update_status(
-    db: &DatabaseConnection,
+/// Sets the Overarching Goal's body back to that of one of its earlier revisions,
+/// recording the restored body as a new revision so that history is never rewritten.
+pub async fn restore_revision<C>(
+    db: &C,
     id: Id,
-    status: Status,
-) -> Result<Model, Error> {
-    let result = Entity::find_by_id(id).one(db).await?;
+    precondition: Precondition,
+    revision_id: Id,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = soft_delete::find_by_id::<Entity>(id).one(&txn).await?;
 
     match result {
         Some(overarching_goal) => {
+            let subject = revision::Subject::OverarchingGoal(overarching_goal.id);
+            let revision = revision::find_by_id(&txn, subject, revision_id).await?;
+
             debug!(
-                "Existing Overarching Goal model to be Updated: {:?}",
-                overarching_goal
+                "Existing Overarching Goal model to be restored to revision {}: {:?}",
+                revision.id, overarching_goal
             );
 
+            precondition.check("Overarching Goal", id, &overarching_goal.updated_at)?;
+
             let active_model: ActiveModel = ActiveModel {
                 id: Unchanged(overarching_goal.id),
-                coaching_session_id: Unchanged(overarching_goal.coaching_session_id),
-                user_id: Unchanged(overarching_goal.user_id),
-                body: Unchanged(overarching_goal.body),
-                title: Unchanged(overarching_goal.title),
-                status: Set(status),
-                status_changed_at: Set(Some(chrono::Utc::now().into())),
-                completed_at: Unchanged(overarching_goal.completed_at),
+                body: Set(revision.body.clone()),
                 updated_at: Set(chrono::Utc::now().into()),
-                created_at: Unchanged(overarching_goal.created_at),
+                ..Default::default()
             };
 
-            Ok(active_model.update(db).await?.try_into_model()?)
+            let changed_fields = audit::changed_fields(&active_model);
+
+            let restored_overarching_goal = precondition::update_unchanged(
+                &txn,
+                "Overarching Goal",
+                id,
+                active_model,
+                overarching_goals::Column::UpdatedAt,
+                overarching_goal.updated_at,
+            )
+            .await?;
+
+            revision::create(&txn, subject, revision.body, user_id).await?;
+
+            audit::record(
+                &txn,
+                Entity,
+                AuditAction::Update,
+                id,
+                changed_fields,
+                Within::CoachingSession(overarching_goal.coaching_session_id),
+            )
+            .await?;
+
+            txn.commit().await?;
+
+            Ok(restored_overarching_goal)
         }
-        None => {
-            error!("Overarching Goal with id {} not found", id);
+        None => Err(Error::not_found("Overarching Goal", id)),
+    }
+}
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
+// Keeps completed_at in sync with the goal entering or leaving the Completed status,
+// leaving it as-is when the goal stays on either side of that boundary.
+fn completed_at(
+    previous_status: Option<&Status>,
+    status: &Status,
+    completed_at: &Option<DateTimeWithTimeZone>,
+) -> Option<DateTimeWithTimeZone> {
+    let was_completed = previous_status == Some(&Status::Completed);
+
+    match (was_completed, *status == Status::Completed) {
+        (false, true) => Some(chrono::Utc::now().into()),
+        (true, false) => None,
+        _ => *completed_at,
     }
 }
 
-pub async fn find_by_id(db: &DatabaseConnection, id: Id) -> Result<Option<Model>, Error> {
-    match Entity::find_by_id(id).one(db).await {
-        Ok(Some(overarching_goal)) => {
-            debug!("Overarching Goal found: {:?}", overarching_goal);
+/// Deletes an Overarching Goal by hiding it until it is restored or purged after the retention
+/// period.
+pub async fn delete_by_id<C>(db: &C, id: Id, precondition: Precondition) -> Result<(), Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let overarching_goal_model = find_by_id(db, id).await?;
+    debug!(
+        "Existing Overarching Goal model to be deleted: {:?}",
+        overarching_goal_model
+    );
 
-            Ok(Some(overarching_goal))
-        }
-        Ok(None) => {
-            error!("Overarching Goal with id {} not found", id);
+    precondition.check("Overarching Goal", id, &overarching_goal_model.updated_at)?;
 
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
-        Err(err) => {
-            error!(
-                "Overarching Goal with id {} not found and returned error {}",
-                id, err
-            );
-            Err(Error {
-                inner: None,
-                error_code: EntityApiErrorCode::RecordNotFound,
-            })
-        }
-    }
+    let now = chrono::Utc::now();
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(Some(now.into())),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    precondition::update_unchanged(
+        &txn,
+        "Overarching Goal",
+        id,
+        active_model,
+        overarching_goals::Column::UpdatedAt,
+        overarching_goal_model.updated_at,
+    )
+    .await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Delete,
+        id,
+        changed_fields,
+        Within::CoachingSession(overarching_goal_model.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
 }
 
-pub async fn find_by(
-    db: &DatabaseConnection,
-    query_params: HashMap<String, String>,
-) -> Result<Vec<Model>, Error> {
-    let mut query = Entity::find();
+/// Restores a deleted Overarching Goal that hasn't been purged yet.
+pub async fn restore<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let overarching_goal_model = soft_delete::find_deleted_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Overarching Goal", id))?;
+    debug!(
+        "Deleted Overarching Goal model to be restored: {:?}",
+        overarching_goal_model
+    );
+
+    let active_model = ActiveModel {
+        id: Unchanged(id),
+        deleted_at: Set(None),
+        updated_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    };
+    let changed_fields = audit::changed_fields(&active_model);
+
+    let txn = db.begin().await?;
+    let restored_overarching_goal = active_model.update(&txn).await?;
+
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Restore,
+        id,
+        changed_fields,
+        Within::CoachingSession(restored_overarching_goal.coaching_session_id),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(restored_overarching_goal)
+}
+
+pub async fn find_by_id<C>(db: &C, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let overarching_goal = soft_delete::find_by_id::<Entity>(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Overarching Goal", id))?;
+    debug!("Overarching Goal found: {:?}", overarching_goal);
+
+    Ok(overarching_goal)
+}
+
+/// Finds an Overarching Goal that `user_id` takes part in, as either the coach or the coachee of the
+/// relationship its coaching session belongs to. Any other user is told it was not found.
+pub async fn find_by_id_for_member<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let overarching_goal = with_member(soft_delete::find_by_id::<Entity>(id), user_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Overarching Goal", id))?;
+    debug!("Overarching Goal found: {:?}", overarching_goal);
+
+    Ok(overarching_goal)
+}
+
+/// Like `find_by_id_for_member`, for an Overarching Goal that has been deleted.
+pub async fn find_deleted_by_id_for_member<C>(db: &C, id: Id, user_id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    let overarching_goal = with_member(soft_delete::find_deleted_by_id::<Entity>(id), user_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::not_found("Overarching Goal", id))?;
+    debug!("Deleted Overarching Goal found: {:?}", overarching_goal);
+
+    Ok(overarching_goal)
+}
+
+pub async fn find_by<C>(db: &C, query_params: HashMap<String, String>) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut query = soft_delete::find::<Entity>();
 
     for (key, value) in query_params {
         match key.as_str() {
             "coaching_session_id" => {
-                let coaching_session_id = uuid_parse_str(&value)?;
+                let coaching_session_id = uuid_parse_str(&key, &value)?;
 
                 query = query
                     .filter(overarching_goals::Column::CoachingSessionId.eq(coaching_session_id));
             }
             _ => {
-                return Err(Error {
-                    inner: None,
-                    error_code: EntityApiErrorCode::InvalidQueryTerm,
-                });
+                return Err(unknown_query_param(&key));
             }
         }
     }
@@ -190,7 +480,8 @@ pub async fn find_by(
 #[cfg(feature = "mock")]
 mod tests {
     use super::*;
-    use entity::{overarching_goals::Model, Id};
+    use crate::error::EntityApiErrorCode;
+    use entity::{overarching_goals::Model, revisions, status_transitions, Id};
     use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
 
     #[tokio::test]
@@ -208,17 +499,34 @@ mod tests {
             completed_at: Some(now.into()),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let user_id = Id::new_v4();
+
+        let revision_model = revisions::Model {
+            id: Id::new_v4(),
+            note_id: None,
+            agreement_id: None,
+            overarching_goal_id: Some(overarching_goal_model.id),
+            body: overarching_goal_model.body.clone(),
+            user_id,
+            created_at: now.into(),
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
             .append_query_results(vec![vec![overarching_goal_model.clone()]])
+            .append_query_results(vec![vec![revision_model]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let overarching_goal =
-            create(&db, overarching_goal_model.clone().into(), Id::new_v4()).await?;
+        let overarching_goal = create(&db, overarching_goal_model.clone(), user_id).await?;
 
         assert_eq!(overarching_goal.id, overarching_goal_model.id);
 
+        // The goal and its initial revision must be written in a single transaction
+        assert_eq!(db.into_transaction_log().len(), 1);
+
         Ok(())
     }
 
@@ -237,6 +545,7 @@ mod tests {
             status: Default::default(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
@@ -244,12 +553,15 @@ mod tests {
                 vec![overarching_goal_model.clone()],
                 vec![overarching_goal_model.clone()],
             ])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
         let overarching_goal = update(
             &db,
             overarching_goal_model.id,
+            Precondition::Any,
             overarching_goal_model.clone(),
+            Id::new_v4(),
         )
         .await?;
 
@@ -273,6 +585,7 @@ mod tests {
             status: Default::default(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
         };
 
         let updated_overarching_goal_model = Model {
@@ -286,6 +599,19 @@ mod tests {
             status: Status::Completed,
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let user_id = Id::new_v4();
+
+        let status_transition_model = status_transitions::Model {
+            id: Id::new_v4(),
+            action_id: None,
+            overarching_goal_id: Some(overarching_goal_model.id),
+            from_status: Some(overarching_goal_model.status.clone()),
+            to_status: Status::Completed,
+            user_id,
+            created_at: now.into(),
         };
 
         let db = MockDatabase::new(DatabaseBackend::Postgres)
@@ -293,21 +619,126 @@ mod tests {
                 vec![overarching_goal_model.clone()],
                 vec![updated_overarching_goal_model.clone()],
             ])
+            .append_query_results(vec![vec![status_transition_model.clone()]])
+            .append_exec_results([audit::recorded()])
             .into_connection();
 
-        let overarching_goal =
-            update_status(&db, overarching_goal_model.id, Status::Completed).await?;
+        let overarching_goal = update_status(
+            &db,
+            overarching_goal_model.id,
+            Precondition::Any,
+            Status::Completed,
+            user_id,
+        )
+        .await?;
 
         assert_eq!(overarching_goal.status, Status::Completed);
 
+        // The lookup, the update and the status transition must all be logged as a
+        // single transaction rather than as separate statements
+        assert_eq!(db.into_transaction_log().len(), 1);
+
         Ok(())
     }
 
+    #[tokio::test]
+    async fn patch_changes_only_the_fields_it_names() -> Result<(), Error> {
+        let now = chrono::Utc::now();
+
+        let overarching_goal_model = Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            title: Some("title".to_owned()),
+            body: Some("This is a overarching_goal".to_owned()),
+            user_id: Id::new_v4(),
+            completed_at: None,
+            status_changed_at: Some(now.into()),
+            status: Status::InProgress,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![
+                vec![overarching_goal_model.clone()],
+                vec![Model {
+                    title: Some("Grow the team".to_owned()),
+                    ..overarching_goal_model.clone()
+                }],
+            ])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let changes = Patch {
+            title: Some(Some("Grow the team".to_owned())),
+            status: Some(Status::InProgress),
+            ..Default::default()
+        };
+        let overarching_goal = patch(
+            &db,
+            overarching_goal_model.id,
+            Precondition::Any,
+            changes,
+            Id::new_v4(),
+        )
+        .await?;
+
+        assert_eq!(overarching_goal.title, Some("Grow the team".to_owned()));
+
+        // The status stays the same, so neither it nor status_changed_at is written
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(r#"SET \"title\" = $1, \"updated_at\" = $2 WHERE"#));
+        assert!(!transaction_log.contains("status_transitions"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn completed_at_is_set_when_entering_completed() {
+        assert!(completed_at(Some(&Status::InProgress), &Status::Completed, &None).is_some());
+    }
+
+    #[test]
+    fn completed_at_is_cleared_when_leaving_completed() {
+        let now = chrono::Utc::now();
+
+        assert_eq!(
+            completed_at(
+                Some(&Status::Completed),
+                &Status::InProgress,
+                &Some(now.into())
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn completed_at_is_kept_when_staying_completed() {
+        let completed: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+        assert_eq!(
+            completed_at(
+                Some(&Status::Completed),
+                &Status::Completed,
+                &Some(completed)
+            ),
+            Some(completed)
+        );
+    }
+
     #[tokio::test]
     async fn update_status_returns_error_when_overarching_goal_not_found() -> Result<(), Error> {
         let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
 
-        let result = update_status(&db, Id::new_v4(), Status::Completed).await;
+        let result = update_status(
+            &db,
+            Id::new_v4(),
+            Precondition::Any,
+            Status::Completed,
+            Id::new_v4(),
+        )
+        .await;
 
         assert_eq!(result.is_err(), true);
 
@@ -332,11 +763,30 @@ mod tests {
             db.into_transaction_log(),
             [Transaction::from_sql_and_values(
                 DatabaseBackend::Postgres,
-                r#"SELECT "overarching_goals"."id", "overarching_goals"."coaching_session_id", "overarching_goals"."user_id", "overarching_goals"."title", "overarching_goals"."body", CAST("overarching_goals"."status" AS text), "overarching_goals"."status_changed_at", "overarching_goals"."completed_at", "overarching_goals"."created_at", "overarching_goals"."updated_at" FROM "refactor_platform"."overarching_goals" WHERE "overarching_goals"."coaching_session_id" = $1"#,
+                r#"SELECT "overarching_goals"."id", "overarching_goals"."coaching_session_id", "overarching_goals"."user_id", "overarching_goals"."title", "overarching_goals"."body", CAST("overarching_goals"."status" AS text), "overarching_goals"."status_changed_at", "overarching_goals"."completed_at", "overarching_goals"."created_at", "overarching_goals"."updated_at", "overarching_goals"."deleted_at" FROM "refactor_platform"."overarching_goals" WHERE "overarching_goals"."deleted_at" IS NULL AND "overarching_goals"."coaching_session_id" = $1"#,
                 [coaching_session_id.into()]
             )]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn find_by_id_returns_not_found_for_a_missing_overarching_goal() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = find_by_id(&db, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,130 @@
+//! Optimistic concurrency for changes to a record. A client names the versions of a record it
+//! is willing to change, identified by their `updated_at`, and a change to any other version
+//! fails instead of silently overwriting what someone else wrote in the meantime.
+
+use super::error::Error;
+use entity::Id;
+use sea_orm::{
+    entity::prelude::*, ActiveModelTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter,
+};
+
+use log::*;
+
+/// The versions of a record that a change may be made to, as named by an `If-Match` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Precondition {
+    /// Whatever version of the record is current, as with `If-Match: *`
+    Any,
+    /// Only a version last updated at one of these times
+    UpdatedAt(Vec<DateTimeWithTimeZone>),
+}
+
+impl Precondition {
+    /// Checks that `updated_at`, the current version of the `kind` of record with `id`, is one
+    /// that the change may be made to.
+    pub(crate) fn check(
+        &self,
+        kind: &str,
+        id: Id,
+        updated_at: &DateTimeWithTimeZone,
+    ) -> Result<(), Error> {
+        match self {
+            Precondition::Any => Ok(()),
+            Precondition::UpdatedAt(versions)
+                if versions
+                    .iter()
+                    .any(|version| same_version(version, updated_at)) =>
+            {
+                Ok(())
+            }
+            Precondition::UpdatedAt(versions) => {
+                debug!(
+                    "{} with id {} is at version {}, not one of {:?}",
+                    kind, id, updated_at, versions
+                );
+
+                Err(Error::precondition_failed(kind, id))
+            }
+        }
+    }
+}
+
+// Postgres keeps timestamps to the microsecond, so any finer precision a client sends back
+// can't tell versions apart.
+fn same_version(a: &DateTimeWithTimeZone, b: &DateTimeWithTimeZone) -> bool {
+    a.timestamp_micros() == b.timestamp_micros()
+}
+
+/// Writes `active_model` to the `kind` of record with `id` only while the record is still at
+/// the version it was read at, `updated_at`. The check is part of the UPDATE statement itself,
+/// so a change committed by anyone else since the record was read makes this one fail rather
+/// than overwrite it.
+pub(crate) async fn update_unchanged<A, C>(
+    db: &C,
+    kind: &str,
+    id: Id,
+    active_model: A,
+    updated_at_column: <A::Entity as EntityTrait>::Column,
+    updated_at: DateTimeWithTimeZone,
+) -> Result<<A::Entity as EntityTrait>::Model, Error>
+where
+    A: ActiveModelTrait,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    C: ConnectionTrait,
+{
+    A::Entity::update(active_model)
+        .filter(updated_at_column.eq(updated_at))
+        .exec(db)
+        .await
+        .map_err(|err| match err {
+            DbErr::RecordNotUpdated => Error::precondition_failed(kind, id),
+            err => err.into(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EntityApiErrorCode;
+
+    fn timestamp(rfc3339: &str) -> DateTimeWithTimeZone {
+        DateTimeWithTimeZone::parse_from_rfc3339(rfc3339).unwrap()
+    }
+
+    #[test]
+    fn check_accepts_any_version_of_the_record() {
+        let updated_at = timestamp("2024-06-01T12:00:00.123456Z");
+
+        assert!(Precondition::Any
+            .check("Action", Id::new_v4(), &updated_at)
+            .is_ok());
+    }
+
+    #[test]
+    fn check_accepts_a_listed_version_to_the_microsecond() {
+        let updated_at = timestamp("2024-06-01T12:00:00.123456789Z");
+        let precondition = Precondition::UpdatedAt(vec![
+            timestamp("2024-05-01T08:00:00Z"),
+            timestamp("2024-06-01T14:00:00.123456+02:00"),
+        ]);
+
+        assert!(precondition
+            .check("Action", Id::new_v4(), &updated_at)
+            .is_ok());
+    }
+
+    #[test]
+    fn check_rejects_a_version_that_is_not_listed() {
+        let updated_at = timestamp("2024-06-01T12:00:00.123457Z");
+        let precondition = Precondition::UpdatedAt(vec![timestamp("2024-06-01T12:00:00.123456Z")]);
+
+        assert!(matches!(
+            precondition.check("Action", Id::new_v4(), &updated_at),
+            Err(Error {
+                error_code: EntityApiErrorCode::PreconditionFailed,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+use super::error::{EntityApiErrorCode, Error};
+use entity::revisions::{self, ActiveModel, Entity, Model};
+use entity::Id;
+use sea_orm::{entity::prelude::*, ConnectionTrait, QueryOrder, Set, TransactionTrait};
+use serde::Deserialize;
+use similar::TextDiff;
+use utoipa::IntoParams;
+
+use log::*;
+
+/// The entity whose body a Revision is a snapshot of.
+#[derive(Debug, Clone, Copy)]
+pub enum Subject {
+    Note(Id),
+    Agreement(Id),
+    OverarchingGoal(Id),
+}
+
+impl Subject {
+    fn condition(&self) -> sea_orm::sea_query::SimpleExpr {
+        match *self {
+            Subject::Note(id) => revisions::Column::NoteId.eq(id),
+            Subject::Agreement(id) => revisions::Column::AgreementId.eq(id),
+            Subject::OverarchingGoal(id) => revisions::Column::OverarchingGoalId.eq(id),
+        }
+    }
+}
+
+/// Records a new head revision. This takes any connection so that callers can write
+/// the revision in the same database transaction as the body change itself.
+pub(crate) async fn create<C>(
+    db: &C,
+    subject: Subject,
+    body: Option<String>,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    debug!("New Revision to be inserted for {:?}", subject);
+
+    let (note_id, agreement_id, overarching_goal_id) = match subject {
+        Subject::Note(id) => (Some(id), None, None),
+        Subject::Agreement(id) => (None, Some(id), None),
+        Subject::OverarchingGoal(id) => (None, None, Some(id)),
+    };
+
+    let revision_active_model: ActiveModel = ActiveModel {
+        note_id: Set(note_id),
+        agreement_id: Set(agreement_id),
+        overarching_goal_id: Set(overarching_goal_id),
+        body: Set(body),
+        user_id: Set(user_id),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    };
+
+    Ok(revision_active_model.insert(db).await?)
+}
+
+/// Returns every revision of `subject`, newest (the current body) first.
+pub async fn find_by_subject<C>(db: &C, subject: Subject) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .filter(subject.condition())
+        .order_by_desc(revisions::Column::CreatedAt)
+        .all(db)
+        .await?)
+}
+
+/// The newest revision of `subject`, which holds its current body.
+pub(crate) async fn find_head<C>(db: &C, subject: Subject) -> Result<Option<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .filter(subject.condition())
+        .order_by_desc(revisions::Column::CreatedAt)
+        .one(db)
+        .await?)
+}
+
+/// Finds a revision by its id, treating a revision of any other subject as not found.
+pub(crate) async fn find_by_id<C>(db: &C, subject: Subject, id: Id) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    match Entity::find_by_id(id)
+        .filter(subject.condition())
+        .one(db)
+        .await?
+    {
+        Some(revision) => Ok(revision),
+        None => {
+            error!("Revision with id {} not found for {:?}", id, subject);
+
+            Err(Error {
+                inner: None,
+                error_code: EntityApiErrorCode::RecordNotFound,
+                detail: None,
+            })
+        }
+    }
+}
+
+/// The pair of revisions to compare when diffing a subject's body.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DiffParams {
+    /// Id of the older revision
+    pub from: Id,
+    /// Id of the newer revision
+    pub to: Id,
+}
+
+/// Returns a unified diff of the body going from revision `from_id` to revision `to_id`.
+pub async fn diff<C>(db: &C, subject: Subject, from_id: Id, to_id: Id) -> Result<String, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let from = find_by_id(&txn, subject, from_id).await?;
+    let to = find_by_id(&txn, subject, to_id).await?;
+    txn.commit().await?;
+
+    Ok(unified_diff(&from, &to))
+}
+
+fn unified_diff(from: &Model, to: &Model) -> String {
+    let from_body = from.body.as_deref().unwrap_or_default();
+    let to_body = to.body.as_deref().unwrap_or_default();
+
+    TextDiff::from_lines(from_body, to_body)
+        .unified_diff()
+        .header(&from.id.to_string(), &to.id.to_string())
+        .to_string()
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
+
+    fn revision(note_id: Id, body: &str) -> Model {
+        Model {
+            id: Id::new_v4(),
+            note_id: Some(note_id),
+            agreement_id: None,
+            overarching_goal_id: None,
+            body: Some(body.to_owned()),
+            user_id: Id::new_v4(),
+            created_at: chrono::Utc::now().into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn find_by_subject_returns_revisions_newest_first() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let note_id = Id::new_v4();
+
+        let _ = find_by_subject(&db, Subject::Note(note_id)).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"SELECT "revisions"."id", "revisions"."note_id", "revisions"."agreement_id", "revisions"."overarching_goal_id", "revisions"."body", "revisions"."user_id", "revisions"."created_at" FROM "refactor_platform"."revisions" WHERE "revisions"."note_id" = $1 ORDER BY "revisions"."created_at" DESC"#,
+                [note_id.into()]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn diff_returns_unified_diff_between_revisions() -> Result<(), Error> {
+        let note_id = Id::new_v4();
+        let from = revision(
+            note_id,
+            "Agreed to rotate on-call weekly\nPair on incidents\n",
+        );
+        let to = revision(
+            note_id,
+            "Agreed to rotate on-call biweekly\nPair on incidents\n",
+        );
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![from.clone()], vec![to.clone()]])
+            .into_connection();
+
+        let diff = diff(&db, Subject::Note(note_id), from.id, to.id).await?;
+
+        assert_eq!(
+            diff,
+            format!(
+                "--- {}\n+++ {}\n@@ -1,2 +1,2 @@\n-Agreed to rotate on-call weekly\n+Agreed to rotate on-call biweekly\n Pair on incidents\n",
+                from.id, to.id
+            )
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn diff_returns_error_for_revision_of_another_subject() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<Model>::new()])
+            .into_connection();
+
+        let result = diff(&db, Subject::Note(Id::new_v4()), Id::new_v4(), Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+}
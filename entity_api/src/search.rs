@@ -0,0 +1,262 @@
+use super::error::Error;
+use crate::unknown_query_param;
+use entity::Id;
+use sea_orm::{ConnectionTrait, DbBackend, FromQueryResult, Statement};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use log::*;
+
+/// The maximum number of results returned for a single search.
+const SEARCH_RESULT_LIMIT: i64 = 50;
+
+// ts_headline marks matching terms with these private use characters rather than with HTML
+// tags, so that the user-authored text around them can be escaped before the tags are added
+const HIGHLIGHT_START: char = '\u{E000}';
+const HIGHLIGHT_STOP: char = '\u{E001}';
+
+// Searches notes, agreements, actions and overarching goals through their generated
+// `search_vector` columns, keeping only records from coaching sessions in a coaching
+// relationship of an organization that hasn't been deleted, which the user is the coach or
+// coachee of, and skipping deleted records and other users' private notes. Snippets are only
+// generated for the page of results that is actually returned since ts_headline is expensive.
+const SEARCH_SQL: &str = r#"WITH "query" AS (SELECT websearch_to_tsquery('english', $1) AS "q"),
+"matches" AS (
+  SELECT 'note' AS "kind", "id", "coaching_session_id", "body" AS "text", "search_vector" FROM "refactor_platform"."notes"
+    WHERE "deleted_at" IS NULL AND ("visibility" = 'shared' OR "user_id" = $2)
+  UNION ALL
+  SELECT 'agreement', "id", "coaching_session_id", "body", "search_vector" FROM "refactor_platform"."agreements"
+    WHERE "deleted_at" IS NULL
+  UNION ALL
+  SELECT 'action', "id", "coaching_session_id", "body", "search_vector" FROM "refactor_platform"."actions"
+    WHERE "deleted_at" IS NULL
+  UNION ALL
+  SELECT 'overarching_goal', "id", "coaching_session_id", concat_ws(E'\n', "title", "body"), "search_vector" FROM "refactor_platform"."overarching_goals"
+    WHERE "deleted_at" IS NULL
+),
+"ranked" AS (
+  SELECT "matches"."kind", "matches"."id", "matches"."coaching_session_id", "coaching_sessions"."coaching_relationship_id", "matches"."text", ts_rank("matches"."search_vector", "query"."q") AS "rank"
+  FROM "matches"
+  CROSS JOIN "query"
+  JOIN "refactor_platform"."coaching_sessions" ON "coaching_sessions"."id" = "matches"."coaching_session_id"
+  JOIN "refactor_platform"."coaching_relationships" ON "coaching_relationships"."id" = "coaching_sessions"."coaching_relationship_id"
+  WHERE "matches"."search_vector" @@ "query"."q"
+    AND "coaching_sessions"."deleted_at" IS NULL
+    AND "coaching_relationships"."deleted_at" IS NULL
+    AND ("coaching_relationships"."coach_id" = $2 OR "coaching_relationships"."coachee_id" = $2)
+    AND "coaching_relationships"."organization_id" IN (SELECT "id" FROM "refactor_platform"."organizations" WHERE "deleted_at" IS NULL)
+  ORDER BY "rank" DESC
+  LIMIT $3
+)
+SELECT "ranked"."kind", "ranked"."id", "ranked"."coaching_session_id", "ranked"."coaching_relationship_id", "ranked"."rank", ts_headline('english', coalesce("ranked"."text", ''), "query"."q", 'StartSel=' || chr(57344) || ', StopSel=' || chr(57345) || ', MaxFragments=2') AS "snippet"
+FROM "ranked"
+CROSS JOIN "query"
+ORDER BY "ranked"."rank" DESC"#;
+
+/// A single search hit. `kind` is one of `note`, `agreement`, `action` or
+/// `overarching_goal` and `id` is the id of that record. `snippet` is HTML that is safe to
+/// render: the record's text is escaped and only its matching terms are wrapped in `<mark>`
+/// tags.
+#[derive(Debug, FromQueryResult, Serialize, ToSchema)]
+#[schema(as = entity_api::search::SearchResult)] // OpenAPI schema
+pub struct SearchResult {
+    pub kind: String,
+    pub id: Id,
+    pub coaching_session_id: Id,
+    pub coaching_relationship_id: Id,
+    pub rank: f32,
+    pub snippet: String,
+}
+
+/// Full-text search across the coaching content the user has access to, best match first.
+pub async fn find_by<C>(
+    db: &C,
+    user_id: Id,
+    query_params: HashMap<String, String>,
+) -> Result<Vec<SearchResult>, Error>
+where
+    C: ConnectionTrait,
+{
+    let mut search_terms: Option<String> = None;
+
+    for (key, value) in query_params {
+        match key.as_str() {
+            "q" => {
+                search_terms = Some(value);
+            }
+            _ => {
+                return Err(unknown_query_param(&key));
+            }
+        }
+    }
+
+    let search_terms = match search_terms {
+        Some(terms) if !terms.trim().is_empty() => terms,
+        _ => return Err(Error::invalid_field("q", "missing", "is required")),
+    };
+
+    debug!(
+        "Searching for {:?} on behalf of user {}",
+        search_terms, user_id
+    );
+
+    let search_results = SearchResult::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        SEARCH_SQL,
+        [
+            search_terms.into(),
+            user_id.into(),
+            SEARCH_RESULT_LIMIT.into(),
+        ],
+    ))
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|search_result| SearchResult {
+        snippet: highlighted_html(&search_result.snippet),
+        ..search_result
+    })
+    .collect();
+
+    Ok(search_results)
+}
+
+// Escapes a snippet from ts_headline as HTML, turning its highlight markers into `<mark>` tags
+fn highlighted_html(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+
+    for c in snippet.chars() {
+        match c {
+            HIGHLIGHT_START => html.push_str("<mark>"),
+            HIGHLIGHT_STOP => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+
+    html
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, Transaction, Value};
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn find_by_searches_content_scoped_to_the_user() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let user_id = Id::new_v4();
+        let mut query_params = HashMap::new();
+
+        query_params.insert("q".to_owned(), "on-call rotation".to_owned());
+
+        let _ = find_by(&db, user_id, query_params).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                SEARCH_SQL,
+                [
+                    "on-call rotation".into(),
+                    user_id.into(),
+                    SEARCH_RESULT_LIMIT.into()
+                ]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_escapes_the_text_of_snippets() -> Result<(), Error> {
+        let snippet = format!(
+            "Run the {HIGHLIGHT_START}script{HIGHLIGHT_STOP} <script>alert('hi') && fetch(\"/\")</script>"
+        );
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![BTreeMap::from([
+                ("kind", Value::from("note")),
+                ("id", Id::new_v4().into()),
+                ("coaching_session_id", Id::new_v4().into()),
+                ("coaching_relationship_id", Id::new_v4().into()),
+                ("rank", 0.5_f32.into()),
+                ("snippet", snippet.into()),
+            ])]])
+            .into_connection();
+        let query_params = HashMap::from([("q".to_owned(), "script".to_owned())]);
+
+        let search_results = find_by(&db, Id::new_v4(), query_params).await?;
+
+        assert_eq!(
+            search_results[0].snippet,
+            "Run the <mark>script</mark> &lt;script&gt;alert(&#39;hi&#39;) &amp;&amp; fetch(&quot;/&quot;)&lt;/script&gt;"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_returns_error_for_blank_search_terms() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let mut query_params = HashMap::new();
+
+        query_params.insert("q".to_owned(), "   ".to_owned());
+
+        let result = find_by(&db, Id::new_v4(), query_params).await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_returns_error_for_unknown_query_term() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let mut query_params = HashMap::new();
+
+        query_params.insert("coaching_session_id".to_owned(), Id::new_v4().to_string());
+
+        let result = find_by(&db, Id::new_v4(), query_params).await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    // Coach journals are private to the coach, so a coachee's search must never match them
+    #[test]
+    fn search_excludes_other_users_private_notes() {
+        assert!(SEARCH_SQL.contains(
+            r#"FROM "refactor_platform"."notes"
+    WHERE "deleted_at" IS NULL AND ("visibility" = 'shared' OR "user_id" = $2)"#
+        ));
+    }
+
+    #[test]
+    fn search_excludes_deleted_records() {
+        assert!(SEARCH_SQL.contains(
+            r#"FROM "refactor_platform"."agreements"
+    WHERE "deleted_at" IS NULL"#
+        ));
+        assert!(SEARCH_SQL.contains(
+            r#"FROM "refactor_platform"."actions"
+    WHERE "deleted_at" IS NULL"#
+        ));
+        assert!(SEARCH_SQL.contains(
+            r#"FROM "refactor_platform"."overarching_goals"
+    WHERE "deleted_at" IS NULL"#
+        ));
+        assert!(SEARCH_SQL.contains(r#"AND "coaching_sessions"."deleted_at" IS NULL"#));
+        assert!(SEARCH_SQL.contains(r#"AND "coaching_relationships"."deleted_at" IS NULL"#));
+    }
+}
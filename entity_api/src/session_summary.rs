@@ -0,0 +1,180 @@
+//! Everything that goes into the printable summary of a coaching session: when it took place,
+//! who took part, what was agreed, the actions it produced or left open and where each
+//! overarching goal of the relationship stands.
+
+use super::error::Error;
+use crate::coaching_relationship::{self, CoachingRelationshipWithUserNames};
+use crate::coaching_session::{self, in_relationship};
+use crate::soft_delete;
+use entity::{
+    actions, agreements, coaching_sessions, organizations, overarching_goals, status::Status, Id,
+};
+use sea_orm::{entity::prelude::*, ConnectionTrait, QueryOrder};
+
+use log::*;
+
+/// The records summarized for one coaching session.
+#[derive(Debug)]
+pub struct SessionSummary {
+    pub coaching_session: coaching_sessions::Model,
+    pub relationship: CoachingRelationshipWithUserNames,
+    pub organization: organizations::Model,
+    pub agreements: Vec<agreements::Model>,
+    /// Actions created during this session
+    pub new_actions: Vec<actions::Model>,
+    /// Actions from earlier sessions of the relationship that are not yet completed or won't do
+    pub open_actions: Vec<actions::Model>,
+    /// Every overarching goal of the relationship, whatever its status
+    pub overarching_goals: Vec<overarching_goals::Model>,
+}
+
+/// Gathers the summary of the coaching session with `id` for `user_id`, who must be the coach
+/// or the coachee of its coaching relationship.
+pub async fn find_by_coaching_session<C>(
+    db: &C,
+    id: Id,
+    user_id: Id,
+) -> Result<SessionSummary, Error>
+where
+    C: ConnectionTrait,
+{
+    debug!("Summarizing Coaching Session {} for user {}", id, user_id);
+
+    let (coaching_session, relationship) =
+        coaching_session::find_with_relationship_for_member(db, id, user_id).await?;
+
+    let organization =
+        soft_delete::find_by_id::<organizations::Entity>(relationship.organization_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| Error::not_found("Organization", relationship.organization_id))?;
+    let relationship = coaching_relationship::get_relationship_with_user_names(db, relationship.id)
+        .await?
+        .ok_or_else(|| Error::not_found("Coaching Relationship", relationship.id))?;
+
+    let agreements = soft_delete::find::<agreements::Entity>()
+        .filter(agreements::Column::CoachingSessionId.eq(id))
+        .order_by_asc(agreements::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let new_actions = soft_delete::find::<actions::Entity>()
+        .filter(actions::Column::CoachingSessionId.eq(id))
+        .order_by_asc(actions::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let open_actions = in_relationship(soft_delete::find::<actions::Entity>(), relationship.id)
+        .filter(actions::Column::CoachingSessionId.ne(id))
+        .filter(actions::Column::Status.is_in([Status::NotStarted, Status::InProgress]))
+        .order_by_asc(actions::Column::DueBy)
+        .order_by_asc(actions::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let overarching_goals = in_relationship(
+        soft_delete::find::<overarching_goals::Entity>(),
+        relationship.id,
+    )
+    .order_by_asc(overarching_goals::Column::CreatedAt)
+    .all(db)
+    .await?;
+
+    Ok(SessionSummary {
+        coaching_session,
+        relationship,
+        organization,
+        agreements,
+        new_actions,
+        open_actions,
+        overarching_goals,
+    })
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use crate::error::EntityApiErrorCode;
+    use entity::{coaching_relationships, relationship_status::RelationshipStatus};
+    use sea_orm::{DatabaseBackend, MockDatabase};
+
+    fn relationship(coach_id: Id, coachee_id: Id) -> coaching_relationships::Model {
+        let now = chrono::Utc::now();
+
+        coaching_relationships::Model {
+            id: Id::new_v4(),
+            organization_id: Id::new_v4(),
+            coach_id,
+            coachee_id,
+            status: RelationshipStatus::Active,
+            start_date: now.date_naive(),
+            end_date: None,
+            reassigned_to_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    fn session(relationship: &coaching_relationships::Model) -> coaching_sessions::Model {
+        let now = chrono::Utc::now();
+
+        coaching_sessions::Model {
+            id: Id::new_v4(),
+            coaching_relationship_id: relationship.id,
+            date: now.naive_utc(),
+            timezone: "America/Chicago".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn find_by_coaching_session_returns_error_for_non_member() -> Result<(), Error> {
+        let relationship = relationship(Id::new_v4(), Id::new_v4());
+        let session = session(&relationship);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(session.clone(), relationship.clone())]])
+            .into_connection();
+
+        let result = find_by_coaching_session(&db, session.id, Id::new_v4()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_coaching_session_returns_error_when_organization_is_missing(
+    ) -> Result<(), Error> {
+        let coach_id = Id::new_v4();
+        let relationship = relationship(coach_id, Id::new_v4());
+        let session = session(&relationship);
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![(session.clone(), relationship.clone())]])
+            .append_query_results(vec![Vec::<organizations::Model>::new()])
+            .into_connection();
+
+        let result = find_by_coaching_session(&db, session.id, coach_id).await;
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::RecordNotFound,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,606 @@
+//! Soft deletion, which hides a record instead of removing it so that it can be restored.
+//!
+//! Queries of a record that can be soft deleted start from `find` or `find_by_id` here, which
+//! leave out deleted records, rather than from the entity itself. Deleted records are kept until
+//! they have been deleted for longer than the retention period, and are then hard deleted by
+//! `purge`.
+
+use super::error::Error;
+use chrono::{Days, Utc};
+use entity::{
+    actions, agreements, attachments, coaching_relationships, coaching_sessions, notes,
+    organization_admins, organizations, overarching_goals, Id,
+};
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{SelectStatement, SimpleExpr},
+    DatabaseConnection, QuerySelect, QueryTrait, Select, TransactionTrait,
+};
+use service::storage::Storage;
+use std::{sync::Arc, time::Duration};
+
+use log::*;
+
+/// How often `purge_continuously` looks for records past the retention period.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// An entity whose records are soft deleted.
+pub(crate) trait SoftDelete: EntityTrait {
+    /// The column recording when a record was deleted, which is null until it is.
+    fn deleted_at() -> Self::Column;
+
+    /// The column recording when a record last changed, which deleting or restoring it
+    /// touches. Attachments never change once uploaded, so they have none.
+    fn updated_at() -> Option<Self::Column>;
+}
+
+impl SoftDelete for actions::Entity {
+    fn deleted_at() -> Self::Column {
+        actions::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        Some(actions::Column::UpdatedAt)
+    }
+}
+
+impl SoftDelete for agreements::Entity {
+    fn deleted_at() -> Self::Column {
+        agreements::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        Some(agreements::Column::UpdatedAt)
+    }
+}
+
+impl SoftDelete for attachments::Entity {
+    fn deleted_at() -> Self::Column {
+        attachments::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        None
+    }
+}
+
+impl SoftDelete for coaching_relationships::Entity {
+    fn deleted_at() -> Self::Column {
+        coaching_relationships::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        Some(coaching_relationships::Column::UpdatedAt)
+    }
+}
+
+impl SoftDelete for coaching_sessions::Entity {
+    fn deleted_at() -> Self::Column {
+        coaching_sessions::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        Some(coaching_sessions::Column::UpdatedAt)
+    }
+}
+
+impl SoftDelete for notes::Entity {
+    fn deleted_at() -> Self::Column {
+        notes::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        Some(notes::Column::UpdatedAt)
+    }
+}
+
+impl SoftDelete for organizations::Entity {
+    fn deleted_at() -> Self::Column {
+        organizations::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        Some(organizations::Column::UpdatedAt)
+    }
+}
+
+impl SoftDelete for overarching_goals::Entity {
+    fn deleted_at() -> Self::Column {
+        overarching_goals::Column::DeletedAt
+    }
+
+    fn updated_at() -> Option<Self::Column> {
+        Some(overarching_goals::Column::UpdatedAt)
+    }
+}
+
+/// Every record of `E` that hasn't been deleted.
+pub(crate) fn find<E: SoftDelete>() -> Select<E> {
+    E::find().filter(E::deleted_at().is_null())
+}
+
+/// The record of `E` with `id`, unless it has been deleted.
+pub(crate) fn find_by_id<E>(id: Id) -> Select<E>
+where
+    E: SoftDelete,
+    <E::PrimaryKey as PrimaryKeyTrait>::ValueType: From<Id>,
+{
+    E::find_by_id(id).filter(E::deleted_at().is_null())
+}
+
+/// The record of `E` with `id`, only if it has been deleted.
+pub(crate) fn find_deleted_by_id<E>(id: Id) -> Select<E>
+where
+    E: SoftDelete,
+    <E::PrimaryKey as PrimaryKeyTrait>::ValueType: From<Id>,
+{
+    E::find_by_id(id).filter(E::deleted_at().is_not_null())
+}
+
+/// Deletes every record of `E` that `filter` selects and that isn't deleted already, as of
+/// `now`.
+pub(crate) async fn delete_many<E, C>(
+    db: &C,
+    filter: SimpleExpr,
+    now: DateTimeWithTimeZone,
+) -> Result<(), Error>
+where
+    E: SoftDelete,
+    C: ConnectionTrait,
+{
+    let mut update = E::update_many().col_expr(E::deleted_at(), Expr::value(now));
+    if let Some(updated_at) = E::updated_at() {
+        update = update.col_expr(updated_at, Expr::value(now));
+    }
+
+    update
+        .filter(E::deleted_at().is_null())
+        .filter(filter)
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Restores every record of `E` that `filter` selects and that was deleted at `deleted_at`,
+/// which is when the record they were deleted along with was. Records deleted on their own
+/// at another time stay deleted.
+pub(crate) async fn restore_many<E, C>(
+    db: &C,
+    filter: SimpleExpr,
+    deleted_at: DateTimeWithTimeZone,
+    now: DateTimeWithTimeZone,
+) -> Result<(), Error>
+where
+    E: SoftDelete,
+    C: ConnectionTrait,
+{
+    let mut update =
+        E::update_many().col_expr(E::deleted_at(), Expr::value(None::<DateTimeWithTimeZone>));
+    if let Some(updated_at) = E::updated_at() {
+        update = update.col_expr(updated_at, Expr::value(now));
+    }
+
+    update
+        .filter(E::deleted_at().eq(deleted_at))
+        .filter(filter)
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes the coaching relationships that `relationships` selects, along with their sessions
+/// and everything in them.
+pub(crate) async fn delete_relationships<C>(
+    db: &C,
+    relationships: SimpleExpr,
+    now: DateTimeWithTimeZone,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    delete_sessions(db, in_relationships(relationships.clone()), now).await?;
+    delete_many::<coaching_relationships::Entity, _>(db, relationships, now).await
+}
+
+/// Restores the coaching relationships that `relationships` selects which were deleted at
+/// `deleted_at`, along with the sessions and content deleted with them.
+pub(crate) async fn restore_relationships<C>(
+    db: &C,
+    relationships: SimpleExpr,
+    deleted_at: DateTimeWithTimeZone,
+    now: DateTimeWithTimeZone,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    restore_sessions(db, in_relationships(relationships.clone()), deleted_at, now).await?;
+    restore_many::<coaching_relationships::Entity, _>(db, relationships, deleted_at, now).await
+}
+
+/// Deletes the coaching sessions that `sessions` selects, along with their notes, agreements,
+/// actions, overarching goals and attachments.
+pub(crate) async fn delete_sessions<C>(
+    db: &C,
+    sessions: SimpleExpr,
+    now: DateTimeWithTimeZone,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    let session_ids = session_ids(sessions.clone());
+
+    delete_many::<notes::Entity, _>(
+        db,
+        notes::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        now,
+    )
+    .await?;
+    delete_many::<agreements::Entity, _>(
+        db,
+        agreements::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        now,
+    )
+    .await?;
+    delete_many::<actions::Entity, _>(
+        db,
+        actions::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        now,
+    )
+    .await?;
+    delete_many::<overarching_goals::Entity, _>(
+        db,
+        overarching_goals::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        now,
+    )
+    .await?;
+    delete_many::<attachments::Entity, _>(
+        db,
+        attachments::Column::CoachingSessionId.in_subquery(session_ids),
+        now,
+    )
+    .await?;
+
+    delete_many::<coaching_sessions::Entity, _>(db, sessions, now).await
+}
+
+/// Restores the coaching sessions that `sessions` selects which were deleted at `deleted_at`,
+/// along with the content deleted with them.
+pub(crate) async fn restore_sessions<C>(
+    db: &C,
+    sessions: SimpleExpr,
+    deleted_at: DateTimeWithTimeZone,
+    now: DateTimeWithTimeZone,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait,
+{
+    let session_ids = session_ids(sessions.clone());
+
+    restore_many::<notes::Entity, _>(
+        db,
+        notes::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        deleted_at,
+        now,
+    )
+    .await?;
+    restore_many::<agreements::Entity, _>(
+        db,
+        agreements::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        deleted_at,
+        now,
+    )
+    .await?;
+    restore_many::<actions::Entity, _>(
+        db,
+        actions::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        deleted_at,
+        now,
+    )
+    .await?;
+    restore_many::<overarching_goals::Entity, _>(
+        db,
+        overarching_goals::Column::CoachingSessionId.in_subquery(session_ids.clone()),
+        deleted_at,
+        now,
+    )
+    .await?;
+    restore_many::<attachments::Entity, _>(
+        db,
+        attachments::Column::CoachingSessionId.in_subquery(session_ids),
+        deleted_at,
+        now,
+    )
+    .await?;
+
+    restore_many::<coaching_sessions::Entity, _>(db, sessions, deleted_at, now).await
+}
+
+// Selects the coaching sessions of the relationships that `relationships` selects.
+fn in_relationships(relationships: SimpleExpr) -> SimpleExpr {
+    coaching_sessions::Column::CoachingRelationshipId.in_subquery(
+        coaching_relationships::Entity::find()
+            .select_only()
+            .column(coaching_relationships::Column::Id)
+            .filter(relationships)
+            .into_query(),
+    )
+}
+
+// The ids of the coaching sessions that `sessions` selects.
+fn session_ids(sessions: SimpleExpr) -> SelectStatement {
+    coaching_sessions::Entity::find()
+        .select_only()
+        .column(coaching_sessions::Column::Id)
+        .filter(sessions)
+        .into_query()
+}
+
+/// Hard deletes every record that was deleted before `deleted_before`, returning how many were
+/// removed. A record is removed along with everything beneath it, including the contents of
+/// attachments in `storage`, even if something beneath it was restored on its own since.
+pub async fn purge<C>(
+    db: &C,
+    storage: &dyn Storage,
+    deleted_before: DateTimeWithTimeZone,
+) -> Result<u64, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let organizations = organizations::Column::DeletedAt.lt(deleted_before);
+    let relationships = coaching_relationships::Column::DeletedAt
+        .lt(deleted_before)
+        .or(coaching_relationships::Column::OrganizationId.in_subquery(
+            organizations::Entity::find()
+                .select_only()
+                .column(organizations::Column::Id)
+                .filter(organizations.clone())
+                .into_query(),
+        ));
+    let sessions = coaching_sessions::Column::DeletedAt
+        .lt(deleted_before)
+        .or(in_relationships(relationships.clone()));
+    let session_ids = session_ids(sessions.clone());
+
+    let txn = db.begin().await?;
+
+    let attachments = attachments::Column::DeletedAt
+        .lt(deleted_before)
+        .or(attachments::Column::CoachingSessionId.in_subquery(session_ids.clone()));
+    let storage_keys = attachments::Entity::find()
+        .select_only()
+        .column(attachments::Column::StorageKey)
+        .filter(attachments.clone())
+        .into_tuple::<String>()
+        .all(&txn)
+        .await?;
+
+    // Status transitions and revisions go with their records through ON DELETE CASCADE
+    let mut purged = attachments::Entity::delete_many()
+        .filter(attachments)
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    purged += notes::Entity::delete_many()
+        .filter(
+            notes::Column::DeletedAt
+                .lt(deleted_before)
+                .or(notes::Column::CoachingSessionId.in_subquery(session_ids.clone())),
+        )
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    purged += agreements::Entity::delete_many()
+        .filter(
+            agreements::Column::DeletedAt
+                .lt(deleted_before)
+                .or(agreements::Column::CoachingSessionId.in_subquery(session_ids.clone())),
+        )
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    purged += actions::Entity::delete_many()
+        .filter(
+            actions::Column::DeletedAt
+                .lt(deleted_before)
+                .or(actions::Column::CoachingSessionId.in_subquery(session_ids.clone())),
+        )
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    purged += overarching_goals::Entity::delete_many()
+        .filter(
+            overarching_goals::Column::DeletedAt
+                .lt(deleted_before)
+                .or(overarching_goals::Column::CoachingSessionId.in_subquery(session_ids)),
+        )
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    purged += coaching_sessions::Entity::delete_many()
+        .filter(sessions)
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    // A relationship that survives the purge, or is only purged later, may have been
+    // reassigned to one purged now, which would otherwise still be referenced
+    coaching_relationships::Entity::update_many()
+        .col_expr(
+            coaching_relationships::Column::ReassignedToId,
+            Expr::value(Option::<Id>::None),
+        )
+        .filter(
+            coaching_relationships::Column::ReassignedToId.in_subquery(
+                coaching_relationships::Entity::find()
+                    .select_only()
+                    .column(coaching_relationships::Column::Id)
+                    .filter(relationships.clone())
+                    .into_query(),
+            ),
+        )
+        .exec(&txn)
+        .await?;
+    purged += coaching_relationships::Entity::delete_many()
+        .filter(relationships)
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    organization_admins::Entity::delete_many()
+        .filter(
+            organization_admins::Column::OrganizationId.in_subquery(
+                organizations::Entity::find()
+                    .select_only()
+                    .column(organizations::Column::Id)
+                    .filter(organizations.clone())
+                    .into_query(),
+            ),
+        )
+        .exec(&txn)
+        .await?;
+    purged += organizations::Entity::delete_many()
+        .filter(organizations)
+        .exec(&txn)
+        .await?
+        .rows_affected;
+
+    txn.commit().await?;
+
+    // The records are gone either way, so leftover contents only cost storage space
+    for storage_key in storage_keys {
+        if let Err(err) = storage.delete(&storage_key).await {
+            warn!(
+                "Failed to delete purged attachment contents {}: {:?}",
+                storage_key, err
+            );
+        }
+    }
+
+    debug!(
+        "Purged {} records deleted before {}",
+        purged, deleted_before
+    );
+
+    Ok(purged)
+}
+
+/// Purges records that have been deleted for longer than `retention_days`, checking again every
+/// hour for as long as the server runs.
+pub async fn purge_continuously(
+    db: Arc<DatabaseConnection>,
+    storage: Arc<dyn Storage>,
+    retention_days: u64,
+) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let deleted_before = Utc::now() - Days::new(retention_days);
+        match purge(db.as_ref(), storage.as_ref(), deleted_before.into()).await {
+            Ok(0) => {}
+            Ok(purged) => info!(
+                "Purged {} records deleted before {}",
+                purged, deleted_before
+            ),
+            Err(err) => error!("Failed to purge deleted records: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use service::storage::LocalStorage;
+    use std::collections::BTreeMap;
+
+    fn rows_affected(rows_affected: u64) -> MockExecResult {
+        MockExecResult {
+            last_insert_id: 0,
+            rows_affected,
+        }
+    }
+
+    #[tokio::test]
+    async fn purge_hard_deletes_records_deleted_before_the_cutoff() -> Result<(), Error> {
+        // Attachments, notes, agreements, actions, overarching goals, sessions, the
+        // reassignments to relationships, relationships, organization admins and then
+        // organizations
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<BTreeMap<&str, Value>>::new()])
+            .append_exec_results([
+                rows_affected(1),
+                rows_affected(0),
+                rows_affected(1),
+                rows_affected(2),
+                rows_affected(0),
+                rows_affected(1),
+                rows_affected(0),
+                rows_affected(1),
+                rows_affected(1),
+                rows_affected(1),
+            ])
+            .into_connection();
+        let storage = LocalStorage::new(std::env::temp_dir().join("purge"));
+
+        let purged = purge(&db, &storage, Utc::now().into()).await?;
+
+        // Organization admins aren't soft deleted records of their own
+        assert_eq!(purged, 7);
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(transaction_log.contains(
+            r#"DELETE FROM \"refactor_platform\".\"actions\" WHERE \"actions\".\"deleted_at\" < $1 OR \"actions\".\"coaching_session_id\" IN (SELECT \"coaching_sessions\".\"id\" FROM \"refactor_platform\".\"coaching_sessions\" WHERE \"coaching_sessions\".\"deleted_at\" < $2 OR"#
+        ));
+        assert!(transaction_log.contains(
+            r#"DELETE FROM \"refactor_platform\".\"coaching_relationships\" WHERE \"coaching_relationships\".\"deleted_at\" < $1 OR \"coaching_relationships\".\"organization_id\" IN (SELECT \"organizations\".\"id\" FROM \"refactor_platform\".\"organizations\" WHERE \"organizations\".\"deleted_at\" < $2)"#
+        ));
+        assert!(transaction_log.contains(
+            r#"DELETE FROM \"refactor_platform\".\"organizations\" WHERE \"organizations\".\"deleted_at\" < $1"#
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn purge_clears_reassignments_to_purged_relationships_before_deleting_them(
+    ) -> Result<(), Error> {
+        // A predecessor kept on after its successor was deleted, and the successor itself
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<BTreeMap<&str, Value>>::new()])
+            .append_exec_results([
+                rows_affected(0),
+                rows_affected(0),
+                rows_affected(0),
+                rows_affected(0),
+                rows_affected(0),
+                rows_affected(0),
+                rows_affected(1),
+                rows_affected(1),
+                rows_affected(0),
+                rows_affected(0),
+            ])
+            .into_connection();
+        let storage = LocalStorage::new(std::env::temp_dir().join("purge"));
+
+        let purged = purge(&db, &storage, Utc::now().into()).await?;
+
+        assert_eq!(purged, 1);
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        let reassignments = transaction_log
+            .find(r#"UPDATE \"refactor_platform\".\"coaching_relationships\" SET \"reassigned_to_id\" = $1 WHERE \"coaching_relationships\".\"reassigned_to_id\" IN (SELECT \"coaching_relationships\".\"id\" FROM \"refactor_platform\".\"coaching_relationships\" WHERE \"coaching_relationships\".\"deleted_at\" < $2 OR"#)
+            .expect("reassignments to purged relationships were not cleared");
+        let relationships = transaction_log
+            .find(r#"DELETE FROM \"refactor_platform\".\"coaching_relationships\""#)
+            .expect("relationships were not purged");
+        assert!(reassignments < relationships);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use super::error::{EntityApiErrorCode, Error};
+use entity::status_transitions::{self, ActiveModel, Entity, Model};
+use entity::{
+    status::{Status, TransitionPolicy},
+    Id,
+};
+use sea_orm::{entity::prelude::*, ConnectionTrait, QueryOrder, Set};
+
+use log::*;
+
+/// The entity whose status changed, used to record a StatusTransition against it.
+#[derive(Debug, Clone, Copy)]
+pub enum Subject {
+    Action(Id),
+    OverarchingGoal(Id),
+}
+
+/// Rejects a status change that `policy` does not permit.
+pub(crate) fn check(from: &Status, to: &Status, policy: &TransitionPolicy) -> Result<(), Error> {
+    if from.can_transition_to(to, policy) {
+        Ok(())
+    } else {
+        error!(
+            "Status transition from {:?} to {:?} is not permitted",
+            from, to
+        );
+
+        Err(Error {
+            inner: None,
+            error_code: EntityApiErrorCode::InvalidStatusTransition,
+            detail: Some(format!("The status cannot change from {from:?} to {to:?}.")),
+        })
+    }
+}
+
+/// Records a single status change. This takes any connection so that callers can
+/// write the transition in the same database transaction as the status change itself.
+pub(crate) async fn create<C>(
+    db: &C,
+    subject: Subject,
+    from_status: Option<Status>,
+    to_status: Status,
+    user_id: Id,
+) -> Result<Model, Error>
+where
+    C: ConnectionTrait,
+{
+    debug!(
+        "New Status Transition to be inserted for {:?}: {:?} -> {:?}",
+        subject, from_status, to_status
+    );
+
+    let (action_id, overarching_goal_id) = match subject {
+        Subject::Action(id) => (Some(id), None),
+        Subject::OverarchingGoal(id) => (None, Some(id)),
+    };
+
+    let status_transition_active_model: ActiveModel = ActiveModel {
+        action_id: Set(action_id),
+        overarching_goal_id: Set(overarching_goal_id),
+        from_status: Set(from_status),
+        to_status: Set(to_status),
+        user_id: Set(user_id),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    };
+
+    Ok(status_transition_active_model.insert(db).await?)
+}
+
+/// Returns every status change for an Action, oldest first.
+pub async fn find_by_action<C>(db: &C, action_id: Id) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .filter(status_transitions::Column::ActionId.eq(action_id))
+        .order_by_asc(status_transitions::Column::CreatedAt)
+        .all(db)
+        .await?)
+}
+
+/// Returns every status change for an Overarching Goal, oldest first.
+pub async fn find_by_overarching_goal<C>(
+    db: &C,
+    overarching_goal_id: Id,
+) -> Result<Vec<Model>, Error>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .filter(status_transitions::Column::OverarchingGoalId.eq(overarching_goal_id))
+        .order_by_asc(status_transitions::Column::CreatedAt)
+        .all(db)
+        .await?)
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use entity::status::{ACTION_TRANSITION_POLICY, OVERARCHING_GOAL_TRANSITION_POLICY};
+    use sea_orm::{DatabaseBackend, MockDatabase, Transaction};
+
+    #[test]
+    fn check_permits_staying_on_the_same_status() {
+        assert!(check(
+            &Status::Completed,
+            &Status::Completed,
+            &ACTION_TRANSITION_POLICY
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn check_rejects_completing_an_action_that_was_never_started() {
+        let result = check(
+            &Status::NotStarted,
+            &Status::Completed,
+            &ACTION_TRANSITION_POLICY,
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error {
+                error_code: EntityApiErrorCode::InvalidStatusTransition,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn check_applies_the_policy_of_the_entity_type() {
+        assert!(check(
+            &Status::Completed,
+            &Status::WontDo,
+            &OVERARCHING_GOAL_TRANSITION_POLICY
+        )
+        .is_ok());
+        assert!(check(
+            &Status::Completed,
+            &Status::WontDo,
+            &ACTION_TRANSITION_POLICY
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn find_by_action_returns_transitions_oldest_first() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let action_id = Id::new_v4();
+
+        let _ = find_by_action(&db, action_id).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"SELECT "status_transitions"."id", "status_transitions"."action_id", "status_transitions"."overarching_goal_id", CAST("status_transitions"."from_status" AS text), CAST("status_transitions"."to_status" AS text), "status_transitions"."user_id", "status_transitions"."created_at" FROM "refactor_platform"."status_transitions" WHERE "status_transitions"."action_id" = $1 ORDER BY "status_transitions"."created_at" ASC"#,
+                [action_id.into()]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_by_overarching_goal_returns_transitions_oldest_first() -> Result<(), Error> {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let overarching_goal_id = Id::new_v4();
+
+        let _ = find_by_overarching_goal(&db, overarching_goal_id).await;
+
+        assert_eq!(
+            db.into_transaction_log(),
+            [Transaction::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"SELECT "status_transitions"."id", "status_transitions"."action_id", "status_transitions"."overarching_goal_id", CAST("status_transitions"."from_status" AS text), CAST("status_transitions"."to_status" AS text), "status_transitions"."user_id", "status_transitions"."created_at" FROM "refactor_platform"."status_transitions" WHERE "status_transitions"."overarching_goal_id" = $1 ORDER BY "status_transitions"."created_at" ASC"#,
+                [overarching_goal_id.into()]
+            )]
+        );
+
+        Ok(())
+    }
+}
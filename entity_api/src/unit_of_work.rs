@@ -0,0 +1,133 @@
+//! Units of work, which apply several entity_api operations all together or not at all.
+//!
+//! Every entity_api function takes any connection, so the operations of a unit of work are
+//! simply given the transaction that `run` hands to it. Operations that use a transaction of
+//! their own nest it inside the unit of work's, so they only take effect once the whole unit
+//! of work commits.
+
+use super::error::Error;
+use sea_orm::{DatabaseTransaction, TransactionTrait};
+use std::{future::Future, pin::Pin};
+
+use log::*;
+
+/// The operations of a unit of work, run against the transaction they are given.
+pub type Work<'txn, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'txn>>;
+
+/// Runs `work` in a single transaction, committing it if `work` succeeds and rolling back
+/// everything `work` did if it fails. `work` returns its operations boxed, as in
+/// `|txn| Box::pin(async move { ... })`.
+pub async fn run<C, T, F>(db: &C, work: F) -> Result<T, Error>
+where
+    C: TransactionTrait,
+    F: for<'txn> FnOnce(&'txn DatabaseTransaction) -> Work<'txn, T>,
+{
+    let txn = db.begin().await?;
+
+    match work(&txn).await {
+        Ok(value) => {
+            txn.commit().await?;
+
+            Ok(value)
+        }
+        Err(err) => {
+            debug!("Rolling back unit of work that failed with: {:?}", err);
+
+            // The transaction is rolled back when dropped anyway, so the original error is
+            // what's worth reporting
+            if let Err(rollback_err) = txn.rollback().await {
+                warn!("Failed to roll back unit of work: {:?}", rollback_err);
+            }
+
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+// We need to gate seaORM's mock feature behind conditional compilation because
+// the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
+// see https://github.com/SeaQL/sea-orm/issues/830
+#[cfg(feature = "mock")]
+mod tests {
+    use super::*;
+    use crate::{audit, organization};
+    use entity::{organizations, Id};
+    use sea_orm::{DatabaseBackend, MockDatabase};
+
+    fn organization_model(name: &str) -> organizations::Model {
+        let now = chrono::Utc::now();
+
+        organizations::Model {
+            id: Id::new_v4(),
+            name: name.to_owned(),
+            logo: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn run_commits_every_operation_in_one_transaction() -> Result<(), Error> {
+        let first = organization_model("First");
+        let second = organization_model("Second");
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![first.clone()], vec![second.clone()]])
+            .append_exec_results([audit::recorded(), audit::recorded()])
+            .into_connection();
+
+        let created = run(&db, |txn| {
+            Box::pin(async move {
+                let first = organization::create(txn, first).await?;
+                let second = organization::create(txn, second).await?;
+
+                Ok((first.name, second.name))
+            })
+        })
+        .await?;
+
+        assert_eq!(created, ("First".to_owned(), "Second".to_owned()));
+
+        let transaction_log = db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 1);
+
+        let transaction_log = format!("{:?}", transaction_log);
+        assert_eq!(transaction_log.matches("SAVEPOINT").count(), 4);
+        assert!(transaction_log
+            .ends_with(r#"Statement { sql: "COMMIT", values: None, db_backend: Postgres }] }]"#));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_rolls_back_every_operation_when_one_fails() -> Result<(), Error> {
+        let first = organization_model("First");
+
+        // Nothing is left for the second organization, so creating it fails
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![first.clone()]])
+            .append_exec_results([audit::recorded()])
+            .into_connection();
+
+        let result = run(&db, |txn| {
+            Box::pin(async move {
+                organization::create(txn, first).await?;
+                organization::create(txn, organization_model("Second")).await?;
+
+                Ok(())
+            })
+        })
+        .await;
+
+        assert!(result.is_err());
+
+        let transaction_log = format!("{:?}", db.into_transaction_log());
+        assert!(!transaction_log.contains("COMMIT"));
+        assert!(transaction_log
+            .ends_with(r#"Statement { sql: "ROLLBACK", values: None, db_backend: Postgres }] }]"#));
+
+        Ok(())
+    }
+}
@@ -2,17 +2,21 @@ use super::error::{EntityApiErrorCode, Error};
 use async_trait::async_trait;
 use axum_login::{AuthnBackend, UserId};
 use chrono::Utc;
-use entity::users::*;
+use entity::{audit_action::AuditAction, users::*};
 use log::*;
 use password_auth::{generate_hash, verify_password};
-use sea_orm::{entity::prelude::*, DatabaseConnection, Set};
+use sea_orm::{entity::prelude::*, DatabaseConnection, Set, TransactionTrait};
 use serde::Deserialize;
 use std::sync::Arc;
 use utoipa::ToSchema;
 
+use crate::audit::{self, Within};
 use crate::user::Entity;
 
-pub async fn create(db: &DatabaseConnection, user_model: Model) -> Result<Model, Error> {
+pub async fn create<C>(db: &C, user_model: Model) -> Result<Model, Error>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     debug!(
         "New User Relationship Model to be inserted: {:?}",
         user_model
@@ -33,10 +37,31 @@ pub async fn create(db: &DatabaseConnection, user_model: Model) -> Result<Model,
         ..Default::default()
     };
 
-    Ok(user_active_model.insert(db).await?)
+    let changed_fields = audit::changed_fields(&user_active_model);
+
+    let txn = db.begin().await?;
+    let user = user_active_model.insert(&txn).await?;
+
+    // Users belong to no organization of their own
+    audit::record(
+        &txn,
+        Entity,
+        AuditAction::Create,
+        user.id,
+        changed_fields,
+        Within::Nothing,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(user)
 }
 
-pub async fn find_by_email(db: &DatabaseConnection, email: &str) -> Result<Option<Model>, Error> {
+pub async fn find_by_email<C>(db: &C, email: &str) -> Result<Option<Model>, Error>
+where
+    C: ConnectionTrait,
+{
     let user: Option<Model> = Entity::find()
         .filter(Column::Email.contains(email))
         .one(db)
@@ -53,6 +78,7 @@ async fn authenticate_user(creds: Credentials, user: Model) -> Result<Option<Mod
         Err(_) => Err(Error {
             inner: None,
             error_code: EntityApiErrorCode::RecordUnauthenticated,
+            detail: None,
         }),
     }
 }
@@ -93,11 +119,12 @@ impl AuthnBackend for Backend {
     ) -> Result<Option<Self::User>, Self::Error> {
         debug!("** authenticate(): {:?}:{:?}", creds.email, creds.password);
 
-        match find_by_email(&self.db, &creds.email).await? {
+        match find_by_email(self.db.as_ref(), &creds.email).await? {
             Some(user) => authenticate_user(creds, user).await,
             None => Err(Error {
                 inner: None,
                 error_code: EntityApiErrorCode::RecordUnauthenticated,
+                detail: None,
             }),
         }
     }
@@ -1,12 +1,40 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20240211_174355_base_migration;
+mod m20241220_154512_create_status_transitions;
+mod m20241227_101845_add_search_vectors;
+mod m20250106_143020_create_revisions;
+mod m20250110_091530_add_note_visibility;
+mod m20250114_102210_create_attachments;
+mod m20250117_140405_add_agreement_acknowledgments;
+mod m20250121_093512_add_coaching_relationship_lifecycle;
+mod m20250123_161045_unique_active_coaching_relationships;
+mod m20250128_104730_create_organization_admins;
+mod m20250204_091530_add_soft_delete;
+mod m20250211_103015_create_audit_events;
+mod m20250214_094520_add_agreement_acknowledged_revision;
+mod m20250218_101240_add_soft_delete_to_coaching_content;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20240211_174355_base_migration::Migration)]
+        vec![
+            Box::new(m20240211_174355_base_migration::Migration),
+            Box::new(m20241220_154512_create_status_transitions::Migration),
+            Box::new(m20241227_101845_add_search_vectors::Migration),
+            Box::new(m20250106_143020_create_revisions::Migration),
+            Box::new(m20250110_091530_add_note_visibility::Migration),
+            Box::new(m20250114_102210_create_attachments::Migration),
+            Box::new(m20250117_140405_add_agreement_acknowledgments::Migration),
+            Box::new(m20250121_093512_add_coaching_relationship_lifecycle::Migration),
+            Box::new(m20250123_161045_unique_active_coaching_relationships::Migration),
+            Box::new(m20250128_104730_create_organization_admins::Migration),
+            Box::new(m20250204_091530_add_soft_delete::Migration),
+            Box::new(m20250211_103015_create_audit_events::Migration),
+            Box::new(m20250214_094520_add_agreement_acknowledged_revision::Migration),
+            Box::new(m20250218_101240_add_soft_delete_to_coaching_content::Migration),
+        ]
     }
 }
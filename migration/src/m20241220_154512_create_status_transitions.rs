@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"CREATE TABLE "refactor_platform"."status_transitions" (
+  "id" uuid UNIQUE PRIMARY KEY NOT NULL DEFAULT (gen_random_uuid()),
+  "action_id" uuid,
+  "overarching_goal_id" uuid,
+  "from_status" refactor_platform.status,
+  "to_status" refactor_platform.status NOT NULL,
+  "user_id" uuid NOT NULL,
+  "created_at" timestamptz NOT NULL DEFAULT (now()),
+  CONSTRAINT "status_transitions_single_subject" CHECK (num_nonnulls("action_id", "overarching_goal_id") = 1)
+);
+
+COMMENT ON COLUMN "refactor_platform"."status_transitions"."action_id" IS 'The action whose status changed, if the subject is an action';
+
+COMMENT ON COLUMN "refactor_platform"."status_transitions"."overarching_goal_id" IS 'The overarching goal whose status changed, if the subject is an overarching goal';
+
+COMMENT ON COLUMN "refactor_platform"."status_transitions"."from_status" IS 'The status before the change';
+
+COMMENT ON COLUMN "refactor_platform"."status_transitions"."to_status" IS 'The status after the change';
+
+COMMENT ON COLUMN "refactor_platform"."status_transitions"."user_id" IS 'User that made the status change';
+
+CREATE INDEX "status_transitions_action_id_idx" ON "refactor_platform"."status_transitions" ("action_id");
+
+CREATE INDEX "status_transitions_overarching_goal_id_idx" ON "refactor_platform"."status_transitions" ("overarching_goal_id");
+
+ALTER TABLE "refactor_platform"."status_transitions" ADD FOREIGN KEY ("action_id") REFERENCES "refactor_platform"."actions" ("id") ON DELETE CASCADE;
+
+ALTER TABLE "refactor_platform"."status_transitions" ADD FOREIGN KEY ("overarching_goal_id") REFERENCES "refactor_platform"."overarching_goals" ("id") ON DELETE CASCADE;
+
+ALTER TABLE "refactor_platform"."status_transitions" ADD FOREIGN KEY ("user_id") REFERENCES "refactor_platform"."users" ("id");"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(r#"DROP TABLE IF EXISTS "refactor_platform"."status_transitions";"#)
+            .await?;
+
+        Ok(())
+    }
+}
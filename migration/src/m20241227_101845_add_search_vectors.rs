@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."notes" ADD COLUMN "search_vector" tsvector GENERATED ALWAYS AS (to_tsvector('english', coalesce("body", ''))) STORED;
+
+ALTER TABLE "refactor_platform"."agreements" ADD COLUMN "search_vector" tsvector GENERATED ALWAYS AS (to_tsvector('english', coalesce("body", ''))) STORED;
+
+ALTER TABLE "refactor_platform"."actions" ADD COLUMN "search_vector" tsvector GENERATED ALWAYS AS (to_tsvector('english', coalesce("body", ''))) STORED;
+
+ALTER TABLE "refactor_platform"."overarching_goals" ADD COLUMN "search_vector" tsvector GENERATED ALWAYS AS (setweight(to_tsvector('english', coalesce("title", '')), 'A') || setweight(to_tsvector('english', coalesce("body", '')), 'B')) STORED;
+
+CREATE INDEX "notes_search_vector_idx" ON "refactor_platform"."notes" USING GIN ("search_vector");
+
+CREATE INDEX "agreements_search_vector_idx" ON "refactor_platform"."agreements" USING GIN ("search_vector");
+
+CREATE INDEX "actions_search_vector_idx" ON "refactor_platform"."actions" USING GIN ("search_vector");
+
+CREATE INDEX "overarching_goals_search_vector_idx" ON "refactor_platform"."overarching_goals" USING GIN ("search_vector");"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Dropping the columns also drops their indexes
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."notes" DROP COLUMN IF EXISTS "search_vector";
+
+ALTER TABLE "refactor_platform"."agreements" DROP COLUMN IF EXISTS "search_vector";
+
+ALTER TABLE "refactor_platform"."actions" DROP COLUMN IF EXISTS "search_vector";
+
+ALTER TABLE "refactor_platform"."overarching_goals" DROP COLUMN IF EXISTS "search_vector";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
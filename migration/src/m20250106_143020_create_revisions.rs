@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"CREATE TABLE "refactor_platform"."revisions" (
+  "id" uuid UNIQUE PRIMARY KEY NOT NULL DEFAULT (gen_random_uuid()),
+  "note_id" uuid,
+  "agreement_id" uuid,
+  "overarching_goal_id" uuid,
+  "body" varchar,
+  "user_id" uuid NOT NULL,
+  "created_at" timestamptz NOT NULL DEFAULT (now()),
+  CONSTRAINT "revisions_single_subject" CHECK (num_nonnulls("note_id", "agreement_id", "overarching_goal_id") = 1)
+);
+
+COMMENT ON COLUMN "refactor_platform"."revisions"."note_id" IS 'The note this is a revision of, if the subject is a note';
+
+COMMENT ON COLUMN "refactor_platform"."revisions"."agreement_id" IS 'The agreement this is a revision of, if the subject is an agreement';
+
+COMMENT ON COLUMN "refactor_platform"."revisions"."overarching_goal_id" IS 'The overarching goal this is a revision of, if the subject is an overarching goal';
+
+COMMENT ON COLUMN "refactor_platform"."revisions"."body" IS 'The full body text as of this revision';
+
+COMMENT ON COLUMN "refactor_platform"."revisions"."user_id" IS 'User that authored this revision';
+
+CREATE INDEX "revisions_note_id_idx" ON "refactor_platform"."revisions" ("note_id");
+
+CREATE INDEX "revisions_agreement_id_idx" ON "refactor_platform"."revisions" ("agreement_id");
+
+CREATE INDEX "revisions_overarching_goal_id_idx" ON "refactor_platform"."revisions" ("overarching_goal_id");
+
+ALTER TABLE "refactor_platform"."revisions" ADD FOREIGN KEY ("note_id") REFERENCES "refactor_platform"."notes" ("id") ON DELETE CASCADE;
+
+ALTER TABLE "refactor_platform"."revisions" ADD FOREIGN KEY ("agreement_id") REFERENCES "refactor_platform"."agreements" ("id") ON DELETE CASCADE;
+
+ALTER TABLE "refactor_platform"."revisions" ADD FOREIGN KEY ("overarching_goal_id") REFERENCES "refactor_platform"."overarching_goals" ("id") ON DELETE CASCADE;
+
+ALTER TABLE "refactor_platform"."revisions" ADD FOREIGN KEY ("user_id") REFERENCES "refactor_platform"."users" ("id");
+
+INSERT INTO "refactor_platform"."revisions" ("note_id", "body", "user_id", "created_at")
+  SELECT "id", "body", "user_id", "updated_at" FROM "refactor_platform"."notes";
+
+INSERT INTO "refactor_platform"."revisions" ("agreement_id", "body", "user_id", "created_at")
+  SELECT "id", "body", "user_id", "updated_at" FROM "refactor_platform"."agreements";
+
+INSERT INTO "refactor_platform"."revisions" ("overarching_goal_id", "body", "user_id", "created_at")
+  SELECT "id", "body", "user_id", "updated_at" FROM "refactor_platform"."overarching_goals";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(r#"DROP TABLE IF EXISTS "refactor_platform"."revisions";"#)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"CREATE TYPE "refactor_platform"."visibility" AS ENUM (
+  'shared',
+  'private'
+);
+
+ALTER TABLE "refactor_platform"."notes" ADD COLUMN "visibility" refactor_platform.visibility NOT NULL DEFAULT 'shared';
+
+COMMENT ON COLUMN "refactor_platform"."notes"."visibility" IS 'Whether the note is shared with everyone in the coaching session or private to its author';"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."notes" DROP COLUMN IF EXISTS "visibility";
+
+DROP TYPE IF EXISTS "refactor_platform"."visibility";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"CREATE TABLE "refactor_platform"."attachments" (
+  "id" uuid UNIQUE PRIMARY KEY NOT NULL DEFAULT (gen_random_uuid()),
+  "coaching_session_id" uuid NOT NULL,
+  "user_id" uuid NOT NULL,
+  "filename" varchar NOT NULL,
+  "content_type" varchar NOT NULL,
+  "size_bytes" bigint NOT NULL,
+  "storage_key" varchar UNIQUE NOT NULL,
+  "created_at" timestamptz NOT NULL DEFAULT (now())
+);
+
+COMMENT ON COLUMN "refactor_platform"."attachments"."user_id" IS 'User that uploaded the attachment';
+
+COMMENT ON COLUMN "refactor_platform"."attachments"."filename" IS 'The name of the file as uploaded';
+
+COMMENT ON COLUMN "refactor_platform"."attachments"."content_type" IS 'The MIME type of the file';
+
+COMMENT ON COLUMN "refactor_platform"."attachments"."storage_key" IS 'Where the file contents are kept in the configured attachment storage';
+
+CREATE INDEX "attachments_coaching_session_id_idx" ON "refactor_platform"."attachments" ("coaching_session_id");
+
+ALTER TABLE "refactor_platform"."attachments" ADD FOREIGN KEY ("coaching_session_id") REFERENCES "refactor_platform"."coaching_sessions" ("id");
+
+ALTER TABLE "refactor_platform"."attachments" ADD FOREIGN KEY ("user_id") REFERENCES "refactor_platform"."users" ("id");"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(r#"DROP TABLE IF EXISTS "refactor_platform"."attachments";"#)
+            .await?;
+
+        Ok(())
+    }
+}
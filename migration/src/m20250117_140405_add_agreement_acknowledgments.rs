@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."agreements" ADD COLUMN "coach_acknowledged_at" timestamptz;
+
+ALTER TABLE "refactor_platform"."agreements" ADD COLUMN "coachee_acknowledged_at" timestamptz;
+
+COMMENT ON COLUMN "refactor_platform"."agreements"."coach_acknowledged_at" IS 'When the coach acknowledged the current body of the agreement, cleared when the body changes';
+
+COMMENT ON COLUMN "refactor_platform"."agreements"."coachee_acknowledged_at" IS 'When the coachee acknowledged the current body of the agreement, cleared when the body changes';"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."agreements" DROP COLUMN IF EXISTS "coach_acknowledged_at";
+
+ALTER TABLE "refactor_platform"."agreements" DROP COLUMN IF EXISTS "coachee_acknowledged_at";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
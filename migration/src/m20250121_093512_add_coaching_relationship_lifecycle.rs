@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"CREATE TYPE "refactor_platform"."relationship_status" AS ENUM (
+  'active',
+  'paused',
+  'ended'
+);
+
+ALTER TABLE "refactor_platform"."coaching_relationships" ADD COLUMN "status" refactor_platform.relationship_status NOT NULL DEFAULT 'active';
+
+ALTER TABLE "refactor_platform"."coaching_relationships" ADD COLUMN "start_date" date NOT NULL DEFAULT (CURRENT_DATE);
+
+ALTER TABLE "refactor_platform"."coaching_relationships" ADD COLUMN "end_date" date;
+
+ALTER TABLE "refactor_platform"."coaching_relationships" ADD COLUMN "reassigned_to_id" uuid;
+
+UPDATE "refactor_platform"."coaching_relationships" SET "start_date" = "created_at"::date;
+
+COMMENT ON COLUMN "refactor_platform"."coaching_relationships"."status" IS 'Whether coaching is ongoing, on hold or over';
+
+COMMENT ON COLUMN "refactor_platform"."coaching_relationships"."start_date" IS 'The date coaching began';
+
+COMMENT ON COLUMN "refactor_platform"."coaching_relationships"."end_date" IS 'The date coaching ended, set once the relationship has ended';
+
+COMMENT ON COLUMN "refactor_platform"."coaching_relationships"."reassigned_to_id" IS 'The relationship that replaced this one when the coachee was reassigned to another coach';
+
+ALTER TABLE "refactor_platform"."coaching_relationships" ADD FOREIGN KEY ("reassigned_to_id") REFERENCES "refactor_platform"."coaching_relationships" ("id");"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."coaching_relationships" DROP COLUMN IF EXISTS "reassigned_to_id";
+
+ALTER TABLE "refactor_platform"."coaching_relationships" DROP COLUMN IF EXISTS "end_date";
+
+ALTER TABLE "refactor_platform"."coaching_relationships" DROP COLUMN IF EXISTS "start_date";
+
+ALTER TABLE "refactor_platform"."coaching_relationships" DROP COLUMN IF EXISTS "status";
+
+DROP TYPE IF EXISTS "refactor_platform"."relationship_status";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Pairings duplicated before this constraint existed can't be told apart, so all but
+        // the most recent of each are ended. Their sessions stay with them and remain readable.
+        db.execute_unprepared(
+            r#"UPDATE "refactor_platform"."coaching_relationships" AS "duplicate"
+SET "status" = 'ended', "end_date" = CURRENT_DATE, "updated_at" = now()
+WHERE "duplicate"."status" <> 'ended'
+  AND EXISTS (
+    SELECT 1 FROM "refactor_platform"."coaching_relationships" AS "newer"
+    WHERE "newer"."organization_id" = "duplicate"."organization_id"
+      AND "newer"."coach_id" = "duplicate"."coach_id"
+      AND "newer"."coachee_id" = "duplicate"."coachee_id"
+      AND "newer"."status" <> 'ended'
+      AND ("newer"."created_at", "newer"."id") > ("duplicate"."created_at", "duplicate"."id")
+  );
+
+CREATE UNIQUE INDEX "coaching_relationships_unique_active_pairing_idx" ON "refactor_platform"."coaching_relationships" ("organization_id", "coach_id", "coachee_id") WHERE "status" <> 'ended';
+
+ALTER TABLE "refactor_platform"."coaching_relationships" ADD CONSTRAINT "coaching_relationships_distinct_coach_and_coachee" CHECK ("coach_id" <> "coachee_id") NOT VALID;"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."coaching_relationships" DROP CONSTRAINT IF EXISTS "coaching_relationships_distinct_coach_and_coachee";
+
+DROP INDEX IF EXISTS "refactor_platform"."coaching_relationships_unique_active_pairing_idx";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"CREATE TABLE "refactor_platform"."organization_admins" (
+  "id" uuid UNIQUE PRIMARY KEY NOT NULL DEFAULT (gen_random_uuid()),
+  "organization_id" uuid NOT NULL,
+  "user_id" uuid NOT NULL,
+  "created_at" timestamptz NOT NULL DEFAULT (now())
+);
+
+COMMENT ON TABLE "refactor_platform"."organization_admins" IS 'Users that administer an organization, e.g. the sponsor paying for its coaching';
+
+CREATE UNIQUE INDEX "organization_admins_organization_id_user_id_idx" ON "refactor_platform"."organization_admins" ("organization_id", "user_id");
+
+ALTER TABLE "refactor_platform"."organization_admins" ADD FOREIGN KEY ("organization_id") REFERENCES "refactor_platform"."organizations" ("id");
+
+ALTER TABLE "refactor_platform"."organization_admins" ADD FOREIGN KEY ("user_id") REFERENCES "refactor_platform"."users" ("id");"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(r#"DROP TABLE IF EXISTS "refactor_platform"."organization_admins";"#)
+            .await?;
+
+        Ok(())
+    }
+}
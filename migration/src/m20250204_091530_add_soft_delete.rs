@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."actions" ADD COLUMN "deleted_at" timestamptz;
+
+ALTER TABLE "refactor_platform"."agreements" ADD COLUMN "deleted_at" timestamptz;
+
+ALTER TABLE "refactor_platform"."organizations" ADD COLUMN "deleted_at" timestamptz;
+
+COMMENT ON COLUMN "refactor_platform"."actions"."deleted_at" IS 'When the action was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+COMMENT ON COLUMN "refactor_platform"."agreements"."deleted_at" IS 'When the agreement was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+COMMENT ON COLUMN "refactor_platform"."organizations"."deleted_at" IS 'When the organization was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+CREATE INDEX "actions_deleted_at_idx" ON "refactor_platform"."actions" ("deleted_at") WHERE "deleted_at" IS NOT NULL;
+
+CREATE INDEX "agreements_deleted_at_idx" ON "refactor_platform"."agreements" ("deleted_at") WHERE "deleted_at" IS NOT NULL;
+
+CREATE INDEX "organizations_deleted_at_idx" ON "refactor_platform"."organizations" ("deleted_at") WHERE "deleted_at" IS NOT NULL;"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."actions" DROP COLUMN IF EXISTS "deleted_at";
+
+ALTER TABLE "refactor_platform"."agreements" DROP COLUMN IF EXISTS "deleted_at";
+
+ALTER TABLE "refactor_platform"."organizations" DROP COLUMN IF EXISTS "deleted_at";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
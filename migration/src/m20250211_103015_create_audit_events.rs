@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"CREATE TYPE "refactor_platform"."audit_action" AS ENUM (
+  'create',
+  'update',
+  'delete',
+  'restore'
+);
+
+CREATE TABLE "refactor_platform"."audit_events" (
+  "id" uuid UNIQUE PRIMARY KEY NOT NULL DEFAULT (gen_random_uuid()),
+  "organization_id" uuid,
+  "actor_id" uuid,
+  "action" refactor_platform.audit_action NOT NULL,
+  "entity_type" varchar NOT NULL,
+  "entity_id" uuid NOT NULL,
+  "changed_fields" jsonb NOT NULL DEFAULT '[]',
+  "request_id" varchar,
+  "created_at" timestamptz NOT NULL DEFAULT (now())
+);
+
+COMMENT ON TABLE "refactor_platform"."audit_events" IS 'Append-only log of every write made to a record, kept for compliance. Without foreign keys, so that events outlive the records they describe';
+
+COMMENT ON COLUMN "refactor_platform"."audit_events"."organization_id" IS 'The organization the record belongs to, if any';
+
+COMMENT ON COLUMN "refactor_platform"."audit_events"."actor_id" IS 'The user that made the write, unset for writes the server makes on its own';
+
+COMMENT ON COLUMN "refactor_platform"."audit_events"."changed_fields" IS 'Names of the fields the write set';
+
+COMMENT ON COLUMN "refactor_platform"."audit_events"."request_id" IS 'The X-Request-Id of the request that made the write';
+
+CREATE INDEX "audit_events_organization_id_created_at_idx" ON "refactor_platform"."audit_events" ("organization_id", "created_at");
+
+CREATE INDEX "audit_events_entity_type_entity_id_idx" ON "refactor_platform"."audit_events" ("entity_type", "entity_id");
+
+CREATE INDEX "audit_events_actor_id_idx" ON "refactor_platform"."audit_events" ("actor_id");
+
+CREATE FUNCTION "refactor_platform"."reject_audit_event_changes"() RETURNS trigger AS $$
+BEGIN
+  RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER "audit_events_append_only"
+  BEFORE UPDATE OR DELETE ON "refactor_platform"."audit_events"
+  FOR EACH ROW EXECUTE FUNCTION "refactor_platform"."reject_audit_event_changes"();"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"DROP TABLE IF EXISTS "refactor_platform"."audit_events";
+
+DROP FUNCTION IF EXISTS "refactor_platform"."reject_audit_event_changes"();
+
+DROP TYPE IF EXISTS "refactor_platform"."audit_action";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."agreements" ADD COLUMN "acknowledged_revision_id" uuid;
+
+ALTER TABLE "refactor_platform"."agreements" ADD FOREIGN KEY ("acknowledged_revision_id") REFERENCES "refactor_platform"."revisions" ("id") ON DELETE SET NULL;
+
+COMMENT ON COLUMN "refactor_platform"."agreements"."acknowledged_revision_id" IS 'The revision of the body that the coach and coachee acknowledged, cleared along with their acknowledgments when the body changes';"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."agreements" DROP COLUMN IF EXISTS "acknowledged_revision_id";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
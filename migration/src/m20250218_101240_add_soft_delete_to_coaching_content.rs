@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // A deleted relationship no longer holds its pairing, so the pair can be coached again
+        // in a new relationship while the deleted one waits to be restored or purged.
+        db.execute_unprepared(
+            r#"ALTER TABLE "refactor_platform"."coaching_relationships" ADD COLUMN "deleted_at" timestamptz;
+
+ALTER TABLE "refactor_platform"."coaching_sessions" ADD COLUMN "deleted_at" timestamptz;
+
+ALTER TABLE "refactor_platform"."notes" ADD COLUMN "deleted_at" timestamptz;
+
+ALTER TABLE "refactor_platform"."overarching_goals" ADD COLUMN "deleted_at" timestamptz;
+
+ALTER TABLE "refactor_platform"."attachments" ADD COLUMN "deleted_at" timestamptz;
+
+COMMENT ON COLUMN "refactor_platform"."coaching_relationships"."deleted_at" IS 'When the coaching relationship was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+COMMENT ON COLUMN "refactor_platform"."coaching_sessions"."deleted_at" IS 'When the coaching session was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+COMMENT ON COLUMN "refactor_platform"."notes"."deleted_at" IS 'When the note was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+COMMENT ON COLUMN "refactor_platform"."overarching_goals"."deleted_at" IS 'When the overarching goal was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+COMMENT ON COLUMN "refactor_platform"."attachments"."deleted_at" IS 'When the attachment was deleted, hiding it until it is restored or purged once the retention period has passed';
+
+CREATE INDEX "coaching_relationships_deleted_at_idx" ON "refactor_platform"."coaching_relationships" ("deleted_at") WHERE "deleted_at" IS NOT NULL;
+
+CREATE INDEX "coaching_sessions_deleted_at_idx" ON "refactor_platform"."coaching_sessions" ("deleted_at") WHERE "deleted_at" IS NOT NULL;
+
+CREATE INDEX "notes_deleted_at_idx" ON "refactor_platform"."notes" ("deleted_at") WHERE "deleted_at" IS NOT NULL;
+
+CREATE INDEX "overarching_goals_deleted_at_idx" ON "refactor_platform"."overarching_goals" ("deleted_at") WHERE "deleted_at" IS NOT NULL;
+
+CREATE INDEX "attachments_deleted_at_idx" ON "refactor_platform"."attachments" ("deleted_at") WHERE "deleted_at" IS NOT NULL;
+
+DROP INDEX "refactor_platform"."coaching_relationships_unique_active_pairing_idx";
+
+CREATE UNIQUE INDEX "coaching_relationships_unique_active_pairing_idx" ON "refactor_platform"."coaching_relationships" ("organization_id", "coach_id", "coachee_id") WHERE "status" <> 'ended' AND "deleted_at" IS NULL;"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Deleted relationships would otherwise come back as duplicates of the pairings that
+        // replaced them, so they are ended before the index covers them again.
+        db.execute_unprepared(
+            r#"UPDATE "refactor_platform"."coaching_relationships"
+SET "status" = 'ended', "end_date" = coalesce("end_date", CURRENT_DATE), "updated_at" = now()
+WHERE "deleted_at" IS NOT NULL AND "status" <> 'ended';
+
+DROP INDEX IF EXISTS "refactor_platform"."coaching_relationships_unique_active_pairing_idx";
+
+CREATE UNIQUE INDEX "coaching_relationships_unique_active_pairing_idx" ON "refactor_platform"."coaching_relationships" ("organization_id", "coach_id", "coachee_id") WHERE "status" <> 'ended';
+
+ALTER TABLE "refactor_platform"."coaching_relationships" DROP COLUMN IF EXISTS "deleted_at";
+
+ALTER TABLE "refactor_platform"."coaching_sessions" DROP COLUMN IF EXISTS "deleted_at";
+
+ALTER TABLE "refactor_platform"."notes" DROP COLUMN IF EXISTS "deleted_at";
+
+ALTER TABLE "refactor_platform"."overarching_goals" DROP COLUMN IF EXISTS "deleted_at";
+
+ALTER TABLE "refactor_platform"."attachments" DROP COLUMN IF EXISTS "deleted_at";"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use clap::builder::TypedValueParser as _;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use dotenvy::dotenv;
 use log::LevelFilter;
 use semver::{BuildMetadata, Prerelease, Version};
@@ -7,12 +7,14 @@ use serde::Deserialize;
 use std::fmt;
 use utoipa::IntoParams;
 
-type APiVersionList = [&'static str; 1];
-
 const DEFAULT_API_VERSION: &str = "0.0.1";
-// Expand this array to include all valid API versions. Versions that have been
-// completely removed should be removed from this list - they're no longer valid.
-const API_VERSIONS: APiVersionList = [DEFAULT_API_VERSION];
+// Expand this array to include all valid API versions, moving each through its lifecycle as
+// newer versions replace it. Versions that have been completely removed should be removed
+// from this list - they're no longer valid.
+const API_VERSIONS: [ReleasedVersion; 1] = [ReleasedVersion {
+    version: DEFAULT_API_VERSION,
+    lifecycle: Lifecycle::Supported,
+}];
 
 static X_VERSION: &str = "x-version";
 
@@ -24,6 +26,37 @@ pub struct ApiVersion {
     pub version: Version,
 }
 
+/// Where a version of the API is in its lifecycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lifecycle {
+    /// Served with no plans to retire it
+    Supported,
+    /// Served until `sunset_at`, though clients should move to a newer version. Both times
+    /// are in seconds since the Unix epoch.
+    Deprecated { deprecated_at: i64, sunset_at: i64 },
+    /// No longer served
+    Sunset,
+}
+
+/// A version of the API that clients may name in the `x-version` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReleasedVersion {
+    pub version: &'static str,
+    pub lifecycle: Lifecycle,
+}
+
+impl ReleasedVersion {
+    /// Whether requests naming this version are still served at `now`, in seconds since the
+    /// Unix epoch. A deprecated version stops being served once its sunset arrives.
+    pub fn is_served_at(&self, now: i64) -> bool {
+        match self.lifecycle {
+            Lifecycle::Supported => true,
+            Lifecycle::Deprecated { sunset_at, .. } => now < sunset_at,
+            Lifecycle::Sunset => false,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Config {
@@ -37,10 +70,15 @@ pub struct Config {
     )]
     pub allowed_origins: Vec<String>,
 
-    /// Set the current semantic version of the endpoint API to expose to clients. All
-    /// endpoints not contained in the specified version will not be exposed by the router.
+    /// Set the current semantic version of the endpoint API. Its OpenAPI document is the one
+    /// the API docs UI shows, and requests that don't name a version are routed to it.
     #[arg(short, long, env, default_value = DEFAULT_API_VERSION,
-        value_parser = clap::builder::PossibleValuesParser::new(API_VERSIONS)
+        value_parser = clap::builder::PossibleValuesParser::new(
+            API_VERSIONS
+                .iter()
+                .filter(|released| released.lifecycle != Lifecycle::Sunset)
+                .map(|released| released.version)
+        )
             .map(|s| s.parse::<String>().unwrap()),
         )]
     pub api_version: Option<String>,
@@ -72,6 +110,63 @@ pub struct Config {
             .map(|s| s.parse::<LevelFilter>().unwrap()),
         )]
     pub log_level_filter: LevelFilter,
+
+    /// Where uploaded coaching session attachments are stored
+    #[arg(long, env, value_enum, default_value_t = StorageBackend::Local)]
+    pub attachment_storage: StorageBackend,
+
+    /// The directory attachments are written to when using local storage
+    #[arg(long, env, default_value = "./attachments")]
+    pub attachment_dir: String,
+
+    /// The largest attachment, in bytes, that may be uploaded
+    #[arg(long, env, default_value_t = 25 * 1024 * 1024)]
+    pub attachment_max_bytes: usize,
+
+    /// The MIME types that attachments may have
+    #[arg(
+        long,
+        env,
+        value_delimiter = ',',
+        use_value_delimiter = true,
+        default_value = "application/pdf,image/png,image/jpeg,image/gif,image/webp,text/plain,text/markdown,text/csv,application/vnd.openxmlformats-officedocument.wordprocessingml.document,application/vnd.openxmlformats-officedocument.spreadsheetml.sheet,application/vnd.openxmlformats-officedocument.presentationml.presentation"
+    )]
+    pub attachment_allowed_types: Vec<String>,
+
+    /// The endpoint URL of an S3-compatible service, e.g. a MinIO instance. Leave unset
+    /// to use AWS S3 itself.
+    #[arg(long, env)]
+    pub s3_endpoint: Option<String>,
+
+    /// The bucket attachments are stored in when using S3 storage
+    #[arg(long, env)]
+    pub s3_bucket: Option<String>,
+
+    /// The region of the S3 bucket
+    #[arg(long, env, default_value = "us-east-1")]
+    pub s3_region: String,
+
+    /// The access key id used to authenticate with S3
+    #[arg(long, env)]
+    pub s3_access_key_id: Option<String>,
+
+    /// The secret access key used to authenticate with S3
+    #[arg(long, env)]
+    pub s3_secret_access_key: Option<String>,
+
+    /// How many days deleted actions, agreements and organizations are kept, so that they can
+    /// still be restored, before they are purged for good
+    #[arg(long, env, default_value_t = 30)]
+    pub soft_delete_retention_days: u64,
+}
+
+/// The backends that attachments can be stored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StorageBackend {
+    /// A directory on the local filesystem
+    Local,
+    /// An S3-compatible object store
+    S3,
 }
 
 impl Default for Config {
@@ -127,8 +222,9 @@ impl ApiVersion {
         X_VERSION
     }
 
-    pub fn versions() -> APiVersionList {
-        API_VERSIONS
+    /// Every released version of the API, whether or not it is still served.
+    pub fn versions() -> &'static [ReleasedVersion] {
+        &API_VERSIONS
     }
 }
 
@@ -1,10 +1,12 @@
 use config::Config;
 use sea_orm::{ConnectOptions, Database, DatabaseConnection, DbErr};
 use std::sync::Arc;
+use storage::Storage;
 use tokio::time::Duration;
 
 pub mod config;
 pub mod logging;
+pub mod storage;
 
 pub async fn init_database(database_uri: &str) -> Result<DatabaseConnection, DbErr> {
     let mut opt = ConnectOptions::new::<&str>(database_uri);
@@ -28,13 +30,18 @@ pub async fn init_database(database_uri: &str) -> Result<DatabaseConnection, DbE
 pub struct AppState {
     pub database_connection: Arc<DatabaseConnection>,
     pub config: Config,
+    pub storage: Arc<dyn Storage>,
 }
 
 impl AppState {
     pub fn new(app_config: Config, db: &Arc<DatabaseConnection>) -> Self {
+        let storage = storage::from_config(&app_config)
+            .unwrap_or_else(|err| panic!("Failed to set up attachment storage: {err}"));
+
         Self {
             database_connection: Arc::clone(db),
             config: app_config,
+            storage,
         }
     }
 
@@ -45,4 +52,8 @@ impl AppState {
     pub fn set_db_conn(&mut self, db: DatabaseConnection) {
         self.database_connection = Arc::new(db);
     }
+
+    pub fn set_storage(&mut self, storage: Arc<dyn Storage>) {
+        self.storage = storage;
+    }
 }
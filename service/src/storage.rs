@@ -0,0 +1,376 @@
+//! Storage for the contents of uploaded files. The database only records an attachment's
+//! metadata along with the key its contents are stored under in one of these backends.
+
+use crate::config::{Config, StorageBackend};
+use async_trait::async_trait;
+use object_store::{aws::AmazonS3Builder, path::Path as ObjectPath, ObjectStore, PutPayload};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use log::*;
+
+/// Errors while reading or writing stored objects.
+#[derive(Debug)]
+pub enum Error {
+    /// Nothing is stored under the key
+    NotFound(String),
+    /// The key is not a relative path made up of plain segments
+    InvalidKey(String),
+    /// The backend is missing required configuration
+    Config(String),
+    /// The backend failed to carry out the operation
+    Backend(Box<dyn StdError + Send + Sync>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotFound(key) => write!(f, "No object stored under key {key}"),
+            Error::InvalidKey(key) => write!(f, "Invalid storage key {key}"),
+            Error::Config(reason) => write!(f, "Storage misconfigured: {reason}"),
+            Error::Backend(err) => write!(f, "Storage backend error: {err}"),
+        }
+    }
+}
+
+impl StdError for Error {}
+
+/// A place to keep the contents of uploaded files, addressed by `/` separated keys.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `contents` under `key`, replacing anything already stored there.
+    async fn put(&self, key: &str, contents: Vec<u8>) -> Result<(), Error>;
+
+    /// Returns the contents stored under `key`.
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Error>;
+
+    /// Removes the contents stored under `key`. Removing a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+}
+
+/// Builds the storage backend selected by `config`.
+pub fn from_config(config: &Config) -> Result<Arc<dyn Storage>, Error> {
+    match config.attachment_storage {
+        StorageBackend::Local => Ok(Arc::new(LocalStorage::new(&config.attachment_dir))),
+        StorageBackend::S3 => {
+            let bucket = config
+                .s3_bucket
+                .as_deref()
+                .ok_or_else(|| Error::Config("S3_BUCKET must be set".to_owned()))?;
+
+            Ok(Arc::new(S3Storage::new(
+                config.s3_endpoint.as_deref(),
+                bucket,
+                &config.s3_region,
+                config.s3_access_key_id.as_deref(),
+                config.s3_secret_access_key.as_deref(),
+            )?))
+        }
+    }
+}
+
+/// Stores each object as a file below a root directory.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Keys come from our own code, but never let one resolve outside of the root
+    fn path_for(&self, key: &str) -> Result<PathBuf, Error> {
+        let relative = Path::new(key);
+        let is_plain = relative.components().next().is_some()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+
+        if is_plain {
+            Ok(self.root.join(relative))
+        } else {
+            Err(Error::InvalidKey(key.to_owned()))
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, contents: Vec<u8>) -> Result<(), Error> {
+        let path = self.path_for(key)?;
+        debug!("Writing {} bytes to {:?}", contents.len(), path);
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|err| Error::Backend(err.into()))?;
+        }
+
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|err| Error::Backend(err.into()))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        let path = self.path_for(key)?;
+
+        tokio::fs::read(&path)
+            .await
+            .map_err(|err| match err.kind() {
+                ErrorKind::NotFound => Error::NotFound(key.to_owned()),
+                _ => Error::Backend(err.into()),
+            })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let path = self.path_for(key)?;
+
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::Backend(err.into())),
+        }
+    }
+}
+
+/// Stores each object in a bucket of AWS S3 or any service that speaks its API, like MinIO.
+pub struct S3Storage {
+    store: Box<dyn ObjectStore>,
+}
+
+impl S3Storage {
+    pub fn new(
+        endpoint: Option<&str>,
+        bucket: &str,
+        region: &str,
+        access_key_id: Option<&str>,
+        secret_access_key: Option<&str>,
+    ) -> Result<Self, Error> {
+        let mut builder = AmazonS3Builder::new()
+            .with_bucket_name(bucket)
+            .with_region(region);
+
+        if let Some(endpoint) = endpoint {
+            // Self-hosted services are commonly reached over plain http on a private network
+            builder = builder.with_endpoint(endpoint).with_allow_http(true);
+        }
+        if let Some(access_key_id) = access_key_id {
+            builder = builder.with_access_key_id(access_key_id);
+        }
+        if let Some(secret_access_key) = secret_access_key {
+            builder = builder.with_secret_access_key(secret_access_key);
+        }
+
+        let store = builder
+            .build()
+            .map_err(|err| Error::Config(err.to_string()))?;
+
+        Ok(Self {
+            store: Box::new(store),
+        })
+    }
+
+    fn path_for(key: &str) -> Result<ObjectPath, Error> {
+        ObjectPath::parse(key).map_err(|_| Error::InvalidKey(key.to_owned()))
+    }
+}
+
+fn map_object_store_error(key: &str, err: object_store::Error) -> Error {
+    match err {
+        object_store::Error::NotFound { .. } => Error::NotFound(key.to_owned()),
+        err => Error::Backend(err.into()),
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, contents: Vec<u8>) -> Result<(), Error> {
+        let path = Self::path_for(key)?;
+        debug!("Uploading {} bytes to S3 key {}", contents.len(), path);
+
+        self.store
+            .put(&path, PutPayload::from(contents))
+            .await
+            .map(|_| ())
+            .map_err(|err| map_object_store_error(key, err))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        let path = Self::path_for(key)?;
+
+        let result = self
+            .store
+            .get(&path)
+            .await
+            .map_err(|err| map_object_store_error(key, err))?;
+
+        result
+            .bytes()
+            .await
+            .map(|bytes| bytes.to_vec())
+            .map_err(|err| map_object_store_error(key, err))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        let path = Self::path_for(key)?;
+
+        match self.store.delete(&path).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(err) => Err(Error::Backend(err.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Bytes,
+        extract::{Path as UrlPath, State},
+        http::{header, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+
+    type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    // A minimal stand-in for an S3-compatible service such as MinIO: path-style object
+    // PUT, GET and DELETE on a single in-memory bucket, with request signatures ignored.
+    async fn spawn_s3_stand_in() -> (String, Objects) {
+        async fn put_object(
+            State(objects): State<Objects>,
+            UrlPath((_bucket, key)): UrlPath<(String, String)>,
+            body: Bytes,
+        ) -> impl IntoResponse {
+            objects.lock().unwrap().insert(key, body.to_vec());
+            [(header::ETAG, "\"stand-in\"")]
+        }
+
+        async fn get_object(
+            State(objects): State<Objects>,
+            UrlPath((_bucket, key)): UrlPath<(String, String)>,
+        ) -> axum::response::Response {
+            match objects.lock().unwrap().get(&key) {
+                Some(contents) => (
+                    [
+                        (header::ETAG, "\"stand-in\""),
+                        (header::LAST_MODIFIED, "Wed, 08 Jan 2025 10:00:00 GMT"),
+                    ],
+                    contents.clone(),
+                )
+                    .into_response(),
+                None => (
+                    StatusCode::NOT_FOUND,
+                    "<Error><Code>NoSuchKey</Code></Error>",
+                )
+                    .into_response(),
+            }
+        }
+
+        async fn delete_object(
+            State(objects): State<Objects>,
+            UrlPath((_bucket, key)): UrlPath<(String, String)>,
+        ) -> StatusCode {
+            objects.lock().unwrap().remove(&key);
+            StatusCode::NO_CONTENT
+        }
+
+        let objects = Objects::default();
+        let router = Router::new()
+            .route(
+                "/:bucket/*key",
+                get(get_object).put(put_object).delete(delete_object),
+            )
+            .with_state(objects.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        (format!("http://{addr}"), objects)
+    }
+
+    #[tokio::test]
+    async fn s3_storage_round_trips_objects_through_an_s3_compatible_service() {
+        let (endpoint, objects) = spawn_s3_stand_in().await;
+        let storage = S3Storage::new(
+            Some(&endpoint),
+            "attachments",
+            "us-east-1",
+            Some("minioadmin"),
+            Some("minioadmin"),
+        )
+        .unwrap();
+
+        storage
+            .put("coaching_sessions/1/2", b"design doc".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            objects.lock().unwrap().get("coaching_sessions/1/2"),
+            Some(&b"design doc".to_vec())
+        );
+        assert_eq!(
+            storage.get("coaching_sessions/1/2").await.unwrap(),
+            b"design doc"
+        );
+
+        storage.delete("coaching_sessions/1/2").await.unwrap();
+
+        assert!(matches!(
+            storage.get("coaching_sessions/1/2").await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn local_storage_round_trips_objects_below_its_root() {
+        let root = std::env::temp_dir().join(format!("attachments-test-{}", std::process::id()));
+        let storage = LocalStorage::new(&root);
+
+        storage
+            .put("coaching_sessions/1/2", b"screenshot".to_vec())
+            .await
+            .unwrap();
+
+        assert!(root.join("coaching_sessions/1/2").exists());
+        assert_eq!(
+            storage.get("coaching_sessions/1/2").await.unwrap(),
+            b"screenshot"
+        );
+
+        storage.delete("coaching_sessions/1/2").await.unwrap();
+        // Deleting is idempotent, like it is for S3
+        storage.delete("coaching_sessions/1/2").await.unwrap();
+
+        assert!(matches!(
+            storage.get("coaching_sessions/1/2").await,
+            Err(Error::NotFound(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn local_storage_rejects_keys_that_escape_its_root() {
+        let storage = LocalStorage::new(std::env::temp_dir());
+
+        assert!(matches!(
+            storage.put("../escape", Vec::new()).await,
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(
+            storage.get("/etc/passwd").await,
+            Err(Error::InvalidKey(_))
+        ));
+    }
+}
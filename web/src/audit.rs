@@ -0,0 +1,20 @@
+//! Attribution of the writes a request makes, for the audit log.
+//!
+//! Every request is handled within an audit `Context` naming the user that made it and its
+//! request id, which entity_api records alongside each write.
+
+use crate::request_id;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use entity_api::{audit, user::AuthSession};
+
+/// Middleware that attributes the writes a request makes to its user and request id.
+pub(crate) async fn attribute(auth_session: AuthSession, request: Request, next: Next) -> Response {
+    let context = audit::Context {
+        actor_id: auth_session.user.map(|user| user.id),
+        request_id: request_id::current(),
+    };
+
+    audit::scope(context, next.run(request)).await
+}
@@ -0,0 +1,760 @@
+//! Real-time collaborative editing of Note bodies.
+//!
+//! Every Note that has at least one connected editor gets a [`Room`] holding a CRDT document
+//! seeded from the Note's stored body. Clients speak the y-websocket protocol (Yjs sync and
+//! awareness messages, one per binary frame), so a stock Yjs `WebsocketProvider` bound to the
+//! `body` text type can join a Room without any custom client code. Presence and cursor
+//! positions are carried in each client's awareness state and relayed to every other editor.
+//!
+//! The merged body is written back through `entity_api::note` shortly after edits settle and
+//! when an editor disconnects, so each persisted snapshot also becomes a Note revision. Each
+//! write is conditional on the version of the Note that the Room last saw, and any change saved
+//! outside of the Room in the meantime, such as through `PUT /notes/:id`, is merged into the
+//! Room first rather than overwritten.
+
+use axum::extract::ws::{Message as WsMessage, WebSocket};
+use axum::http::{header::ORIGIN, HeaderMap};
+use entity::{notes, Id};
+use entity_api::audit;
+use entity_api::error::EntityApiErrorCode;
+use entity_api::note as NoteApi;
+use entity_api::precondition::Precondition;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use service::AppState;
+use similar::{DiffTag, TextDiff};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use yrs::block::ClientID;
+use yrs::encoding::read::Cursor;
+use yrs::sync::{Awareness, DefaultProtocol, Error, Message, MessageReader, Protocol, SyncMessage};
+use yrs::updates::decoder::{Decode, DecoderV1};
+use yrs::updates::encoder::Encode;
+use yrs::{Doc, GetString, ReadTxn, StateVector, Text, TextRef, Transact, TransactionMut, Update};
+
+use log::*;
+
+/// Name of the shared Yjs text type holding the Note body.
+const BODY: &str = "body";
+
+/// How long edits must settle before the merged body is persisted.
+const PERSIST_DELAY: Duration = Duration::from_secs(5);
+
+/// How many relayed messages a slow editor may fall behind by before being resynced.
+const BROADCAST_CAPACITY: usize = 256;
+
+/// How many times persisting is attempted when the Note keeps changing underneath the Room.
+const PERSIST_ATTEMPTS: usize = 3;
+
+/// The sender of relays that come from the server rather than from an editor.
+const SERVER_PEER_ID: u64 = u64::MAX;
+
+/// Whether a WebSocket handshake with `headers` comes from one of `allowed_origins`. Browsers
+/// don't apply CORS to WebSockets, so without this check any site could open a connection
+/// with the session cookie of a user visiting it. Clients other than browsers send no
+/// `Origin`, and can't be made to connect on a user's behalf.
+pub(crate) fn allowed_origin(headers: &HeaderMap, allowed_origins: &[String]) -> bool {
+    match headers.get(ORIGIN) {
+        Some(origin) => allowed_origins
+            .iter()
+            .any(|allowed| origin.as_bytes() == allowed.as_bytes()),
+        None => true,
+    }
+}
+
+/// Every open Room, keyed by Note id.
+#[derive(Clone, Default)]
+pub(crate) struct NoteRooms(Arc<Mutex<HashMap<Id, OpenRoom>>>);
+
+struct OpenRoom {
+    room: Arc<Room>,
+    connections: usize,
+}
+
+impl NoteRooms {
+    /// Returns the Room for `note`, opening it from the Note's stored body if nobody is
+    /// editing it yet.
+    pub(crate) fn join(&self, note: &notes::Model) -> Arc<Room> {
+        let mut rooms = self.0.lock().unwrap();
+        let open_room = rooms.entry(note.id).or_insert_with(|| {
+            debug!("Opening collaboration room for Note {}", note.id);
+            OpenRoom {
+                room: Arc::new(Room::new(note)),
+                connections: 0,
+            }
+        });
+        open_room.connections += 1;
+
+        Arc::clone(&open_room.room)
+    }
+
+    /// Releases one editor's hold on `room`, closing it once the last editor has left.
+    pub(crate) fn leave(&self, room: &Room) {
+        let mut rooms = self.0.lock().unwrap();
+        if let Some(open_room) = rooms.get_mut(&room.note_id) {
+            open_room.connections -= 1;
+            if open_room.connections == 0 {
+                debug!("Closing collaboration room for Note {}", room.note_id);
+                rooms.remove(&room.note_id);
+            }
+        }
+    }
+}
+
+/// A message relayed from one editor to every other editor in the same Room.
+#[derive(Clone, Debug)]
+pub(crate) struct Relay {
+    from: u64,
+    payload: Arc<[u8]>,
+}
+
+/// A user editing in a Room, along with the request that opened their connection, so that
+/// writes made on their behalf are audited as theirs.
+#[derive(Clone, Debug, PartialEq)]
+struct Editor {
+    user_id: Id,
+    request_id: Option<String>,
+}
+
+/// A single editor's connection to a Room.
+#[derive(Debug)]
+pub(crate) struct Peer {
+    id: u64,
+    editor: Editor,
+    // The Yjs client ids this connection has published awareness state for
+    client_ids: HashSet<ClientID>,
+}
+
+impl Peer {
+    pub(crate) fn new(user_id: Id, request_id: Option<String>) -> Self {
+        static NEXT_PEER_ID: AtomicU64 = AtomicU64::new(0);
+
+        Self {
+            id: NEXT_PEER_ID.fetch_add(1, Ordering::Relaxed),
+            editor: Editor {
+                user_id,
+                request_id,
+            },
+            client_ids: HashSet::new(),
+        }
+    }
+}
+
+/// The version of a Note that a Room last saw, either when it was opened or when it persisted
+/// or merged the Note's body.
+struct Seen {
+    updated_at: DateTimeWithTimeZone,
+    // The whole document as of that version, which changes made to the Note outside of the
+    // Room are applied on top of so that they merge with the edits made since
+    state: Vec<u8>,
+}
+
+/// The shared editing state of a single Note.
+pub(crate) struct Room {
+    note_id: Id,
+    awareness: Mutex<Awareness>,
+    relays: broadcast::Sender<Relay>,
+    // The most recent editor whose changes have not been persisted yet
+    unsaved_editor: Mutex<Option<Editor>>,
+    persist_scheduled: AtomicBool,
+    seen: Mutex<Seen>,
+}
+
+impl Room {
+    fn new(note: &notes::Model) -> Self {
+        let doc = Doc::new();
+        {
+            let body = doc.get_or_insert_text(BODY);
+            let mut txn = doc.transact_mut();
+            body.insert(&mut txn, 0, note.body.as_deref().unwrap_or_default());
+        }
+        let seen = Seen {
+            updated_at: note.updated_at,
+            state: doc
+                .transact()
+                .encode_state_as_update_v1(&StateVector::default()),
+        };
+
+        let (relays, _) = broadcast::channel(BROADCAST_CAPACITY);
+
+        Self {
+            note_id: note.id,
+            awareness: Mutex::new(Awareness::new(doc)),
+            relays,
+            unsaved_editor: Mutex::new(None),
+            persist_scheduled: AtomicBool::new(false),
+            seen: Mutex::new(seen),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Relay> {
+        self.relays.subscribe()
+    }
+
+    /// The current merged Note body along with the whole document it is the body of, which
+    /// becomes the Room's last seen version once the body is persisted.
+    fn draft(&self) -> (String, Vec<u8>) {
+        let awareness = self.awareness.lock().unwrap();
+        let doc = awareness.doc();
+        let body = doc.get_or_insert_text(BODY);
+        let txn = doc.transact();
+
+        (
+            body.get_string(&txn),
+            txn.encode_state_as_update_v1(&StateVector::default()),
+        )
+    }
+
+    /// Records that the Note was persisted from `state` as the version updated at `updated_at`.
+    fn persisted(&self, updated_at: DateTimeWithTimeZone, state: Vec<u8>) {
+        *self.seen.lock().unwrap() = Seen { updated_at, state };
+    }
+
+    /// Merges the body of `note` into the Room if it has changed since the version the Room
+    /// last saw. The change from that version's body to `note`'s is replayed on top of the
+    /// document as it was then, so it merges with the edits made in the Room since, and is
+    /// relayed to every editor.
+    fn merge(&self, note: &notes::Model) -> Result<(), Error> {
+        let mut seen = self.seen.lock().unwrap();
+        if seen.updated_at.timestamp_micros() == note.updated_at.timestamp_micros() {
+            return Ok(());
+        }
+
+        debug!(
+            "Merging changes saved outside of the Room into Note {}",
+            note.id
+        );
+
+        let replica = Doc::new();
+        replica
+            .transact_mut()
+            .apply_update(Update::decode_v1(&seen.state)?)?;
+        let body = replica.get_or_insert_text(BODY);
+        let before = replica.transact().state_vector();
+        {
+            let mut txn = replica.transact_mut();
+            let seen_body = body.get_string(&txn);
+            let note_body = note.body.as_deref().unwrap_or_default();
+            replay(&body, &mut txn, &seen_body, note_body);
+        }
+        let txn = replica.transact();
+        let update = txn.encode_diff_v1(&before);
+
+        {
+            let awareness = self.awareness.lock().unwrap();
+            DefaultProtocol.handle_update(&awareness, Update::decode_v1(&update)?)?;
+        }
+        self.relay(SERVER_PEER_ID, Message::Sync(SyncMessage::Update(update)));
+
+        *seen = Seen {
+            updated_at: note.updated_at,
+            state: txn.encode_state_as_update_v1(&StateVector::default()),
+        };
+
+        Ok(())
+    }
+
+    /// The frames sent to a newly connected editor: our state vector, so the client replies
+    /// with whatever it has that we lack, followed by the presence of everyone else.
+    pub(crate) fn greeting(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let awareness = self.awareness.lock().unwrap();
+        let state_vector = awareness.doc().transact().state_vector();
+        let mut frames = vec![Message::Sync(SyncMessage::SyncStep1(state_vector)).encode_v1()];
+
+        let presence = awareness.update()?;
+        if !presence.clients.is_empty() {
+            frames.push(Message::Awareness(presence).encode_v1());
+        }
+
+        Ok(frames)
+    }
+
+    /// The whole document as a single update, used to catch up an editor that fell too far
+    /// behind to be sent the individual updates it missed.
+    fn snapshot(&self) -> Vec<u8> {
+        let awareness = self.awareness.lock().unwrap();
+        let update = awareness
+            .doc()
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        Message::Sync(SyncMessage::Update(update)).encode_v1()
+    }
+
+    /// Applies a frame received from `peer`, relaying document and presence changes to the
+    /// other editors. Returns the frames to send back to `peer` alone, and whether the
+    /// document changed.
+    pub(crate) fn handle(
+        &self,
+        peer: &mut Peer,
+        data: &[u8],
+    ) -> Result<(Vec<Vec<u8>>, bool), Error> {
+        let awareness = self.awareness.lock().unwrap();
+        let mut decoder = DecoderV1::new(Cursor::new(data));
+        let mut replies = Vec::new();
+        let mut changed = false;
+
+        for message in MessageReader::new(&mut decoder) {
+            match message? {
+                Message::Sync(SyncMessage::SyncStep2(update))
+                | Message::Sync(SyncMessage::Update(update)) => {
+                    DefaultProtocol.handle_update(&awareness, Update::decode_v1(&update)?)?;
+                    self.relay(peer.id, Message::Sync(SyncMessage::Update(update)));
+                    changed = true;
+                }
+                Message::Awareness(presence) => {
+                    peer.client_ids.extend(presence.clients.keys().copied());
+                    awareness.apply_update(presence.clone())?;
+                    self.relay(peer.id, Message::Awareness(presence));
+                }
+                message => {
+                    if let Some(reply) = DefaultProtocol.handle_message(&awareness, message)? {
+                        replies.push(reply.encode_v1());
+                    }
+                }
+            }
+        }
+
+        if changed {
+            *self.unsaved_editor.lock().unwrap() = Some(peer.editor.clone());
+        }
+
+        Ok((replies, changed))
+    }
+
+    /// Clears the presence `peer` published so that other editors stop showing its cursor.
+    pub(crate) fn depart(&self, peer: &Peer) -> Result<(), Error> {
+        if peer.client_ids.is_empty() {
+            return Ok(());
+        }
+
+        let awareness = self.awareness.lock().unwrap();
+        for client_id in &peer.client_ids {
+            awareness.remove_state(*client_id);
+        }
+        let presence = awareness.update_with_clients(peer.client_ids.iter().copied())?;
+        self.relay(peer.id, Message::Awareness(presence));
+
+        Ok(())
+    }
+
+    fn relay(&self, from: u64, message: Message) {
+        // Sending only fails when no other editor is subscribed, which is fine to ignore
+        let _ = self.relays.send(Relay {
+            from,
+            payload: message.encode_v1().into(),
+        });
+    }
+}
+
+// Applies the change from `from` to `to` to `body`, whose current text is `from`
+fn replay(body: &TextRef, txn: &mut TransactionMut, from: &str, to: &str) {
+    // Yrs indexes text by byte, while the diff is by character
+    let from_offsets = byte_offsets(from);
+    let to_offsets = byte_offsets(to);
+    let mut index = 0;
+
+    for op in TextDiff::from_chars(from, to).ops() {
+        let (tag, from_range, to_range) = op.as_tag_tuple();
+        let removed = from_offsets[from_range.end] - from_offsets[from_range.start];
+        let inserted = &to[to_offsets[to_range.start]..to_offsets[to_range.end]];
+
+        match tag {
+            DiffTag::Equal => index += removed,
+            DiffTag::Delete | DiffTag::Insert | DiffTag::Replace => {
+                if removed > 0 {
+                    body.remove_range(txn, index as u32, removed as u32);
+                }
+                if !inserted.is_empty() {
+                    body.insert(txn, index as u32, inserted);
+                }
+                index += inserted.len();
+            }
+        }
+    }
+}
+
+fn byte_offsets(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(offset, _)| offset)
+        .chain([text.len()])
+        .collect()
+}
+
+/// Persists the merged body of `room` through `entity_api::note` if it has unsaved changes,
+/// only over the version of the Note it has seen. The write is audited as made by the editor
+/// of those changes, within the request that opened their connection.
+async fn persist(
+    app_state: &AppState,
+    room: &Room,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(editor) = room.unsaved_editor.lock().unwrap().take() else {
+        return Ok(());
+    };
+
+    let context = audit::Context {
+        actor_id: Some(editor.user_id),
+        request_id: editor.request_id.clone(),
+    };
+    let result = audit::scope(context, persist_as(app_state, room, editor.user_id)).await;
+    if result.is_err() {
+        // Leave the changes for the next persist to retry, unless someone has edited since
+        room.unsaved_editor.lock().unwrap().get_or_insert(editor);
+    }
+
+    result
+}
+
+async fn persist_as(
+    app_state: &AppState,
+    room: &Room,
+    user_id: Id,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut attempt = 1;
+
+    loop {
+        let note = NoteApi::find_by_id(app_state.db_conn_ref(), room.note_id, user_id).await?;
+        room.merge(&note)?;
+
+        let (body, state) = room.draft();
+        if note.body.as_deref() == Some(body.as_str()) {
+            room.persisted(note.updated_at, state);
+
+            return Ok(());
+        }
+
+        debug!("Persisting collaboratively edited Note {}", room.note_id);
+
+        let updated_at = note.updated_at;
+        let note = notes::Model {
+            body: Some(body),
+            ..note
+        };
+        match NoteApi::update(
+            app_state.db_conn_ref(),
+            room.note_id,
+            Precondition::UpdatedAt(vec![updated_at]),
+            note,
+            user_id,
+        )
+        .await
+        {
+            Ok(note) => {
+                room.persisted(note.updated_at, state);
+
+                return Ok(());
+            }
+            // Changed again between reading and writing it, so merge that change in too
+            Err(err)
+                if matches!(err.error_code, EntityApiErrorCode::PreconditionFailed)
+                    && attempt < PERSIST_ATTEMPTS =>
+            {
+                attempt += 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Persists `room` once edits have settled for [`PERSIST_DELAY`], unless a persist is already
+/// pending.
+fn schedule_persist(app_state: &AppState, room: &Arc<Room>) {
+    if room.persist_scheduled.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    let app_state = app_state.clone();
+    let room = Arc::clone(room);
+    tokio::spawn(async move {
+        tokio::time::sleep(PERSIST_DELAY).await;
+        room.persist_scheduled.store(false, Ordering::Release);
+
+        if let Err(err) = persist(&app_state, &room).await {
+            error!("Failed to persist Note {}: {:?}", room.note_id, err);
+        }
+    });
+}
+
+/// Runs one editor's WebSocket connection to the Room for `note` until it disconnects. The
+/// connection outlives the request with `request_id` that opened it, which the writes made
+/// from it are still attributed to.
+pub(crate) async fn serve(
+    mut socket: WebSocket,
+    app_state: AppState,
+    rooms: NoteRooms,
+    note: notes::Model,
+    user_id: Id,
+    request_id: Option<String>,
+) {
+    let room = rooms.join(&note);
+    let mut relays = room.subscribe();
+    let mut peer = Peer::new(user_id, request_id);
+
+    debug!("User {} joined collaboration on Note {}", user_id, note.id);
+
+    if let Err(err) = connect(&mut socket, &app_state, &room, &mut relays, &mut peer).await {
+        warn!(
+            "Collaboration on Note {} ended for user {}: {:?}",
+            note.id, user_id, err
+        );
+    }
+
+    if let Err(err) = room.depart(&peer) {
+        error!("Failed to clear presence on Note {}: {:?}", note.id, err);
+    }
+    if let Err(err) = persist(&app_state, &room).await {
+        error!("Failed to persist Note {}: {:?}", note.id, err);
+    }
+    rooms.leave(&room);
+
+    debug!("User {} left collaboration on Note {}", user_id, note.id);
+}
+
+async fn connect(
+    socket: &mut WebSocket,
+    app_state: &AppState,
+    room: &Arc<Room>,
+    relays: &mut broadcast::Receiver<Relay>,
+    peer: &mut Peer,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for frame in room.greeting()? {
+        socket.send(WsMessage::Binary(frame)).await?;
+    }
+
+    loop {
+        tokio::select! {
+            received = socket.recv() => match received {
+                Some(Ok(WsMessage::Binary(data))) => {
+                    let (replies, changed) = room.handle(peer, &data)?;
+                    for reply in replies {
+                        socket.send(WsMessage::Binary(reply)).await?;
+                    }
+                    if changed {
+                        schedule_persist(app_state, room);
+                    }
+                }
+                Some(Ok(WsMessage::Close(_))) | None => return Ok(()),
+                // Pings are answered by axum, and y-websocket sends no text frames
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err.into()),
+            },
+            relayed = relays.recv() => match relayed {
+                Ok(relay) if relay.from != peer.id => {
+                    socket.send(WsMessage::Binary(relay.payload.to_vec())).await?;
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Resyncing editor that missed {} updates", skipped);
+                    socket.send(WsMessage::Binary(room.snapshot())).await?;
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::visibility::Visibility;
+    use yrs::sync::AwarenessUpdate;
+
+    fn note(body: &str) -> notes::Model {
+        let now = chrono::Utc::now();
+
+        notes::Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some(body.to_owned()),
+            user_id: Id::new_v4(),
+            visibility: Visibility::Shared,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    // A client-side replica that has fully synced with `room`
+    fn synced_client(room: &Room) -> Doc {
+        let doc = Doc::new();
+        let snapshot = room
+            .awareness
+            .lock()
+            .unwrap()
+            .doc()
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&snapshot).unwrap())
+            .unwrap();
+
+        doc
+    }
+
+    fn insert(doc: &Doc, index: u32, chunk: &str) -> Vec<u8> {
+        let body = doc.get_or_insert_text(BODY);
+        let mut txn = doc.transact_mut();
+        body.insert(&mut txn, index, chunk);
+
+        Message::Sync(SyncMessage::Update(txn.encode_update_v1())).encode_v1()
+    }
+
+    #[test]
+    fn concurrent_edits_from_two_editors_are_merged() -> Result<(), Error> {
+        let room = Room::new(&note("Agenda"));
+        let coach = synced_client(&room);
+        let coachee = synced_client(&room);
+        let mut coach_peer = Peer::new(Id::new_v4(), None);
+        let mut coachee_peer = Peer::new(Id::new_v4(), None);
+
+        // Both type before seeing the other's change
+        let coach_edit = insert(&coach, 0, "Weekly ");
+        let coachee_edit = insert(&coachee, 6, ": on-call");
+
+        let (_, coach_changed) = room.handle(&mut coach_peer, &coach_edit)?;
+        let (_, coachee_changed) = room.handle(&mut coachee_peer, &coachee_edit)?;
+
+        assert!(coach_changed && coachee_changed);
+        assert_eq!(room.draft().0, "Weekly Agenda: on-call");
+
+        Ok(())
+    }
+
+    #[test]
+    fn changes_saved_outside_the_room_are_merged_with_its_edits() -> Result<(), Error> {
+        let seen = note("Café agenda");
+        let room = Room::new(&seen);
+        let mut relays = room.subscribe();
+        let coach = synced_client(&room);
+        let mut coach_peer = Peer::new(Id::new_v4(), None);
+
+        // The coach edits in the Room while the coachee saves a change through PUT /notes/:id
+        let edit = insert(&coach, 0, "Weekly ");
+        room.handle(&mut coach_peer, &edit)?;
+        relays.try_recv().expect("edit was not relayed");
+        let saved = notes::Model {
+            body: Some("Café agenda: hiring".to_owned()),
+            updated_at: (chrono::Utc::now() + chrono::Duration::seconds(1)).into(),
+            ..seen
+        };
+
+        room.merge(&saved)?;
+
+        assert_eq!(room.draft().0, "Weekly Café agenda: hiring");
+        let relay = relays.try_recv().expect("merge was not relayed");
+        assert_eq!(relay.from, SERVER_PEER_ID);
+
+        // Merging the same version again changes nothing
+        room.merge(&saved)?;
+        assert_eq!(room.draft().0, "Weekly Café agenda: hiring");
+        assert!(relays.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_handshakes_from_allowed_origins_are_accepted() {
+        let allowed_origins = vec!["https://app.example.com".to_owned()];
+        let from = |origin: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ORIGIN, origin.parse().unwrap());
+            headers
+        };
+
+        assert!(allowed_origin(
+            &from("https://app.example.com"),
+            &allowed_origins
+        ));
+        assert!(!allowed_origin(
+            &from("https://evil.example.com"),
+            &allowed_origins
+        ));
+        assert!(allowed_origin(&HeaderMap::new(), &allowed_origins));
+    }
+
+    #[test]
+    fn edits_are_relayed_to_other_editors_only() -> Result<(), Error> {
+        let room = Room::new(&note("Agenda"));
+        let mut relays = room.subscribe();
+        let coach = synced_client(&room);
+        let mut coach_peer = Peer::new(Id::new_v4(), Some("req-1".to_owned()));
+
+        let edit = insert(&coach, 6, " items");
+        room.handle(&mut coach_peer, &edit)?;
+
+        let relay = relays.try_recv().expect("edit was not relayed");
+        assert_eq!(relay.from, coach_peer.id);
+        assert_eq!(&*relay.payload, edit.as_slice());
+        assert_eq!(
+            *room.unsaved_editor.lock().unwrap(),
+            Some(coach_peer.editor.clone())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_step1_is_answered_with_the_missing_state() -> Result<(), Error> {
+        let room = Room::new(&note("Agenda"));
+        let latecomer = Doc::new();
+        let mut peer = Peer::new(Id::new_v4(), None);
+
+        let state_vector = latecomer.transact().state_vector();
+        let request = Message::Sync(SyncMessage::SyncStep1(state_vector)).encode_v1();
+        let (replies, changed) = room.handle(&mut peer, &request)?;
+
+        assert!(!changed);
+        assert_eq!(replies.len(), 1);
+
+        let Message::Sync(SyncMessage::SyncStep2(update)) = Message::decode_v1(&replies[0])? else {
+            panic!("expected a sync step 2 reply");
+        };
+        latecomer
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update)?)?;
+        let body = latecomer.get_or_insert_text(BODY);
+        assert_eq!(body.get_string(&latecomer.transact()), "Agenda");
+
+        Ok(())
+    }
+
+    #[test]
+    fn departing_editor_presence_is_cleared() -> Result<(), Error> {
+        let room = Room::new(&note("Agenda"));
+        let mut relays = room.subscribe();
+        let mut peer = Peer::new(Id::new_v4(), None);
+
+        let client = Awareness::new(Doc::new());
+        client.set_local_state_raw(r#"{"user":{"name":"Coach"},"cursor":{"anchor":3}}"#);
+        let presence = Message::Awareness(client.update()?).encode_v1();
+        room.handle(&mut peer, &presence)?;
+        assert!(room
+            .awareness
+            .lock()
+            .unwrap()
+            .state::<serde_json::Value>(client.client_id())
+            .is_some());
+
+        room.depart(&peer)?;
+
+        assert!(room
+            .awareness
+            .lock()
+            .unwrap()
+            .state::<serde_json::Value>(client.client_id())
+            .is_none());
+
+        // The presence announcement followed by its removal
+        relays.try_recv().expect("presence was not relayed");
+        let removal = relays.try_recv().expect("removal was not relayed");
+        let Message::Awareness(AwarenessUpdate { clients }) = Message::decode_v1(&removal.payload)?
+        else {
+            panic!("expected an awareness update");
+        };
+        assert_eq!(&*clients[&client.client_id()].json, "null");
+
+        Ok(())
+    }
+}
@@ -1,14 +1,22 @@
 use crate::controller::ApiResponse;
+use crate::etag;
 use crate::extractors::{
     authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+    if_match::IfMatch,
 };
+use crate::extractors::{
+    json::{Json, ValidJson},
+    path::Path,
+};
+use crate::markdown::{self, BodyFormat};
+use crate::params::action::{NewAction, PatchAction, UpdateAction};
 use crate::{AppState, Error};
-use axum::extract::{Path, Query, State};
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::Json;
-use entity::{actions::Model, Id};
+use entity::Id;
 use entity_api::action as ActionApi;
+use entity_api::status_transition as StatusTransitionApi;
 use serde_json::json;
 use service::config::ApiVersion;
 use std::collections::HashMap;
@@ -20,9 +28,10 @@ use log::*;
     post,
     path = "/actions",
     params(ApiVersion),
-    request_body = entity::actions::Model,
+    request_body = web::params::action::NewAction,
     responses(
-        (status = 201, description = "Successfully Created a New Action", body = [entity::actions::Model]),
+        (status = 201, description = "Successfully Created a New Action", body = [entity::actions::Model],
+            headers(("ETag" = String, description = "Version of the Action, to send back in `If-Match` to change it"))),
         (status= 422, description = "Unprocessable Entity"),
         (status = 401, description = "Unauthorized"),
         (status = 405, description = "Method not allowed")
@@ -37,13 +46,16 @@ pub async fn create(
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Json(action_model): Json<Model>,
+    ValidJson(params): ValidJson<NewAction>,
 ) -> Result<impl IntoResponse, Error> {
-    debug!("POST Create a New Action from: {:?}", action_model);
+    debug!("POST Create a New Action from: {:?}", params);
 
-    let action = ActionApi::create(app_state.db_conn_ref(), action_model, user.id).await?;
+    let action = ActionApi::create(app_state.db_conn_ref(), params.into(), user.id).await?;
 
-    Ok(Json(ApiResponse::new(StatusCode::CREATED.into(), action)))
+    Ok((
+        etag::header(&action.updated_at),
+        Json(ApiResponse::new(StatusCode::CREATED.into(), action)),
+    ))
 }
 
 /// GET a particular Action specified by its id.
@@ -52,10 +64,12 @@ pub async fn create(
     path = "/actions/{id}",
     params(
         ApiVersion,
-        ("id" = String, Path, description = "Action id to retrieve")
+        ("id" = String, Path, description = "Action id to retrieve"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
-        (status = 200, description = "Successfully retrieved a specific Action by its id", body = [entity::notes::Model]),
+        (status = 200, description = "Successfully retrieved a specific Action by its id", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the Action, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Action not found"),
         (status = 405, description = "Method not allowed")
@@ -66,14 +80,24 @@ pub async fn create(
 )]
 pub async fn read(
     CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET Action by id: {}", id);
 
-    let note: Option<Model> = ActionApi::find_by_id(app_state.db_conn_ref(), id).await?;
+    let format = BodyFormat::take_from(&mut params)?;
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), note)))
+    let model = ActionApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    Ok((
+        etag::header(&model.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            markdown::present(model, format),
+        )),
+    ))
 }
 
 #[utoipa::path(
@@ -82,12 +106,19 @@ pub async fn read(
     params(
         ApiVersion,
         ("id" = Id, Path, description = "Id of action to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Action to update"),
     ),
-    request_body = entity::actions::Model,
+    request_body = web::params::action::UpdateAction,
     responses(
-        (status = 200, description = "Successfully Updated Action", body = [entity::actions::Model]),
+        (status = 200, description = "Successfully Updated Action", body = [entity::actions::Model],
+            headers(("ETag" = String, description = "Version of the updated Action"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 404, description = "Action not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "Status change not permitted from the current status"),
+        (status = 412, description = "Action has changed since the version named by `If-Match`"),
+        (status = 422, description = "Unknown status value"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -95,20 +126,85 @@ pub async fn read(
 )]
 pub async fn update(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
-    // TODO: create a new Extractor to authorize the user to access
-    // the data requested
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
-    Json(action_model): Json<Model>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<UpdateAction>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("PUT Update Action with id: {}", id);
 
-    let action = ActionApi::update(app_state.db_conn_ref(), id, action_model).await?;
+    ActionApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let action = ActionApi::update(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
+
+    debug!("Updated Action: {:?}", action);
+
+    Ok((
+        etag::header(&action.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), action)),
+    ))
+}
+
+/// PATCH some of an Action's fields, leaving out the ones that should keep their value
+#[utoipa::path(
+    patch,
+    path = "/actions/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of action to change"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Action to change"),
+    ),
+    request_body(content = web::params::action::PatchAction, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Successfully Updated Action", body = [entity::actions::Model],
+            headers(("ETag" = String, description = "Version of the updated Action"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Action not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "Status change not permitted from the current status"),
+        (status = 412, description = "Action has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn patch(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<PatchAction>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("PATCH Action with id: {}, changes: {:?}", id, params);
+
+    ActionApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let action = ActionApi::patch(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
 
     debug!("Updated Action: {:?}", action);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), action)))
+    Ok((
+        etag::header(&action.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), action)),
+    ))
 }
 
 #[utoipa::path(
@@ -118,12 +214,19 @@ pub async fn update(
         ApiVersion,
         ("id" = Id, Path, description = "Id of action to update"),
         ("value" = Option<String>, Query, description = "Status value to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Action to update"),
     ),
     request_body = entity::actions::Model,
     responses(
-        (status = 200, description = "Successfully Updated Action", body = [entity::actions::Model]),
+        (status = 200, description = "Successfully Updated Action", body = [entity::actions::Model],
+            headers(("ETag" = String, description = "Version of the updated Action"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 404, description = "Action not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "Status change not permitted from the current status"),
+        (status = 412, description = "Action has changed since the version named by `If-Match`"),
+        (status = 422, description = "Unknown status value"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -131,19 +234,71 @@ pub async fn update(
 )]
 pub async fn update_status(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     Query(status): Query<String>,
     Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
     State(app_state): State<AppState>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("PUT Update Action Status with id: {}", id);
 
-    let action =
-        ActionApi::update_status(app_state.db_conn_ref(), id, status.as_str().into()).await?;
+    ActionApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let action = ActionApi::update_status(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        status.parse()?,
+        user.id,
+    )
+    .await?;
 
     debug!("Updated Action: {:?}", action);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), action)))
+    Ok((
+        etag::header(&action.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), action)),
+    ))
+}
+
+/// GET the status change history of a particular Action, oldest change first.
+#[utoipa::path(
+    get,
+    path = "/actions/{id}/history",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of action to retrieve the status history for"),
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the status history of an Action", body = [entity::status_transitions::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Action not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn history(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Action status history for id: {}", id);
+
+    // Ensures a 404 for an Action the user can't reach rather than an empty history
+    ActionApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let status_transitions =
+        StatusTransitionApi::find_by_action(app_state.db_conn_ref(), id).await?;
+
+    debug!("Found Action status history: {:?}", status_transitions);
+
+    Ok(Json(ApiResponse::new(
+        StatusCode::OK.into(),
+        status_transitions,
+    )))
 }
 
 #[utoipa::path(
@@ -151,7 +306,8 @@ pub async fn update_status(
     path = "/actions",
     params(
         ApiVersion,
-        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id")
+        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
         (status = 200, description = "Successfully retrieved all Actions", body = [entity::actions::Model]),
@@ -168,31 +324,43 @@ pub async fn index(
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Query(params): Query<HashMap<String, String>>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET all Actions");
     debug!("Filter Params: {:?}", params);
 
+    let format = BodyFormat::take_from(&mut params)?;
+
     let actions = ActionApi::find_by(app_state.db_conn_ref(), params).await?;
 
     debug!("Found Actions: {:?}", actions);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), actions)))
+    Ok(Json(ApiResponse::new(
+        StatusCode::OK.into(),
+        actions
+            .into_iter()
+            .map(|model| markdown::present(model, format))
+            .collect::<Vec<_>>(),
+    )))
 }
 
-/// DELETE an Action specified by its primary key.
+/// DELETE an Action specified by its primary key. It stays restorable until it is purged
+/// after the retention period.
 #[utoipa::path(
     delete,
     path = "/actions/{id}",
     params(
         ApiVersion,
-        ("id" = i32, Path, description = "Action id to delete")
+        ("id" = i32, Path, description = "Action id to delete"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Action to delete")
     ),
     responses(
         (status = 200, description = "Successfully deleted a certain Action by its id", body = [i32]),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Action not found"),
-        (status = 405, description = "Method not allowed")
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Action has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -200,11 +368,54 @@ pub async fn index(
 )]
 pub async fn delete(
     CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
 ) -> Result<impl IntoResponse, Error> {
     debug!("DELETE Action by id: {}", id);
 
-    ActionApi::delete_by_id(app_state.db_conn_ref(), id).await?;
+    ActionApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    ActionApi::delete_by_id(app_state.db_conn_ref(), id, precondition).await?;
     Ok(Json(json!({"id": id})))
 }
+
+/// POST restore a deleted Action that hasn't been purged yet.
+#[utoipa::path(
+    post,
+    path = "/actions/{id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of the deleted action to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted Action", body = [entity::actions::Model],
+            headers(("ETag" = String, description = "Version of the restored Action"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Action not found or not deleted"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Restore deleted Action: {}", id);
+
+    ActionApi::find_deleted_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let action = ActionApi::restore(app_state.db_conn_ref(), id).await?;
+
+    debug!("Restored Action: {:?}", action);
+
+    Ok((
+        etag::header(&action.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), action)),
+    ))
+}
@@ -1,14 +1,22 @@
 use crate::controller::ApiResponse;
+use crate::etag;
 use crate::extractors::{
     authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+    if_match::IfMatch,
 };
+use crate::extractors::{
+    json::{Json, ValidJson},
+    path::Path,
+};
+use crate::markdown::{self, BodyFormat};
+use crate::params::agreement::{NewAgreement, PatchAgreement, UpdateAgreement};
 use crate::{AppState, Error};
-use axum::extract::{Path, Query, State};
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::Json;
-use entity::{agreements::Model, Id};
+use entity::Id;
 use entity_api::agreement as AgreementApi;
+use entity_api::revision::{self as RevisionApi, DiffParams, Subject};
 use serde_json::json;
 use service::config::ApiVersion;
 use std::collections::HashMap;
@@ -20,9 +28,10 @@ use log::*;
     post,
     path = "/agreements",
     params(ApiVersion),
-    request_body = entity::agreements::Model,
+    request_body = web::params::agreement::NewAgreement,
     responses(
-        (status = 201, description = "Successfully Created a New Agreement", body = [entity::agreements::Model]),
+        (status = 201, description = "Successfully Created a New Agreement", body = [entity::agreements::Model],
+            headers(("ETag" = String, description = "Version of the Agreement, to send back in `If-Match` to change it"))),
         (status= 422, description = "Unprocessable Entity"),
         (status = 401, description = "Unauthorized"),
         (status = 405, description = "Method not allowed")
@@ -38,18 +47,18 @@ pub async fn create(
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Json(agreement_model): Json<Model>,
+    ValidJson(params): ValidJson<NewAgreement>,
 ) -> Result<impl IntoResponse, Error> {
-    debug!("POST Create a New Agreement from: {:?}", agreement_model);
+    debug!("POST Create a New Agreement from: {:?}", params);
 
-    let agreement = AgreementApi::create(app_state.db_conn_ref(), agreement_model, user.id).await?;
+    let agreement = AgreementApi::create(app_state.db_conn_ref(), params.into(), user.id).await?;
 
     debug!("New Agreement: {:?}", agreement);
 
-    Ok(Json(ApiResponse::new(
-        StatusCode::CREATED.into(),
-        agreement,
-    )))
+    Ok((
+        etag::header(&agreement.updated_at),
+        Json(ApiResponse::new(StatusCode::CREATED.into(), agreement)),
+    ))
 }
 
 /// GET a particular Agreement specified by its id.
@@ -58,10 +67,12 @@ pub async fn create(
     path = "/agreements/{id}",
     params(
         ApiVersion,
-        ("id" = String, Path, description = "Agreement id to retrieve")
+        ("id" = String, Path, description = "Agreement id to retrieve"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
-        (status = 200, description = "Successfully retrieved a specific Agreement by its id", body = [entity::notes::Model]),
+        (status = 200, description = "Successfully retrieved a specific Agreement by its id", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the Agreement, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Agreement not found"),
         (status = 405, description = "Method not allowed")
@@ -72,14 +83,24 @@ pub async fn create(
 )]
 pub async fn read(
     CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET Agreement by id: {}", id);
 
-    let note: Option<Model> = AgreementApi::find_by_id(app_state.db_conn_ref(), id).await?;
+    let format = BodyFormat::take_from(&mut params)?;
+
+    let model = AgreementApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), note)))
+    Ok((
+        etag::header(&model.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            markdown::present(model, format),
+        )),
+    ))
 }
 
 #[utoipa::path(
@@ -88,12 +109,18 @@ pub async fn read(
     params(
         ApiVersion,
         ("id" = Id, Path, description = "Id of agreement to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Agreement to update"),
     ),
-    request_body = entity::agreements::Model,
+    request_body = web::params::agreement::UpdateAgreement,
     responses(
-        (status = 200, description = "Successfully Updated Agreement", body = [entity::agreements::Model]),
+        (status = 200, description = "Successfully Updated Agreement", body = [entity::agreements::Model],
+            headers(("ETag" = String, description = "Version of the updated Agreement"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 404, description = "Agreement not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Agreement has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -101,20 +128,84 @@ pub async fn read(
 )]
 pub async fn update(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
-    // TODO: create a new Extractor to authorize the user to access
-    // the data requested
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
-    Json(agreement_model): Json<Model>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<UpdateAgreement>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("PUT Update Agreement with id: {}", id);
 
-    let agreement = AgreementApi::update(app_state.db_conn_ref(), id, agreement_model).await?;
+    AgreementApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let agreement = AgreementApi::update(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
 
     debug!("Updated Agreement: {:?}", agreement);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), agreement)))
+    Ok((
+        etag::header(&agreement.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), agreement)),
+    ))
+}
+
+/// PATCH some of an Agreement's fields, leaving out the ones that should keep their value
+#[utoipa::path(
+    patch,
+    path = "/agreements/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of agreement to change"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Agreement to change"),
+    ),
+    request_body(content = web::params::agreement::PatchAgreement, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Successfully Updated Agreement", body = [entity::agreements::Model],
+            headers(("ETag" = String, description = "Version of the updated Agreement"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Agreement not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Agreement has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn patch(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<PatchAgreement>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("PATCH Agreement with id: {}, changes: {:?}", id, params);
+
+    AgreementApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let agreement = AgreementApi::patch(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
+
+    debug!("Updated Agreement: {:?}", agreement);
+
+    Ok((
+        etag::header(&agreement.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), agreement)),
+    ))
 }
 
 #[utoipa::path(
@@ -122,7 +213,10 @@ pub async fn update(
     path = "/agreements",
     params(
         ApiVersion,
-        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id")
+        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id"),
+        ("acknowledged" = Option<bool>, Query, description = "`false` for agreements still missing an acknowledgment from the coach or the coachee, `true` for agreements acknowledged by both"),
+        ("unacknowledged_by" = Option<String>, Query, description = "`coach` or `coachee` for agreements that party has not yet acknowledged"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
         (status = 200, description = "Successfully retrieved all Agreements", body = [entity::agreements::Model]),
@@ -139,31 +233,43 @@ pub async fn index(
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Query(params): Query<HashMap<String, String>>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET all Agreements");
     debug!("Filter Params: {:?}", params);
 
+    let format = BodyFormat::take_from(&mut params)?;
+
     let agreements = AgreementApi::find_by(app_state.db_conn_ref(), params).await?;
 
     debug!("Found Agreements: {:?}", agreements);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), agreements)))
+    Ok(Json(ApiResponse::new(
+        StatusCode::OK.into(),
+        agreements
+            .into_iter()
+            .map(|model| markdown::present(model, format))
+            .collect::<Vec<_>>(),
+    )))
 }
 
-/// DELETE an Agreement specified by its primary key.
+/// DELETE an Agreement specified by its primary key. It stays restorable until it is purged
+/// after the retention period.
 #[utoipa::path(
     delete,
     path = "/agreements/{id}",
     params(
         ApiVersion,
-        ("id" = i32, Path, description = "Agreement id to delete")
+        ("id" = i32, Path, description = "Agreement id to delete"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Agreement to delete")
     ),
     responses(
         (status = 200, description = "Successfully deleted a certain Agreement by its id", body = [i32]),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Agreement not found"),
-        (status = 405, description = "Method not allowed")
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Agreement has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -171,11 +277,229 @@ pub async fn index(
 )]
 pub async fn delete(
     CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
 ) -> Result<impl IntoResponse, Error> {
     debug!("DELETE Agreement by id: {}", id);
 
-    AgreementApi::delete_by_id(app_state.db_conn_ref(), id).await?;
+    AgreementApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    AgreementApi::delete_by_id(app_state.db_conn_ref(), id, precondition).await?;
     Ok(Json(json!({"id": id})))
 }
+
+/// POST restore a deleted Agreement that hasn't been purged yet.
+#[utoipa::path(
+    post,
+    path = "/agreements/{id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of the deleted agreement to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted Agreement", body = [entity::agreements::Model],
+            headers(("ETag" = String, description = "Version of the restored Agreement"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Agreement not found or not deleted"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Restore deleted Agreement: {}", id);
+
+    AgreementApi::find_deleted_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let agreement = AgreementApi::restore(app_state.db_conn_ref(), id).await?;
+
+    debug!("Restored Agreement: {:?}", agreement);
+
+    Ok((
+        etag::header(&agreement.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), agreement)),
+    ))
+}
+
+/// GET every revision of a particular Agreement's body, newest first.
+#[utoipa::path(
+    get,
+    path = "/agreements/{id}/revisions",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of agreement to retrieve the revisions of"),
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the revisions of an Agreement", body = [entity::revisions::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Agreement not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn revisions(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Agreement revisions for id: {}", id);
+
+    // Ensures a 404 for an Agreement the user can't reach rather than an empty list
+    AgreementApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let revisions =
+        RevisionApi::find_by_subject(app_state.db_conn_ref(), Subject::Agreement(id)).await?;
+
+    debug!("Found Agreement revisions: {:?}", revisions);
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), revisions)))
+}
+
+/// GET a unified diff of an Agreement's body between two of its revisions.
+#[utoipa::path(
+    get,
+    path = "/agreements/{id}/revisions/diff",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of agreement to diff the revisions of"),
+        DiffParams,
+    ),
+    responses(
+        (status = 200, description = "Successfully diffed two revisions of an Agreement", body = String),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Agreement or revision not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn diff_revisions(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    Query(params): Query<DiffParams>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Agreement revision diff for id: {}, {:?}", id, params);
+
+    AgreementApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let diff = RevisionApi::diff(
+        app_state.db_conn_ref(),
+        Subject::Agreement(id),
+        params.from,
+        params.to,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), diff)))
+}
+
+/// POST restore an Agreement's body to that of one of its earlier revisions.
+#[utoipa::path(
+    post,
+    path = "/agreements/{id}/revisions/{revision_id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of agreement to restore"),
+        ("revision_id" = Id, Path, description = "Id of the revision to restore the body from"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Agreement to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored an Agreement to an earlier revision", body = [entity::agreements::Model],
+            headers(("ETag" = String, description = "Version of the restored Agreement"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Agreement or revision not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Agreement has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore_revision(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((id, revision_id)): Path<(Id, Id)>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Restore Agreement {} to revision {}", id, revision_id);
+
+    AgreementApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let agreement = AgreementApi::restore_revision(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        revision_id,
+        user.id,
+    )
+    .await?;
+
+    debug!("Restored Agreement: {:?}", agreement);
+
+    Ok((
+        etag::header(&agreement.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), agreement)),
+    ))
+}
+
+/// POST acknowledge the body of an Agreement as its coach or coachee, at the version named by
+/// `If-Match`.
+///
+/// Each party's acknowledgment is timestamped along with the revision of the body that was
+/// acknowledged, and both are cleared whenever the body changes.
+#[utoipa::path(
+    post,
+    path = "/agreements/{id}/acknowledge",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of agreement to acknowledge"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Agreement whose body is acknowledged"),
+    ),
+    responses(
+        (status = 200, description = "Successfully acknowledged an Agreement", body = [entity::agreements::Model],
+            headers(("ETag" = String, description = "Version of the acknowledged Agreement"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Agreement not found, or the user is neither the coach nor the coachee of its coaching relationship"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Agreement has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn acknowledge(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Acknowledge Agreement {} by user {}", id, user.id);
+
+    let agreement =
+        AgreementApi::acknowledge(app_state.db_conn_ref(), id, precondition, user.id).await?;
+
+    debug!("Acknowledged Agreement: {:?}", agreement);
+
+    Ok((
+        etag::header(&agreement.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), agreement)),
+    ))
+}
@@ -0,0 +1,325 @@
+use crate::controller::{content_disposition, ApiResponse};
+use crate::extractors::{
+    authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+};
+use crate::extractors::{json::Json, path::Path};
+use crate::{AppState, Error};
+use axum::extract::multipart::MultipartError;
+use axum::extract::{DefaultBodyLimit, Multipart, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use entity::Id;
+use entity_api::attachment::{self as AttachmentApi, Limits, Upload};
+use entity_api::error::{EntityApiErrorCode, Error as EntityApiError};
+use service::config::{ApiVersion, Config};
+use utoipa::ToSchema;
+
+use log::*;
+
+/// The multipart form field that carries the uploaded file.
+const FILE_FIELD: &str = "file";
+
+/// Room for the multipart boundaries and part headers around the file itself.
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// The request body limit for uploads, which replaces axum's 2MB default.
+pub(crate) fn upload_body_limit(config: &Config) -> DefaultBodyLimit {
+    DefaultBodyLimit::max(config.attachment_max_bytes + MULTIPART_OVERHEAD_BYTES)
+}
+
+/// The multipart form expected when uploading an attachment.
+#[derive(ToSchema)]
+#[allow(dead_code)] // Only describes the request body for OpenAPI
+pub(crate) struct AttachmentUpload {
+    /// The file to attach. Its part's Content-Type must be one of the allowed MIME types.
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// POST upload a new Attachment to a Coaching Session
+#[utoipa::path(
+    post,
+    path = "/coaching_sessions/{coaching_session_id}/attachments",
+    params(
+        ApiVersion,
+        ("coaching_session_id" = Id, Path, description = "Id of the coaching session to attach the file to"),
+    ),
+    request_body(content = AttachmentUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Successfully uploaded a new Attachment", body = entity::attachments::Model),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Coaching Session not found, or not one the user is a member of"),
+        (status = 405, description = "Method not allowed"),
+        (status = 413, description = "File is larger than allowed"),
+        (status = 415, description = "File is not of an allowed MIME type"),
+        (status = 422, description = "Unprocessable Entity")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn create(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(coaching_session_id): Path<Id>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "POST upload a new Attachment to Coaching Session {}",
+        coaching_session_id
+    );
+
+    let limits = Limits::from(&app_state.config);
+    let mut upload: Option<Upload> = None;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+
+        let filename = field.file_name().unwrap_or_default().to_owned();
+        let content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_owned();
+
+        // Checked as the file streams in so an oversized upload is never fully buffered
+        let mut contents = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+            contents.extend_from_slice(&chunk);
+            limits.check(&content_type, contents.len())?;
+        }
+
+        upload = Some(Upload {
+            filename,
+            content_type,
+            contents,
+        });
+        break;
+    }
+
+    let upload = upload.ok_or_else(|| {
+        warn!("Upload is missing its \"{}\" field", FILE_FIELD);
+
+        EntityApiError {
+            inner: None,
+            error_code: EntityApiErrorCode::InvalidQueryTerm,
+            detail: None,
+        }
+    })?;
+
+    let attachment = AttachmentApi::create(
+        app_state.db_conn_ref(),
+        app_state.storage.as_ref(),
+        limits,
+        coaching_session_id,
+        upload,
+        user.id,
+    )
+    .await?;
+
+    debug!("New Attachment: {:?}", attachment);
+
+    Ok(Json(ApiResponse::new(
+        StatusCode::CREATED.into(),
+        attachment,
+    )))
+}
+
+/// GET all Attachments of a Coaching Session
+#[utoipa::path(
+    get,
+    path = "/coaching_sessions/{coaching_session_id}/attachments",
+    params(
+        ApiVersion,
+        ("coaching_session_id" = Id, Path, description = "Id of the coaching session to list attachments of"),
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved all Attachments of a Coaching Session", body = [entity::attachments::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Coaching Session not found, or not one the user is a member of"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn index(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(coaching_session_id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "GET all Attachments of Coaching Session {}",
+        coaching_session_id
+    );
+
+    let attachments = AttachmentApi::find_by_coaching_session(
+        app_state.db_conn_ref(),
+        coaching_session_id,
+        user.id,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), attachments)))
+}
+
+/// GET the contents of an Attachment as a file download.
+///
+/// Browsers following a download link cannot set custom headers, so unlike other endpoints
+/// this one does not require the API version header. The session cookie still applies.
+#[utoipa::path(
+    get,
+    path = "/coaching_sessions/{coaching_session_id}/attachments/{attachment_id}",
+    params(
+        ("coaching_session_id" = Id, Path, description = "Id of the coaching session the attachment belongs to"),
+        ("attachment_id" = Id, Path, description = "Id of the attachment to download"),
+    ),
+    responses(
+        (status = 200, description = "The attachment's contents, served with its MIME type", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Attachment not found, or not of a Coaching Session the user is a member of"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn download(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((coaching_session_id, attachment_id)): Path<(Id, Id)>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "GET download Attachment {} of Coaching Session {}",
+        attachment_id, coaching_session_id
+    );
+
+    let (attachment, contents) = AttachmentApi::download(
+        app_state.db_conn_ref(),
+        app_state.storage.as_ref(),
+        coaching_session_id,
+        attachment_id,
+        user.id,
+    )
+    .await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.content_type.clone()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&attachment.filename),
+            ),
+            // Never let a browser reinterpret an upload as something it can execute
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+        ],
+        contents,
+    ))
+}
+
+/// DELETE an Attachment. Only the user that uploaded it may delete it, and it stays
+/// restorable until it is purged after the retention period.
+#[utoipa::path(
+    delete,
+    path = "/coaching_sessions/{coaching_session_id}/attachments/{attachment_id}",
+    params(
+        ApiVersion,
+        ("coaching_session_id" = Id, Path, description = "Id of the coaching session the attachment belongs to"),
+        ("attachment_id" = Id, Path, description = "Id of the attachment to delete"),
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted an Attachment", body = [Id]),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not the uploader of the attachment"),
+        (status = 404, description = "Attachment not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn delete(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((coaching_session_id, attachment_id)): Path<(Id, Id)>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "DELETE Attachment {} of Coaching Session {}",
+        attachment_id, coaching_session_id
+    );
+
+    AttachmentApi::delete_by_id(
+        app_state.db_conn_ref(),
+        coaching_session_id,
+        attachment_id,
+        user.id,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), attachment_id)))
+}
+
+/// POST restore a deleted Attachment that hasn't been purged yet. Only the user that uploaded
+/// it may restore it.
+#[utoipa::path(
+    post,
+    path = "/coaching_sessions/{coaching_session_id}/attachments/{attachment_id}/restore",
+    params(
+        ApiVersion,
+        ("coaching_session_id" = Id, Path, description = "Id of the coaching session the attachment belongs to"),
+        ("attachment_id" = Id, Path, description = "Id of the deleted attachment to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted Attachment", body = entity::attachments::Model),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not the uploader of the attachment"),
+        (status = 404, description = "Attachment not found or not deleted"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((coaching_session_id, attachment_id)): Path<(Id, Id)>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "POST Restore deleted Attachment {} of Coaching Session {}",
+        attachment_id, coaching_session_id
+    );
+
+    let attachment = AttachmentApi::restore(
+        app_state.db_conn_ref(),
+        coaching_session_id,
+        attachment_id,
+        user.id,
+    )
+    .await?;
+
+    debug!("Restored Attachment: {:?}", attachment);
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), attachment)))
+}
+
+fn multipart_error(err: MultipartError) -> EntityApiError {
+    warn!("Failed to read multipart upload: {}", err);
+
+    let error_code = if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        EntityApiErrorCode::PayloadTooLarge
+    } else {
+        EntityApiErrorCode::InvalidQueryTerm
+    };
+
+    EntityApiError {
+        inner: None,
+        error_code,
+        detail: None,
+    }
+}
@@ -0,0 +1,2 @@
+pub(crate) mod attachment_controller;
+pub(crate) mod summary_controller;
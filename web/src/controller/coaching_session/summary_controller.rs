@@ -0,0 +1,64 @@
+use crate::controller::content_disposition;
+use crate::extractors::authenticated_user::AuthenticatedUser;
+use crate::extractors::path::Path;
+use crate::pdf;
+use crate::{AppState, Error};
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use entity::Id;
+use entity_api::session_summary as SessionSummaryApi;
+
+use log::*;
+
+/// GET a printable summary of a Coaching Session as a PDF download.
+///
+/// Like other downloads, this endpoint does not require the API version header so that
+/// browsers can follow a link to it. The session cookie still applies.
+#[utoipa::path(
+    get,
+    path = "/coaching_sessions/{coaching_session_id}/summary.pdf",
+    params(
+        ("coaching_session_id" = Id, Path, description = "Id of the coaching session to summarize"),
+    ),
+    responses(
+        (status = 200, description = "The session's date, participants, agreements, actions and goal statuses, branded with the organization's logo", content_type = "application/pdf", body = Vec<u8>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Coaching Session not found, or not one the user is a member of"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn show(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(coaching_session_id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET summary of Coaching Session {}", coaching_session_id);
+
+    let summary = SessionSummaryApi::find_by_coaching_session(
+        app_state.db_conn_ref(),
+        coaching_session_id,
+        user.id,
+    )
+    .await?;
+
+    let logo = match summary.organization.logo.as_deref() {
+        Some(url) => pdf::fetch_logo(url).await,
+        None => None,
+    };
+    let document = pdf::render_session_summary(&summary, logo.as_ref());
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&pdf::summary_filename(&summary)),
+            ),
+        ],
+        document,
+    ))
+}
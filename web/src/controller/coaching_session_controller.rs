@@ -1,14 +1,18 @@
 use crate::controller::ApiResponse;
+use crate::etag;
+use crate::extractors::json::{Json, ValidJson};
 use crate::extractors::{
     authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+    if_match::IfMatch, path::Path,
 };
+use crate::params::coaching_session::{agenda_note, NewCoachingSession, PatchCoachingSession};
 use crate::{AppState, Error};
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::Json;
-use entity::coaching_sessions::Model;
-use entity_api::coaching_session as CoachingSessionApi;
+use entity::Id;
+use entity_api::{coaching_session as CoachingSessionApi, note as NoteApi, unit_of_work};
+use serde_json::json;
 use service::config::ApiVersion;
 use std::collections::HashMap;
 
@@ -53,14 +57,16 @@ pub async fn index(
     )))
 }
 
-/// POST create a new Coaching Session
+/// POST create a new Coaching Session, along with a shared Note for each item of its agenda.
+/// Either all of them are created or none are.
 #[utoipa::path(
     post,
     path = "/coaching_sessions",
     params(ApiVersion),
-    request_body = entity::coaching_sessions::Model,
+    request_body = web::params::coaching_session::NewCoachingSession,
     responses(
-        (status = 201, description = "Successfully Created a new Coaching Session", body = [entity::coaching_sessions::Model]),
+        (status = 201, description = "Successfully Created a new Coaching Session", body = [entity::coaching_sessions::Model],
+            headers(("ETag" = String, description = "Version of the new Coaching Session"))),
         (status= 422, description = "Unprocessable Entity"),
         (status = 401, description = "Unauthorized"),
         (status = 405, description = "Method not allowed")
@@ -71,24 +77,200 @@ pub async fn index(
 )]
 pub async fn create(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Json(coaching_sessions_model): Json<Model>,
+    ValidJson(mut params): ValidJson<NewCoachingSession>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Create a new Coaching Session from: {:?}", params);
+
+    let agenda = std::mem::take(&mut params.agenda);
+    let coaching_session = unit_of_work::run(app_state.db_conn_ref(), |txn| {
+        Box::pin(async move {
+            let coaching_session = CoachingSessionApi::create(txn, params.into()).await?;
+
+            for item in agenda {
+                NoteApi::create(txn, agenda_note(coaching_session.id, item), user.id).await?;
+            }
+
+            Ok(coaching_session)
+        })
+    })
+    .await?;
+
+    debug!("New Coaching Session: {:?}", coaching_session);
+
+    Ok((
+        etag::header(&coaching_session.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::CREATED.into(),
+            coaching_session,
+        )),
+    ))
+}
+
+/// GET a particular Coaching Session that the user takes part in
+#[utoipa::path(
+    get,
+    path = "/coaching_sessions/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Coaching Session id to retrieve")
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved a Coaching Session", body = entity::coaching_sessions::Model,
+            headers(("ETag" = String, description = "Version of the Coaching Session"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Coaching Session not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn read(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Coaching Session by id: {}", id);
+
+    let coaching_session =
+        CoachingSessionApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    Ok((
+        etag::header(&coaching_session.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), coaching_session)),
+    ))
+}
+
+/// PATCH some of a Coaching Session's fields, leaving out the ones that should keep their value
+#[utoipa::path(
+    patch,
+    path = "/coaching_sessions/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of coaching session to change"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Coaching Session to change"),
+    ),
+    request_body(content = web::params::coaching_session::PatchCoachingSession, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Successfully Updated Coaching Session", body = [entity::coaching_sessions::Model],
+            headers(("ETag" = String, description = "Version of the updated Coaching Session"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Coaching Session not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Coaching Session has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn patch(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<PatchCoachingSession>,
 ) -> Result<impl IntoResponse, Error> {
     debug!(
-        "POST Create a new Coaching Session from: {:?}",
-        coaching_sessions_model
+        "PATCH Coaching Session with id: {}, changes: {:?}",
+        id, params
     );
 
+    let coaching_session = CoachingSessionApi::patch(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
+
+    debug!("Updated Coaching Session: {:?}", coaching_session);
+
+    Ok((
+        etag::header(&coaching_session.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), coaching_session)),
+    ))
+}
+
+/// DELETE a Coaching Session along with its notes, agreements, actions, overarching goals and
+/// attachments. They stay restorable together until they are purged after the retention
+/// period.
+#[utoipa::path(
+    delete,
+    path = "/coaching_sessions/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Coaching Session id to delete"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Coaching Session to delete")
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted a certain Coaching Session by its id", body = [Id]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Coaching Session not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Coaching Session has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn delete(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!("DELETE Coaching Session by id: {}", id);
+
+    CoachingSessionApi::delete_by_id(app_state.db_conn_ref(), id, precondition, user.id).await?;
+    Ok(Json(json!({"id": id})))
+}
+
+/// POST restore a deleted Coaching Session that hasn't been purged yet, along with the content
+/// that was deleted with it.
+#[utoipa::path(
+    post,
+    path = "/coaching_sessions/{id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of the deleted coaching session to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted Coaching Session", body = [entity::coaching_sessions::Model],
+            headers(("ETag" = String, description = "Version of the restored Coaching Session"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Coaching Session not found or not deleted"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Restore deleted Coaching Session: {}", id);
+
     let coaching_session =
-        CoachingSessionApi::create(app_state.db_conn_ref(), coaching_sessions_model).await?;
+        CoachingSessionApi::restore(app_state.db_conn_ref(), id, user.id).await?;
 
-    debug!("New Coaching Session: {:?}", coaching_session);
+    debug!("Restored Coaching Session: {:?}", coaching_session);
 
-    Ok(Json(ApiResponse::new(
-        StatusCode::CREATED.into(),
-        coaching_session,
-    )))
+    Ok((
+        etag::header(&coaching_session.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), coaching_session)),
+    ))
 }
@@ -2,11 +2,13 @@ use serde::Serialize;
 
 pub(crate) mod action_controller;
 pub(crate) mod agreement_controller;
+pub(crate) mod coaching_session;
 pub(crate) mod coaching_session_controller;
 pub(crate) mod note_controller;
 pub(crate) mod organization;
 pub(crate) mod organization_controller;
 pub(crate) mod overarching_goal_controller;
+pub(crate) mod search_controller;
 pub(crate) mod user_controller;
 pub(crate) mod user_session_controller;
 
@@ -22,3 +24,44 @@ impl<T: Serialize> ApiResponse<T> {
         Self { status_code, data }
     }
 }
+
+/// The Content-Disposition of a file download: an ASCII-only `filename` for older clients
+/// plus the exact name, percent-encoded, in `filename*` (RFC 6266).
+pub(crate) fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let encoded: String = filename
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect();
+
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_disposition_keeps_an_ascii_fallback_and_the_exact_name() {
+        assert_eq!(
+            content_disposition("Rückblick Q4.pdf"),
+            "attachment; filename=\"R_ckblick Q4.pdf\"; filename*=UTF-8''R%C3%BCckblick%20Q4.pdf"
+        );
+    }
+}
@@ -1,14 +1,27 @@
+use crate::collaboration::{self, NoteRooms};
 use crate::controller::ApiResponse;
+use crate::etag;
 use crate::extractors::{
     authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+    if_match::IfMatch,
 };
+use crate::extractors::{
+    json::{Json, ValidJson},
+    path::Path,
+};
+use crate::markdown::{self, BodyFormat};
+use crate::params::note::{NewNote, PatchNote, UpdateNote};
+use crate::request_id;
 use crate::{AppState, Error};
-use axum::extract::{Path, Query, State};
-use axum::http::StatusCode;
+use axum::extract::{ws::WebSocketUpgrade, Query, State};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
-use axum::Json;
-use entity::{notes, Id};
+use axum::Extension;
+use entity::Id;
+use entity_api::error::{EntityApiErrorCode, Error as EntityApiError};
 use entity_api::note as NoteApi;
+use entity_api::revision::{self as RevisionApi, DiffParams, Subject};
+use serde_json::json;
 use service::config::ApiVersion;
 use std::collections::HashMap;
 
@@ -19,9 +32,10 @@ use log::*;
     post,
     path = "/notes",
     params(ApiVersion),
-    request_body = entity::notes::Model,
+    request_body = web::params::note::NewNote,
     responses(
-        (status = 201, description = "Successfully Created a New Note", body = [entity::notes::Model]),
+        (status = 201, description = "Successfully Created a New Note", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the Note, to send back in `If-Match` to change it"))),
         (status= 422, description = "Unprocessable Entity"),
         (status = 401, description = "Unauthorized"),
         (status = 405, description = "Method not allowed")
@@ -37,15 +51,18 @@ pub async fn create(
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Json(note_model): Json<notes::Model>,
+    ValidJson(params): ValidJson<NewNote>,
 ) -> Result<impl IntoResponse, Error> {
-    debug!("POST Create a New Note from: {:?}", note_model);
+    debug!("POST Create a New Note from: {:?}", params);
 
-    let note = NoteApi::create(app_state.db_conn_ref(), note_model, user.id).await?;
+    let note = NoteApi::create(app_state.db_conn_ref(), params.into(), user.id).await?;
 
     debug!("New Note: {:?}", note);
 
-    Ok(Json(ApiResponse::new(StatusCode::CREATED.into(), note)))
+    Ok((
+        etag::header(&note.updated_at),
+        Json(ApiResponse::new(StatusCode::CREATED.into(), note)),
+    ))
 }
 
 #[utoipa::path(
@@ -54,12 +71,18 @@ pub async fn create(
     params(
         ApiVersion,
         ("id" = Id, Path, description = "Id of note to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Note to update"),
     ),
-    request_body = entity::notes::Model,
+    request_body = web::params::note::UpdateNote,
     responses(
-        (status = 200, description = "Successfully Updated Note", body = [entity::notes::Model]),
+        (status = 200, description = "Successfully Updated Note", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the updated Note"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 404, description = "Note not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Note has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -67,20 +90,84 @@ pub async fn create(
 )]
 pub async fn update(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
-    Json(note_model): Json<notes::Model>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<UpdateNote>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("PUT Update Note with id: {}", id);
 
-    let note = NoteApi::update(app_state.db_conn_ref(), id, note_model).await?;
+    let note = NoteApi::update(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
+
+    debug!("Updated Note: {:?}", note);
+
+    Ok((
+        etag::header(&note.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), note)),
+    ))
+}
+
+/// PATCH some of an Note's fields, leaving out the ones that should keep their value
+#[utoipa::path(
+    patch,
+    path = "/notes/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of note to change"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Note to change"),
+    ),
+    request_body(content = web::params::note::PatchNote, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Successfully Updated Note", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the updated Note"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Note not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Note has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn patch(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    // TODO: create a new Extractor to authorize the user to access
+    // the data requested
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<PatchNote>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("PATCH Note with id: {}, changes: {:?}", id, params);
+
+    let note = NoteApi::patch(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
 
     debug!("Updated Note: {:?}", note);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), note)))
+    Ok((
+        etag::header(&note.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), note)),
+    ))
 }
 
 #[utoipa::path(
@@ -88,7 +175,8 @@ pub async fn update(
     path = "/notes",
     params(
         ApiVersion,
-        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id")
+        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
         (status = 200, description = "Successfully retrieved all Notes", body = [entity::coaching_sessions::Model]),
@@ -101,20 +189,28 @@ pub async fn update(
 )]
 pub async fn index(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Query(params): Query<HashMap<String, String>>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET all Notes");
     debug!("Filter Params: {:?}", params);
 
-    let notes = NoteApi::find_by(app_state.db_conn_ref(), params).await?;
+    let format = BodyFormat::take_from(&mut params)?;
+
+    let notes = NoteApi::find_by(app_state.db_conn_ref(), params, user.id).await?;
 
     debug!("Found Notes: {:?}", notes);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), notes)))
+    Ok(Json(ApiResponse::new(
+        StatusCode::OK.into(),
+        notes
+            .into_iter()
+            .map(|model| markdown::present(model, format))
+            .collect::<Vec<_>>(),
+    )))
 }
 
 /// GET a particular Note specified by its id.
@@ -123,10 +219,12 @@ pub async fn index(
     path = "/notes/{id}",
     params(
         ApiVersion,
-        ("id" = String, Path, description = "Note id to retrieve")
+        ("id" = String, Path, description = "Note id to retrieve"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
-        (status = 200, description = "Successfully retrieved a certain Note by its id", body = [entity::notes::Model]),
+        (status = 200, description = "Successfully retrieved a certain Note by its id", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the Note, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Note not found"),
         (status = 405, description = "Method not allowed")
@@ -137,12 +235,290 @@ pub async fn index(
 )]
 pub async fn read(
     CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET Organization by id: {}", id);
 
-    let note: Option<notes::Model> = NoteApi::find_by_id(app_state.db_conn_ref(), id).await?;
+    let format = BodyFormat::take_from(&mut params)?;
+
+    let note = NoteApi::find_by_id(app_state.db_conn_ref(), id, user.id).await?;
+
+    Ok((
+        etag::header(&note.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            markdown::present(note, format),
+        )),
+    ))
+}
+
+/// DELETE a Note specified by its primary key. It stays restorable until it is purged after
+/// the retention period.
+#[utoipa::path(
+    delete,
+    path = "/notes/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Note id to delete"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Note to delete")
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted a certain Note by its id", body = [Id]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Note not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Note has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn delete(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!("DELETE Note by id: {}", id);
+
+    NoteApi::delete_by_id(app_state.db_conn_ref(), id, precondition, user.id).await?;
+    Ok(Json(json!({"id": id})))
+}
+
+/// POST restore a deleted Note that hasn't been purged yet.
+#[utoipa::path(
+    post,
+    path = "/notes/{id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of the deleted note to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted Note", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the restored Note"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Note not found or not deleted"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Restore deleted Note: {}", id);
+
+    let note = NoteApi::restore(app_state.db_conn_ref(), id, user.id).await?;
+
+    debug!("Restored Note: {:?}", note);
+
+    Ok((
+        etag::header(&note.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), note)),
+    ))
+}
+
+/// GET a WebSocket connection for editing a Note's body together in real time.
+///
+/// The connection speaks the y-websocket protocol, so any Yjs `WebsocketProvider` bound to
+/// the `body` text type can join. Presence and cursor positions are shared through Yjs
+/// awareness, and the merged body is persisted back to the Note as edits settle.
+///
+/// Browsers cannot set custom headers on a WebSocket handshake, so unlike other endpoints
+/// this one does not require the API version header. The session cookie still applies, and a
+/// handshake from a browser must come from one of the allowed origins.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/collaborate",
+    params(
+        ("id" = Id, Path, description = "Id of note to collaboratively edit"),
+    ),
+    responses(
+        (status = 101, description = "Switching to the y-websocket protocol"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Handshake came from an origin that isn't allowed"),
+        (status = 404, description = "Note not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn collaborate(
+    AuthenticatedUser(user): AuthenticatedUser,
+    // TODO: create a new Extractor to authorize the user to access
+    // the data requested
+    State(app_state): State<AppState>,
+    Extension(rooms): Extension<NoteRooms>,
+    Path(id): Path<Id>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Collaborate on Note with id: {}", id);
+
+    if !collaboration::allowed_origin(&headers, &app_state.config.allowed_origins) {
+        warn!(
+            "Rejecting collaboration on Note {} from origin {:?}",
+            id,
+            headers.get(axum::http::header::ORIGIN)
+        );
+
+        return Err(EntityApiError {
+            inner: None,
+            error_code: EntityApiErrorCode::RecordUnauthorized,
+            detail: None,
+        }
+        .into());
+    }
+
+    let note = NoteApi::find_by_id(app_state.db_conn_ref(), id, user.id).await?;
+    let request_id = request_id::current();
+
+    Ok(ws.on_upgrade(move |socket| {
+        collaboration::serve(socket, app_state, rooms, note, user.id, request_id)
+    }))
+}
+
+/// GET every revision of a particular Note's body, newest first.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/revisions",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of note to retrieve the revisions of"),
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the revisions of a Note", body = [entity::revisions::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Note not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn revisions(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    // TODO: create a new Extractor to authorize the user to access
+    // the data requested
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Note revisions for id: {}", id);
+
+    // Ensures a 404 for a Note that doesn't exist, or is another user's private Note,
+    // rather than an empty list
+    NoteApi::find_by_id(app_state.db_conn_ref(), id, user.id).await?;
+
+    let revisions =
+        RevisionApi::find_by_subject(app_state.db_conn_ref(), Subject::Note(id)).await?;
+
+    debug!("Found Note revisions: {:?}", revisions);
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), revisions)))
+}
+
+/// GET a unified diff of a Note's body between two of its revisions.
+#[utoipa::path(
+    get,
+    path = "/notes/{id}/revisions/diff",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of note to diff the revisions of"),
+        DiffParams,
+    ),
+    responses(
+        (status = 200, description = "Successfully diffed two revisions of a Note", body = String),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Note or revision not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn diff_revisions(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    // TODO: create a new Extractor to authorize the user to access
+    // the data requested
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    Query(params): Query<DiffParams>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Note revision diff for id: {}, {:?}", id, params);
+
+    // Ensures another user's private Note can't be read through its revisions
+    NoteApi::find_by_id(app_state.db_conn_ref(), id, user.id).await?;
+
+    let diff = RevisionApi::diff(
+        app_state.db_conn_ref(),
+        Subject::Note(id),
+        params.from,
+        params.to,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), diff)))
+}
+
+/// POST restore a Note's body to that of one of its earlier revisions.
+#[utoipa::path(
+    post,
+    path = "/notes/{id}/revisions/{revision_id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of note to restore"),
+        ("revision_id" = Id, Path, description = "Id of the revision to restore the body from"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Note to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a Note to an earlier revision", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the restored Note"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Note or revision not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Note has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore_revision(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    // TODO: create a new Extractor to authorize the user to access
+    // the data requested
+    State(app_state): State<AppState>,
+    Path((id, revision_id)): Path<(Id, Id)>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Restore Note {} to revision {}", id, revision_id);
+
+    let note = NoteApi::restore_revision(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        revision_id,
+        user.id,
+    )
+    .await?;
+
+    debug!("Restored Note: {:?}", note);
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), note)))
+    Ok((
+        etag::header(&note.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), note)),
+    ))
 }
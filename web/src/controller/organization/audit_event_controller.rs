@@ -0,0 +1,59 @@
+use crate::controller::ApiResponse;
+use crate::extractors::{
+    authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+};
+use crate::extractors::{json::Json, path::Path};
+use crate::{AppState, Error};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use entity::Id;
+use entity_api::audit as AuditApi;
+use service::config::ApiVersion;
+use std::collections::HashMap;
+
+use log::*;
+
+/// GET the audit log of an Organization, newest first: who created, changed or deleted which of
+/// its records and when. Only admins of the Organization may view it.
+#[utoipa::path(
+    get,
+    path = "/organizations/{organization_id}/audit_events",
+    params(
+        ApiVersion,
+        ("organization_id" = Id, Path, description = "Organization id to retrieve Audit Events for"),
+        ("actor_id" = Option<Id>, Query, description = "Only writes made by this user"),
+        ("entity_type" = Option<String>, Query, description = "Only writes to this kind of record, e.g. `actions`"),
+        ("entity_id" = Option<Id>, Query, description = "Only writes to this record"),
+        ("action" = Option<entity::audit_action::AuditAction>, Query, description = "Only this kind of write"),
+        ("request_id" = Option<String>, Query, description = "Only writes made by the request with this `X-Request-Id`"),
+        ("from_date" = Option<String>, Query, description = "Only writes made on or after this day, e.g. `2025-01-01`"),
+        ("to_date" = Option<String>, Query, description = "Only writes made on or before this day, e.g. `2025-12-31`")
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the Organization's Audit Events", body = [entity::audit_events::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Organization not found, or not one the user is an admin of"),
+        (status = 405, description = "Method not allowed"),
+        (status = 422, description = "Invalid or unknown filter")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn index(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(organization_id): Path<Id>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Audit Events of Organization {}", organization_id);
+    debug!("Filter Params: {:?}", params);
+
+    let audit_events =
+        AuditApi::find_by_organization(app_state.db_conn_ref(), organization_id, user.id, params)
+            .await?;
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), audit_events)))
+}
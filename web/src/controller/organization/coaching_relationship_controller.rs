@@ -1,30 +1,52 @@
-use crate::controller::ApiResponse;
+use crate::controller::{content_disposition, ApiResponse};
+use crate::etag;
 use crate::extractors::{
     authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+    if_match::IfMatch,
 };
+use crate::extractors::{
+    json::{Json, ValidJson},
+    path::Path,
+};
+use crate::params::coaching_relationship::{NewCoachingRelationship, UpdateCoachingRelationship};
 use crate::{AppState, Error};
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use axum::Json;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{AppendHeaders, IntoResponse};
 use entity::{coaching_relationships, Id};
-use entity_api::coaching_relationship as CoachingRelationshipApi;
+use entity_api::coaching_relationship::{self as CoachingRelationshipApi, Reassignment};
+use entity_api::export as ExportApi;
+use entity_api::organization as OrganizationApi;
+use futures::{future, stream, StreamExt};
+use serde_json::json;
 use service::config::ApiVersion;
+use std::io;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
 
 use log::*;
 
-/// CREATE a new CoachingRelationship.
+/// How much of an export archive may be buffered ahead of the client reading it.
+const EXPORT_BUFFER_BYTES: usize = 64 * 1024;
+
+/// CREATE a new CoachingRelationship. A coach and coachee may only have one relationship
+/// in an organization that has not ended.
 #[utoipa::path(
     post,
     path = "/organizations/{organization_id}/coaching_relationships",
     params(
         ApiVersion,
+        ("organization_id" = Id, Path, description = "Organization id to create the CoachingRelationship in")
     ),
-    request_body = entity::coaching_relationships::Model,
+    request_body = web::params::coaching_relationship::NewCoachingRelationship,
     responses(
-        (status = 200, description = "Successfully created a new Coaching Relationship", body = [entity::coaching_relationships::Model]),
+        (status = 200, description = "Successfully created a new Coaching Relationship", body = [entity::coaching_relationships::Model],
+            headers(("ETag" = String, description = "Version of the CoachingRelationship, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "The coach and coachee already have a relationship in the organization that has not ended"),
+        (status = 422, description = "The coach and coachee are the same user or another field is invalid")
     ),
     security(
         ("cookie_auth" = [])
@@ -33,26 +55,32 @@ use log::*;
 pub async fn create(
     CompareApiVersion(_v): CompareApiVersion,
     State(app_state): State<AppState>,
-    Json(coaching_relationship_model): Json<coaching_relationships::Model>,
+    Path(organization_id): Path<Id>,
+    ValidJson(params): ValidJson<NewCoachingRelationship>,
 ) -> Result<impl IntoResponse, Error> {
     debug!(
-        "CREATE new Coaching Relationship from: {:?}",
-        coaching_relationship_model
+        "CREATE new Coaching Relationship in Organization {} from: {:?}",
+        organization_id, params
     );
 
-    let coaching_relationship: coaching_relationships::Model =
-        CoachingRelationshipApi::create(app_state.db_conn_ref(), coaching_relationship_model)
-            .await?;
+    let coaching_relationship: coaching_relationships::Model = CoachingRelationshipApi::create(
+        app_state.db_conn_ref(),
+        params.into_model(organization_id),
+    )
+    .await?;
 
     debug!(
         "Newly created Coaching Relationship: {:?}",
         &coaching_relationship
     );
 
-    Ok(Json(ApiResponse::new(
-        StatusCode::CREATED.into(),
-        coaching_relationship,
-    )))
+    Ok((
+        etag::header(&coaching_relationship.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::CREATED.into(),
+            coaching_relationship,
+        )),
+    ))
 }
 
 /// GET a particular CoachingRelationship specified by the organization Id and relationship Id.
@@ -65,7 +93,8 @@ pub async fn create(
         ("relationship_id" = String, Path, description = "CoachingRelationship id to retrieve")
     ),
     responses(
-        (status = 200, description = "Successfully retrieved a certain CoachingRelationship by its id", body = [entity::coaching_relationships::Model]),
+        (status = 200, description = "Successfully retrieved a certain CoachingRelationship by its id", body = [entity::coaching_relationships::Model],
+            headers(("ETag" = String, description = "Version of the CoachingRelationship, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "CoachingRelationship not found"),
         (status = 405, description = "Method not allowed")
@@ -91,7 +120,14 @@ pub async fn read(
         )
         .await?;
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), relationship)))
+    let etag = relationship
+        .as_ref()
+        .map(|relationship| (header::ETAG, etag::of(&relationship.updated_at)));
+
+    Ok((
+        AppendHeaders(etag),
+        Json(ApiResponse::new(StatusCode::OK.into(), relationship)),
+    ))
 }
 
 /// GET all CoachingRelationships by organization_id
@@ -134,3 +170,285 @@ pub async fn index(
         coaching_relationships,
     )))
 }
+
+/// PUT update the status and dates of a CoachingRelationship, e.g. to pause or end it.
+#[utoipa::path(
+    put,
+    path = "/organizations/{organization_id}/coaching_relationships/{relationship_id}",
+    params(
+        ApiVersion,
+        ("organization_id" = Id, Path, description = "Organization id the CoachingRelationship belongs to"),
+        ("relationship_id" = Id, Path, description = "CoachingRelationship id to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the CoachingRelationship to update")
+    ),
+    request_body = web::params::coaching_relationship::UpdateCoachingRelationship,
+    responses(
+        (status = 200, description = "Successfully updated a CoachingRelationship", body = [entity::coaching_relationships::Model],
+            headers(("ETag" = String, description = "Version of the updated CoachingRelationship"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "CoachingRelationship not found, or the user is not an admin of its Organization"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "An ended CoachingRelationship cannot be resumed"),
+        (status = 412, description = "CoachingRelationship has changed since the version named by `If-Match`"),
+        (status = 422, description = "Unprocessable Entity"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn update(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((organization_id, relationship_id)): Path<(Id, Id)>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<UpdateCoachingRelationship>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "PUT Update CoachingRelationship with id: {}",
+        relationship_id
+    );
+
+    OrganizationApi::find_by_id_for_admin(app_state.db_conn_ref(), organization_id, user.id)
+        .await?;
+
+    let coaching_relationship = CoachingRelationshipApi::update(
+        app_state.db_conn_ref(),
+        organization_id,
+        relationship_id,
+        precondition,
+        params.into_model(organization_id),
+    )
+    .await?;
+
+    debug!("Updated CoachingRelationship: {:?}", coaching_relationship);
+
+    Ok((
+        etag::header(&coaching_relationship.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            coaching_relationship,
+        )),
+    ))
+}
+
+/// DELETE a CoachingRelationship along with its sessions and everything in them. They stay
+/// restorable together until they are purged after the retention period.
+#[utoipa::path(
+    delete,
+    path = "/organizations/{organization_id}/coaching_relationships/{relationship_id}",
+    params(
+        ApiVersion,
+        ("organization_id" = Id, Path, description = "Organization id the CoachingRelationship belongs to"),
+        ("relationship_id" = Id, Path, description = "CoachingRelationship id to delete"),
+        ("If-Match" = String, Header, description = "ETag of the version of the CoachingRelationship to delete")
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted a CoachingRelationship", body = [Id]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "CoachingRelationship not found, or the user is not an admin of its Organization"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "CoachingRelationship has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn delete(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((organization_id, relationship_id)): Path<(Id, Id)>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!("DELETE CoachingRelationship with id: {}", relationship_id);
+
+    OrganizationApi::find_by_id_for_admin(app_state.db_conn_ref(), organization_id, user.id)
+        .await?;
+
+    CoachingRelationshipApi::delete_by_id(
+        app_state.db_conn_ref(),
+        organization_id,
+        relationship_id,
+        precondition,
+    )
+    .await?;
+
+    Ok(Json(json!({"id": relationship_id})))
+}
+
+/// POST restore a deleted CoachingRelationship that hasn't been purged yet, along with the
+/// sessions and content that were deleted with it.
+#[utoipa::path(
+    post,
+    path = "/organizations/{organization_id}/coaching_relationships/{relationship_id}/restore",
+    params(
+        ApiVersion,
+        ("organization_id" = Id, Path, description = "Organization id the CoachingRelationship belongs to"),
+        ("relationship_id" = Id, Path, description = "Id of the deleted CoachingRelationship to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted CoachingRelationship", body = [entity::coaching_relationships::Model],
+            headers(("ETag" = String, description = "Version of the restored CoachingRelationship"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "CoachingRelationship not found or not deleted, or the user is not an admin of its Organization"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "The coach and coachee have another CoachingRelationship that has not ended")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((organization_id, relationship_id)): Path<(Id, Id)>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "POST Restore deleted CoachingRelationship: {}",
+        relationship_id
+    );
+
+    OrganizationApi::find_by_id_for_admin(app_state.db_conn_ref(), organization_id, user.id)
+        .await?;
+
+    let coaching_relationship =
+        CoachingRelationshipApi::restore(app_state.db_conn_ref(), organization_id, relationship_id)
+            .await?;
+
+    debug!("Restored CoachingRelationship: {:?}", coaching_relationship);
+
+    Ok((
+        etag::header(&coaching_relationship.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            coaching_relationship,
+        )),
+    ))
+}
+
+/// POST hand the coachee of a CoachingRelationship to another coach.
+///
+/// The existing relationship is ended and keeps its sessions, while a new active relationship
+/// between the new coach and the coachee is returned.
+#[utoipa::path(
+    post,
+    path = "/organizations/{organization_id}/coaching_relationships/{relationship_id}/reassign",
+    params(
+        ApiVersion,
+        ("organization_id" = Id, Path, description = "Organization id the CoachingRelationship belongs to"),
+        ("relationship_id" = Id, Path, description = "CoachingRelationship id to reassign")
+    ),
+    request_body = entity_api::coaching_relationship::Reassignment,
+    responses(
+        (status = 201, description = "Successfully reassigned the coachee to a new CoachingRelationship", body = [entity::coaching_relationships::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "CoachingRelationship not found, or the user is not an admin of its Organization"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "The CoachingRelationship has already ended, or the new coach already has a relationship with the coachee"),
+        (status = 422, description = "The new coach is already the coach or coachee of the CoachingRelationship")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn reassign(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((organization_id, relationship_id)): Path<(Id, Id)>,
+    Json(reassignment): Json<Reassignment>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "POST Reassign CoachingRelationship {} to coach {}",
+        relationship_id, reassignment.coach_id
+    );
+
+    OrganizationApi::find_by_id_for_admin(app_state.db_conn_ref(), organization_id, user.id)
+        .await?;
+
+    let coaching_relationship = CoachingRelationshipApi::reassign(
+        app_state.db_conn_ref(),
+        organization_id,
+        relationship_id,
+        reassignment,
+    )
+    .await?;
+
+    debug!("New CoachingRelationship: {:?}", coaching_relationship);
+
+    Ok(Json(ApiResponse::new(
+        StatusCode::CREATED.into(),
+        coaching_relationship,
+    )))
+}
+
+/// GET a ZIP archive of a CoachingRelationship's full history: its coaching sessions and
+/// their notes, agreements, actions and overarching goals, each as JSON and CSV. Only the
+/// coach and coachee may export it, and private notes are only included for their author.
+///
+/// Like attachment downloads, this does not require the API version header so that a plain
+/// link can start the download.
+#[utoipa::path(
+    get,
+    path = "/organizations/{organization_id}/coaching_relationships/{relationship_id}/export",
+    params(
+        ("organization_id" = Id, Path, description = "Organization id the CoachingRelationship belongs to"),
+        ("relationship_id" = Id, Path, description = "CoachingRelationship id to export")
+    ),
+    responses(
+        (status = 200, description = "The CoachingRelationship's history, streamed as a ZIP archive", content_type = "application/zip", body = Vec<u8>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "CoachingRelationship not found, or not one the user is the coach or coachee of"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn export(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((organization_id, relationship_id)): Path<(Id, Id)>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET export of CoachingRelationship {}", relationship_id);
+
+    let relationship = CoachingRelationshipApi::find_in_organization_for_member(
+        app_state.db_conn_ref(),
+        organization_id,
+        relationship_id,
+        user.id,
+    )
+    .await?;
+    let filename = ExportApi::archive_filename(&relationship);
+
+    // The archive is written into one end of a pipe by a background task while the response
+    // body is read from the other, so only a small buffer of it is ever held in memory
+    let (writer, reader) = tokio::io::duplex(EXPORT_BUFFER_BYTES);
+    let db = Arc::clone(&app_state.database_connection);
+    let job = tokio::spawn(async move {
+        ExportApi::write_archive(db.as_ref(), &relationship, user.id, writer).await
+    });
+
+    // Once the archive is done, a failed export aborts the response instead of letting it
+    // end as though the archive were complete
+    let outcome = stream::once(job).filter_map(|result| {
+        future::ready(match result {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(Err(io::Error::other(err))),
+            Err(err) => Some(Err(io::Error::other(err))),
+        })
+    });
+    let body = Body::from_stream(ReaderStream::new(reader).chain(outcome));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_owned()),
+            (header::CONTENT_DISPOSITION, content_disposition(&filename)),
+        ],
+        body,
+    ))
+}
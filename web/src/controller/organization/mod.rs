@@ -1 +1,3 @@
+pub(crate) mod audit_event_controller;
 pub(crate) mod coaching_relationship_controller;
+pub(crate) mod report_controller;
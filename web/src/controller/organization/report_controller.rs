@@ -0,0 +1,59 @@
+use crate::controller::ApiResponse;
+use crate::extractors::{
+    authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+};
+use crate::extractors::{json::Json, path::Path};
+use crate::{AppState, Error};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use entity::Id;
+use entity_api::analytics as AnalyticsApi;
+use service::config::ApiVersion;
+use std::collections::HashMap;
+
+use log::*;
+
+/// GET the coaching utilization report of an Organization, one entry per period. Only admins
+/// of the Organization may view it, and it only ever contains aggregate numbers.
+#[utoipa::path(
+    get,
+    path = "/organizations/{organization_id}/reports",
+    params(
+        ApiVersion,
+        ("organization_id" = Id, Path, description = "Organization id to report on"),
+        ("from_date" = String, Query, description = "First day of the report, e.g. `2025-01-01`"),
+        ("to_date" = String, Query, description = "Last day of the report, e.g. `2025-12-31`"),
+        ("group_by" = Option<String>, Query, description = "`week`, `month` (the default) or `quarter`")
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the Organization's report", body = [entity_api::analytics::PeriodReport]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Organization not found, or not one the user is an admin of"),
+        (status = 405, description = "Method not allowed"),
+        (status = 422, description = "Missing or invalid date range, or unknown grouping")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn index(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(organization_id): Path<Id>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Report of Organization {}", organization_id);
+    debug!("Report Params: {:?}", params);
+
+    let report = AnalyticsApi::find_by_organization(
+        app_state.db_conn_ref(),
+        organization_id,
+        user.id,
+        params,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), report)))
+}
@@ -1,12 +1,21 @@
 use crate::controller::ApiResponse;
+use crate::error::Problem;
+use crate::etag;
 use crate::extractors::{
     authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+    if_match::IfMatch,
+};
+use crate::extractors::{
+    json::{Json, ValidJson},
+    path::Path,
+};
+use crate::params::organization::{
+    DeleteOrganization, NewOrganization, PatchOrganization, UpdateOrganization,
 };
 use crate::{AppState, Error};
-use axum::extract::{Path, Query, State};
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use axum::Json;
+use axum::response::{IntoResponse, Response};
 use entity::{organizations, Id};
 use entity_api::organization as OrganizationApi;
 use serde_json::json;
@@ -59,7 +68,8 @@ pub async fn index(
         ("id" = String, Path, description = "Organization id to retrieve")
     ),
     responses(
-        (status = 200, description = "Successfully retrieved a certain Organization by its id", body = [entity::organizations::Model]),
+        (status = 200, description = "Successfully retrieved a certain Organization by its id", body = [entity::organizations::Model],
+            headers(("ETag" = String, description = "Version of the Organization, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Organization not found"),
         (status = 405, description = "Method not allowed")
@@ -75,10 +85,12 @@ pub async fn read(
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET Organization by id: {}", id);
 
-    let organization: Option<organizations::Model> =
-        OrganizationApi::find_by_id(app_state.db_conn_ref(), id).await?;
+    let organization = OrganizationApi::find_by_id(app_state.db_conn_ref(), id).await?;
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), organization)))
+    Ok((
+        etag::header(&organization.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), organization)),
+    ))
 }
 
 /// CREATE a new Organization.
@@ -88,11 +100,13 @@ pub async fn read(
     params(
         ApiVersion,
     ),
-    request_body = entity::organizations::Model,
+    request_body = web::params::organization::NewOrganization,
     responses(
-        (status = 200, description = "Successfully created a new Organization", body = [entity::organizations::Model]),
+        (status = 200, description = "Successfully created a new Organization", body = [entity::organizations::Model],
+            headers(("ETag" = String, description = "Version of the Organization, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 405, description = "Method not allowed"),
+        (status = 422, description = "One or more fields of the request body are invalid")
     ),
     security(
         ("cookie_auth" = [])
@@ -101,19 +115,19 @@ pub async fn read(
 pub async fn create(
     CompareApiVersion(_v): CompareApiVersion,
     State(app_state): State<AppState>,
-    Json(organization_model): Json<organizations::Model>,
+    ValidJson(params): ValidJson<NewOrganization>,
 ) -> Result<impl IntoResponse, Error> {
-    debug!("CREATE new Organization: {:?}", organization_model.name);
+    debug!("CREATE new Organization: {:?}", params.name);
 
     let organization: organizations::Model =
-        OrganizationApi::create(app_state.db_conn_ref(), organization_model).await?;
+        OrganizationApi::create(app_state.db_conn_ref(), params.into()).await?;
 
     debug!("Newly Created Organization: {:?}", &organization);
 
-    Ok(Json(ApiResponse::new(
-        StatusCode::CREATED.into(),
-        organization,
-    )))
+    Ok((
+        etag::header(&organization.updated_at),
+        Json(ApiResponse::new(StatusCode::CREATED.into(), organization)),
+    ))
 }
 
 /// UPDATE a particular Organization specified by its primary key.
@@ -122,14 +136,19 @@ pub async fn create(
     path = "/organizations/{id}",
     params(
         ApiVersion,
-        ("id" = i32, Path, description = "Organization id to update")
+        ("id" = i32, Path, description = "Organization id to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Organization to update")
     ),
-    request_body = entity::organizations::Model,
+    request_body = web::params::organization::UpdateOrganization,
     responses(
-        (status = 200, description = "Successfully updated a certain Organization by its id", body = [entity::organizations::Model]),
+        (status = 200, description = "Successfully updated a certain Organization by its id", body = [entity::organizations::Model],
+            headers(("ETag" = String, description = "Version of the updated Organization"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Organization not found"),
-        (status = 405, description = "Method not allowed")
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Organization has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -139,35 +158,91 @@ pub async fn update(
     CompareApiVersion(_v): CompareApiVersion,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
-    Json(organization_model): Json<organizations::Model>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<UpdateOrganization>,
 ) -> Result<impl IntoResponse, Error> {
     debug!(
         "UPDATE the entire Organization by id: {:?}, new name: {:?}",
-        id, organization_model.name
+        id, params.name
     );
 
     let updated_organization: organizations::Model =
-        OrganizationApi::update(app_state.db_conn_ref(), id, organization_model).await?;
+        OrganizationApi::update(app_state.db_conn_ref(), id, precondition, params.into()).await?;
 
-    Ok(Json(ApiResponse::new(
-        StatusCode::OK.into(),
-        updated_organization,
-    )))
+    Ok((
+        etag::header(&updated_organization.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            updated_organization,
+        )),
+    ))
 }
 
-/// DELETE an Organization specified by its primary key.
+/// PATCH some of an Organization's fields, leaving out the ones that should keep their value
 #[utoipa::path(
-    delete,
+    patch,
     path = "/organizations/{id}",
     params(
         ApiVersion,
-        ("id" = i32, Path, description = "Organization id to delete")
+        ("id" = i32, Path, description = "Organization id to change"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Organization to change")
     ),
+    request_body(content = web::params::organization::PatchOrganization, content_type = "application/merge-patch+json"),
     responses(
-        (status = 200, description = "Successfully deleted a certain Organization by its id", body = [i32]),
+        (status = 200, description = "Successfully updated a certain Organization by its id", body = [entity::organizations::Model],
+            headers(("ETag" = String, description = "Version of the updated Organization"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Organization not found"),
-        (status = 405, description = "Method not allowed")
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Organization has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn patch(
+    CompareApiVersion(_v): CompareApiVersion,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<PatchOrganization>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("PATCH Organization by id: {:?}, changes: {:?}", id, params);
+
+    let updated_organization: organizations::Model =
+        OrganizationApi::patch(app_state.db_conn_ref(), id, precondition, params.into()).await?;
+
+    Ok((
+        etag::header(&updated_organization.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            updated_organization,
+        )),
+    ))
+}
+
+/// DELETE an Organization specified by its primary key, along with the agreements and actions
+/// of its coaching relationships, which are hidden with it. All of it stays restorable until it
+/// is purged after the retention period. Only admins of the Organization may delete it, and with
+/// `dry_run=true` they get counts of everything the deletion would affect instead.
+#[utoipa::path(
+    delete,
+    path = "/organizations/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Organization id to delete"),
+        DeleteOrganization,
+        ("If-Match" = Option<String>, Header, description = "ETag of the version of the Organization to delete, required unless `dry_run` is set")
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted a certain Organization by its id, or the impact of deleting it for a dry run", body = entity_api::organization::DeletionImpact),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Organization not found, or not one the user is an admin of"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Organization has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -175,11 +250,72 @@ pub async fn update(
 )]
 pub async fn delete(
     CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    Query(params): Query<DeleteOrganization>,
+    if_match: Result<IfMatch, Problem>,
+) -> Result<Response, Error> {
+    debug!(
+        "DELETE Organization by id: {} (dry run: {})",
+        id, params.dry_run
+    );
+
+    OrganizationApi::find_by_id_for_admin(app_state.db_conn_ref(), id, user.id).await?;
+
+    if params.dry_run {
+        let impact = OrganizationApi::deletion_impact(app_state.db_conn_ref(), id).await?;
+
+        debug!("Deleting Organization {} would affect: {:?}", id, impact);
+
+        return Ok(Json(ApiResponse::new(StatusCode::OK.into(), impact)).into_response());
+    }
+
+    // A dry run changes nothing, so only an actual deletion needs to name a version
+    let precondition = match if_match {
+        Ok(IfMatch(precondition)) => precondition,
+        Err(problem) => return Ok(problem.into_response()),
+    };
+
+    OrganizationApi::delete_by_id(app_state.db_conn_ref(), id, precondition).await?;
+    Ok(Json(json!({"id": id})).into_response())
+}
+
+/// POST restore a deleted Organization that hasn't been purged yet.
+#[utoipa::path(
+    post,
+    path = "/organizations/{id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of the deleted organization to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted Organization", body = [entity::organizations::Model],
+            headers(("ETag" = String, description = "Version of the restored Organization"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Organization not found, not deleted, or not one the user is an admin of"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
 ) -> Result<impl IntoResponse, Error> {
-    debug!("DELETE Organization by id: {}", id);
+    debug!("POST Restore deleted Organization: {}", id);
+
+    OrganizationApi::find_deleted_by_id_for_admin(app_state.db_conn_ref(), id, user.id).await?;
+
+    let organization = OrganizationApi::restore(app_state.db_conn_ref(), id).await?;
+
+    debug!("Restored Organization: {:?}", organization);
 
-    OrganizationApi::delete_by_id(app_state.db_conn_ref(), id).await?;
-    Ok(Json(json!({"id": id})))
+    Ok((
+        etag::header(&organization.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), organization)),
+    ))
 }
@@ -1,14 +1,26 @@
 use crate::controller::ApiResponse;
+use crate::etag;
 use crate::extractors::{
     authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+    if_match::IfMatch,
+};
+use crate::extractors::{
+    json::{Json, ValidJson},
+    path::Path,
+};
+use crate::markdown::{self, BodyFormat};
+use crate::params::overarching_goal::{
+    NewOverarchingGoal, PatchOverarchingGoal, UpdateOverarchingGoal,
 };
 use crate::{AppState, Error};
-use axum::extract::{Path, Query, State};
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::Json;
-use entity::{overarching_goals::Model, Id};
+use entity::Id;
 use entity_api::overarching_goal as OverarchingGoalApi;
+use entity_api::revision::{self as RevisionApi, DiffParams, Subject};
+use entity_api::status_transition as StatusTransitionApi;
+use serde_json::json;
 use service::config::ApiVersion;
 use std::collections::HashMap;
 
@@ -19,9 +31,10 @@ use log::*;
     post,
     path = "/overarching_goals",
     params(ApiVersion),
-    request_body = entity::overarching_goals::Model,
+    request_body = web::params::overarching_goal::NewOverarchingGoal,
     responses(
-        (status = 201, description = "Successfully Created a New Overarching Goal", body = [entity::overarching_goals::Model]),
+        (status = 201, description = "Successfully Created a New Overarching Goal", body = [entity::overarching_goals::Model],
+            headers(("ETag" = String, description = "Version of the Overarching Goal, to send back in `If-Match` to change it"))),
         (status= 422, description = "Unprocessable Entity"),
         (status = 401, description = "Unauthorized"),
         (status = 405, description = "Method not allowed")
@@ -36,23 +49,22 @@ pub async fn create(
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Json(overarching_goals_model): Json<Model>,
+    ValidJson(params): ValidJson<NewOverarchingGoal>,
 ) -> Result<impl IntoResponse, Error> {
-    debug!(
-        "POST Create a New Overarching Goal from: {:?}",
-        overarching_goals_model
-    );
+    debug!("POST Create a New Overarching Goal from: {:?}", params);
 
     let overarching_goals =
-        OverarchingGoalApi::create(app_state.db_conn_ref(), overarching_goals_model, user.id)
-            .await?;
+        OverarchingGoalApi::create(app_state.db_conn_ref(), params.into(), user.id).await?;
 
     debug!("New Overarching Goal: {:?}", overarching_goals);
 
-    Ok(Json(ApiResponse::new(
-        StatusCode::CREATED.into(),
-        overarching_goals,
-    )))
+    Ok((
+        etag::header(&overarching_goals.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::CREATED.into(),
+            overarching_goals,
+        )),
+    ))
 }
 
 /// GET a particular Overarching Goal specified by its id.
@@ -61,10 +73,12 @@ pub async fn create(
     path = "/overarching_goals/{id}",
     params(
         ApiVersion,
-        ("id" = String, Path, description = "Overarching Goal id to retrieve")
+        ("id" = String, Path, description = "Overarching Goal id to retrieve"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
-        (status = 200, description = "Successfully retrieved a specific Overarching Goal by its id", body = [entity::notes::Model]),
+        (status = 200, description = "Successfully retrieved a specific Overarching Goal by its id", body = [entity::notes::Model],
+            headers(("ETag" = String, description = "Version of the Overarching Goal, to send back in `If-Match` to change it"))),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Overarching Goal not found"),
         (status = 405, description = "Method not allowed")
@@ -75,14 +89,101 @@ pub async fn create(
 )]
 pub async fn read(
     CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET Overarching Goal by id: {}", id);
 
-    let note: Option<Model> = OverarchingGoalApi::find_by_id(app_state.db_conn_ref(), id).await?;
+    let format = BodyFormat::take_from(&mut params)?;
+
+    let model =
+        OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    Ok((
+        etag::header(&model.updated_at),
+        Json(ApiResponse::new(
+            StatusCode::OK.into(),
+            markdown::present(model, format),
+        )),
+    ))
+}
+
+/// DELETE an Overarching Goal specified by its primary key. It stays restorable until it is
+/// purged after the retention period.
+#[utoipa::path(
+    delete,
+    path = "/overarching_goals/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Overarching Goal id to delete"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Overarching Goal to delete")
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted a certain Overarching Goal by its id", body = [Id]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Overarching Goal not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Overarching Goal has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn delete(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!("DELETE Overarching Goal by id: {}", id);
+
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
 
-    Ok(Json(ApiResponse::new(StatusCode::OK.into(), note)))
+    OverarchingGoalApi::delete_by_id(app_state.db_conn_ref(), id, precondition).await?;
+    Ok(Json(json!({"id": id})))
+}
+
+/// POST restore a deleted Overarching Goal that hasn't been purged yet.
+#[utoipa::path(
+    post,
+    path = "/overarching_goals/{id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of the deleted overarching goal to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored a deleted Overarching Goal", body = [entity::overarching_goals::Model],
+            headers(("ETag" = String, description = "Version of the restored Overarching Goal"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Overarching Goal not found or not deleted"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("POST Restore deleted Overarching Goal: {}", id);
+
+    OverarchingGoalApi::find_deleted_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let overarching_goal = OverarchingGoalApi::restore(app_state.db_conn_ref(), id).await?;
+
+    debug!("Restored Overarching Goal: {:?}", overarching_goal);
+
+    Ok((
+        etag::header(&overarching_goal.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), overarching_goal)),
+    ))
 }
 
 #[utoipa::path(
@@ -91,12 +192,19 @@ pub async fn read(
     params(
         ApiVersion,
         ("id" = Id, Path, description = "Id of overarching_goals to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Overarching Goal to update"),
     ),
-    request_body = entity::overarching_goals::Model,
+    request_body = web::params::overarching_goal::UpdateOverarchingGoal,
     responses(
-        (status = 200, description = "Successfully Updated Overarching Goal", body = [entity::overarching_goals::Model]),
+        (status = 200, description = "Successfully Updated Overarching Goal", body = [entity::overarching_goals::Model],
+            headers(("ETag" = String, description = "Version of the updated Overarching Goal"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 404, description = "Overarching Goal not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "Status change not permitted from the current status"),
+        (status = 412, description = "Overarching Goal has changed since the version named by `If-Match`"),
+        (status = 422, description = "Unknown status value"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -104,24 +212,88 @@ pub async fn read(
 )]
 pub async fn update(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
-    // TODO: create a new Extractor to authorize the user to access
-    // the data requested
+    AuthenticatedUser(user): AuthenticatedUser,
     State(app_state): State<AppState>,
     Path(id): Path<Id>,
-    Json(overarching_goals_model): Json<Model>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<UpdateOverarchingGoal>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("PUT Update Overarching Goal with id: {}", id);
 
-    let overarching_goals =
-        OverarchingGoalApi::update(app_state.db_conn_ref(), id, overarching_goals_model).await?;
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let overarching_goals = OverarchingGoalApi::update(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
 
     debug!("Updated Overarching Goal: {:?}", overarching_goals);
 
-    Ok(Json(ApiResponse::new(
-        StatusCode::OK.into(),
-        overarching_goals,
-    )))
+    Ok((
+        etag::header(&overarching_goals.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), overarching_goals)),
+    ))
+}
+
+/// PATCH some of an Overarching Goal's fields, leaving out the ones that should keep their value
+#[utoipa::path(
+    patch,
+    path = "/overarching_goals/{id}",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of overarching goal to change"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Overarching Goal to change"),
+    ),
+    request_body(content = web::params::overarching_goal::PatchOverarchingGoal, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Successfully Updated Overarching Goal", body = [entity::overarching_goals::Model],
+            headers(("ETag" = String, description = "Version of the updated Overarching Goal"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Overarching Goal not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "Status change not permitted from the current status"),
+        (status = 412, description = "Overarching Goal has changed since the version named by `If-Match`"),
+        (status = 422, description = "One or more fields of the request body are invalid"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn patch(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
+    ValidJson(params): ValidJson<PatchOverarchingGoal>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "PATCH Overarching Goal with id: {}, changes: {:?}",
+        id, params
+    );
+
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let overarching_goal = OverarchingGoalApi::patch(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        params.into(),
+        user.id,
+    )
+    .await?;
+
+    debug!("Updated Overarching Goal: {:?}", overarching_goal);
+
+    Ok((
+        etag::header(&overarching_goal.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), overarching_goal)),
+    ))
 }
 
 #[utoipa::path(
@@ -131,12 +303,19 @@ pub async fn update(
         ApiVersion,
         ("id" = Id, Path, description = "Id of overarching goal to update"),
         ("value" = Option<String>, Query, description = "Status value to update"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Overarching Goal to update"),
     ),
     request_body = entity::actions::Model,
     responses(
-        (status = 200, description = "Successfully Updated Overarching Goal", body = [entity::overarching_goals::Model]),
+        (status = 200, description = "Successfully Updated Overarching Goal", body = [entity::overarching_goals::Model],
+            headers(("ETag" = String, description = "Version of the updated Overarching Goal"))),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 404, description = "Overarching Goal not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 409, description = "Status change not permitted from the current status"),
+        (status = 412, description = "Overarching Goal has changed since the version named by `If-Match`"),
+        (status = 422, description = "Unknown status value"),
+        (status = 428, description = "`If-Match` header is missing")
     ),
     security(
         ("cookie_auth" = [])
@@ -144,22 +323,73 @@ pub async fn update(
 )]
 pub async fn update_status(
     CompareApiVersion(_v): CompareApiVersion,
-    AuthenticatedUser(_user): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     Query(status): Query<String>,
     Path(id): Path<Id>,
+    IfMatch(precondition): IfMatch,
     State(app_state): State<AppState>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("PUT Update Overarching Goal Status with id: {}", id);
 
-    let overarching_goal =
-        OverarchingGoalApi::update_status(app_state.db_conn_ref(), id, status.as_str().into())
-            .await?;
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let overarching_goal = OverarchingGoalApi::update_status(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        status.parse()?,
+        user.id,
+    )
+    .await?;
 
     debug!("Updated Overarching Goal: {:?}", overarching_goal);
 
+    Ok((
+        etag::header(&overarching_goal.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), overarching_goal)),
+    ))
+}
+
+/// GET the status change history of a particular Overarching Goal, oldest change first.
+#[utoipa::path(
+    get,
+    path = "/overarching_goals/{id}/history",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of overarching goal to retrieve the status history for"),
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the status history of an Overarching Goal", body = [entity::status_transitions::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Overarching Goal not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn history(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Overarching Goal status history for id: {}", id);
+
+    // Ensures a 404 for an Overarching Goal the user can't reach rather than an empty history
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let status_transitions =
+        StatusTransitionApi::find_by_overarching_goal(app_state.db_conn_ref(), id).await?;
+
+    debug!(
+        "Found Overarching Goal status history: {:?}",
+        status_transitions
+    );
+
     Ok(Json(ApiResponse::new(
         StatusCode::OK.into(),
-        overarching_goal,
+        status_transitions,
     )))
 }
 
@@ -168,7 +398,8 @@ pub async fn update_status(
     path = "/overarching_goals",
     params(
         ApiVersion,
-        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id")
+        ("coaching_session_id" = Option<Id>, Query, description = "Filter by coaching_session_id"),
+        ("format" = Option<String>, Query, description = "`html` to also return the body rendered as sanitized HTML, with its mentions and task-list items")
     ),
     responses(
         (status = 200, description = "Successfully retrieved all Overarching Goals", body = [entity::overarching_goals::Model]),
@@ -185,17 +416,157 @@ pub async fn index(
     // TODO: create a new Extractor to authorize the user to access
     // the data requested
     State(app_state): State<AppState>,
-    Query(params): Query<HashMap<String, String>>,
+    Query(mut params): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("GET all Overarching Goals");
     debug!("Filter Params: {:?}", params);
 
+    let format = BodyFormat::take_from(&mut params)?;
+
     let overarching_goals = OverarchingGoalApi::find_by(app_state.db_conn_ref(), params).await?;
 
     debug!("Found Overarching Goals: {:?}", overarching_goals);
 
     Ok(Json(ApiResponse::new(
         StatusCode::OK.into(),
-        overarching_goals,
+        overarching_goals
+            .into_iter()
+            .map(|model| markdown::present(model, format))
+            .collect::<Vec<_>>(),
     )))
 }
+
+/// GET every revision of a particular Overarching Goal's body, newest first.
+#[utoipa::path(
+    get,
+    path = "/overarching_goals/{id}/revisions",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of overarching goal to retrieve the revisions of"),
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the revisions of an Overarching Goal", body = [entity::revisions::Model]),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Overarching Goal not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn revisions(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Overarching Goal revisions for id: {}", id);
+
+    // Ensures a 404 for an Overarching Goal the user can't reach rather than an empty list
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let revisions =
+        RevisionApi::find_by_subject(app_state.db_conn_ref(), Subject::OverarchingGoal(id)).await?;
+
+    debug!("Found Overarching Goal revisions: {:?}", revisions);
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), revisions)))
+}
+
+/// GET a unified diff of an Overarching Goal's body between two of its revisions.
+#[utoipa::path(
+    get,
+    path = "/overarching_goals/{id}/revisions/diff",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of overarching goal to diff the revisions of"),
+        DiffParams,
+    ),
+    responses(
+        (status = 200, description = "Successfully diffed two revisions of an Overarching Goal", body = String),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Overarching Goal or revision not found"),
+        (status = 405, description = "Method not allowed")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn diff_revisions(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path(id): Path<Id>,
+    Query(params): Query<DiffParams>,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "GET Overarching Goal revision diff for id: {}, {:?}",
+        id, params
+    );
+
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let diff = RevisionApi::diff(
+        app_state.db_conn_ref(),
+        Subject::OverarchingGoal(id),
+        params.from,
+        params.to,
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), diff)))
+}
+
+/// POST restore an Overarching Goal's body to that of one of its earlier revisions.
+#[utoipa::path(
+    post,
+    path = "/overarching_goals/{id}/revisions/{revision_id}/restore",
+    params(
+        ApiVersion,
+        ("id" = Id, Path, description = "Id of overarching goal to restore"),
+        ("revision_id" = Id, Path, description = "Id of the revision to restore the body from"),
+        ("If-Match" = String, Header, description = "ETag of the version of the Overarching Goal to restore"),
+    ),
+    responses(
+        (status = 200, description = "Successfully restored an Overarching Goal to an earlier revision", body = [entity::overarching_goals::Model],
+            headers(("ETag" = String, description = "Version of the restored Overarching Goal"))),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Overarching Goal or revision not found"),
+        (status = 405, description = "Method not allowed"),
+        (status = 412, description = "Overarching Goal has changed since the version named by `If-Match`"),
+        (status = 428, description = "`If-Match` header is missing")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn restore_revision(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Path((id, revision_id)): Path<(Id, Id)>,
+    IfMatch(precondition): IfMatch,
+) -> Result<impl IntoResponse, Error> {
+    debug!(
+        "POST Restore Overarching Goal {} to revision {}",
+        id, revision_id
+    );
+
+    OverarchingGoalApi::find_by_id_for_member(app_state.db_conn_ref(), id, user.id).await?;
+
+    let overarching_goal = OverarchingGoalApi::restore_revision(
+        app_state.db_conn_ref(),
+        id,
+        precondition,
+        revision_id,
+        user.id,
+    )
+    .await?;
+
+    debug!("Restored Overarching Goal: {:?}", overarching_goal);
+
+    Ok((
+        etag::header(&overarching_goal.updated_at),
+        Json(ApiResponse::new(StatusCode::OK.into(), overarching_goal)),
+    ))
+}
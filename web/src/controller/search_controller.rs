@@ -0,0 +1,52 @@
+use crate::controller::ApiResponse;
+use crate::extractors::json::Json;
+use crate::extractors::{
+    authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+};
+use crate::{AppState, Error};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use entity_api::search as SearchApi;
+use service::config::ApiVersion;
+use std::collections::HashMap;
+
+use log::*;
+
+/// GET full-text search results across the Notes, Agreements, Actions and Overarching
+/// Goals of every coaching relationship the current user belongs to, best match first.
+#[utoipa::path(
+    get,
+    path = "/search",
+    params(
+        ApiVersion,
+        ("q" = String, Query, description = "Search terms, e.g. `on-call rotation` or `\"on-call\" -pager`")
+    ),
+    responses(
+        (status = 200, description = "Successfully searched coaching content", body = [entity_api::search::SearchResult]),
+        (status = 401, description = "Unauthorized"),
+        (status = 405, description = "Method not allowed"),
+        (status = 422, description = "Missing or blank search terms")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn index(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Search");
+    debug!("Search Params: {:?}", params);
+
+    let search_results = SearchApi::find_by(app_state.db_conn_ref(), user.id, params).await?;
+
+    debug!("Found {} search results", search_results.len());
+
+    Ok(Json(ApiResponse::new(
+        StatusCode::OK.into(),
+        search_results,
+    )))
+}
@@ -1,9 +1,16 @@
-use crate::{controller::ApiResponse, extractors::compare_api_version::CompareApiVersion};
+use crate::controller::ApiResponse;
+use crate::extractors::json::{Json, ValidJson};
+use crate::extractors::{
+    authenticated_user::AuthenticatedUser, compare_api_version::CompareApiVersion,
+};
+use crate::params::user::NewUser;
 use crate::{AppState, Error};
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::extract::{Query, State};
+use axum::{http::StatusCode, response::IntoResponse};
 use entity::users;
-use entity_api::user as UserApi;
+use entity_api::{caseload as CaseloadApi, user as UserApi};
 use service::config::ApiVersion;
+use std::collections::HashMap;
 
 use log::*;
 
@@ -14,11 +21,12 @@ use log::*;
     params(
         ApiVersion,
     ),
-    request_body = entity::users::Model,
+    request_body = web::params::user::NewUser,
     responses(
         (status = 200, description = "Successfully created a new User", body = [entity::users::Model]),
         (status = 401, description = "Unauthorized"),
-        (status = 405, description = "Method not allowed")
+        (status = 405, description = "Method not allowed"),
+        (status = 422, description = "One or more fields of the request body are invalid")
     ),
     security(
         ("cookie_auth" = [])
@@ -27,13 +35,49 @@ use log::*;
 pub async fn create(
     CompareApiVersion(_v): CompareApiVersion,
     State(app_state): State<AppState>,
-    Json(user_model): Json<users::Model>,
+    ValidJson(params): ValidJson<NewUser>,
 ) -> Result<impl IntoResponse, Error> {
-    debug!("CREATE new User from: {:?}", user_model);
+    debug!("CREATE new User from: {:?}", params);
 
-    let user: users::Model = UserApi::create(app_state.db_conn_ref(), user_model).await?;
+    let user: users::Model = UserApi::create(app_state.db_conn_ref(), params.into()).await?;
 
     debug!("Newly created Users {:?}", &user);
 
     Ok(Json(ApiResponse::new(StatusCode::CREATED.into(), user)))
 }
+
+/// GET the current user's caseload: for each of their coaching relationships that has not
+/// ended, the last and next session dates, open and overdue action counts and active
+/// overarching goal count.
+#[utoipa::path(
+    get,
+    path = "/users/me/caseload",
+    params(
+        ApiVersion,
+        ("sort" = Option<String>, Query, description = "`coachee` (the default) to sort by coachee name, or `needs_attention` to list coachees with overdue actions or no upcoming session first")
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved the caseload of the current user", body = [entity_api::caseload::CaseloadEntry]),
+        (status = 401, description = "Unauthorized"),
+        (status = 405, description = "Method not allowed"),
+        (status = 422, description = "Unknown query parameter or sort order")
+    ),
+    security(
+        ("cookie_auth" = [])
+    )
+)]
+pub async fn caseload(
+    CompareApiVersion(_v): CompareApiVersion,
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("GET Caseload of User {}", user.id);
+    debug!("Caseload Params: {:?}", params);
+
+    let caseload = CaseloadApi::find_by_coach(app_state.db_conn_ref(), user.id, params).await?;
+
+    debug!("Found {} relationships in the caseload", caseload.len());
+
+    Ok(Json(ApiResponse::new(StatusCode::OK.into(), caseload)))
+}
@@ -1,5 +1,6 @@
 use crate::controller::ApiResponse;
-use axum::{http::StatusCode, response::IntoResponse, Form, Json};
+use crate::extractors::json::Json;
+use axum::{http::StatusCode, response::IntoResponse, Form};
 use entity_api::user as UserApi;
 use log::*;
 use serde::Deserialize;
@@ -1,10 +1,15 @@
 use std::error::Error as StdError;
 
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use utoipa::ToSchema;
 
 use entity_api::error::EntityApiErrorCode;
 use entity_api::error::Error as EntityApiError;
+use entity_api::error::FieldError;
+
+use crate::request_id;
 
 extern crate log;
 use log::*;
@@ -22,10 +27,95 @@ impl std::fmt::Display for Error {
     }
 }
 
+/// The body of every error response, an RFC 7807 problem details object served as
+/// `application/problem+json`.
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = web::error::Problem)] // OpenAPI schema
+pub struct Problem {
+    /// Always `about:blank`, since `code` identifies the kind of problem
+    #[serde(rename = "type")]
+    #[schema(example = "about:blank")]
+    problem_type: &'static str,
+    /// The reason phrase of the HTTP status
+    #[schema(example = "Unprocessable Entity")]
+    title: &'static str,
+    #[schema(example = 422)]
+    status: u16,
+    /// A human-readable explanation of this occurrence of the problem
+    #[schema(value_type = String)]
+    detail: Box<str>,
+    /// A stable, machine-readable code for the kind of problem, such as `record_not_found`
+    #[schema(example = "validation_failed")]
+    code: &'static str,
+    /// The id of the request, also returned in its `X-Request-Id` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// What is wrong with each invalid field, when the problem lies with particular fields
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[schema(value_type = Vec<entity_api::error::FieldError>)]
+    errors: Vec<FieldError>,
+}
+
+impl Problem {
+    pub(crate) fn new(status: StatusCode, code: &'static str, detail: &str) -> Self {
+        Problem {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or_default(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            code,
+            request_id: request_id::current(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Lists what is wrong with each invalid field, both in `errors` and at the end of `detail`.
+    pub(crate) fn with_errors(mut self, errors: Vec<FieldError>) -> Self {
+        if !errors.is_empty() {
+            let fields = errors
+                .iter()
+                .map(|error| format!("{} {}", error.field, error.message))
+                .collect::<Vec<_>>()
+                .join("; ");
+            self.detail = format!("{}: {}.", self.detail.trim_end_matches('.'), fields).into();
+        }
+        self.errors = errors;
+        self
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        match serde_json::to_vec(&self) {
+            Ok(body) => (
+                status,
+                [(header::CONTENT_TYPE, "application/problem+json")],
+                body,
+            )
+                .into_response(),
+            Err(err) => {
+                error!("Failed to serialize problem details {:?}: {}", self, err);
+
+                status.into_response()
+            }
+        }
+    }
+}
+
+impl Error {
+    // The explanation the error carries of what went wrong with which record, or else the
+    // `generic` one for its kind of error
+    fn detail<'a>(&'a self, generic: &'a str) -> &'a str {
+        self.0.detail.as_deref().unwrap_or(generic)
+    }
+}
+
 // List of possible StatusCode variants https://docs.rs/http/latest/http/status/struct.StatusCode.html#associatedconstant.UNPROCESSABLE_ENTITY
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        match self.0.error_code {
+        match &self.0.error_code {
             EntityApiErrorCode::InvalidQueryTerm => {
                 error!(
                     "Error: {:#?}, mapping to UNPROCESSABLE_ENTITY (reason: {})",
@@ -36,7 +126,21 @@ impl IntoResponse for Error {
                         .map_or_else(|| "unspecified".to_string(), |err| err.to_string())
                 );
 
-                (StatusCode::UNPROCESSABLE_ENTITY, "UNPROCESSABLE ENTITY").into_response()
+                Problem::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "invalid_query_term",
+                    self.detail("The request is invalid."),
+                )
+            }
+            EntityApiErrorCode::ValidationFailed(errors) => {
+                error!("Error: {:#?}, mapping to UNPROCESSABLE_ENTITY", self);
+
+                Problem::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "validation_failed",
+                    "One or more fields of the request are invalid.",
+                )
+                .with_errors(errors.clone())
             }
             EntityApiErrorCode::SystemError => {
                 error!(
@@ -48,12 +152,21 @@ impl IntoResponse for Error {
                         .map_or_else(|| "unspecified".to_string(), |err| err.to_string())
                 );
 
-                (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL SERVER ERROR").into_response()
+                Problem::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "system_error",
+                    "The server failed to complete the request.",
+                )
             }
             EntityApiErrorCode::RecordNotFound => {
-                error!("Error: {:#?}, mapping to NO_CONTENT", self);
+                // Expected in normal use, such as following a stale link, so not an error
+                debug!("Error: {:#?}, mapping to NOT_FOUND", self);
 
-                (StatusCode::NOT_FOUND, "NOT FOUND").into_response()
+                Problem::new(
+                    StatusCode::NOT_FOUND,
+                    "record_not_found",
+                    self.detail("The requested record was not found."),
+                )
             }
             EntityApiErrorCode::RecordNotUpdated => {
                 error!(
@@ -65,14 +178,79 @@ impl IntoResponse for Error {
                         .map_or_else(|| "unspecified".to_string(), |err| err.to_string())
                 );
 
-                (StatusCode::UNPROCESSABLE_ENTITY, "UNPROCESSABLE ENTITY").into_response()
+                Problem::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "record_not_updated",
+                    self.detail("The record could not be updated."),
+                )
             }
             EntityApiErrorCode::RecordUnauthenticated => {
                 error!("Error: {:#?}, mapping to UNAUTHORIZED", self);
 
-                (StatusCode::UNAUTHORIZED, "UNAUTHORIZED").into_response()
+                Problem::new(
+                    StatusCode::UNAUTHORIZED,
+                    "record_unauthenticated",
+                    self.detail("The request requires an authenticated user."),
+                )
+            }
+            EntityApiErrorCode::RecordUnauthorized => {
+                error!("Error: {:#?}, mapping to FORBIDDEN", self);
+
+                Problem::new(
+                    StatusCode::FORBIDDEN,
+                    "record_unauthorized",
+                    self.detail("The authenticated user may not access the requested record."),
+                )
+            }
+            EntityApiErrorCode::PayloadTooLarge => {
+                error!("Error: {:#?}, mapping to PAYLOAD_TOO_LARGE", self);
+
+                Problem::new(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "payload_too_large",
+                    self.detail("The uploaded content is larger than allowed."),
+                )
+            }
+            EntityApiErrorCode::UnsupportedMediaType => {
+                error!("Error: {:#?}, mapping to UNSUPPORTED_MEDIA_TYPE", self);
+
+                Problem::new(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "unsupported_media_type",
+                    self.detail("The uploaded content is not of an allowed type."),
+                )
+            }
+            EntityApiErrorCode::InvalidStatusTransition => {
+                error!("Error: {:#?}, mapping to CONFLICT", self);
+
+                Problem::new(
+                    StatusCode::CONFLICT,
+                    "invalid_status_transition",
+                    self.detail(
+                        "The record cannot move from its current status to the requested one.",
+                    ),
+                )
+            }
+            EntityApiErrorCode::RecordConflict => {
+                error!("Error: {:#?}, mapping to CONFLICT", self);
+
+                Problem::new(
+                    StatusCode::CONFLICT,
+                    "record_conflict",
+                    self.detail("The request conflicts with the current state of the record."),
+                )
+            }
+            EntityApiErrorCode::PreconditionFailed => {
+                error!("Error: {:#?}, mapping to PRECONDITION_FAILED", self);
+
+                Problem::new(
+                    StatusCode::PRECONDITION_FAILED,
+                    "precondition_failed",
+                    self.detail("The record has changed since the version named by `If-Match`."),
+                )
             }
         }
+        .into_response()
     }
 }
 
@@ -84,3 +262,131 @@ where
         Self(err.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    async fn problem_of(error: EntityApiError) -> (StatusCode, String, serde_json::Value) {
+        let response = Error(error).into_response();
+        let status = response.status();
+        let content_type = response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, content_type, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn into_response_returns_problem_details_with_a_stable_code() {
+        let (status, content_type, problem) = problem_of(EntityApiError {
+            inner: None,
+            error_code: EntityApiErrorCode::RecordNotFound,
+            detail: None,
+        })
+        .await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(content_type, "application/problem+json");
+        assert_eq!(
+            problem,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "The requested record was not found.",
+                "code": "record_not_found",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn into_response_lists_each_invalid_field() {
+        let (status, _, problem) = problem_of(EntityApiError::invalid_field(
+            "from_date",
+            "invalid_date",
+            "must be a date formatted as YYYY-MM-DD",
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(problem["code"], "validation_failed");
+        assert_eq!(
+            problem["errors"],
+            serde_json::json!([{
+                "field": "from_date",
+                "code": "invalid_date",
+                "message": "must be a date formatted as YYYY-MM-DD",
+            }])
+        );
+        assert_eq!(
+            problem["detail"],
+            "One or more fields of the request are invalid: from_date must be a date formatted as YYYY-MM-DD."
+        );
+    }
+
+    #[tokio::test]
+    async fn into_response_names_the_record_in_the_detail() {
+        let id = entity::Id::new_v4();
+
+        let (_, _, not_found) = problem_of(EntityApiError::not_found("Organization", id)).await;
+        assert_eq!(not_found["code"], "record_not_found");
+        assert_eq!(
+            not_found["detail"],
+            format!("Organization {id} was not found.")
+        );
+
+        let (_, _, stale) = problem_of(EntityApiError::precondition_failed("Note", id)).await;
+        assert_eq!(stale["code"], "precondition_failed");
+        assert_eq!(
+            stale["detail"],
+            format!("Note {id} has changed since the version named by `If-Match`.")
+        );
+
+        let (status, _, conflict) = problem_of(EntityApiError::conflict(
+            "Coaching Relationship",
+            id,
+            "has ended and cannot be reassigned",
+        ))
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(conflict["code"], "record_conflict");
+        assert_eq!(
+            conflict["detail"],
+            format!("Coaching Relationship {id} has ended and cannot be reassigned.")
+        );
+    }
+
+    #[tokio::test]
+    async fn into_response_does_not_leak_system_error_details() {
+        let (status, _, problem) = problem_of(EntityApiError {
+            inner: Some(sea_orm::DbErr::Custom(
+                "connection to 10.0.0.5 refused".to_owned(),
+            )),
+            error_code: EntityApiErrorCode::SystemError,
+            detail: None,
+        })
+        .await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!problem.to_string().contains("10.0.0.5"));
+    }
+
+    #[tokio::test]
+    async fn into_response_includes_the_current_request_id() {
+        let (_, _, problem) = request_id::scope("req-123".to_owned(), async {
+            problem_of(EntityApiError {
+                inner: None,
+                error_code: EntityApiErrorCode::RecordUnauthorized,
+                detail: None,
+            })
+            .await
+        })
+        .await;
+
+        assert_eq!(problem["request_id"], "req-123");
+    }
+}
@@ -0,0 +1,46 @@
+//! Entity tags name the version of a record, derived from its `updated_at`. Reads return one in
+//! the `ETag` header, and a request to change the record sends it back in `If-Match` so the
+//! change only applies to the version the client last saw.
+
+use axum::http::{header, HeaderName};
+use chrono::DateTime;
+use sea_orm::prelude::DateTimeWithTimeZone;
+
+/// The entity tag of the version of a record last updated at `updated_at`.
+pub(crate) fn of(updated_at: &DateTimeWithTimeZone) -> String {
+    format!("\"{}\"", updated_at.timestamp_micros())
+}
+
+/// The `ETag` response header for the version of a record last updated at `updated_at`.
+pub(crate) fn header(updated_at: &DateTimeWithTimeZone) -> [(HeaderName, String); 1] {
+    [(header::ETAG, of(updated_at))]
+}
+
+/// The `updated_at` of the version that `tag` names. Weak tags never name a version, since
+/// `If-Match` only matches strongly.
+pub(crate) fn parse(tag: &str) -> Option<DateTimeWithTimeZone> {
+    let micros = tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok()?;
+
+    DateTime::from_timestamp_micros(micros).map(|updated_at| updated_at.fixed_offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_back_the_version_a_tag_was_made_of() {
+        let updated_at =
+            DateTimeWithTimeZone::parse_from_rfc3339("2024-06-01T14:00:00.123456+02:00").unwrap();
+
+        assert_eq!(of(&updated_at), "\"1717243200123456\"");
+        assert_eq!(parse(&of(&updated_at)), Some(updated_at));
+    }
+
+    #[test]
+    fn parse_ignores_weak_and_foreign_tags() {
+        assert_eq!(parse("W/\"1717243200123456\""), None);
+        assert_eq!(parse("\"v2\""), None);
+        assert_eq!(parse("1717243200123456"), None);
+    }
+}
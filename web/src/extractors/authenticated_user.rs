@@ -1,3 +1,4 @@
+use crate::error::Problem;
 use crate::extractors::RejectionType;
 use axum::{
     async_trait,
@@ -22,10 +23,14 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let session: user::AuthSession = AuthSession::from_request_parts(parts, state)
             .await
-            .map_err(|(status, msg)| (status, msg.to_string()))?;
+            .map_err(|(status, msg)| Problem::new(status, "system_error", msg))?;
         match session.user {
             Some(user) => Ok(AuthenticatedUser(user)),
-            None => Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string())),
+            None => Err(Problem::new(
+                StatusCode::UNAUTHORIZED,
+                "record_unauthenticated",
+                "The request requires an authenticated user.",
+            )),
         }
     }
 }
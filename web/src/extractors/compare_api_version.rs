@@ -1,3 +1,4 @@
+use crate::error::Problem;
 use crate::extractors::RejectionType;
 use crate::AppState;
 use axum::{
@@ -6,7 +7,7 @@ use axum::{
     http::{header::HeaderValue, request::Parts, StatusCode},
 };
 use log::*;
-use service::config::ApiVersion;
+use service::config::{ApiVersion, ReleasedVersion};
 
 pub struct CompareApiVersion(pub HeaderValue);
 
@@ -19,22 +20,25 @@ where
     type Rejection = RejectionType;
 
     // A custom Extractor that extracts and checks that the API version number
-    // provided in the "X-Version" header is equal to the API version specified
-    // in AppState.
+    // provided in the "X-Version" header is equal to the API version whose routes
+    // the request was dispatched to, or else the one specified in AppState.
     // If this Extractor fails any Handler methods that use it will not be called
     // successfully.
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let state = AppState::from_ref(state);
         // Provided by the client in the HTTP header
         let version = get_x_version(parts)?;
-        // Provided as part of the AppState environment configuration
-        let api_version = HeaderValue::from_str(state.config.api_version())
-            .ok()
-            .unwrap_or_else(|| HeaderValue::from_static(ApiVersion::default_version()));
+        // Provided by the versioned router, or else the AppState environment configuration
+        let api_version = match parts.extensions.get::<ReleasedVersion>() {
+            Some(released) => HeaderValue::from_static(released.version),
+            None => HeaderValue::from_str(state.config.api_version())
+                .ok()
+                .unwrap_or_else(|| HeaderValue::from_static(ApiVersion::default_version())),
+        };
 
         trace!("API version provided by client: {:?}", version);
         trace!(
-            "API version set in AppState.config.api_version: {:?}",
+            "API version of the routes serving the request: {:?}",
             api_version
         );
 
@@ -46,9 +50,10 @@ fn get_x_version(parts: &mut Parts) -> Result<HeaderValue, RejectionType> {
     if let Some(version) = parts.headers.get(ApiVersion::field_name()) {
         Ok(version.clone())
     } else {
-        Err((
+        Err(Problem::new(
             StatusCode::BAD_REQUEST,
-            format!("`{}` header is missing", ApiVersion::field_name()),
+            "missing_api_version",
+            &format!("`{}` header is missing", ApiVersion::field_name()),
         ))
     }
 }
@@ -60,9 +65,10 @@ fn is_current_api_version(
     if version == api_version {
         Ok(CompareApiVersion(version))
     } else {
-        Err((
+        Err(Problem::new(
             StatusCode::BAD_REQUEST,
-            format!(
+            "invalid_api_version",
+            &format!(
                 "`{}` header is not a valid API version",
                 ApiVersion::field_name()
             ),
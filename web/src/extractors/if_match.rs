@@ -0,0 +1,102 @@
+use crate::error::Problem;
+use crate::etag;
+use crate::extractors::RejectionType;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::IF_MATCH, request::Parts, StatusCode},
+};
+use entity_api::precondition::Precondition;
+use log::*;
+
+/// The versions of a record that a request to change it applies to, from its `If-Match`
+/// header. Requests without one are rejected, so that a client can't overwrite a change it
+/// never saw.
+pub(crate) struct IfMatch(pub Precondition);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = RejectionType;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let values = parts.headers.get_all(IF_MATCH);
+
+        if values.iter().next().is_none() {
+            return Err(Problem::new(
+                StatusCode::PRECONDITION_REQUIRED,
+                "missing_if_match",
+                "`If-Match` header with the record's ETag is required to change it",
+            ));
+        }
+
+        // A tag that isn't one of ours can't match any version, so it is left out rather than
+        // rejected, and a header naming only such tags fails as a mismatch.
+        let tags = values
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+
+        trace!("If-Match tags provided by client: {:?}", tags);
+
+        if tags.contains(&"*") {
+            return Ok(IfMatch(Precondition::Any));
+        }
+
+        Ok(IfMatch(Precondition::UpdatedAt(
+            tags.into_iter().filter_map(etag::parse).collect(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn if_match(values: &[&str]) -> Result<Precondition, Problem> {
+        let mut request = Request::builder();
+        for value in values {
+            request = request.header(IF_MATCH, *value);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+
+        IfMatch::from_request_parts(&mut parts, &())
+            .await
+            .map(|IfMatch(precondition)| precondition)
+    }
+
+    #[tokio::test]
+    async fn from_request_parts_requires_the_header() {
+        let problem = if_match(&[]).await.err().unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&problem).unwrap()["status"],
+            StatusCode::PRECONDITION_REQUIRED.as_u16()
+        );
+    }
+
+    #[tokio::test]
+    async fn from_request_parts_matches_any_version_for_a_wildcard() {
+        assert_eq!(if_match(&["*"]).await.unwrap(), Precondition::Any);
+    }
+
+    #[tokio::test]
+    async fn from_request_parts_collects_the_versions_of_every_tag() {
+        let precondition = if_match(&["\"1717243200123456\", W/\"1\"", "\"1717243200654321\""])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            precondition,
+            Precondition::UpdatedAt(vec![
+                etag::parse("\"1717243200123456\"").unwrap(),
+                etag::parse("\"1717243200654321\"").unwrap(),
+            ])
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use crate::error::Problem;
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use entity_api::error::FieldError;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::error::Category;
+use validator::{Validate, ValidationErrors};
+
+use log::*;
+
+/// JSON request and response bodies. Unlike `axum::Json`, a body that can't be deserialized is
+/// rejected with problem details naming the field that is missing or invalid.
+pub(crate) struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Problem;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !has_json_content_type(request.headers()) {
+            return Err(Problem::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "Expected a request body with a Content-Type of application/json.",
+            ));
+        }
+
+        let bytes = Bytes::from_request(request, state)
+            .await
+            .map_err(|rejection| {
+                Problem::new(rejection.status(), "invalid_body", &rejection.body_text())
+            })?;
+
+        let deserializer = &mut serde_json::Deserializer::from_slice(&bytes);
+        serde_path_to_error::deserialize(deserializer)
+            .map(Json)
+            .map_err(deserialization_problem)
+    }
+}
+
+/// A JSON request body that must also follow the rules `T` declares with `Validate`. A body
+/// that breaks any of them is rejected with every violation listed, not just the first.
+pub(crate) struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Problem;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await?;
+
+        value.validate().map_err(validation_problem)?;
+
+        Ok(ValidJson(value))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+// Accepts application/json and its structured syntax suffix variants like
+// application/merge-patch+json, with or without parameters
+fn has_json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    essence == "application/json"
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+fn deserialization_problem(err: serde_path_to_error::Error<serde_json::Error>) -> Problem {
+    warn!("Failed to deserialize JSON request body: {}", err);
+
+    let path = err.path().to_string();
+    let inner = err.into_inner();
+
+    if inner.classify() != Category::Data {
+        return Problem::new(
+            StatusCode::BAD_REQUEST,
+            "malformed_body",
+            "The request body is not valid JSON.",
+        );
+    }
+
+    // serde_json ends its messages with the position of the error in the body
+    let message = inner.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+
+    let field_error = match message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+    {
+        Some(missing) => FieldError {
+            field: nested_field(&path, missing),
+            code: "missing".to_owned(),
+            message: "is required".to_owned(),
+        },
+        None => FieldError {
+            field: path,
+            code: if message.starts_with("invalid type") {
+                "invalid_type".to_owned()
+            } else {
+                "invalid_value".to_owned()
+            },
+            message: message.to_owned(),
+        },
+    };
+
+    Problem::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "validation_failed",
+        "One or more fields of the request are invalid.",
+    )
+    .with_errors(vec![field_error])
+}
+
+fn validation_problem(errors: ValidationErrors) -> Problem {
+    warn!("JSON request body failed validation: {}", errors);
+
+    // A violation of a rule that spans several fields names the field it is reported against
+    let mut field_errors: Vec<FieldError> = errors
+        .field_errors()
+        .into_iter()
+        .flat_map(|(field, violations)| {
+            violations.iter().map(move |violation| FieldError {
+                field: violation
+                    .params
+                    .get("field")
+                    .and_then(|field| field.as_str())
+                    .unwrap_or(&field)
+                    .to_owned(),
+                code: violation.code.to_string(),
+                message: violation
+                    .message
+                    .as_ref()
+                    .map_or_else(|| violation.to_string(), |message| message.to_string()),
+            })
+        })
+        .collect();
+    field_errors.sort_by(|a, b| a.field.cmp(&b.field));
+
+    Problem::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "validation_failed",
+        "One or more fields of the request are invalid.",
+    )
+    .with_errors(field_errors)
+}
+
+// serde_path_to_error reports a missing field at the path of the object it is missing from,
+// which is `.` for the body itself
+fn nested_field(path: &str, field: &str) -> String {
+    if path == "." {
+        field.to_owned()
+    } else {
+        format!("{path}.{field}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{
+        action::NewAction, coaching_relationship::NewCoachingRelationship,
+        coaching_session::NewCoachingSession, organization::NewOrganization,
+    };
+    use axum::body::Body;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Action {
+        body: String,
+        due_by: Option<i64>,
+        owner: Owner,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Owner {
+        id: String,
+    }
+
+    async fn extract(content_type: &str, body: &str) -> Result<Json<Action>, Problem> {
+        let request = Request::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_owned()))
+            .unwrap();
+
+        Json::<Action>::from_request(request, &()).await
+    }
+
+    async fn problem(content_type: &str, body: &str) -> serde_json::Value {
+        let response = extract(content_type, body)
+            .await
+            .err()
+            .unwrap()
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn from_request_deserializes_json_bodies() {
+        let action = extract(
+            "application/json; charset=utf-8",
+            r#"{"body": "Ship it", "due_by": null, "owner": {"id": "a"}}"#,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(action.0.body, "Ship it");
+    }
+
+    #[tokio::test]
+    async fn from_request_names_missing_fields() {
+        let problem = problem("application/json", r#"{"body": "Ship it", "owner": {}}"#).await;
+
+        assert_eq!(problem["status"], 422);
+        assert_eq!(
+            problem["errors"],
+            serde_json::json!([{"field": "owner.id", "code": "missing", "message": "is required"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn from_request_names_fields_of_the_wrong_type() {
+        let problem = problem(
+            "application/json",
+            r#"{"body": "Ship it", "due_by": "soon", "owner": {"id": "a"}}"#,
+        )
+        .await;
+
+        assert_eq!(problem["errors"][0]["field"], "due_by");
+        assert_eq!(problem["errors"][0]["code"], "invalid_type");
+    }
+
+    #[tokio::test]
+    async fn from_request_rejects_malformed_json_and_other_content_types() {
+        let malformed = problem("application/json", r#"{"body": "#).await;
+        assert_eq!(malformed["status"], 400);
+        assert_eq!(malformed["code"], "malformed_body");
+
+        let form = problem("application/x-www-form-urlencoded", "body=Ship+it").await;
+        assert_eq!(form["status"], 415);
+        assert_eq!(form["code"], "unsupported_media_type");
+    }
+
+    async fn violations<T: DeserializeOwned + Validate>(body: String) -> serde_json::Value {
+        let request = Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = ValidJson::<T>::from_request(request, &())
+            .await
+            .err()
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["code"], "validation_failed");
+
+        problem["errors"].clone()
+    }
+
+    #[tokio::test]
+    async fn valid_json_lists_every_violation_at_once() {
+        let errors = violations::<NewAction>(
+            serde_json::json!({
+                "coaching_session_id": entity::Id::new_v4(),
+                "body": "a".repeat(50_001),
+                "due_by": "2020-01-31T09:00:00-06:00",
+                "status": "NotStarted",
+            })
+            .to_string(),
+        )
+        .await;
+
+        assert_eq!(
+            errors,
+            serde_json::json!([
+                {"field": "body", "code": "too_long", "message": "must be at most 50000 characters"},
+                {"field": "due_by", "code": "in_past", "message": "must not be in the past"},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn valid_json_rejects_blank_names_and_invalid_urls() {
+        let errors =
+            violations::<NewOrganization>(r#"{"name": "  ", "logo": "not a url"}"#.to_owned())
+                .await;
+
+        assert_eq!(errors[0]["field"], "logo");
+        assert_eq!(errors[0]["code"], "invalid_url");
+        assert_eq!(errors[1]["field"], "name");
+        assert_eq!(errors[1]["code"], "blank");
+    }
+
+    #[tokio::test]
+    async fn valid_json_reports_rules_spanning_fields_against_one_field() {
+        let user_id = entity::Id::new_v4();
+        let errors = violations::<NewCoachingRelationship>(
+            serde_json::json!({"coach_id": user_id, "coachee_id": user_id}).to_string(),
+        )
+        .await;
+
+        assert_eq!(
+            errors,
+            serde_json::json!([{
+                "field": "coachee_id",
+                "code": "same_as_coach",
+                "message": "must be a different user than coach_id",
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn valid_json_rejects_agendas_with_too_many_or_invalid_items() {
+        let session = |agenda: Vec<String>| {
+            serde_json::json!({
+                "coaching_relationship_id": entity::Id::new_v4(),
+                "date": "2030-01-31T09:00:00",
+                "timezone": "America/Chicago",
+                "agenda": agenda,
+            })
+            .to_string()
+        };
+
+        let errors = violations::<NewCoachingSession>(session(vec!["Goals".to_owned(); 21])).await;
+        assert_eq!(errors[0]["field"], "agenda");
+        assert_eq!(errors[0]["code"], "too_many");
+
+        let errors =
+            violations::<NewCoachingSession>(session(vec!["Goals".to_owned(), " ".to_owned()]))
+                .await;
+        assert_eq!(errors[0]["field"], "agenda");
+        assert_eq!(errors[0]["code"], "blank");
+
+        let errors = violations::<NewCoachingSession>(session(vec!["a".repeat(50_001)])).await;
+        assert_eq!(errors[0]["field"], "agenda");
+        assert_eq!(errors[0]["code"], "too_long");
+    }
+}
@@ -1,6 +1,7 @@
 pub(crate) mod authenticated_user;
 pub(crate) mod compare_api_version;
+pub(crate) mod if_match;
+pub(crate) mod json;
+pub(crate) mod path;
 
-use axum::http::StatusCode;
-
-type RejectionType = (StatusCode, String);
+type RejectionType = crate::error::Problem;
@@ -0,0 +1,159 @@
+use crate::error::Problem;
+use axum::{
+    async_trait,
+    extract::{path::ErrorKind, rejection::PathRejection, FromRequestParts, RawPathParams},
+    http::{request::Parts, StatusCode},
+};
+use entity::Id;
+use entity_api::error::FieldError;
+use serde::de::DeserializeOwned;
+
+use log::*;
+
+/// Path parameters. Unlike `axum::extract::Path`, a parameter that can't be parsed is rejected
+/// with problem details naming the parameter, so an invalid UUID can be told apart from other
+/// mistakes.
+pub(crate) struct Path<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = Problem;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let rejection = match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Path(value)) => return Ok(Path(value)),
+            Err(rejection) => rejection,
+        };
+
+        warn!("Failed to extract path parameters: {}", rejection);
+
+        let PathRejection::FailedToDeserializePathParams(err) = &rejection else {
+            return Err(Problem::new(
+                rejection.status(),
+                "system_error",
+                "The server failed to complete the request.",
+            ));
+        };
+
+        let (field, expected_type) = match err.kind() {
+            ErrorKind::ParseErrorAtKey {
+                key, expected_type, ..
+            } => (key.clone(), *expected_type),
+            ErrorKind::ParseErrorAtIndex {
+                index,
+                expected_type,
+                ..
+            } => {
+                let key = RawPathParams::from_request_parts(parts, state)
+                    .await
+                    .ok()
+                    .and_then(|params| params.iter().nth(*index).map(|(key, _)| key.to_owned()));
+                (key.unwrap_or_else(|| index.to_string()), *expected_type)
+            }
+            ErrorKind::ParseError { expected_type, .. } => (String::new(), *expected_type),
+            // `Uuid` reports a failed parse as a bare message, so the parameter it failed on is
+            // the first one that isn't a UUID
+            ErrorKind::Message(message) if message.starts_with("UUID parsing failed") => {
+                let key = RawPathParams::from_request_parts(parts, state)
+                    .await
+                    .ok()
+                    .and_then(|params| {
+                        params
+                            .iter()
+                            .find(|(_, value)| Id::parse_str(value).is_err())
+                            .map(|(key, _)| key.to_owned())
+                    });
+                (key.unwrap_or_default(), "Uuid")
+            }
+            _ => {
+                return Err(Problem::new(
+                    err.status(),
+                    "system_error",
+                    "The server failed to complete the request.",
+                ))
+            }
+        };
+
+        Err(Problem::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_path_parameter",
+            "A path parameter of the request is invalid.",
+        )
+        .with_errors(vec![field_error(field, expected_type)]))
+    }
+}
+
+fn field_error(field: String, expected_type: &str) -> FieldError {
+    if expected_type.ends_with("Uuid") {
+        FieldError {
+            field,
+            code: "invalid_uuid".to_owned(),
+            message: "must be a UUID".to_owned(),
+        }
+    } else {
+        FieldError {
+            field,
+            code: "invalid_value".to_owned(),
+            message: format!("must be a valid {expected_type}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, response::IntoResponse, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn problem(app: Router, uri: &str) -> serde_json::Value {
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn from_request_parts_names_an_invalid_uuid() {
+        let app = Router::new().route(
+            "/actions/:id",
+            get(|Path(id): Path<Id>| async move { id.to_string().into_response() }),
+        );
+
+        let problem = problem(app, "/actions/not-a-uuid").await;
+
+        assert_eq!(problem["code"], "invalid_path_parameter");
+        assert_eq!(
+            problem["errors"],
+            serde_json::json!([{"field": "id", "code": "invalid_uuid", "message": "must be a UUID"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn from_request_parts_names_the_invalid_parameter_of_several() {
+        let app = Router::new().route(
+            "/coaching_sessions/:coaching_session_id/attachments/:attachment_id",
+            get(|Path((_, attachment_id)): Path<(Id, Id)>| async move {
+                attachment_id.to_string().into_response()
+            }),
+        );
+
+        let problem = problem(
+            app,
+            &format!("/coaching_sessions/{}/attachments/123", Id::new_v4()),
+        )
+        .await;
+
+        assert_eq!(problem["errors"][0]["field"], "attachment_id");
+        assert_eq!(problem["errors"][0]["code"], "invalid_uuid");
+    }
+}
@@ -1,12 +1,12 @@
 use axum::http::{
-    header::{AUTHORIZATION, CONTENT_TYPE},
+    header::{AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MATCH},
     HeaderName, HeaderValue, Method,
 };
 use axum_login::{
     tower_sessions::{Expiry, SessionManagerLayer},
     AuthManagerLayerBuilder,
 };
-use entity_api::user::Backend;
+use entity_api::{soft_delete, user::Backend};
 use tower_sessions::session_store::ExpiredDeletion;
 use tower_sessions_sqlx_store::PostgresStore;
 
@@ -19,10 +19,18 @@ use time::Duration;
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 
+mod audit;
+mod collaboration;
 mod controller;
 mod error;
+mod etag;
 pub(crate) mod extractors;
+mod markdown;
+mod params;
+mod pdf;
+mod request_id;
 mod router;
+mod versioning;
 
 pub async fn init_server(app_state: AppState) -> Result<()> {
     info!(
@@ -49,6 +57,12 @@ pub async fn init_server(app_state: AppState) -> Result<()> {
             .continuously_delete_expired(tokio::time::Duration::from_secs(60)),
     );
 
+    let purge_task = tokio::task::spawn(soft_delete::purge_continuously(
+        app_state.database_connection.clone(),
+        app_state.storage.clone(),
+        app_state.config.soft_delete_retention_days,
+    ));
+
     let session_layer = SessionManagerLayer::new(session_store)
         .with_secure(false)
         .with_expiry(Expiry::OnInactivity(Duration::days(1)));
@@ -84,26 +98,39 @@ pub async fn init_server(app_state: AppState) -> Result<()> {
             Method::PATCH,
         ])
         .allow_credentials(true)
-        // Allow and expose the X-Version header across origins
+        // Allow and expose the X-Version and X-Request-Id headers across origins, along with
+        // the ETag and If-Match headers that guard updates and the Deprecation and Sunset
+        // headers that warn of a version going away
         .allow_headers([
             ApiVersion::field_name().parse::<HeaderName>().unwrap(),
             AUTHORIZATION,
             CONTENT_TYPE,
+            IF_MATCH,
+            HeaderName::from_static(request_id::REQUEST_ID_HEADER),
+        ])
+        .expose_headers([
+            ApiVersion::field_name().parse::<HeaderName>().unwrap(),
+            ETAG,
+            HeaderName::from_static(request_id::REQUEST_ID_HEADER),
+            versioning::DEPRECATION,
+            versioning::SUNSET,
         ])
-        .expose_headers([ApiVersion::field_name().parse::<HeaderName>().unwrap()])
         .allow_private_network(true)
         .allow_origin(allowed_origins);
 
     axum::serve(
         listener,
         router::define_routes(app_state)
+            .layer(axum::middleware::from_fn(audit::attribute))
             .layer(cors_layer)
             .layer(auth_layer)
+            .layer(axum::middleware::from_fn(request_id::assign))
             .into_make_service(),
     )
     .await
     .unwrap();
 
+    purge_task.abort();
     let _res = deletion_task.await.unwrap();
 
     Ok(())
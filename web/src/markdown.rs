@@ -0,0 +1,378 @@
+//! Server-side rendering of the Markdown body fields of Notes, Agreements, Actions and
+//! Overarching Goals, so that every client displays the same sanitized HTML.
+//!
+//! Read endpoints return the raw model by default. With `?format=html` each model also
+//! carries `body_html` (CommonMark rendered, then sanitized against an allow-list), the
+//! `@mentions` found in the body and its task-list checkboxes.
+
+use ammonia::Builder;
+use entity::{actions, agreements, notes, overarching_goals};
+use entity_api::error::{EntityApiErrorCode, Error};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// The query parameter selecting how body fields are returned.
+const FORMAT_PARAM: &str = "format";
+
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut sanitizer = Builder::default();
+    // Task-list items render as disabled checkboxes, the only form control we let through
+    sanitizer
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            ("input", "type") if value != "checkbox" => None,
+            _ => Some(Cow::Borrowed(value)),
+        });
+
+    sanitizer
+});
+
+/// How a read endpoint returns the body field of the models it responds with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BodyFormat {
+    /// Only the stored Markdown
+    #[default]
+    Raw,
+    /// The stored Markdown along with its rendering
+    Html,
+}
+
+impl BodyFormat {
+    /// Removes the `format` parameter from `params`, leaving the rest to be used as filters.
+    pub(crate) fn take_from(params: &mut HashMap<String, String>) -> Result<Self, Error> {
+        match params.remove(FORMAT_PARAM).as_deref() {
+            None | Some("raw") => Ok(BodyFormat::Raw),
+            Some("html") => Ok(BodyFormat::Html),
+            Some(_) => Err(Error {
+                inner: None,
+                error_code: EntityApiErrorCode::InvalidQueryTerm,
+                detail: None,
+            }),
+        }
+    }
+}
+
+/// A model whose body holds Markdown.
+pub(crate) trait MarkdownBody {
+    fn body(&self) -> Option<&str>;
+}
+
+impl MarkdownBody for actions::Model {
+    fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+}
+
+impl MarkdownBody for agreements::Model {
+    fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+}
+
+impl MarkdownBody for notes::Model {
+    fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+}
+
+impl MarkdownBody for overarching_goals::Model {
+    fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+}
+
+/// A single task-list item, e.g. `- [x] Book the retro`.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct Task {
+    pub text: String,
+    pub checked: bool,
+}
+
+/// The rendering of a Markdown body.
+#[derive(Debug, Serialize)]
+pub(crate) struct RenderedBody {
+    pub body_html: String,
+    pub mentions: Vec<String>,
+    pub tasks: Vec<Task>,
+}
+
+/// A model serialized exactly as before, plus the rendering of its body when requested.
+#[derive(Debug, Serialize)]
+pub(crate) struct Presented<T: Serialize> {
+    #[serde(flatten)]
+    model: T,
+    #[serde(flatten)]
+    rendered: Option<RenderedBody>,
+}
+
+/// Pairs `model` with the rendering of its body when `format` asks for HTML.
+pub(crate) fn present<T>(model: T, format: BodyFormat) -> Presented<T>
+where
+    T: MarkdownBody + Serialize,
+{
+    let rendered = match format {
+        BodyFormat::Raw => None,
+        BodyFormat::Html => Some(render(model.body().unwrap_or_default())),
+    };
+
+    Presented { model, rendered }
+}
+
+/// Renders `markdown` as sanitized HTML, extracting its mentions and task-list items.
+pub(crate) fn render(markdown: &str) -> RenderedBody {
+    let events: Vec<Event> = Parser::new_ext(markdown, Options::ENABLE_TASKLISTS).collect();
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events.iter().cloned());
+
+    RenderedBody {
+        body_html: SANITIZER.clean(&unsafe_html).to_string(),
+        mentions: mentions(&events),
+        tasks: tasks(&events),
+    }
+}
+
+/// The text of `markdown` without its formatting, one line per paragraph, heading or list item.
+/// Task-list items keep a `[ ]` or `[x]` marker.
+pub(crate) fn plain_text(markdown: &str) -> String {
+    let mut lines: Vec<String> = vec![String::new()];
+
+    for event in Parser::new_ext(markdown, Options::ENABLE_TASKLISTS) {
+        match event {
+            Event::Text(text) | Event::Code(text) => lines.last_mut().unwrap().push_str(&text),
+            Event::SoftBreak => lines.last_mut().unwrap().push(' '),
+            Event::TaskListMarker(checked) => {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            Event::HardBreak
+            | Event::Start(Tag::Item)
+            | Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock) => {
+                lines.push(String::new())
+            }
+            _ => {}
+        }
+    }
+
+    lines
+        .iter()
+        .flat_map(|line| line.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The distinct `@handle`s in prose, in order of first appearance. Text inside code spans and
+// blocks is not prose, and an `@` preceded by a word character is part of an email address.
+fn mentions(events: &[Event]) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) if !in_code_block => {
+                let mut previous: Option<char> = None;
+                for (index, character) in text.char_indices() {
+                    let starts_mention =
+                        character == '@' && !previous.is_some_and(|c| c.is_alphanumeric());
+                    previous = Some(character);
+                    if !starts_mention {
+                        continue;
+                    }
+
+                    let handle: String = text[index + 1..]
+                        .chars()
+                        .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                        .collect();
+                    let handle = handle.trim_end_matches('.');
+
+                    if !handle.is_empty() && !mentions.iter().any(|m| m == handle) {
+                        mentions.push(handle.to_owned());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    mentions
+}
+
+// Every task-list item with its plain text. A nested list's text belongs to its own items,
+// not to the task that contains it.
+fn tasks(events: &[Event]) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    // One entry per open list item, holding the index of its task if it is one
+    let mut open_items: Vec<Option<usize>> = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Item) => open_items.push(None),
+            Event::End(TagEnd::Item) => {
+                open_items.pop();
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(item) = open_items.last_mut() {
+                    *item = Some(tasks.len());
+                    tasks.push(Task {
+                        text: String::new(),
+                        checked: *checked,
+                    });
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(Some(task)) = open_items.last() {
+                    tasks[*task].text.push_str(text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(Some(task)) = open_items.last() {
+                    tasks[*task].text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for task in &mut tasks {
+        task.text = task.text.trim().to_owned();
+    }
+
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_drops_formatting_and_keeps_one_line_per_block() {
+        let text = plain_text(
+            "# Goals\n\nShip the **on-call**\nrotation\n\n- [x] Book the retro\n- Write `docs`",
+        );
+
+        assert_eq!(
+            text,
+            "Goals\nShip the on-call rotation\n[x] Book the retro\nWrite docs"
+        );
+    }
+
+    #[test]
+    fn render_produces_commonmark_html() {
+        let rendered = render("# Goals\n\nShip the **on-call** rotation");
+
+        assert_eq!(
+            rendered.body_html,
+            "<h1>Goals</h1>\n<p>Ship the <strong>on-call</strong> rotation</p>\n"
+        );
+    }
+
+    #[test]
+    fn render_strips_scripts_event_handlers_and_javascript_links() {
+        let rendered = render(
+            "<script>alert(1)</script>\n\n<img src=x onerror=alert(1)>\n\n[docs](javascript:alert(1))",
+        );
+
+        assert!(!rendered.body_html.contains("<script"));
+        assert!(!rendered.body_html.contains("onerror"));
+        assert!(!rendered.body_html.contains("javascript:"));
+    }
+
+    #[test]
+    fn render_keeps_task_checkboxes_but_no_other_inputs() {
+        let rendered = render("- [x] Book the retro\n\n<input type=\"text\" value=\"hi\">");
+
+        assert!(rendered
+            .body_html
+            .contains(r#"<input disabled="" type="checkbox" checked="">"#));
+        assert!(!rendered.body_html.contains(r#"type="text""#));
+    }
+
+    #[test]
+    fn render_extracts_mentions_outside_code() {
+        let rendered = render(
+            "Sync with @jim.hodapp and @caleb, then @jim.hodapp again.\n\n\
+             Email ops@example.com, not `@nobody`\n\n    @also_nobody",
+        );
+
+        assert_eq!(rendered.mentions, ["jim.hodapp", "caleb"]);
+    }
+
+    #[test]
+    fn render_extracts_task_list_items() {
+        let rendered = render(
+            "- [x] Book the retro\n- [ ] Draft the `RFC`\n  - [ ] Nested review\n- Not a task",
+        );
+
+        assert_eq!(
+            rendered.tasks,
+            [
+                Task {
+                    text: "Book the retro".to_owned(),
+                    checked: true
+                },
+                Task {
+                    text: "Draft the RFC".to_owned(),
+                    checked: false
+                },
+                Task {
+                    text: "Nested review".to_owned(),
+                    checked: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn take_from_removes_format_and_rejects_unknown_formats() {
+        let mut params = HashMap::from([
+            ("format".to_owned(), "html".to_owned()),
+            ("coaching_session_id".to_owned(), "1".to_owned()),
+        ]);
+
+        assert_eq!(
+            BodyFormat::take_from(&mut params).unwrap(),
+            BodyFormat::Html
+        );
+        assert_eq!(params.len(), 1);
+        assert_eq!(BodyFormat::take_from(&mut params).unwrap(), BodyFormat::Raw);
+
+        params.insert("format".to_owned(), "pdf".to_owned());
+        assert!(BodyFormat::take_from(&mut params).is_err());
+    }
+
+    #[test]
+    fn present_leaves_raw_models_unchanged_and_adds_rendering_for_html() {
+        let now = chrono::Utc::now();
+        let note = notes::Model {
+            id: entity::Id::new_v4(),
+            coaching_session_id: entity::Id::new_v4(),
+            body: Some("- [ ] Ask @caleb".to_owned()),
+            user_id: entity::Id::new_v4(),
+            visibility: Default::default(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        let raw = serde_json::to_value(present(note.clone(), BodyFormat::Raw)).unwrap();
+        let html = serde_json::to_value(present(note.clone(), BodyFormat::Html)).unwrap();
+
+        assert_eq!(raw, serde_json::to_value(&note).unwrap());
+        assert_eq!(html["body"], raw["body"]);
+        assert_eq!(html["mentions"], serde_json::json!(["caleb"]));
+        assert_eq!(
+            html["tasks"],
+            serde_json::json!([{ "text": "Ask @caleb", "checked": false }])
+        );
+    }
+}
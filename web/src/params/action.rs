@@ -0,0 +1,107 @@
+use super::{
+    not_in_past, not_null, nullable, unassigned_timestamp, MAX_BODY_LENGTH, UNASSIGNED_ID,
+};
+use entity::{actions, status::Status, Id};
+use entity_api::action::Patch;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// The body of a request to create an Action.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::action::NewAction)] // OpenAPI schema
+pub(crate) struct NewAction {
+    pub coaching_session_id: Id,
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+    #[validate(custom(function = "not_in_past"))]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub due_by: Option<DateTimeWithTimeZone>,
+    pub status: Status,
+}
+
+/// The body of a request to update an Action. A due date already in the past may be kept.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::action::UpdateAction)] // OpenAPI schema
+pub(crate) struct UpdateAction {
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub due_by: Option<DateTimeWithTimeZone>,
+    pub status: Status,
+}
+
+/// The body of a request to change some of an Action's fields, as a JSON Merge Patch. A field
+/// left out keeps its value and `null` clears it. A due date it sets must not be in the past.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::action::PatchAction)] // OpenAPI schema
+pub(crate) struct PatchAction {
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    #[schema(value_type = Option<String>, nullable)] // Applies to OpenAPI schema
+    pub body: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(custom(function = "not_in_past"))]
+    #[schema(value_type = Option<String>, format = DateTime, nullable)]
+    // Applies to OpenAPI schema
+    pub due_by: Option<Option<DateTimeWithTimeZone>>,
+    #[serde(default, deserialize_with = "not_null")]
+    pub status: Option<Status>,
+}
+
+impl From<NewAction> for actions::Model {
+    fn from(params: NewAction) -> Self {
+        actions::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: params.coaching_session_id,
+            user_id: UNASSIGNED_ID,
+            body: params.body,
+            due_by: params.due_by,
+            status: params.status,
+            status_changed_at: unassigned_timestamp(),
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<UpdateAction> for actions::Model {
+    fn from(params: UpdateAction) -> Self {
+        actions::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: UNASSIGNED_ID,
+            user_id: UNASSIGNED_ID,
+            body: params.body,
+            due_by: params.due_by,
+            status: params.status,
+            status_changed_at: unassigned_timestamp(),
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<PatchAction> for Patch {
+    fn from(params: PatchAction) -> Self {
+        Patch {
+            body: params.body,
+            due_by: params.due_by,
+            status: params.status,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::{nullable, unassigned_timestamp, MAX_BODY_LENGTH, UNASSIGNED_ID};
+use entity::{agreements, Id};
+use entity_api::agreement::Patch;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// The body of a request to create an Agreement.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::agreement::NewAgreement)] // OpenAPI schema
+pub(crate) struct NewAgreement {
+    pub coaching_session_id: Id,
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+}
+
+/// The body of a request to update an Agreement.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::agreement::UpdateAgreement)] // OpenAPI schema
+pub(crate) struct UpdateAgreement {
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+}
+
+/// The body of a request to change some of an Agreement's fields, as a JSON Merge Patch. A
+/// field left out keeps its value and `null` clears it.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::agreement::PatchAgreement)] // OpenAPI schema
+pub(crate) struct PatchAgreement {
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    #[schema(value_type = Option<String>, nullable)] // Applies to OpenAPI schema
+    pub body: Option<Option<String>>,
+}
+
+impl From<NewAgreement> for agreements::Model {
+    fn from(params: NewAgreement) -> Self {
+        agreements::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: params.coaching_session_id,
+            body: params.body,
+            user_id: UNASSIGNED_ID,
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<UpdateAgreement> for agreements::Model {
+    fn from(params: UpdateAgreement) -> Self {
+        agreements::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: UNASSIGNED_ID,
+            body: params.body,
+            user_id: UNASSIGNED_ID,
+            coach_acknowledged_at: None,
+            coachee_acknowledged_at: None,
+            acknowledged_revision_id: None,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<PatchAgreement> for Patch {
+    fn from(params: PatchAgreement) -> Self {
+        Patch { body: params.body }
+    }
+}
@@ -0,0 +1,101 @@
+use super::{field_violation, unassigned_timestamp, UNASSIGNED_ID};
+use chrono::Utc;
+use entity::{coaching_relationships, relationship_status::RelationshipStatus, Id};
+use sea_orm::prelude::Date;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// The body of a request to create a Coaching Relationship in the organization named by the
+/// request's path.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "coach_is_not_coachee"))]
+#[schema(as = web::params::coaching_relationship::NewCoachingRelationship)] // OpenAPI schema
+pub(crate) struct NewCoachingRelationship {
+    pub coach_id: Id,
+    pub coachee_id: Id,
+    #[serde(default)]
+    pub status: RelationshipStatus,
+    #[serde(default = "today")]
+    #[schema(value_type = String, format = Date)] // Applies to OpenAPI schema
+    pub start_date: Date,
+}
+
+/// The body of a request to update the status and dates of a Coaching Relationship.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "ends_after_it_starts"))]
+#[schema(as = web::params::coaching_relationship::UpdateCoachingRelationship)] // OpenAPI schema
+pub(crate) struct UpdateCoachingRelationship {
+    pub status: RelationshipStatus,
+    #[schema(value_type = String, format = Date)] // Applies to OpenAPI schema
+    pub start_date: Date,
+    #[schema(value_type = Option<String>, format = Date)] // Applies to OpenAPI schema
+    pub end_date: Option<Date>,
+}
+
+impl NewCoachingRelationship {
+    pub(crate) fn into_model(self, organization_id: Id) -> coaching_relationships::Model {
+        coaching_relationships::Model {
+            id: UNASSIGNED_ID,
+            organization_id,
+            coach_id: self.coach_id,
+            coachee_id: self.coachee_id,
+            status: self.status,
+            start_date: self.start_date,
+            end_date: None,
+            reassigned_to_id: None,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl UpdateCoachingRelationship {
+    pub(crate) fn into_model(self, organization_id: Id) -> coaching_relationships::Model {
+        coaching_relationships::Model {
+            id: UNASSIGNED_ID,
+            organization_id,
+            coach_id: UNASSIGNED_ID,
+            coachee_id: UNASSIGNED_ID,
+            status: self.status,
+            start_date: self.start_date,
+            end_date: self.end_date,
+            reassigned_to_id: None,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+fn today() -> Date {
+    Utc::now().date_naive()
+}
+
+fn coach_is_not_coachee(params: &NewCoachingRelationship) -> Result<(), ValidationError> {
+    if params.coach_id == params.coachee_id {
+        return Err(field_violation(
+            "coachee_id",
+            "same_as_coach",
+            "must be a different user than coach_id",
+        ));
+    }
+
+    Ok(())
+}
+
+fn ends_after_it_starts(params: &UpdateCoachingRelationship) -> Result<(), ValidationError> {
+    if params
+        .end_date
+        .is_some_and(|end_date| end_date < params.start_date)
+    {
+        return Err(field_violation(
+            "end_date",
+            "before_start_date",
+            "must not be before start_date",
+        ));
+    }
+
+    Ok(())
+}
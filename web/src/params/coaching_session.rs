@@ -0,0 +1,104 @@
+use super::{
+    known_timezone, not_blank, not_null, unassigned_timestamp, violation, MAX_BODY_LENGTH,
+    UNASSIGNED_ID,
+};
+use entity::{coaching_sessions, notes, visibility::Visibility, Id};
+use entity_api::coaching_session::Patch;
+use sea_orm::prelude::DateTime;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// The most items an agenda may have, since each one is created as a Note of its own.
+const MAX_AGENDA_ITEMS: u64 = 20;
+
+/// The body of a request to create a Coaching Session.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::coaching_session::NewCoachingSession)] // OpenAPI schema
+pub(crate) struct NewCoachingSession {
+    pub coaching_relationship_id: Id,
+    #[schema(value_type = String, format = DateTime)] // Applies to OpenAPI schema
+    pub date: DateTime,
+    #[validate(custom(function = "known_timezone"))]
+    #[schema(example = "America/Chicago")]
+    pub timezone: String,
+    /// Items to discuss, each added to the new Coaching Session as a shared Note
+    #[serde(default)]
+    #[validate(
+        length(
+            max = MAX_AGENDA_ITEMS,
+            code = "too_many",
+            message = "must have at most 20 items"
+        ),
+        custom(function = "agenda_items")
+    )]
+    pub agenda: Vec<String>,
+}
+
+/// The body of a request to change some of a Coaching Session's fields, as a JSON Merge Patch.
+/// A field left out keeps its value.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::coaching_session::PatchCoachingSession)] // OpenAPI schema
+pub(crate) struct PatchCoachingSession {
+    #[serde(default, deserialize_with = "not_null")]
+    #[schema(value_type = Option<String>, format = DateTime)] // Applies to OpenAPI schema
+    pub date: Option<DateTime>,
+    #[serde(default, deserialize_with = "not_null")]
+    #[validate(custom(function = "known_timezone"))]
+    #[schema(example = "America/Chicago")]
+    pub timezone: Option<String>,
+}
+
+impl From<NewCoachingSession> for coaching_sessions::Model {
+    fn from(params: NewCoachingSession) -> Self {
+        coaching_sessions::Model {
+            id: UNASSIGNED_ID,
+            coaching_relationship_id: params.coaching_relationship_id,
+            date: params.date,
+            timezone: params.timezone,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+// Each agenda item becomes the body of a Note, so it may be no longer than one, and an item
+// with nothing to discuss is most likely a mistake.
+fn agenda_items(items: &[String]) -> Result<(), ValidationError> {
+    for item in items {
+        not_blank(item)?;
+
+        if item.chars().count() as u64 > MAX_BODY_LENGTH {
+            return Err(violation(
+                "too_long",
+                "each item must be at most 50000 characters",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A Note in the Coaching Session with `coaching_session_id` for one item of its agenda.
+pub(crate) fn agenda_note(coaching_session_id: Id, item: String) -> notes::Model {
+    notes::Model {
+        id: UNASSIGNED_ID,
+        coaching_session_id,
+        body: Some(item),
+        user_id: UNASSIGNED_ID,
+        visibility: Visibility::Shared,
+        created_at: unassigned_timestamp(),
+        updated_at: unassigned_timestamp(),
+        deleted_at: None,
+    }
+}
+
+impl From<PatchCoachingSession> for Patch {
+    fn from(params: PatchCoachingSession) -> Self {
+        Patch {
+            date: params.date,
+            timezone: params.timezone,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! The bodies that endpoints accept, one type per endpoint, each declaring the rules its
+//! fields must follow. They are extracted with `ValidJson`, which rejects a body that breaks
+//! any rule with every violation listed, and are then turned into the entity models that
+//! `entity_api` works with. Fields the server assigns, like ids and timestamps, are left for
+//! `entity_api` to fill in.
+
+use chrono::Utc;
+use entity::Id;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+use validator::ValidationError;
+
+pub(crate) mod action;
+pub(crate) mod agreement;
+pub(crate) mod coaching_relationship;
+pub(crate) mod coaching_session;
+pub(crate) mod note;
+pub(crate) mod organization;
+pub(crate) mod overarching_goal;
+pub(crate) mod user;
+
+/// The longest name, title or other single line of text.
+pub(crate) const MAX_NAME_LENGTH: u64 = 255;
+
+/// The longest Markdown body of a note, agreement, action or overarching goal.
+pub(crate) const MAX_BODY_LENGTH: u64 = 50_000;
+
+/// The longest URL, such as an organization's logo.
+pub(crate) const MAX_URL_LENGTH: u64 = 2_048;
+
+/// Stands in for the ids a model is given by `entity_api` rather than by the request.
+pub(crate) const UNASSIGNED_ID: Id = Id::nil();
+
+/// Stands in for the timestamps a model is given by `entity_api` rather than by the request.
+pub(crate) fn unassigned_timestamp() -> DateTimeWithTimeZone {
+    Utc::now().into()
+}
+
+/// Reads a JSON Merge Patch (RFC 7396) field that may be cleared. Used along with
+/// `#[serde(default)]`, a field left out of the patch is `None`, while `null` is `Some(None)`.
+pub(crate) fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Reads a JSON Merge Patch (RFC 7396) field that can't be cleared. Used along with
+/// `#[serde(default)]`, a field left out of the patch is `None`, while `null` is rejected.
+pub(crate) fn not_null<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+pub(crate) fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(violation("blank", "must not be blank"));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn known_timezone(value: &str) -> Result<(), ValidationError> {
+    if value.parse::<chrono_tz::Tz>().is_err() {
+        return Err(violation(
+            "invalid_timezone",
+            "must be an IANA time zone such as America/Chicago",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Accepts any time on or after today, as today is in the value's own offset.
+pub(crate) fn not_in_past(value: &DateTimeWithTimeZone) -> Result<(), ValidationError> {
+    let today = Utc::now().with_timezone(value.offset()).date_naive();
+
+    if value.date_naive() < today {
+        return Err(violation("in_past", "must not be in the past"));
+    }
+
+    Ok(())
+}
+
+/// A violation of a rule that spans several fields, reported against `field`.
+pub(crate) fn field_violation(
+    field: &'static str,
+    code: &'static str,
+    message: &'static str,
+) -> ValidationError {
+    let mut error = violation(code, message);
+    error.add_param(Cow::from("field"), &field);
+    error
+}
+
+fn violation(code: &'static str, message: &'static str) -> ValidationError {
+    ValidationError::new(code).with_message(Cow::from(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, FixedOffset};
+
+    #[test]
+    fn not_blank_rejects_whitespace() {
+        assert!(not_blank("Refactor Group").is_ok());
+        assert_eq!(not_blank(" \t\n").unwrap_err().code, "blank");
+    }
+
+    #[test]
+    fn known_timezone_accepts_only_iana_names() {
+        assert!(known_timezone("America/Chicago").is_ok());
+        assert!(known_timezone("UTC").is_ok());
+        assert_eq!(
+            known_timezone("Central Time").unwrap_err().code,
+            "invalid_timezone"
+        );
+    }
+
+    #[test]
+    fn not_in_past_accepts_today_in_the_values_offset() {
+        let offset = FixedOffset::west_opt(10 * 3600).unwrap();
+        let now = Utc::now().with_timezone(&offset);
+        let start_of_today = now.date_naive().and_hms_opt(0, 0, 0).unwrap();
+        let start_of_today = start_of_today.and_local_timezone(offset).unwrap();
+
+        assert!(not_in_past(&start_of_today).is_ok());
+        assert_eq!(
+            not_in_past(&(start_of_today - Duration::seconds(1)))
+                .unwrap_err()
+                .code,
+            "in_past"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use super::{not_null, nullable, unassigned_timestamp, MAX_BODY_LENGTH, UNASSIGNED_ID};
+use entity::{notes, visibility::Visibility, Id};
+use entity_api::note::Patch;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// The body of a request to create a Note.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::note::NewNote)] // OpenAPI schema
+pub(crate) struct NewNote {
+    pub coaching_session_id: Id,
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+/// The body of a request to update a Note. Only the Note's author may change its visibility.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::note::UpdateNote)] // OpenAPI schema
+pub(crate) struct UpdateNote {
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+/// The body of a request to change some of a Note's fields, as a JSON Merge Patch. A field
+/// left out keeps its value and `null` clears it. Only the Note's author may change its
+/// visibility.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::note::PatchNote)] // OpenAPI schema
+pub(crate) struct PatchNote {
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    #[schema(value_type = Option<String>, nullable)] // Applies to OpenAPI schema
+    pub body: Option<Option<String>>,
+    #[serde(default, deserialize_with = "not_null")]
+    pub visibility: Option<Visibility>,
+}
+
+impl From<NewNote> for notes::Model {
+    fn from(params: NewNote) -> Self {
+        notes::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: params.coaching_session_id,
+            body: params.body,
+            user_id: UNASSIGNED_ID,
+            visibility: params.visibility,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<UpdateNote> for notes::Model {
+    fn from(params: UpdateNote) -> Self {
+        notes::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: UNASSIGNED_ID,
+            body: params.body,
+            user_id: UNASSIGNED_ID,
+            visibility: params.visibility,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<PatchNote> for Patch {
+    fn from(params: PatchNote) -> Self {
+        Patch {
+            body: params.body,
+            visibility: params.visibility,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use super::{
+    not_blank, not_null, nullable, unassigned_timestamp, MAX_NAME_LENGTH, MAX_URL_LENGTH,
+    UNASSIGNED_ID,
+};
+use entity::organizations;
+use entity_api::organization::Patch;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+/// The body of a request to create an Organization.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::organization::NewOrganization)] // OpenAPI schema
+pub(crate) struct NewOrganization {
+    #[validate(
+        custom(function = "not_blank"),
+        length(
+            max = MAX_NAME_LENGTH,
+            code = "too_long",
+            message = "must be at most 255 characters"
+        )
+    )]
+    pub name: String,
+    #[validate(
+        url(code = "invalid_url", message = "must be a URL"),
+        length(
+            max = MAX_URL_LENGTH,
+            code = "too_long",
+            message = "must be at most 2048 characters"
+        )
+    )]
+    pub logo: Option<String>,
+}
+
+/// The query of a request to delete an Organization.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct DeleteOrganization {
+    /// Only report what deleting the Organization would affect, without deleting anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// The body of a request to update an Organization.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::organization::UpdateOrganization)] // OpenAPI schema
+pub(crate) struct UpdateOrganization {
+    #[validate(
+        custom(function = "not_blank"),
+        length(
+            max = MAX_NAME_LENGTH,
+            code = "too_long",
+            message = "must be at most 255 characters"
+        )
+    )]
+    pub name: String,
+    #[validate(
+        url(code = "invalid_url", message = "must be a URL"),
+        length(
+            max = MAX_URL_LENGTH,
+            code = "too_long",
+            message = "must be at most 2048 characters"
+        )
+    )]
+    pub logo: Option<String>,
+}
+
+/// The body of a request to change some of an Organization's fields, as a JSON Merge Patch. A
+/// field left out keeps its value and `null` clears the logo.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::organization::PatchOrganization)] // OpenAPI schema
+pub(crate) struct PatchOrganization {
+    #[serde(default, deserialize_with = "not_null")]
+    #[validate(
+        custom(function = "not_blank"),
+        length(
+            max = MAX_NAME_LENGTH,
+            code = "too_long",
+            message = "must be at most 255 characters"
+        )
+    )]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(
+        url(code = "invalid_url", message = "must be a URL"),
+        length(
+            max = MAX_URL_LENGTH,
+            code = "too_long",
+            message = "must be at most 2048 characters"
+        )
+    )]
+    #[schema(value_type = Option<String>, nullable)] // Applies to OpenAPI schema
+    pub logo: Option<Option<String>>,
+}
+
+impl From<NewOrganization> for organizations::Model {
+    fn from(params: NewOrganization) -> Self {
+        organizations::Model {
+            id: UNASSIGNED_ID,
+            name: params.name,
+            logo: params.logo,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<UpdateOrganization> for organizations::Model {
+    fn from(params: UpdateOrganization) -> Self {
+        organizations::Model {
+            id: UNASSIGNED_ID,
+            name: params.name,
+            logo: params.logo,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<PatchOrganization> for Patch {
+    fn from(params: PatchOrganization) -> Self {
+        Patch {
+            name: params.name,
+            logo: params.logo,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use super::{
+    not_null, nullable, unassigned_timestamp, MAX_BODY_LENGTH, MAX_NAME_LENGTH, UNASSIGNED_ID,
+};
+use entity::{overarching_goals, status::Status, Id};
+use entity_api::overarching_goal::Patch;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// The body of a request to create an Overarching Goal.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::overarching_goal::NewOverarchingGoal)] // OpenAPI schema
+pub(crate) struct NewOverarchingGoal {
+    pub coaching_session_id: Id,
+    #[validate(length(
+        max = MAX_NAME_LENGTH,
+        code = "too_long",
+        message = "must be at most 255 characters"
+    ))]
+    pub title: Option<String>,
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+    pub status: Status,
+}
+
+/// The body of a request to update an Overarching Goal.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::overarching_goal::UpdateOverarchingGoal)] // OpenAPI schema
+pub(crate) struct UpdateOverarchingGoal {
+    #[validate(length(
+        max = MAX_NAME_LENGTH,
+        code = "too_long",
+        message = "must be at most 255 characters"
+    ))]
+    pub title: Option<String>,
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    pub body: Option<String>,
+    pub status: Status,
+}
+
+/// The body of a request to change some of an Overarching Goal's fields, as a JSON Merge
+/// Patch. A field left out keeps its value and `null` clears it.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::overarching_goal::PatchOverarchingGoal)] // OpenAPI schema
+pub(crate) struct PatchOverarchingGoal {
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(
+        max = MAX_NAME_LENGTH,
+        code = "too_long",
+        message = "must be at most 255 characters"
+    ))]
+    #[schema(value_type = Option<String>, nullable)] // Applies to OpenAPI schema
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(
+        max = MAX_BODY_LENGTH,
+        code = "too_long",
+        message = "must be at most 50000 characters"
+    ))]
+    #[schema(value_type = Option<String>, nullable)] // Applies to OpenAPI schema
+    pub body: Option<Option<String>>,
+    #[serde(default, deserialize_with = "not_null")]
+    pub status: Option<Status>,
+}
+
+impl From<NewOverarchingGoal> for overarching_goals::Model {
+    fn from(params: NewOverarchingGoal) -> Self {
+        overarching_goals::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: params.coaching_session_id,
+            user_id: UNASSIGNED_ID,
+            title: params.title,
+            body: params.body,
+            status: params.status,
+            status_changed_at: None,
+            completed_at: None,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<UpdateOverarchingGoal> for overarching_goals::Model {
+    fn from(params: UpdateOverarchingGoal) -> Self {
+        overarching_goals::Model {
+            id: UNASSIGNED_ID,
+            coaching_session_id: UNASSIGNED_ID,
+            user_id: UNASSIGNED_ID,
+            title: params.title,
+            body: params.body,
+            status: params.status,
+            status_changed_at: None,
+            completed_at: None,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+            deleted_at: None,
+        }
+    }
+}
+
+impl From<PatchOverarchingGoal> for Patch {
+    fn from(params: PatchOverarchingGoal) -> Self {
+        Patch {
+            title: params.title,
+            body: params.body,
+            status: params.status,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use super::{not_blank, unassigned_timestamp, MAX_NAME_LENGTH, MAX_URL_LENGTH, UNASSIGNED_ID};
+use entity::users;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// The body of a request to create a User.
+#[derive(Deserialize, Validate, ToSchema)]
+#[schema(as = web::params::user::NewUser)] // OpenAPI schema
+pub(crate) struct NewUser {
+    #[validate(email(code = "invalid_email", message = "must be an email address"))]
+    pub email: String,
+    #[validate(length(
+        max = MAX_NAME_LENGTH,
+        code = "too_long",
+        message = "must be at most 255 characters"
+    ))]
+    pub first_name: Option<String>,
+    #[validate(length(
+        max = MAX_NAME_LENGTH,
+        code = "too_long",
+        message = "must be at most 255 characters"
+    ))]
+    pub last_name: Option<String>,
+    #[validate(
+        custom(function = "not_blank"),
+        length(
+            max = MAX_NAME_LENGTH,
+            code = "too_long",
+            message = "must be at most 255 characters"
+        )
+    )]
+    pub display_name: Option<String>,
+    #[validate(length(
+        min = 8,
+        max = 128,
+        code = "invalid_length",
+        message = "must be between 8 and 128 characters"
+    ))]
+    #[schema(format = Password)]
+    pub password: String,
+    #[validate(length(max = 39, code = "too_long", message = "must be at most 39 characters"))]
+    pub github_username: Option<String>,
+    #[validate(
+        url(code = "invalid_url", message = "must be a URL"),
+        length(
+            max = MAX_URL_LENGTH,
+            code = "too_long",
+            message = "must be at most 2048 characters"
+        )
+    )]
+    pub github_profile_url: Option<String>,
+}
+
+// Keeps the password out of the logs
+impl std::fmt::Debug for NewUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NewUser")
+            .field("email", &self.email)
+            .field("first_name", &self.first_name)
+            .field("last_name", &self.last_name)
+            .field("display_name", &self.display_name)
+            .field("github_username", &self.github_username)
+            .field("github_profile_url", &self.github_profile_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<NewUser> for users::Model {
+    fn from(params: NewUser) -> Self {
+        users::Model {
+            id: UNASSIGNED_ID,
+            email: params.email,
+            first_name: params.first_name,
+            last_name: params.last_name,
+            display_name: params.display_name,
+            password: params.password,
+            github_username: params.github_username,
+            github_profile_url: params.github_profile_url,
+            created_at: unassigned_timestamp(),
+            updated_at: unassigned_timestamp(),
+        }
+    }
+}
@@ -0,0 +1,1019 @@
+//! Rendering of coaching session summaries as PDF documents.
+//!
+//! Text is set in the standard Helvetica fonts that every PDF viewer provides, so no font files
+//! are installed or embedded and rendering needs nothing outside this crate. The standard fonts
+//! only cover WinAnsi, so other characters are replaced, and text is wrapped using the fonts'
+//! published glyph widths. An organization's logo is embedded when it can be fetched over HTTPS
+//! from a public address as a PNG or JPEG image.
+
+use crate::markdown;
+use entity::{actions, status::Status};
+use entity_api::session_summary::SessionSummary;
+use miniz_oxide::deflate::{compress_to_vec_zlib, CompressionLevel};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use reqwest::{header::LOCATION, Url};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use log::*;
+
+/// US Letter, in points.
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 54.0;
+
+/// How far list items are set in from the margin, leaving room for their bullet.
+const ITEM_INDENT: f32 = 16.0;
+/// How far field values are set in from their labels.
+const FIELD_INDENT: f32 = 80.0;
+const LINE_SPACING: f32 = 1.4;
+
+/// The widths of the printable ASCII characters in thousandths of an em, from the Adobe font
+/// metrics of Helvetica and Helvetica-Bold.
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+#[rustfmt::skip]
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+/// Other characters are taken to be as wide as the widest accented letters.
+const OTHER_GLYPH_WIDTH: u16 = 722;
+
+/// The box the logo is scaled down to fit in, in points.
+const LOGO_MAX_WIDTH: f32 = 160.0;
+const LOGO_MAX_HEIGHT: f32 = 48.0;
+const LOGO_MAX_BYTES: usize = 2 * 1024 * 1024;
+/// Bounds the memory a small but highly compressed PNG can decode into.
+const LOGO_MAX_DECODED_BYTES: usize = 32 * 1024 * 1024;
+const LOGO_TIMEOUT: Duration = Duration::from_secs(5);
+const LOGO_MAX_REDIRECTS: usize = 3;
+
+const REGULAR_FONT: Name = Name(b"F1");
+const BOLD_FONT: Name = Name(b"F2");
+const LOGO_IMAGE: Name = Name(b"Im1");
+
+const ACCENT: [f32; 3] = [0.11, 0.31, 0.53];
+const TEXT: [f32; 3] = [0.1, 0.1, 0.1];
+const MUTED: [f32; 3] = [0.42, 0.42, 0.42];
+
+#[derive(Clone, Copy)]
+struct Style {
+    font: Name<'static>,
+    size: f32,
+    color: [f32; 3],
+}
+
+const TITLE_STYLE: Style = Style {
+    font: BOLD_FONT,
+    size: 18.0,
+    color: ACCENT,
+};
+const SECTION_STYLE: Style = Style {
+    font: BOLD_FONT,
+    size: 12.5,
+    color: ACCENT,
+};
+const LABEL_STYLE: Style = Style {
+    font: BOLD_FONT,
+    size: 10.0,
+    color: TEXT,
+};
+const BODY_STYLE: Style = Style {
+    font: REGULAR_FONT,
+    size: 10.0,
+    color: TEXT,
+};
+const DETAIL_STYLE: Style = Style {
+    font: REGULAR_FONT,
+    size: 8.5,
+    color: MUTED,
+};
+
+/// A decoded PNG or JPEG image, ready to be embedded.
+pub(crate) struct Logo {
+    width: u32,
+    height: u32,
+    grayscale: bool,
+    filter: Filter,
+    data: Vec<u8>,
+    /// The zlib compressed alpha channel of images that have one
+    alpha: Option<Vec<u8>>,
+}
+
+impl Logo {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8]) {
+            Self::from_jpeg(bytes)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Self::from_png(bytes)
+        } else {
+            None
+        }
+    }
+
+    // PDF viewers decode JPEGs themselves, so only the frame header is read for the image's
+    // size and number of color components.
+    fn from_jpeg(bytes: &[u8]) -> Option<Self> {
+        let mut position = 2;
+
+        while let Some(segment) = bytes.get(position..position + 4) {
+            if segment[0] != 0xFF {
+                return None;
+            }
+            let marker = segment[1];
+            let length = u16::from_be_bytes([segment[2], segment[3]]) as usize;
+
+            // The start of frame markers share their range with DHT, JPG and DAC
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                let frame = bytes.get(position + 4..position + 10)?;
+                let grayscale = match frame[5] {
+                    1 => true,
+                    3 => false,
+                    _ => return None,
+                };
+
+                return Some(Logo {
+                    width: u16::from_be_bytes([frame[3], frame[4]]).into(),
+                    height: u16::from_be_bytes([frame[1], frame[2]]).into(),
+                    grayscale,
+                    filter: Filter::DctDecode,
+                    data: bytes.to_vec(),
+                    alpha: None,
+                });
+            }
+
+            position += 2 + length;
+        }
+
+        None
+    }
+
+    fn from_png(bytes: &[u8]) -> Option<Self> {
+        let mut decoder = png::Decoder::new_with_limits(
+            bytes,
+            png::Limits {
+                bytes: LOGO_MAX_DECODED_BYTES,
+            },
+        );
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().ok()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels).ok()?;
+        pixels.truncate(frame.buffer_size());
+
+        let (grayscale, has_alpha) = match frame.color_type {
+            png::ColorType::Grayscale => (true, false),
+            png::ColorType::GrayscaleAlpha => (true, true),
+            png::ColorType::Rgb => (false, false),
+            png::ColorType::Rgba => (false, true),
+            png::ColorType::Indexed => return None,
+        };
+
+        let (color, alpha) = if has_alpha {
+            let stride = if grayscale { 2 } else { 4 };
+            let mut color = Vec::with_capacity(pixels.len() / stride * (stride - 1));
+            let mut alpha = Vec::with_capacity(pixels.len() / stride);
+            for pixel in pixels.chunks_exact(stride) {
+                color.extend_from_slice(&pixel[..stride - 1]);
+                alpha.push(pixel[stride - 1]);
+            }
+            (color, Some(alpha))
+        } else {
+            (pixels, None)
+        };
+
+        let level = CompressionLevel::DefaultLevel as u8;
+
+        Some(Logo {
+            width: frame.width,
+            height: frame.height,
+            grayscale,
+            filter: Filter::FlateDecode,
+            data: compress_to_vec_zlib(&color, level),
+            alpha: alpha.map(|alpha| compress_to_vec_zlib(&alpha, level)),
+        })
+    }
+
+    // The size to draw the logo at, scaled down to fit the logo's box but never scaled up
+    fn size(&self) -> (f32, f32) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let scale = (LOGO_MAX_WIDTH / width)
+            .min(LOGO_MAX_HEIGHT / height)
+            .min(1.0);
+
+        (width * scale, height * scale)
+    }
+
+    fn write(&self, pdf: &mut Pdf, id: Ref, alpha_id: Ref) {
+        let mut image = pdf.image_xobject(id, &self.data);
+        image.filter(self.filter);
+        image.width(self.width as i32);
+        image.height(self.height as i32);
+        if self.grayscale {
+            image.color_space().device_gray();
+        } else {
+            image.color_space().device_rgb();
+        }
+        image.bits_per_component(8);
+        if self.alpha.is_some() {
+            image.s_mask(alpha_id);
+        }
+        image.finish();
+
+        if let Some(alpha) = &self.alpha {
+            let mut mask = pdf.image_xobject(alpha_id, alpha);
+            mask.filter(Filter::FlateDecode);
+            mask.width(self.width as i32);
+            mask.height(self.height as i32);
+            mask.color_space().device_gray();
+            mask.bits_per_component(8);
+        }
+    }
+}
+
+/// Fetches and decodes the logo at `url`. A summary is still useful without its logo, so when
+/// the logo can't be used the reason is logged and `None` returned.
+pub(crate) async fn fetch_logo(url: &str) -> Option<Logo> {
+    let bytes = match download_logo(url).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            warn!("Failed to fetch logo {}: {}", url, err);
+            return None;
+        }
+    };
+
+    let logo = Logo::decode(&bytes);
+    if logo.is_none() {
+        warn!(
+            "Logo {} is not a PNG or JPEG image that can be embedded",
+            url
+        );
+    }
+
+    logo
+}
+
+// Only HTTPS URLs of hosts with public addresses are fetched. Each host is resolved and checked
+// before the request is pinned to the checked addresses, and redirects are followed one at a
+// time so that each is checked the same way. This keeps services on the server's own network
+// out of reach of an organization's logo, including through DNS that later resolves elsewhere.
+async fn download_logo(url: &str) -> Result<Vec<u8>, String> {
+    let mut url = Url::parse(url).map_err(|err| err.to_string())?;
+
+    for _ in 0..=LOGO_MAX_REDIRECTS {
+        let mut response = request_logo(&url).await?;
+
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or("redirected without a location")?;
+            url = url.join(location).map_err(|err| err.to_string())?;
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > LOGO_MAX_BYTES {
+                return Err(format!("larger than {} bytes", LOGO_MAX_BYTES));
+            }
+        }
+
+        return Ok(bytes);
+    }
+
+    Err(format!("redirected more than {} times", LOGO_MAX_REDIRECTS))
+}
+
+async fn request_logo(url: &Url) -> Result<reqwest::Response, String> {
+    if url.scheme() != "https" {
+        return Err("only https URLs are fetched".to_owned());
+    }
+
+    let host = url.host_str().ok_or("has no host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::time::timeout(LOGO_TIMEOUT, tokio::net::lookup_host((host, port)))
+            .await
+            .map_err(|_| format!("timed out resolving {}", host))?
+            .map_err(|err| err.to_string())?
+            .collect(),
+    };
+
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public(addr.ip())) {
+        return Err(format!("{} does not have a public address", host));
+    }
+
+    // Connect to the addresses just checked rather than letting the client resolve the host again
+    let client = reqwest::Client::builder()
+        .timeout(LOGO_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(host, &addrs);
+
+    client
+        .build()
+        .map_err(|err| err.to_string())?
+        .get(url.clone())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())
+}
+
+// Whether `ip` is reachable on the public internet, rather than being on a private network,
+// the server itself or reserved for some other use.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (first == 100 && second & 0xC0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local, fc00::/7, and link-local, fe80::/10
+                    || first & 0xFE00 == 0xFC00
+                    || first & 0xFFC0 == 0xFE80)
+            }
+        },
+    }
+}
+
+/// The name to offer the summary of a coaching session for download under.
+pub(crate) fn summary_filename(summary: &SessionSummary) -> String {
+    format!(
+        "coaching-session-{}-summary.pdf",
+        summary.coaching_session.date.format("%Y-%m-%d")
+    )
+}
+
+/// Renders `summary` as a PDF document, branded with the organization's name and `logo`.
+pub(crate) fn render_session_summary(summary: &SessionSummary, logo: Option<&Logo>) -> Vec<u8> {
+    let session = &summary.coaching_session;
+    let relationship = &summary.relationship;
+    let mut layout = Layout::new();
+
+    if let Some(logo) = logo {
+        layout.logo(logo);
+    }
+    layout.text("Coaching Session Summary", TITLE_STYLE, MARGIN);
+    layout.text(&summary.organization.name, DETAIL_STYLE, MARGIN);
+
+    layout.section("Session");
+    layout.field("Date", &session.date.format("%A, %B %-d, %Y").to_string());
+    layout.field("Time", &session.date.format("%-I:%M %p").to_string());
+    layout.field("Time zone", &session.timezone);
+
+    layout.section("Participants");
+    layout.field(
+        "Coach",
+        &full_name(
+            &relationship.coach_first_name,
+            &relationship.coach_last_name,
+        ),
+    );
+    layout.field(
+        "Coachee",
+        &full_name(
+            &relationship.coachee_first_name,
+            &relationship.coachee_last_name,
+        ),
+    );
+
+    layout.list(
+        "Agreements",
+        summary
+            .agreements
+            .iter()
+            .map(|agreement| (body_text(agreement.body.as_deref()), None)),
+    );
+    layout.list("New Actions", summary.new_actions.iter().map(action_item));
+    layout.list("Open Actions", summary.open_actions.iter().map(action_item));
+    layout.list(
+        "Overarching Goals",
+        summary.overarching_goals.iter().map(|goal| {
+            let mut detail = status_label(&goal.status).to_owned();
+            if let Some(completed_at) = goal.completed_at {
+                detail.push_str(&format!(
+                    " \u{B7} Completed {}",
+                    completed_at.format("%b %-d, %Y")
+                ));
+            }
+
+            (
+                goal.title.as_deref().unwrap_or("Untitled goal").to_owned(),
+                Some(detail),
+            )
+        }),
+    );
+
+    let title = format!(
+        "Coaching Session Summary, {}",
+        session.date.format("%B %-d, %Y")
+    );
+
+    layout.finish(&title, logo)
+}
+
+fn full_name(first_name: &str, last_name: &str) -> String {
+    format!("{} {}", first_name, last_name).trim().to_owned()
+}
+
+fn body_text(body: Option<&str>) -> String {
+    markdown::plain_text(body.unwrap_or_default())
+}
+
+fn action_item(action: &actions::Model) -> (String, Option<String>) {
+    let due = match action.due_by {
+        Some(due_by) => format!("Due {}", due_by.format("%b %-d, %Y")),
+        None => "No due date".to_owned(),
+    };
+
+    (
+        body_text(action.body.as_deref()),
+        Some(format!("{} \u{B7} {}", due, status_label(&action.status))),
+    )
+}
+
+fn status_label(status: &Status) -> &'static str {
+    match status {
+        Status::NotStarted => "Not Started",
+        Status::InProgress => "In Progress",
+        Status::Completed => "Completed",
+        Status::WontDo => "Won't Do",
+    }
+}
+
+/// Lays text out top to bottom, starting a new page whenever the current one is full.
+struct Layout {
+    pages: Vec<Content>,
+    /// The baseline of the last line set on the current page
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Layout {
+            pages: vec![Content::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn content(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("a layout always has a page")
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Content::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    // Moves down by `height`, onto a new page if there isn't room for it on this one
+    fn advance(&mut self, height: f32) -> f32 {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+        self.y -= height;
+
+        self.y
+    }
+
+    fn show(&mut self, text: &str, style: Style, x: f32, y: f32) {
+        let [r, g, b] = style.color;
+
+        let content = self.content();
+        content.set_fill_rgb(r, g, b);
+        content.begin_text();
+        content.set_font(style.font, style.size);
+        content.next_line(x, y);
+        content.show(Str(&win_ansi(text)));
+        content.end_text();
+    }
+
+    fn line(&mut self, text: &str, style: Style, x: f32) {
+        let y = self.advance(style.size * LINE_SPACING);
+        self.show(text, style, x, y);
+    }
+
+    // `text` wrapped to fit between `x` and the right margin
+    fn text(&mut self, text: &str, style: Style, x: f32) {
+        for line in wrap(text, style, PAGE_WIDTH - MARGIN - x) {
+            self.line(&line, style, x);
+        }
+    }
+
+    // Body `text` wrapped to fit between `x` and the right margin, with `marker` set beside its
+    // first line at `marker_x`
+    fn hanging(&mut self, marker: &str, marker_style: Style, marker_x: f32, text: &str, x: f32) {
+        let mut lines = wrap(text, BODY_STYLE, PAGE_WIDTH - MARGIN - x).into_iter();
+        let y = self.advance(BODY_STYLE.size * LINE_SPACING);
+
+        self.show(marker, marker_style, marker_x, y);
+        self.show(&lines.next().unwrap_or_default(), BODY_STYLE, x, y);
+        for line in lines {
+            self.line(&line, BODY_STYLE, x);
+        }
+    }
+
+    fn logo(&mut self, logo: &Logo) {
+        let (width, height) = logo.size();
+        let y = self.advance(height);
+
+        let content = self.content();
+        content.save_state();
+        content.transform([width, 0.0, 0.0, height, MARGIN, y]);
+        content.x_object(LOGO_IMAGE);
+        content.restore_state();
+
+        self.advance(8.0);
+    }
+
+    // A section heading underlined with a rule, kept on the same page as the line after it
+    fn section(&mut self, title: &str) {
+        let height = 10.0 + SECTION_STYLE.size * LINE_SPACING + 6.0;
+        if self.y - height - BODY_STYLE.size * LINE_SPACING < MARGIN {
+            self.new_page();
+        }
+
+        self.advance(10.0);
+        self.line(title, SECTION_STYLE, MARGIN);
+        let y = self.advance(6.0) + 2.0;
+        let [r, g, b] = ACCENT;
+
+        let content = self.content();
+        content.set_stroke_rgb(r, g, b);
+        content.set_line_width(0.75);
+        content.move_to(MARGIN, y);
+        content.line_to(PAGE_WIDTH - MARGIN, y);
+        content.stroke();
+    }
+
+    // A label with its value beside it
+    fn field(&mut self, label: &str, value: &str) {
+        self.hanging(label, LABEL_STYLE, MARGIN, value, MARGIN + FIELD_INDENT);
+    }
+
+    // A section of bulleted items, each with an optional line of detail below it
+    fn list(&mut self, title: &str, items: impl Iterator<Item = (String, Option<String>)>) {
+        self.section(title);
+
+        let mut empty = true;
+        for (text, detail) in items {
+            self.advance(3.0);
+            self.hanging(
+                "\u{2022}",
+                BODY_STYLE,
+                MARGIN + 4.0,
+                &text,
+                MARGIN + ITEM_INDENT,
+            );
+            if let Some(detail) = detail {
+                self.text(&detail, DETAIL_STYLE, MARGIN + ITEM_INDENT);
+            }
+            empty = false;
+        }
+
+        if empty {
+            self.line("None", DETAIL_STYLE, MARGIN);
+        }
+    }
+
+    fn finish(self, title: &str, logo: Option<&Logo>) -> Vec<u8> {
+        let mut pdf = Pdf::new();
+        let mut next_id = Ref::new(1);
+        let catalog_id = next_id.bump();
+        let page_tree_id = next_id.bump();
+        let info_id = next_id.bump();
+        let regular_font_id = next_id.bump();
+        let bold_font_id = next_id.bump();
+        let logo_id = next_id.bump();
+        let logo_alpha_id = next_id.bump();
+        let page_ids: Vec<(Ref, Ref)> = self
+            .pages
+            .iter()
+            .map(|_| (next_id.bump(), next_id.bump()))
+            .collect();
+        let page_count = self.pages.len();
+
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().map(|(page_id, _)| *page_id))
+            .count(page_count as i32);
+        pdf.document_info(info_id)
+            .title(TextStr(title))
+            .producer(TextStr("Refactor Platform"));
+        pdf.type1_font(regular_font_id)
+            .base_font(Name(b"Helvetica"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+        pdf.type1_font(bold_font_id)
+            .base_font(Name(b"Helvetica-Bold"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+        for (index, (mut content, (page_id, content_id))) in
+            self.pages.into_iter().zip(page_ids).enumerate()
+        {
+            let [r, g, b] = MUTED;
+            content.set_fill_rgb(r, g, b);
+            content.begin_text();
+            content.set_font(REGULAR_FONT, DETAIL_STYLE.size);
+            content.next_line(MARGIN, MARGIN / 2.0);
+            content.show(Str(&win_ansi(&format!(
+                "{} \u{B7} Page {} of {}",
+                title,
+                index + 1,
+                page_count
+            ))));
+            content.end_text();
+
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+            page.parent(page_tree_id);
+            page.contents(content_id);
+            let mut resources = page.resources();
+            resources
+                .fonts()
+                .pair(REGULAR_FONT, regular_font_id)
+                .pair(BOLD_FONT, bold_font_id);
+            if index == 0 && logo.is_some() {
+                resources.x_objects().pair(LOGO_IMAGE, logo_id);
+            }
+            resources.finish();
+            page.finish();
+
+            let compressed =
+                compress_to_vec_zlib(&content.finish(), CompressionLevel::DefaultLevel as u8);
+            pdf.stream(content_id, &compressed)
+                .filter(Filter::FlateDecode);
+        }
+
+        if let Some(logo) = logo {
+            logo.write(&mut pdf, logo_id, logo_alpha_id);
+        }
+
+        pdf.finish()
+    }
+}
+
+// Breaks `text` into lines that fit within `width` when set in `style`, keeping its line
+// breaks. Words too long for a line of their own are split.
+fn wrap(text: &str, style: Style, width: f32) -> Vec<String> {
+    let space_width = glyph_width(' ', style);
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0.0;
+
+        for word in paragraph.split_whitespace() {
+            for (piece, piece_width) in split_to_fit(word, style, width) {
+                if !line.is_empty() && line_width + space_width + piece_width > width {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0.0;
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                    line_width += space_width;
+                }
+                line.push_str(&piece);
+                line_width += piece_width;
+            }
+        }
+
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    lines
+}
+
+// Splits `word` into pieces no wider than `width`, along with the width of each
+fn split_to_fit(word: &str, style: Style, width: f32) -> Vec<(String, f32)> {
+    let mut pieces = Vec::new();
+    let mut piece = String::new();
+    let mut piece_width = 0.0;
+
+    for c in word.chars() {
+        let c_width = glyph_width(c, style);
+        if !piece.is_empty() && piece_width + c_width > width {
+            pieces.push((std::mem::take(&mut piece), piece_width));
+            piece_width = 0.0;
+        }
+        piece.push(c);
+        piece_width += c_width;
+    }
+    pieces.push((piece, piece_width));
+
+    pieces
+}
+
+// The width of `c` in points when set in `style`
+fn glyph_width(c: char, style: Style) -> f32 {
+    let widths = if style.font == BOLD_FONT {
+        &HELVETICA_BOLD_WIDTHS
+    } else {
+        &HELVETICA_WIDTHS
+    };
+    let width = match c {
+        ' '..='~' => widths[c as usize - ' ' as usize],
+        _ => OTHER_GLYPH_WIDTH,
+    };
+
+    f32::from(width) * style.size / 1000.0
+}
+
+// Encodes `text` as WinAnsi, the encoding of the standard fonts. Latin-1 maps straight onto it
+// and the common typographic characters have places of their own. Anything else becomes `?`.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+            '\t' => b' ',
+            '\u{20AC}' => 0x80,
+            '\u{2026}' => 0x85,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201C}' => 0x93,
+            '\u{201D}' => 0x94,
+            '\u{2022}' => 0x95,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            '\u{2122}' => 0x99,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use entity::{
+        coaching_sessions, organizations, overarching_goals,
+        relationship_status::RelationshipStatus, Id,
+    };
+    use entity_api::coaching_relationship::CoachingRelationshipWithUserNames;
+
+    fn summary(action_count: usize) -> SessionSummary {
+        let now = Utc::now();
+        let session_id = Id::new_v4();
+        let action = |index: usize| actions::Model {
+            id: Id::new_v4(),
+            coaching_session_id: session_id,
+            user_id: Id::new_v4(),
+            body: Some(format!("Follow up on **item {index}** with the team")),
+            due_by: Some(now.into()),
+            status: Status::InProgress,
+            status_changed_at: now.into(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        };
+
+        SessionSummary {
+            coaching_session: coaching_sessions::Model {
+                id: session_id,
+                coaching_relationship_id: Id::new_v4(),
+                date: now.naive_utc(),
+                timezone: "America/Chicago".to_owned(),
+                created_at: now.into(),
+                updated_at: now.into(),
+                deleted_at: None,
+            },
+            relationship: CoachingRelationshipWithUserNames {
+                id: Id::new_v4(),
+                coach_id: Id::new_v4(),
+                coachee_id: Id::new_v4(),
+                coach_first_name: "Jim".to_owned(),
+                coach_last_name: "Hodapp".to_owned(),
+                coachee_first_name: "Zoë".to_owned(),
+                coachee_last_name: "Nguyễn".to_owned(),
+                status: RelationshipStatus::Active,
+                start_date: now.date_naive(),
+                end_date: None,
+                reassigned_to_id: None,
+                created_at: now.into(),
+                updated_at: now.into(),
+            },
+            organization: organizations::Model {
+                id: Id::new_v4(),
+                name: "Refactor Coaching".to_owned(),
+                logo: None,
+                created_at: now.into(),
+                updated_at: now.into(),
+                deleted_at: None,
+            },
+            agreements: Vec::new(),
+            new_actions: (0..action_count).map(action).collect(),
+            open_actions: Vec::new(),
+            overarching_goals: vec![overarching_goals::Model {
+                id: Id::new_v4(),
+                coaching_session_id: session_id,
+                user_id: Id::new_v4(),
+                title: Some("Grow as a (technical) leader".to_owned()),
+                body: None,
+                status: Status::NotStarted,
+                status_changed_at: None,
+                completed_at: None,
+                created_at: now.into(),
+                updated_at: now.into(),
+                deleted_at: None,
+            }],
+        }
+    }
+
+    fn png(color_type: png::ColorType, width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(pixels).unwrap();
+        writer.finish().unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn render_session_summary_produces_a_pdf() {
+        let document = render_session_summary(&summary(2), None);
+
+        assert!(document.starts_with(b"%PDF-"));
+        assert!(document.ends_with(b"%%EOF"));
+        assert!(contains(&document, b"/Count 1"));
+        assert!(contains(&document, b"/BaseFont /Helvetica-Bold"));
+    }
+
+    #[test]
+    fn render_session_summary_continues_onto_new_pages() {
+        let document = render_session_summary(&summary(60), None);
+
+        assert!(contains(&document, b"/Count 4"));
+    }
+
+    #[test]
+    fn render_session_summary_embeds_the_logo_with_its_transparency() {
+        let logo = Logo::decode(&png(
+            png::ColorType::Rgba,
+            2,
+            1,
+            &[255, 0, 0, 255, 0, 0, 255, 0],
+        ))
+        .unwrap();
+
+        let document = render_session_summary(&summary(1), Some(&logo));
+
+        assert!(contains(&document, b"/XObject"));
+        assert!(contains(&document, b"/SMask"));
+    }
+
+    #[test]
+    fn decode_reads_png_color_and_alpha_separately() {
+        let logo = Logo::decode(&png(
+            png::ColorType::GrayscaleAlpha,
+            2,
+            2,
+            &[10, 255, 20, 128, 30, 0, 40, 255],
+        ))
+        .unwrap();
+
+        assert_eq!((logo.width, logo.height, logo.grayscale), (2, 2, true));
+        assert_eq!(logo.filter, Filter::FlateDecode);
+        assert!(logo.alpha.is_some());
+    }
+
+    #[test]
+    fn decode_reads_the_size_of_a_jpeg_from_its_frame_header() {
+        let jpeg = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, // APP0
+            0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x30, 0x01, 0x40, 0x03, // SOF0, 320x48 RGB
+        ];
+
+        let logo = Logo::decode(&jpeg).unwrap();
+
+        assert_eq!((logo.width, logo.height, logo.grayscale), (320, 48, false));
+        assert_eq!(logo.filter, Filter::DctDecode);
+        assert_eq!(logo.size(), (LOGO_MAX_WIDTH, 24.0));
+    }
+
+    #[test]
+    fn decode_rejects_other_image_formats() {
+        assert!(Logo::decode(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>").is_none());
+    }
+
+    #[tokio::test]
+    async fn fetch_logo_only_fetches_https_urls() {
+        assert!(fetch_logo("http://169.254.169.254/logo.png")
+            .await
+            .is_none());
+        assert!(fetch_logo("file:///etc/passwd").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn download_logo_refuses_hosts_without_a_public_address() {
+        for url in [
+            "https://169.254.169.254/logo.png",
+            "https://127.0.0.1/logo.png",
+            "https://10.0.0.8/logo.png",
+            "https://[::1]/logo.png",
+            "https://[::ffff:192.168.1.1]/logo.png",
+            "https://localhost/logo.png",
+        ] {
+            let err = download_logo(url).await.unwrap_err();
+
+            assert!(
+                err.ends_with("does not have a public address"),
+                "{url}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn is_public_only_accepts_addresses_on_the_public_internet() {
+        for ip in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "0.0.0.0",
+            "100.64.0.1",
+            "172.16.0.1",
+            "192.168.0.1",
+            "fc00::1",
+            "fe80::1",
+            "::",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn wrap_breaks_between_words_and_splits_long_words() {
+        let style = Style {
+            size: 10.0,
+            ..BODY_STYLE
+        };
+
+        // Narrow letters like i, j and f let more of a word fit
+        assert_eq!(
+            wrap("a quick brown fox\nabcdefghijklmno", style, 55.0),
+            ["a quick", "brown fox", "abcdefghijkl", "mno"]
+        );
+    }
+
+    #[test]
+    fn wrap_measures_bold_text_with_the_bold_widths() {
+        let style = Style {
+            size: 10.0,
+            ..BODY_STYLE
+        };
+        let bold = Style {
+            font: BOLD_FONT,
+            ..style
+        };
+
+        assert_eq!(wrap("mmmmm", style, 42.0), ["mmmmm"]);
+        assert_eq!(wrap("mmmmm", bold, 42.0), ["mmmm", "m"]);
+    }
+
+    #[test]
+    fn win_ansi_keeps_latin_1_and_replaces_what_the_standard_fonts_lack() {
+        assert_eq!(win_ansi("Zoë – “ok”"), b"Zo\xEB \x96 \x93ok\x94");
+        assert_eq!(win_ansi("Nguyễn 👋"), b"Nguy?n ?");
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+}
@@ -0,0 +1,111 @@
+//! Request ids, which tie a response and its problem details to the server's logs.
+//!
+//! A request keeps the `X-Request-Id` that a client or proxy in front of the server sent, or
+//! is given a new UUID, and the id is returned in the response's `X-Request-Id` header.
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use entity::Id;
+use std::future::Future;
+
+use log::*;
+
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longer ids sent by a client are replaced rather than echoed back.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request being handled, if any.
+pub(crate) fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Runs `future` as part of handling the request with `id`.
+pub(crate) async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(id, future).await
+}
+
+/// Middleware that assigns every request its id.
+pub(crate) async fn assign(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(str::to_owned)
+        .unwrap_or_else(|| Id::new_v4().to_string());
+
+    trace!(
+        "Request {} {} has id {}",
+        request.method(),
+        request.uri(),
+        id
+    );
+
+    let mut response = scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request as HttpRequest, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|| async { current().unwrap_or_default() }))
+            .layer(middleware::from_fn(assign))
+    }
+
+    #[tokio::test]
+    async fn assign_keeps_a_valid_request_id() {
+        let response = app()
+            .oneshot(
+                HttpRequest::get("/")
+                    .header(REQUEST_ID_HEADER, "abc-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc-123");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"abc-123");
+    }
+
+    #[tokio::test]
+    async fn assign_replaces_a_missing_or_invalid_request_id() {
+        let response = app()
+            .oneshot(
+                HttpRequest::get("/")
+                    .header(REQUEST_ID_HEADER, "has spaces")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Id::parse_str(id).is_ok());
+    }
+}
@@ -1,27 +1,36 @@
+use crate::collaboration::NoteRooms;
+use crate::versioning::VersionedRouter;
 use crate::AppState;
 use axum::{
-    routing::{delete, get, post, put},
-    Router,
+    routing::{delete, get, patch, post, put},
+    Extension, Json, Router,
 };
 use axum_login::login_required;
 use entity_api::user::Backend;
+use service::config::{ApiVersion, Lifecycle};
 use tower_http::services::ServeDir;
 
 use crate::controller::{
-    action_controller, agreement_controller, coaching_session_controller, note_controller,
-    organization, organization_controller, overarching_goal_controller, user_controller,
-    user_session_controller,
+    action_controller, agreement_controller, coaching_session,
+    coaching_session::attachment_controller, coaching_session::summary_controller,
+    coaching_session_controller, note_controller, organization, organization_controller,
+    overarching_goal_controller, search_controller, user_controller, user_session_controller,
 };
 
 use utoipa::{
-    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    openapi::{
+        security::{ApiKey, ApiKeyValue, SecurityScheme},
+        Content, Ref, RefOr,
+    },
     Modify, OpenApi,
 };
 use utoipa_rapidoc::RapiDoc;
 
+use log::*;
+
 use self::organization::coaching_relationship_controller;
 
-// This is the global definition of our OpenAPI spec. To be a part
+// This is the definition of the OpenAPI spec of version 0.0.1 of the API. To be a part
 // of the rendered spec, a path and schema must be listed here.
 #[derive(OpenApi)]
 #[openapi(
@@ -31,35 +40,79 @@ use self::organization::coaching_relationship_controller;
         paths(
             action_controller::create,
             action_controller::update,
+            action_controller::patch,
             action_controller::index,
             action_controller::read,
             action_controller::update_status,
+            action_controller::history,
             action_controller::delete,
+            action_controller::restore,
             agreement_controller::create,
             agreement_controller::update,
+            agreement_controller::patch,
             agreement_controller::index,
             agreement_controller::read,
             agreement_controller::delete,
+            agreement_controller::restore,
+            agreement_controller::revisions,
+            agreement_controller::diff_revisions,
+            agreement_controller::restore_revision,
+            agreement_controller::acknowledge,
             coaching_session_controller::index,
             coaching_session_controller::create,
+            coaching_session_controller::read,
+            coaching_session_controller::patch,
+            coaching_session_controller::delete,
+            coaching_session_controller::restore,
+            coaching_session::attachment_controller::create,
+            coaching_session::attachment_controller::index,
+            coaching_session::attachment_controller::download,
+            coaching_session::attachment_controller::delete,
+            coaching_session::attachment_controller::restore,
+            coaching_session::summary_controller::show,
             note_controller::create,
             note_controller::update,
+            note_controller::patch,
             note_controller::index,
             note_controller::read,
+            note_controller::delete,
+            note_controller::restore,
+            note_controller::collaborate,
+            note_controller::revisions,
+            note_controller::diff_revisions,
+            note_controller::restore_revision,
             organization_controller::index,
             organization_controller::read,
             organization_controller::create,
             organization_controller::update,
+            organization_controller::patch,
             organization_controller::delete,
+            organization_controller::restore,
+            organization::audit_event_controller::index,
             organization::coaching_relationship_controller::create,
             organization::coaching_relationship_controller::index,
             organization::coaching_relationship_controller::read,
+            organization::coaching_relationship_controller::update,
+            organization::coaching_relationship_controller::delete,
+            organization::coaching_relationship_controller::restore,
+            organization::coaching_relationship_controller::reassign,
+            organization::coaching_relationship_controller::export,
+            organization::report_controller::index,
             overarching_goal_controller::create,
             overarching_goal_controller::update,
+            overarching_goal_controller::patch,
             overarching_goal_controller::index,
             overarching_goal_controller::read,
+            overarching_goal_controller::delete,
+            overarching_goal_controller::restore,
             overarching_goal_controller::update_status,
+            overarching_goal_controller::history,
+            overarching_goal_controller::revisions,
+            overarching_goal_controller::diff_revisions,
+            overarching_goal_controller::restore_revision,
+            search_controller::index,
             user_controller::create,
+            user_controller::caseload,
             user_session_controller::login,
             user_session_controller::logout,
         ),
@@ -67,16 +120,49 @@ use self::organization::coaching_relationship_controller;
             schemas(
                 entity::actions::Model,
                 entity::agreements::Model,
+                entity::attachments::Model,
+                entity::audit_action::AuditAction,
+                entity::audit_events::Model,
                 entity::coaching_sessions::Model,
                 entity::coaching_relationships::Model,
                 entity::notes::Model,
                 entity::organizations::Model,
                 entity::overarching_goals::Model,
+                entity::revisions::Model,
+                entity::status_transitions::Model,
                 entity::users::Model,
+                entity_api::analytics::PeriodReport,
+                entity_api::caseload::CaseloadEntry,
+                entity_api::organization::DeletionImpact,
+                entity_api::search::SearchResult,
+                entity_api::coaching_relationship::Reassignment,
+                entity_api::error::FieldError,
                 entity_api::user::Credentials,
+                attachment_controller::AttachmentUpload,
+                crate::error::Problem,
+                crate::params::action::NewAction,
+                crate::params::action::UpdateAction,
+                crate::params::action::PatchAction,
+                crate::params::agreement::NewAgreement,
+                crate::params::agreement::UpdateAgreement,
+                crate::params::agreement::PatchAgreement,
+                crate::params::coaching_relationship::NewCoachingRelationship,
+                crate::params::coaching_relationship::UpdateCoachingRelationship,
+                crate::params::coaching_session::NewCoachingSession,
+                crate::params::coaching_session::PatchCoachingSession,
+                crate::params::note::NewNote,
+                crate::params::note::UpdateNote,
+                crate::params::note::PatchNote,
+                crate::params::organization::NewOrganization,
+                crate::params::organization::UpdateOrganization,
+                crate::params::organization::PatchOrganization,
+                crate::params::overarching_goal::NewOverarchingGoal,
+                crate::params::overarching_goal::UpdateOverarchingGoal,
+                crate::params::overarching_goal::PatchOverarchingGoal,
+                crate::params::user::NewUser,
             )
         ),
-        modifiers(&SecurityAddon),
+        modifiers(&SecurityAddon, &ProblemResponses),
         tags(
             (name = "refactor_platform", description = "Refactor Coaching & Mentorship API")
         )
@@ -101,20 +187,109 @@ impl Modify for SecurityAddon {
     }
 }
 
+struct ProblemResponses;
+
+// Every error response carries an RFC 7807 problem details body, so rather than repeating it on
+// each path it is added here to all 4xx and 5xx responses.
+impl Modify for ProblemResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for path_item in openapi.paths.paths.values_mut() {
+            for operation in path_item.operations.values_mut() {
+                for (status, response) in operation.responses.responses.iter_mut() {
+                    if let (true, RefOr::T(response)) =
+                        (status.starts_with('4') || status.starts_with('5'), response)
+                    {
+                        response.content.insert(
+                            "application/problem+json".to_owned(),
+                            Content::new(Ref::from_schema_name("web.error.Problem")),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The routes of a version of the API along with the OpenAPI document describing them.
+struct VersionedApi {
+    version: &'static str,
+    routes: fn(AppState) -> Router,
+    openapi: fn() -> utoipa::openapi::OpenApi,
+}
+
+// Every version of the API that is still served, as listed in `service::config`, needs an
+// entry here. A version that changes an endpoint in a way its clients can't handle registers
+// its own handler for that endpoint, and shares the rest with the version before it.
+const VERSIONED_APIS: [VersionedApi; 1] = [VersionedApi {
+    version: "0.0.1",
+    routes: v0_0_1_routes,
+    openapi: ApiDoc::openapi,
+}];
+
+fn versioned_api(version: &str) -> Option<&'static VersionedApi> {
+    VERSIONED_APIS.iter().find(|api| api.version == version)
+}
+
+/// The OpenAPI document of a version of the API, naming the version it describes.
+fn openapi(api: &VersionedApi) -> utoipa::openapi::OpenApi {
+    let mut openapi = (api.openapi)();
+    openapi.info.version = api.version.to_owned();
+    openapi
+}
+
 pub fn define_routes(app_state: AppState) -> Router {
+    let current = ApiVersion::versions()
+        .iter()
+        .map(|released| released.version)
+        .find(|version| *version == app_state.config.api_version())
+        .unwrap_or(ApiVersion::default_version());
+
+    let mut versions = VersionedRouter::new(current, ApiVersion::versions());
+    let mut docs = Router::new();
+
+    for released in ApiVersion::versions()
+        .iter()
+        .filter(|released| released.lifecycle != Lifecycle::Sunset)
+    {
+        let api = versioned_api(released.version)
+            .unwrap_or_else(|| panic!("API version {} has no routes registered", released.version));
+        info!(
+            "Serving API version {} ({:?})",
+            api.version, released.lifecycle
+        );
+
+        let openapi = openapi(api);
+        docs = docs.route(
+            &format!("/api-docs/{}/openapi.json", api.version),
+            get(move || async move { Json(openapi) }),
+        );
+        versions = versions.version(api.version, (api.routes)(app_state.clone()));
+    }
+
+    Router::new()
+        // FIXME: protect the OpenAPI web UI
+        .merge(RapiDoc::new(&format!("/api-docs/{current}/openapi.json")).path("/rapidoc"))
+        .merge(docs)
+        .fallback_service(versions.into_router())
+}
+
+fn v0_0_1_routes(app_state: AppState) -> Router {
     Router::new()
         .merge(action_routes(app_state.clone()))
         .merge(agreement_routes(app_state.clone()))
         .merge(organization_routes(app_state.clone()))
         .merge(note_routes(app_state.clone()))
         .merge(organization_coaching_relationship_routes(app_state.clone()))
+        .merge(organization_report_routes(app_state.clone()))
+        .merge(organization_audit_event_routes(app_state.clone()))
         .merge(overarching_goal_routes(app_state.clone()))
+        .merge(search_routes(app_state.clone()))
         .merge(user_routes(app_state.clone()))
         .merge(user_session_routes())
         .merge(user_session_protected_routes())
         .merge(coaching_sessions_routes(app_state.clone()))
-        // FIXME: protect the OpenAPI web UI
-        .merge(RapiDoc::with_openapi("/api-docs/openapi2.json", ApiDoc::openapi()).path("/rapidoc"))
+        .merge(coaching_session_attachment_routes(app_state.clone()))
+        .merge(coaching_session_summary_routes(app_state.clone()))
         .fallback_service(static_routes())
 }
 
@@ -122,10 +297,13 @@ fn action_routes(app_state: AppState) -> Router {
     Router::new()
         .route("/actions", post(action_controller::create))
         .route("/actions/:id", put(action_controller::update))
+        .route("/actions/:id", patch(action_controller::patch))
         .route("/actions", get(action_controller::index))
         .route("/actions/:id", get(action_controller::read))
         .route("/actions/:id/status", put(action_controller::update_status))
+        .route("/actions/:id/history", get(action_controller::history))
         .route("/actions/:id", delete(action_controller::delete))
+        .route("/actions/:id/restore", post(action_controller::restore))
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(app_state)
 }
@@ -134,9 +312,30 @@ fn agreement_routes(app_state: AppState) -> Router {
     Router::new()
         .route("/agreements", post(agreement_controller::create))
         .route("/agreements/:id", put(agreement_controller::update))
+        .route("/agreements/:id", patch(agreement_controller::patch))
         .route("/agreements", get(agreement_controller::index))
         .route("/agreements/:id", get(agreement_controller::read))
         .route("/agreements/:id", delete(agreement_controller::delete))
+        .route(
+            "/agreements/:id/restore",
+            post(agreement_controller::restore),
+        )
+        .route(
+            "/agreements/:id/revisions",
+            get(agreement_controller::revisions),
+        )
+        .route(
+            "/agreements/:id/revisions/diff",
+            get(agreement_controller::diff_revisions),
+        )
+        .route(
+            "/agreements/:id/revisions/:revision_id/restore",
+            post(agreement_controller::restore_revision),
+        )
+        .route(
+            "/agreements/:id/acknowledge",
+            post(agreement_controller::acknowledge),
+        )
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(app_state)
 }
@@ -151,6 +350,59 @@ pub fn coaching_sessions_routes(app_state: AppState) -> Router {
             "/coaching_sessions",
             get(coaching_session_controller::index),
         )
+        .route(
+            "/coaching_sessions/:id",
+            get(coaching_session_controller::read),
+        )
+        .route(
+            "/coaching_sessions/:id",
+            patch(coaching_session_controller::patch),
+        )
+        .route(
+            "/coaching_sessions/:id",
+            delete(coaching_session_controller::delete),
+        )
+        .route(
+            "/coaching_sessions/:id/restore",
+            post(coaching_session_controller::restore),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(app_state)
+}
+
+fn coaching_session_attachment_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/coaching_sessions/:coaching_session_id/attachments",
+            post(attachment_controller::create)
+                .layer(attachment_controller::upload_body_limit(&app_state.config)),
+        )
+        .route(
+            "/coaching_sessions/:coaching_session_id/attachments",
+            get(attachment_controller::index),
+        )
+        .route(
+            "/coaching_sessions/:coaching_session_id/attachments/:attachment_id",
+            get(attachment_controller::download),
+        )
+        .route(
+            "/coaching_sessions/:coaching_session_id/attachments/:attachment_id",
+            delete(attachment_controller::delete),
+        )
+        .route(
+            "/coaching_sessions/:coaching_session_id/attachments/:attachment_id/restore",
+            post(attachment_controller::restore),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(app_state)
+}
+
+fn coaching_session_summary_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/coaching_sessions/:coaching_session_id/summary.pdf",
+            get(summary_controller::show),
+        )
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(app_state)
 }
@@ -159,9 +411,23 @@ fn note_routes(app_state: AppState) -> Router {
     Router::new()
         .route("/notes", post(note_controller::create))
         .route("/notes/:id", put(note_controller::update))
+        .route("/notes/:id", patch(note_controller::patch))
         .route("/notes", get(note_controller::index))
         .route("/notes/:id", get(note_controller::read))
+        .route("/notes/:id", delete(note_controller::delete))
+        .route("/notes/:id/restore", post(note_controller::restore))
+        .route("/notes/:id/collaborate", get(note_controller::collaborate))
+        .route("/notes/:id/revisions", get(note_controller::revisions))
+        .route(
+            "/notes/:id/revisions/diff",
+            get(note_controller::diff_revisions),
+        )
+        .route(
+            "/notes/:id/revisions/:revision_id/restore",
+            post(note_controller::restore_revision),
+        )
         .route_layer(login_required!(Backend, login_url = "/login"))
+        .layer(Extension(NoteRooms::default()))
         .with_state(app_state)
 }
 
@@ -179,6 +445,46 @@ fn organization_coaching_relationship_routes(app_state: AppState) -> Router {
             "/organizations/:organization_id/coaching_relationships/:relationship_id",
             get(organization::coaching_relationship_controller::read),
         )
+        .route(
+            "/organizations/:organization_id/coaching_relationships/:relationship_id",
+            put(organization::coaching_relationship_controller::update),
+        )
+        .route(
+            "/organizations/:organization_id/coaching_relationships/:relationship_id",
+            delete(organization::coaching_relationship_controller::delete),
+        )
+        .route(
+            "/organizations/:organization_id/coaching_relationships/:relationship_id/restore",
+            post(organization::coaching_relationship_controller::restore),
+        )
+        .route(
+            "/organizations/:organization_id/coaching_relationships/:relationship_id/reassign",
+            post(organization::coaching_relationship_controller::reassign),
+        )
+        .route(
+            "/organizations/:organization_id/coaching_relationships/:relationship_id/export",
+            get(organization::coaching_relationship_controller::export),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(app_state)
+}
+
+fn organization_report_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/organizations/:organization_id/reports",
+            get(organization::report_controller::index),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(app_state)
+}
+
+fn organization_audit_event_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/organizations/:organization_id/audit_events",
+            get(organization::audit_event_controller::index),
+        )
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(app_state)
 }
@@ -193,10 +499,15 @@ pub fn organization_routes(app_state: AppState) -> Router {
         .route("/organizations/:id", get(organization_controller::read))
         .route("/organizations", post(organization_controller::create))
         .route("/organizations/:id", put(organization_controller::update))
+        .route("/organizations/:id", patch(organization_controller::patch))
         .route(
             "/organizations/:id",
             delete(organization_controller::delete),
         )
+        .route(
+            "/organizations/:id/restore",
+            post(organization_controller::restore),
+        )
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(app_state)
 }
@@ -211,6 +522,10 @@ pub fn overarching_goal_routes(app_state: AppState) -> Router {
             "/overarching_goals/:id",
             put(overarching_goal_controller::update),
         )
+        .route(
+            "/overarching_goals/:id",
+            patch(overarching_goal_controller::patch),
+        )
         .route(
             "/overarching_goals",
             get(overarching_goal_controller::index),
@@ -219,10 +534,41 @@ pub fn overarching_goal_routes(app_state: AppState) -> Router {
             "/overarching_goals/:id",
             get(overarching_goal_controller::read),
         )
+        .route(
+            "/overarching_goals/:id",
+            delete(overarching_goal_controller::delete),
+        )
+        .route(
+            "/overarching_goals/:id/restore",
+            post(overarching_goal_controller::restore),
+        )
         .route(
             "/overarching_goals/:id/status",
             put(overarching_goal_controller::update_status),
         )
+        .route(
+            "/overarching_goals/:id/history",
+            get(overarching_goal_controller::history),
+        )
+        .route(
+            "/overarching_goals/:id/revisions",
+            get(overarching_goal_controller::revisions),
+        )
+        .route(
+            "/overarching_goals/:id/revisions/diff",
+            get(overarching_goal_controller::diff_revisions),
+        )
+        .route(
+            "/overarching_goals/:id/revisions/:revision_id/restore",
+            post(overarching_goal_controller::restore_revision),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(app_state)
+}
+
+pub fn search_routes(app_state: AppState) -> Router {
+    Router::new()
+        .route("/search", get(search_controller::index))
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(app_state)
 }
@@ -230,6 +576,7 @@ pub fn overarching_goal_routes(app_state: AppState) -> Router {
 pub fn user_routes(app_state: AppState) -> Router {
     Router::new()
         .route("/users", post(user_controller::create))
+        .route("/users/me/caseload", get(user_controller::caseload))
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(app_state)
 }
@@ -250,6 +597,47 @@ pub fn static_routes() -> Router {
     Router::new().nest_service("/", ServeDir::new("./"))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_doc_documents_problem_details_for_error_responses() {
+        let openapi = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+        let not_found = &openapi["paths"]["/actions/{id}"]["get"]["responses"]["404"];
+        assert_eq!(
+            not_found["content"]["application/problem+json"]["schema"]["$ref"],
+            "#/components/schemas/web.error.Problem"
+        );
+        assert!(
+            openapi["paths"]["/actions/{id}"]["get"]["responses"]["200"]["content"]
+                .get("application/problem+json")
+                .is_none()
+        );
+
+        for name in [
+            "web.error.Problem",
+            "entity_api.error.FieldError",
+            "web.params.action.NewAction",
+        ] {
+            assert!(openapi["components"]["schemas"].get(name).is_some());
+        }
+    }
+
+    #[test]
+    fn every_served_api_version_has_routes_and_its_own_openapi_document() {
+        for released in ApiVersion::versions()
+            .iter()
+            .filter(|released| released.lifecycle != Lifecycle::Sunset)
+        {
+            let api = versioned_api(released.version).unwrap();
+
+            assert_eq!(openapi(api).info.version, released.version);
+        }
+    }
+}
+
 #[cfg(test)]
 // We need to gate seaORM's mock feature behind conditional compilation because
 // the feature removes the Clone trait implementation from seaORM's DatabaseConnection.
@@ -258,24 +646,36 @@ pub fn static_routes() -> Router {
 mod organization_endpoints_tests {
 
     use super::*;
+    use crate::etag;
     use anyhow::Ok;
     use axum_login::{
         tower_sessions::{Expiry, MemoryStore, SessionManagerLayer},
         AuthManagerLayerBuilder,
     };
     use chrono::Utc;
-    use entity::{organizations, users, Id};
+    use entity::{
+        actions, agreements, audit_action::AuditAction, audit_events, coaching_relationships,
+        coaching_sessions, notes, organization_admins, organizations, overarching_goals, users,
+        visibility::Visibility, Id,
+    };
     use entity_api::user::Backend;
     use log::{debug, LevelFilter};
     use password_auth::generate_hash;
     use reqwest::{header, header::HeaderValue, Url};
-    use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase, MockExecResult};
+    use sea_orm::{
+        DatabaseBackend, DatabaseConnection, DbBackend, DbErr, ExecResult, IntoMockRow,
+        MockDatabase, MockDatabaseConnection, MockDatabaseTrait, MockExecResult, QueryResult,
+        Statement, Transaction,
+    };
     use serde_json::json;
     use service::{
         config::{ApiVersion, Config},
         logging::Logger,
     };
-    use std::{net::SocketAddr, sync::Arc, sync::Once};
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex, Once},
+    };
     use time::Duration;
     use tokio::net::TcpListener;
 
@@ -386,6 +786,66 @@ mod organization_endpoints_tests {
         }
     }
 
+    // A MockDatabase that also keeps every statement it is sent, with its values filled in, so
+    // that a test can check what an endpoint asked of the database while the server still
+    // holds the connection.
+    #[derive(Debug)]
+    struct RecordingDatabase {
+        mock: MockDatabase,
+        statements: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl RecordingDatabase {
+        fn connect(mock: MockDatabase) -> (Arc<DatabaseConnection>, Arc<Mutex<Vec<String>>>) {
+            let statements = Arc::new(Mutex::new(Vec::new()));
+            let recording = RecordingDatabase {
+                mock,
+                statements: Arc::clone(&statements),
+            };
+            let db = DatabaseConnection::MockDatabaseConnection(Arc::new(
+                MockDatabaseConnection::new(recording),
+            ));
+
+            (Arc::new(db), statements)
+        }
+    }
+
+    impl MockDatabaseTrait for RecordingDatabase {
+        fn execute(&mut self, counter: usize, stmt: Statement) -> Result<ExecResult, DbErr> {
+            self.statements.lock().unwrap().push(stmt.to_string());
+            self.mock.execute(counter, stmt)
+        }
+
+        fn query(&mut self, counter: usize, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
+            self.statements.lock().unwrap().push(stmt.to_string());
+            self.mock.query(counter, stmt)
+        }
+
+        fn begin(&mut self) {
+            self.mock.begin()
+        }
+
+        fn commit(&mut self) {
+            self.mock.commit()
+        }
+
+        fn rollback(&mut self) {
+            self.mock.rollback()
+        }
+
+        fn drain_transaction_log(&mut self) -> Vec<Transaction> {
+            self.mock.drain_transaction_log()
+        }
+
+        fn get_database_backend(&self) -> DbBackend {
+            self.mock.get_database_backend()
+        }
+
+        fn ping(&self) -> Result<(), DbErr> {
+            self.mock.ping()
+        }
+    }
+
     // Purpose: adds an Organization instance to a mock DB and tests the API to successfully
     // retrieve it by a specific ID and as expected and valid JSON.
     #[tokio::test]
@@ -403,6 +863,7 @@ mod organization_endpoints_tests {
             name: "Organization One".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         };
 
@@ -434,6 +895,11 @@ mod organization_endpoints_tests {
             .send()
             .await?;
 
+        assert_eq!(
+            response.headers()[header::ETAG],
+            etag::of(&organization.updated_at)
+        );
+
         // We need to parse the values to serde_json::Value to compare them
         // so that the attribute order does not matter.
         let parsed_result: serde_json::Value =
@@ -449,6 +915,49 @@ mod organization_endpoints_tests {
         Ok(())
     }
 
+    // Purpose: tests that the API responds to a request for an Organization that doesn't exist
+    // with a 404 and problem details rather than a 200 with a null body.
+    #[tokio::test]
+    async fn read_returns_not_found_for_a_missing_organization() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let endpoint_path = format!("/organizations/{}", Id::new_v4());
+
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+
+        let db = Arc::new(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                // initial login auth check
+                .append_query_results([vec![user.clone()]])
+                // check auth for the next endpoint call
+                .append_query_results([vec![user.clone()]])
+                .append_query_results([Vec::<organizations::Model>::new()])
+                .into_connection(),
+        );
+
+        let app_state = AppState::new(config, &db);
+
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+
+        test_client_server.login(&user).await?;
+
+        let response = test_client_server
+            .client
+            .get(test_client_server.url(endpoint_path).unwrap())
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let problem: serde_json::Value = serde_json::from_str(&response.text().await?).unwrap();
+        assert_eq!(problem["code"], "record_not_found");
+
+        Ok(())
+    }
+
     // Purpose: adds multiple Organization instances to a mock DB and tests the API to successfully
     // retrieve all of them as expected and valid JSON without specifying any particular ID.
     #[tokio::test]
@@ -463,6 +972,7 @@ mod organization_endpoints_tests {
             name: "Organization One".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         };
         let organization2 = organizations::Model {
@@ -470,6 +980,7 @@ mod organization_endpoints_tests {
             name: "Organization Two".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         };
         let organization3 = organizations::Model {
@@ -477,6 +988,7 @@ mod organization_endpoints_tests {
             name: "Organization Three".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         };
 
@@ -542,6 +1054,7 @@ mod organization_endpoints_tests {
             name: "Organization Two".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         }]];
 
@@ -551,16 +1064,18 @@ mod organization_endpoints_tests {
             name: "Organization Three".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         }]];
 
-        let exec_results1 = [MockExecResult {
-            last_insert_id: 2,
-            rows_affected: 1,
-        }];
-
-        let exec_results2 = [MockExecResult {
-            last_insert_id: 3,
+        // Notes, agreements, actions, overarching goals, attachments, coaching sessions and then
+        // coaching relationships
+        let archived_content = [1, 2, 0, 0, 0, 1, 1].map(|rows_affected| MockExecResult {
+            last_insert_id: 0,
+            rows_affected,
+        });
+        let audit_event = [MockExecResult {
+            last_insert_id: 0,
             rows_affected: 1,
         }];
 
@@ -568,11 +1083,19 @@ mod organization_endpoints_tests {
             MockDatabase::new(DatabaseBackend::Postgres)
                 .append_query_results(user_results1.clone()) // For the initial login auth check
                 .append_query_results(user_results1.clone()) // For the AuthSession check done with the next endpoint call
+                .append_query_results(organization_results1.clone()) // For checking that the user is an admin of the first organization
+                .append_query_results(admin_results(user.id, user_id1))
                 .append_query_results(organization_results1.clone()) // For comparing the first organization query results with
-                .append_exec_results(exec_results1) // For comparing the first organization query execution results with
+                .append_query_results(organization_results1.clone()) // For the first organization as it is soft deleted
+                .append_exec_results(archived_content.clone()) // For everything in it deleted with it
+                .append_exec_results(audit_event.clone()) // For recording the deletion in the audit log
                 .append_query_results(user_results1.clone()) // For the AuthSession check done with the next endpoint call
+                .append_query_results(organization_results2.clone()) // For checking that the user is an admin of the second organization
+                .append_query_results(admin_results(user.id, user_id2))
                 .append_query_results(organization_results1.clone()) // For compare the second organization query results with
-                .append_exec_results(exec_results2) // For comparing the second organization query execution results with
+                .append_query_results(organization_results2.clone()) // For the second organization as it is soft deleted
+                .append_exec_results(archived_content) // For everything in it deleted with it
+                .append_exec_results(audit_event) // For recording the deletion in the audit log
                 .into_connection(),
         );
 
@@ -593,6 +1116,10 @@ mod organization_endpoints_tests {
                         .url(format!("/organizations/{}", user_id1))
                         .unwrap(),
                 )
+                .header(
+                    header::IF_MATCH,
+                    etag::of(&organization_results1[0][0].updated_at),
+                )
                 .send()
                 .await?
                 .text()
@@ -617,6 +1144,7 @@ mod organization_endpoints_tests {
                         .url(format!("/organizations/{}", user_id2))
                         .unwrap(),
                 )
+                .header(header::IF_MATCH, "*")
                 .send()
                 .await?
                 .text()
@@ -636,52 +1164,58 @@ mod organization_endpoints_tests {
         Ok(())
     }
 
-    // Purpose: creates multiple new Organization instances to a mock DB by calling
-    // the post endpoint supplying the appropriate instance as a JSON payload.
+    fn admin_results(user_id: Id, organization_id: Id) -> [Vec<organization_admins::Model>; 1] {
+        [vec![organization_admins::Model {
+            id: Id::new_v4(),
+            organization_id,
+            user_id,
+            created_at: Utc::now().into(),
+        }]]
+    }
+
+    // Purpose: tests that an admin of an Organization can read its audit log, newest first,
+    // while anyone else is refused.
     #[tokio::test]
-    async fn create_new_organizations_successfully() -> anyhow::Result<()> {
+    async fn only_admins_can_read_an_organizations_audit_events() -> anyhow::Result<()> {
         let mut config = Config::default();
         let now = Utc::now();
         enable_test_logging(&mut config);
 
         let user = TestClientServer::get_user().expect("Creating a new test user failed");
-        let user_results1 = [vec![user.clone()]];
+        let user_results = [vec![user.clone()]];
 
-        let organization_results1 = [vec![organizations::Model {
+        let organization_results = [vec![organizations::Model {
             id: Id::new_v4(),
-            name: "New Organization Five".to_owned(),
+            name: "Organization One".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         }]];
+        let organization_id = organization_results[0][0].id;
 
-        let organization_results2 = [vec![organizations::Model {
+        let audit_event = audit_events::Model {
             id: Id::new_v4(),
-            name: "Second Organization Six".to_owned(),
+            organization_id: Some(organization_id),
+            actor_id: Some(user.id),
+            action: AuditAction::Update,
+            entity_type: "actions".to_owned(),
+            entity_id: Id::new_v4(),
+            changed_fields: json!(["body"]),
+            request_id: Some("req-1".to_owned()),
             created_at: now.into(),
-            updated_at: now.into(),
-            logo: None,
-        }]];
-
-        let exec_results1 = [MockExecResult {
-            last_insert_id: 5,
-            rows_affected: 1,
-        }];
-
-        let exec_results2 = [MockExecResult {
-            last_insert_id: 6,
-            rows_affected: 1,
-        }];
+        };
 
         let db = Arc::new(
             MockDatabase::new(DatabaseBackend::Postgres)
-                .append_query_results(user_results1.clone())
-                .append_query_results(user_results1.clone())
-                .append_query_results(organization_results1.clone())
-                .append_exec_results(exec_results1)
-                .append_query_results(user_results1.clone())
-                .append_query_results(organization_results2.clone())
-                .append_exec_results(exec_results2)
+                .append_query_results(user_results.clone()) // For the initial login auth check
+                .append_query_results(user_results.clone()) // For the AuthSession check done with the next endpoint call
+                .append_query_results(organization_results.clone()) // For checking that the user is an admin of the organization
+                .append_query_results(admin_results(user.id, organization_id))
+                .append_query_results([vec![audit_event.clone()]])
+                .append_query_results(user_results.clone()) // For the AuthSession check done with the next endpoint call
+                .append_query_results(organization_results.clone()) // For finding that the user is not an admin
+                .append_query_results([Vec::<organization_admins::Model>::new()])
                 .into_connection(),
         );
 
@@ -691,32 +1225,195 @@ mod organization_endpoints_tests {
             .await
             .unwrap();
 
-        let response = test_client_server.login(&user).await?;
-        assert_eq!(response, ());
+        test_client_server.login(&user).await?;
 
-        {
-            let organization5 = &organization_results1[0][0];
+        let url = test_client_server
+            .url(format!(
+                "/organizations/{}/audit_events?entity_type=actions",
+                organization_id
+            ))
+            .unwrap();
 
-            let response_text = test_client_server
-                .client
-                .post(test_client_server.url("/organizations").unwrap())
-                .json(&organization5)
-                .send()
-                .await?
-                .text()
-                .await?;
+        let response = test_client_server.client.get(url.clone()).send().await?;
 
-            let parsed_response: serde_json::Value = serde_json::from_str(&response_text).unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.json::<serde_json::Value>().await?["data"],
+            json!([audit_event])
+        );
 
-            let expected_response = json!({
-                "status_code": 201,
-                "data": organization5
-            });
+        let response = test_client_server.client.get(url).send().await?;
 
-            assert_eq!(parsed_response, expected_response);
-        }
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
 
-        {
+        Ok(())
+    }
+
+    // Purpose: tests that a dry run of deleting an Organization reports what it would affect
+    // without deleting anything, and without needing an `If-Match` header.
+    #[tokio::test]
+    async fn dry_run_of_deleting_an_organization_reports_its_impact() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let now = Utc::now();
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+        let user_results = [vec![user.clone()]];
+
+        let organization_results = [vec![organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+            logo: None,
+        }]];
+        let organization_id = organization_results[0][0].id;
+
+        let count = |n: i64| {
+            vec![std::collections::BTreeMap::from([(
+                "num_items",
+                sea_orm::Value::BigInt(Some(n)),
+            )])]
+        };
+
+        let db = Arc::new(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results(user_results.clone()) // For the initial login auth check
+                .append_query_results(user_results.clone()) // For the AuthSession check done with the next endpoint call
+                .append_query_results(organization_results.clone()) // For checking that the user is an admin of the organization
+                .append_query_results(admin_results(user.id, organization_id))
+                .append_query_results(organization_results.clone()) // For the organization whose impact is counted
+                .append_query_results([
+                    count(1),
+                    count(4),
+                    count(6),
+                    count(2),
+                    count(3),
+                    count(1),
+                    count(2),
+                ])
+                .into_connection(),
+        );
+
+        let app_state = AppState::new(config, &db);
+
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+
+        test_client_server.login(&user).await?;
+
+        let response = test_client_server
+            .client
+            .delete(
+                test_client_server
+                    .url(format!("/organizations/{}?dry_run=true", organization_id))
+                    .unwrap(),
+            )
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.json::<serde_json::Value>().await?["data"],
+            json!({
+                "coaching_relationships": 1,
+                "coaching_sessions": 4,
+                "notes": 6,
+                "agreements": 2,
+                "actions": 3,
+                "overarching_goals": 1,
+                "attachments": 2,
+            })
+        );
+
+        Ok(())
+    }
+
+    // Purpose: creates multiple new Organization instances to a mock DB by calling
+    // the post endpoint supplying the appropriate instance as a JSON payload.
+    #[tokio::test]
+    async fn create_new_organizations_successfully() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let now = Utc::now();
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+        let user_results1 = [vec![user.clone()]];
+
+        let organization_results1 = [vec![organizations::Model {
+            id: Id::new_v4(),
+            name: "New Organization Five".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+            logo: None,
+        }]];
+
+        let organization_results2 = [vec![organizations::Model {
+            id: Id::new_v4(),
+            name: "Second Organization Six".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+            logo: None,
+        }]];
+
+        let exec_results1 = [MockExecResult {
+            last_insert_id: 5,
+            rows_affected: 1,
+        }];
+
+        let exec_results2 = [MockExecResult {
+            last_insert_id: 6,
+            rows_affected: 1,
+        }];
+
+        let db = Arc::new(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results(user_results1.clone())
+                .append_query_results(user_results1.clone())
+                .append_query_results(organization_results1.clone())
+                .append_exec_results(exec_results1)
+                .append_query_results(user_results1.clone())
+                .append_query_results(organization_results2.clone())
+                .append_exec_results(exec_results2)
+                .into_connection(),
+        );
+
+        let app_state = AppState::new(config, &db);
+
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+
+        let response = test_client_server.login(&user).await?;
+        assert_eq!(response, ());
+
+        {
+            let organization5 = &organization_results1[0][0];
+
+            let response_text = test_client_server
+                .client
+                .post(test_client_server.url("/organizations").unwrap())
+                .json(&organization5)
+                .send()
+                .await?
+                .text()
+                .await?;
+
+            let parsed_response: serde_json::Value = serde_json::from_str(&response_text).unwrap();
+
+            let expected_response = json!({
+                "status_code": 201,
+                "data": organization5
+            });
+
+            assert_eq!(parsed_response, expected_response);
+        }
+
+        {
             let organization6 = &organization_results2[0][0];
 
             let response_text = test_client_server
@@ -762,6 +1459,7 @@ mod organization_endpoints_tests {
                 name: "Organization Two".to_owned(),
                 created_at: now.into(),
                 updated_at: now.into(),
+                deleted_at: None,
                 logo: None,
             }],
             vec![organizations::Model {
@@ -769,6 +1467,7 @@ mod organization_endpoints_tests {
                 name: "Updated Organization Two".to_owned(),
                 created_at: now.into(),
                 updated_at: now.into(),
+                deleted_at: None,
                 logo: None,
             }],
         ];
@@ -801,22 +1500,29 @@ mod organization_endpoints_tests {
             name: "Updated Organization Two".to_owned(),
             created_at: now.into(),
             updated_at: now.into(),
+            deleted_at: None,
             logo: None,
         };
 
-        let response_text = test_client_server
+        let response = test_client_server
             .client
             .put(
                 test_client_server
                     .url(format!("/organizations/{}", user_id2))
                     .unwrap(),
             )
+            .header(header::IF_MATCH, etag::of(&organizations[0][0].updated_at))
             .json(&updated_organization2)
             .send()
-            .await?
-            .text()
             .await?;
 
+        assert_eq!(
+            response.headers()[header::ETAG],
+            etag::of(&updated_organization2.updated_at)
+        );
+
+        let response_text = response.text().await?;
+
         // We need to parse the values to serde_json::Value to compare them
         // so that the attribute order does not matter.
         let parsed_response: serde_json::Value =
@@ -831,4 +1537,681 @@ mod organization_endpoints_tests {
 
         Ok(())
     }
+
+    // Purpose: tests that the API refuses to update an Organization without an If-Match header,
+    // and refuses to overwrite a version of it other than the one named there.
+    #[tokio::test]
+    async fn update_requires_the_current_version_of_an_organization() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let now = Utc::now();
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization Two".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+            logo: None,
+        };
+
+        let db = Arc::new(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                // initial login auth check
+                .append_query_results([vec![user.clone()]])
+                // check auth for each of the next two endpoint calls
+                .append_query_results([vec![user.clone()]])
+                .append_query_results([vec![user.clone()]])
+                .append_query_results([vec![organization.clone()]])
+                .into_connection(),
+        );
+
+        let app_state = AppState::new(config, &db);
+
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+
+        test_client_server.login(&user).await?;
+
+        let url = test_client_server
+            .url(format!("/organizations/{}", organization.id))
+            .unwrap();
+        let body = json!({ "name": "Updated Organization Two" });
+
+        let response = test_client_server
+            .client
+            .put(&url)
+            .json(&body)
+            .send()
+            .await?;
+
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::PRECONDITION_REQUIRED
+        );
+
+        let problem: serde_json::Value = serde_json::from_str(&response.text().await?).unwrap();
+        assert_eq!(problem["code"], "missing_if_match");
+
+        let stale = etag::of(&(organization.updated_at - chrono::Duration::minutes(1)));
+        let response = test_client_server
+            .client
+            .put(&url)
+            .header(header::IF_MATCH, stale)
+            .json(&body)
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::PRECONDITION_FAILED);
+
+        let problem: serde_json::Value = serde_json::from_str(&response.text().await?).unwrap();
+        assert_eq!(problem["code"], "precondition_failed");
+
+        Ok(())
+    }
+
+    // Tests that PATCH /organizations/:id changes only the fields in the merge patch, clearing
+    // those set to null, and rejects null for a field that can't be cleared.
+    #[tokio::test]
+    async fn patch_changes_only_the_named_fields_of_an_organization() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let now = Utc::now();
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization Two".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+            logo: Some("https://example.com/logo.png".to_owned()),
+        };
+        let patched_organization = organizations::Model {
+            logo: None,
+            updated_at: (now + chrono::Duration::seconds(1)).into(),
+            ..organization.clone()
+        };
+
+        let db = Arc::new(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                // initial login auth check
+                .append_query_results([vec![user.clone()]])
+                // check auth, then read and write the organization for the merge patch
+                .append_query_results([vec![user.clone()]])
+                .append_query_results([vec![organization.clone()]])
+                .append_query_results([vec![patched_organization.clone()]])
+                .append_exec_results([MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 1,
+                }])
+                // check auth for the rejected merge patch
+                .append_query_results([vec![user.clone()]])
+                .into_connection(),
+        );
+
+        let app_state = AppState::new(config, &db);
+
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+
+        test_client_server.login(&user).await?;
+
+        let url = test_client_server
+            .url(format!("/organizations/{}", organization.id))
+            .unwrap();
+
+        let response = test_client_server
+            .client
+            .patch(&url)
+            .header(header::IF_MATCH, etag::of(&organization.updated_at))
+            .header(header::CONTENT_TYPE, "application/merge-patch+json")
+            .body(json!({ "logo": null }).to_string())
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ETAG],
+            etag::of(&patched_organization.updated_at)
+        );
+
+        let parsed_result: serde_json::Value =
+            serde_json::from_str(&response.text().await?).unwrap();
+        assert_eq!(parsed_result["data"]["name"], "Organization Two");
+        assert_eq!(parsed_result["data"]["logo"], serde_json::Value::Null);
+
+        let response = test_client_server
+            .client
+            .patch(&url)
+            .header(header::IF_MATCH, "*")
+            .header(header::CONTENT_TYPE, "application/merge-patch+json")
+            .body(json!({ "name": null }).to_string())
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let problem: serde_json::Value = serde_json::from_str(&response.text().await?).unwrap();
+        assert_eq!(problem["errors"][0]["field"], "name");
+
+        Ok(())
+    }
+
+    // The coaching relationship that the logged in test user is the coachee of, with a coach
+    // who keeps private notes.
+    fn coachee_relationship(coachee: &users::Model) -> coaching_relationships::Model {
+        let now = Utc::now();
+
+        coaching_relationships::Model {
+            id: Id::new_v4(),
+            organization_id: Id::new_v4(),
+            coach_id: Id::new_v4(),
+            coachee_id: coachee.id,
+            status: Default::default(),
+            start_date: now.date_naive(),
+            end_date: None,
+            reassigned_to_id: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    fn note_by(user_id: Id, visibility: Visibility) -> notes::Model {
+        let now = Utc::now();
+
+        notes::Model {
+            id: Id::new_v4(),
+            coaching_session_id: Id::new_v4(),
+            body: Some("Coachee seems disengaged".to_owned()),
+            user_id,
+            visibility,
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+        }
+    }
+
+    // Whether every query of Notes in `statements` only matches shared Notes and those of
+    // `user_id`
+    fn only_visible_notes_queried(statements: &[String], user_id: Id) -> bool {
+        let visible_to = format!(
+            "(\"notes\".\"visibility\" = (CAST('shared' AS visibility)) OR \"notes\".\"user_id\" = '{user_id}')"
+        );
+        let queries = statements
+            .iter()
+            .filter(|statement| statement.contains("FROM \"refactor_platform\".\"notes\""))
+            .collect::<Vec<_>>();
+
+        !queries.is_empty() && queries.iter().all(|query| query.contains(&visible_to))
+    }
+
+    // Purpose: tests that a coachee asking for their coach's private Note by its id is told it
+    // was not found, because the Note is only looked up among those the coachee can see.
+    #[tokio::test]
+    async fn read_returns_not_found_for_a_coachs_private_note() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        enable_test_logging(&mut config);
+
+        let coachee = TestClientServer::get_user().expect("Creating a new test user failed");
+        let relationship = coachee_relationship(&coachee);
+        let private_note = note_by(relationship.coach_id, Visibility::Private);
+
+        let (db, statements) = RecordingDatabase::connect(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results([vec![coachee.clone()]]) // For the initial login auth check
+                .append_query_results([vec![coachee.clone()]]) // For the AuthSession check done with the next endpoint call
+                .append_query_results([Vec::<notes::Model>::new()]),
+        );
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&coachee).await?;
+
+        let response = test_client_server
+            .client
+            .get(test_client_server.url(format!("/notes/{}", private_note.id))?)
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        assert!(only_visible_notes_queried(
+            &statements.lock().unwrap(),
+            coachee.id
+        ));
+
+        Ok(())
+    }
+
+    // Purpose: tests that listing a coaching session's Notes as the coachee leaves out the
+    // coach's private Notes while returning the shared ones.
+    #[tokio::test]
+    async fn index_leaves_out_a_coachs_private_notes() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        enable_test_logging(&mut config);
+
+        let coachee = TestClientServer::get_user().expect("Creating a new test user failed");
+        let relationship = coachee_relationship(&coachee);
+        let shared_note = note_by(relationship.coach_id, Visibility::Shared);
+
+        let (db, statements) = RecordingDatabase::connect(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results([vec![coachee.clone()]]) // For the initial login auth check
+                .append_query_results([vec![coachee.clone()]]) // For the AuthSession check done with the next endpoint call
+                .append_query_results([vec![shared_note.clone()]]),
+        );
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&coachee).await?;
+
+        let response = test_client_server
+            .client
+            .get(test_client_server.url(format!(
+                "/notes?coaching_session_id={}",
+                shared_note.coaching_session_id
+            ))?)
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.json::<serde_json::Value>().await?["data"],
+            json!([shared_note])
+        );
+        assert!(only_visible_notes_queried(
+            &statements.lock().unwrap(),
+            coachee.id
+        ));
+
+        Ok(())
+    }
+
+    // Purpose: tests that the API refuses to restore a Note to an earlier revision without an
+    // If-Match header, so that a restore can't overwrite an edit made in the meantime.
+    #[tokio::test]
+    async fn restore_revision_requires_the_version_of_the_note() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+
+        let db = Arc::new(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results([vec![user.clone()]]) // For the initial login auth check
+                .append_query_results([vec![user.clone()]]) // For the AuthSession check done with the next endpoint call
+                .into_connection(),
+        );
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&user).await?;
+
+        let response = test_client_server
+            .client
+            .post(test_client_server.url(format!(
+                "/notes/{}/revisions/{}/restore",
+                Id::new_v4(),
+                Id::new_v4()
+            ))?)
+            .send()
+            .await?;
+
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::PRECONDITION_REQUIRED
+        );
+
+        let problem: serde_json::Value = serde_json::from_str(&response.text().await?).unwrap();
+        assert_eq!(problem["code"], "missing_if_match");
+
+        Ok(())
+    }
+
+    // Purpose: tests that a coachee's search only matches shared Notes and their own, never
+    // their coach's private Notes.
+    #[tokio::test]
+    async fn search_leaves_out_a_coachs_private_notes() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        enable_test_logging(&mut config);
+
+        let coachee = TestClientServer::get_user().expect("Creating a new test user failed");
+
+        let (db, statements) = RecordingDatabase::connect(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results([vec![coachee.clone()]]) // For the initial login auth check
+                .append_query_results([vec![coachee.clone()]]) // For the AuthSession check done with the next endpoint call
+                .append_query_results([Vec::<notes::Model>::new()]),
+        );
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&coachee).await?;
+
+        let response = test_client_server
+            .client
+            .get(test_client_server.url("/search?q=disengaged")?)
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.json::<serde_json::Value>().await?["data"],
+            json!([])
+        );
+
+        let statements = statements.lock().unwrap();
+        let search = statements.last().unwrap();
+        assert!(search.contains(&format!(
+            "FROM \"refactor_platform\".\"notes\"\n    WHERE \"deleted_at\" IS NULL AND (\"visibility\" = 'shared' OR \"user_id\" = '{}')",
+            coachee.id
+        )));
+
+        Ok(())
+    }
+
+    // Purpose: tests that a coachee's export of their coaching relationship only includes
+    // shared Notes and their own, never their coach's private Notes.
+    #[tokio::test]
+    async fn export_leaves_out_a_coachs_private_notes() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        enable_test_logging(&mut config);
+
+        let coachee = TestClientServer::get_user().expect("Creating a new test user failed");
+        let relationship = coachee_relationship(&coachee);
+
+        // Each kind of record is read twice, once for its JSON file and once for its CSV file
+        let mut mock = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![coachee.clone()]]) // For the initial login auth check
+            .append_query_results([vec![coachee.clone()]]) // For the AuthSession check done with the next endpoint call
+            .append_query_results([vec![relationship.clone()]]);
+        for _ in 0..2 {
+            mock = mock
+                .append_query_results([Vec::<coaching_sessions::Model>::new()])
+                .append_query_results([Vec::<coaching_sessions::Model>::new()]);
+        }
+        for _ in 0..2 {
+            mock = mock.append_query_results([Vec::<notes::Model>::new()]);
+        }
+        for _ in 0..2 {
+            mock = mock.append_query_results([Vec::<agreements::Model>::new()]);
+        }
+        for _ in 0..2 {
+            mock = mock.append_query_results([Vec::<actions::Model>::new()]);
+        }
+        for _ in 0..2 {
+            mock = mock.append_query_results([Vec::<overarching_goals::Model>::new()]);
+        }
+        let (db, statements) = RecordingDatabase::connect(mock);
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&coachee).await?;
+
+        let response = test_client_server
+            .client
+            .get(test_client_server.url(format!(
+                "/organizations/{}/coaching_relationships/{}/export",
+                relationship.organization_id, relationship.id
+            ))?)
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        response.bytes().await?;
+
+        assert!(only_visible_notes_queried(
+            &statements.lock().unwrap(),
+            coachee.id
+        ));
+
+        Ok(())
+    }
+
+    // Purpose: tests that only an admin of the Organization can update or reassign one of its
+    // coaching relationships, so that a coachee can't end or take over their own relationship.
+    #[tokio::test]
+    async fn only_admins_can_update_or_reassign_a_coaching_relationship() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let now = Utc::now();
+        enable_test_logging(&mut config);
+
+        let coachee = TestClientServer::get_user().expect("Creating a new test user failed");
+        let relationship = coachee_relationship(&coachee);
+        let organization_results = [vec![organizations::Model {
+            id: relationship.organization_id,
+            name: "Organization One".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: None,
+            logo: None,
+        }]];
+
+        let (db, statements) = RecordingDatabase::connect(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results([vec![coachee.clone()]]) // For the initial login auth check
+                .append_query_results([vec![coachee.clone()]]) // For the AuthSession check done with the next endpoint call
+                .append_query_results(organization_results.clone()) // For finding that the user is not an admin
+                .append_query_results([Vec::<organization_admins::Model>::new()])
+                .append_query_results([vec![coachee.clone()]]) // For the AuthSession check done with the next endpoint call
+                .append_query_results(organization_results.clone()) // For finding that the user is not an admin
+                .append_query_results([Vec::<organization_admins::Model>::new()]),
+        );
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&coachee).await?;
+
+        let url = format!(
+            "/organizations/{}/coaching_relationships/{}",
+            relationship.organization_id, relationship.id
+        );
+
+        let response = test_client_server
+            .client
+            .put(test_client_server.url(&url)?)
+            .header(header::IF_MATCH, etag::of(&relationship.updated_at))
+            .json(&json!({
+                "status": "Ended",
+                "start_date": relationship.start_date,
+                "end_date": now.date_naive(),
+            }))
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let response = test_client_server
+            .client
+            .post(test_client_server.url(format!("{url}/reassign"))?)
+            .json(&json!({ "coach_id": coachee.id }))
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        assert!(!statements
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|statement| statement.contains("coaching_relationships")));
+
+        Ok(())
+    }
+
+    // Sends each of `requests` as a user who takes no part in the coaching session of the record
+    // they name, checking that each is told the record was not found because it was only looked
+    // up among the user's own coaching relationships.
+    async fn requests_for_another_pairs_record_are_not_found<M>(
+        requests: &[(reqwest::Method, String)],
+    ) -> anyhow::Result<()>
+    where
+        M: IntoMockRow,
+    {
+        let mut config = Config::default();
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+
+        let mut mock =
+            MockDatabase::new(DatabaseBackend::Postgres).append_query_results([vec![user.clone()]]); // For the initial login auth check
+        for _ in requests {
+            mock = mock
+                .append_query_results([vec![user.clone()]]) // For the AuthSession check done with the next endpoint call
+                .append_query_results([Vec::<M>::new()]);
+        }
+        let (db, statements) = RecordingDatabase::connect(mock);
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&user).await?;
+
+        for (method, path) in requests {
+            let response = test_client_server
+                .client
+                .request(method.clone(), test_client_server.url(path)?)
+                .header(header::IF_MATCH, etag::of(&Utc::now().into()))
+                .send()
+                .await?;
+
+            assert_eq!(
+                response.status(),
+                reqwest::StatusCode::NOT_FOUND,
+                "{method} {path}"
+            );
+        }
+
+        let member = format!(
+            "(\"coaching_relationships\".\"coach_id\" = '{0}' OR \"coaching_relationships\".\"coachee_id\" = '{0}')",
+            user.id
+        );
+        let statements = statements.lock().unwrap();
+        assert_eq!(
+            statements
+                .iter()
+                .filter(|statement| statement.contains(&member))
+                .count(),
+            requests.len()
+        );
+        assert!(!statements
+            .iter()
+            .any(|statement| statement.starts_with("UPDATE")));
+
+        Ok(())
+    }
+
+    // Purpose: tests that an Action of another coach and coachee's session can't be read,
+    // deleted, restored or have its history read by its id.
+    #[tokio::test]
+    async fn another_pairs_action_is_not_found() -> anyhow::Result<()> {
+        let id = Id::new_v4();
+
+        requests_for_another_pairs_record_are_not_found::<actions::Model>(&[
+            (reqwest::Method::GET, format!("/actions/{id}")),
+            (reqwest::Method::DELETE, format!("/actions/{id}")),
+            (reqwest::Method::GET, format!("/actions/{id}/history")),
+            (reqwest::Method::POST, format!("/actions/{id}/restore")),
+        ])
+        .await
+    }
+
+    // Purpose: tests that an Agreement of another coach and coachee's session can't be read,
+    // deleted, restored or have its revisions read by its id.
+    #[tokio::test]
+    async fn another_pairs_agreement_is_not_found() -> anyhow::Result<()> {
+        let id = Id::new_v4();
+
+        requests_for_another_pairs_record_are_not_found::<agreements::Model>(&[
+            (reqwest::Method::GET, format!("/agreements/{id}")),
+            (reqwest::Method::DELETE, format!("/agreements/{id}")),
+            (reqwest::Method::GET, format!("/agreements/{id}/revisions")),
+            (reqwest::Method::POST, format!("/agreements/{id}/restore")),
+        ])
+        .await
+    }
+
+    // Purpose: tests that an Overarching Goal of another coach and coachee's session can't be
+    // read or have its history or revisions read by its id.
+    #[tokio::test]
+    async fn another_pairs_overarching_goal_is_not_found() -> anyhow::Result<()> {
+        let id = Id::new_v4();
+
+        requests_for_another_pairs_record_are_not_found::<overarching_goals::Model>(&[
+            (reqwest::Method::GET, format!("/overarching_goals/{id}")),
+            (
+                reqwest::Method::GET,
+                format!("/overarching_goals/{id}/history"),
+            ),
+            (
+                reqwest::Method::GET,
+                format!("/overarching_goals/{id}/revisions"),
+            ),
+        ])
+        .await
+    }
+
+    // Purpose: tests that only an admin of a deleted Organization can restore it.
+    #[tokio::test]
+    async fn restore_returns_not_found_for_users_that_are_not_admins() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let now = Utc::now();
+        enable_test_logging(&mut config);
+
+        let user = TestClientServer::get_user().expect("Creating a new test user failed");
+        let organization = organizations::Model {
+            id: Id::new_v4(),
+            name: "Organization One".to_owned(),
+            created_at: now.into(),
+            updated_at: now.into(),
+            deleted_at: Some(now.into()),
+            logo: None,
+        };
+
+        let (db, statements) = RecordingDatabase::connect(
+            MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results([vec![user.clone()]]) // For the initial login auth check
+                .append_query_results([vec![user.clone()]]) // For the AuthSession check done with the next endpoint call
+                .append_query_results([vec![organization.clone()]])
+                .append_query_results([Vec::<organization_admins::Model>::new()]),
+        );
+
+        let app_state = AppState::new(config, &db);
+        let mut test_client_server = TestClientServer::new(define_routes(app_state), &db)
+            .await
+            .unwrap();
+        test_client_server.login(&user).await?;
+
+        let response = test_client_server
+            .client
+            .post(test_client_server.url(format!("/organizations/{}/restore", organization.id))?)
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        assert!(!statements
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|statement| statement.starts_with("UPDATE")));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,208 @@
+//! Routing of requests to the version of the API they name in their `x-version` header.
+//!
+//! Each version that is still served registers its own routes, so a breaking change ships as a
+//! new version while clients of the older ones keep working. A response from a deprecated
+//! version carries `Deprecation` and `Sunset` headers warning that it will go away, and once it
+//! has, requests naming it are refused with `410 Gone`.
+
+use crate::error::Problem;
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use chrono::{DateTime, Utc};
+use service::config::{ApiVersion, Lifecycle, ReleasedVersion};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use log::*;
+
+pub(crate) const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+pub(crate) const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// Routes each request to the routes of the version of the API it names.
+#[derive(Clone)]
+pub(crate) struct VersionedRouter {
+    current: &'static str,
+    released: Vec<ReleasedVersion>,
+    routes: HashMap<&'static str, Router>,
+}
+
+impl VersionedRouter {
+    /// A router for the `released` versions of the API, which sends requests that don't name
+    /// a version to the `current` one.
+    pub(crate) fn new(current: &'static str, released: &[ReleasedVersion]) -> Self {
+        VersionedRouter {
+            current,
+            released: released.to_vec(),
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Serves requests naming `version` with `routes`.
+    pub(crate) fn version(mut self, version: &'static str, routes: Router) -> Self {
+        self.routes.insert(version, routes);
+        self
+    }
+
+    pub(crate) fn into_router(self) -> Router {
+        Router::new().fallback(dispatch).with_state(Arc::new(self))
+    }
+
+    fn released(&self, version: &str) -> Option<&ReleasedVersion> {
+        self.released
+            .iter()
+            .find(|released| released.version == version)
+    }
+}
+
+async fn dispatch(State(versions): State<Arc<VersionedRouter>>, mut request: Request) -> Response {
+    // Requests that don't name a version, like those for static files, are left to the current
+    // version, where any endpoint that needs one rejects them.
+    let version = match request.headers().get(ApiVersion::field_name()) {
+        Some(value) => value.to_str().unwrap_or_default(),
+        None => versions.current,
+    };
+
+    let Some(released) = versions.released(version).copied() else {
+        return Problem::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_api_version",
+            &format!(
+                "`{}` header is not a valid API version",
+                ApiVersion::field_name()
+            ),
+        )
+        .into_response();
+    };
+
+    let routes = versions.routes.get(released.version);
+    let Some(routes) = routes.filter(|_| released.is_served_at(Utc::now().timestamp())) else {
+        debug!("API version {} is no longer served", released.version);
+
+        return Problem::new(
+            StatusCode::GONE,
+            "api_version_sunset",
+            &format!(
+                "API version {} is no longer served, use a newer version",
+                released.version
+            ),
+        )
+        .into_response();
+    };
+
+    trace!("Routing request to API version {}", released.version);
+
+    request.extensions_mut().insert(released);
+    let Ok(mut response) = routes.clone().oneshot(request).await;
+
+    if let Lifecycle::Deprecated {
+        deprecated_at,
+        sunset_at,
+    } = released.lifecycle
+    {
+        let headers = response.headers_mut();
+        headers.insert(DEPRECATION, deprecation_date(deprecated_at));
+        headers.insert(SUNSET, http_date(sunset_at));
+    }
+
+    response
+}
+
+// The Deprecation header gives its date as a structured field date (RFC 9745)
+fn deprecation_date(timestamp: i64) -> HeaderValue {
+    HeaderValue::from_str(&format!("@{timestamp}")).unwrap()
+}
+
+// The Sunset header gives its date as an HTTP-date (RFC 8594)
+fn http_date(timestamp: i64) -> HeaderValue {
+    let date = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+
+    HeaderValue::from_str(&date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get};
+
+    const DEPRECATED_AT: i64 = 1_735_689_600; // 2025-01-01T00:00:00Z
+    const FAR_FUTURE: i64 = 4_102_444_800; // 2100-01-01T00:00:00Z
+
+    fn released(version: &'static str, lifecycle: Lifecycle) -> ReleasedVersion {
+        ReleasedVersion { version, lifecycle }
+    }
+
+    fn app() -> Router {
+        let released = [
+            released(
+                "0.0.1",
+                Lifecycle::Deprecated {
+                    deprecated_at: DEPRECATED_AT,
+                    sunset_at: FAR_FUTURE,
+                },
+            ),
+            released("0.0.2", Lifecycle::Supported),
+            released(
+                "0.0.0",
+                Lifecycle::Deprecated {
+                    deprecated_at: DEPRECATED_AT - 1,
+                    sunset_at: DEPRECATED_AT,
+                },
+            ),
+        ];
+
+        VersionedRouter::new("0.0.2", &released)
+            .version("0.0.0", Router::new().route("/", get(|| async { "v0" })))
+            .version("0.0.1", Router::new().route("/", get(|| async { "v1" })))
+            .version("0.0.2", Router::new().route("/", get(|| async { "v2" })))
+            .into_router()
+    }
+
+    async fn call(version: Option<&str>) -> Response {
+        let mut request = Request::builder().uri("/");
+        if let Some(version) = version {
+            request = request.header(ApiVersion::field_name(), version);
+        }
+
+        app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn dispatch_routes_each_request_to_the_version_it_names() {
+        assert_eq!(text(call(Some("0.0.1")).await).await, "v1");
+        assert_eq!(text(call(Some("0.0.2")).await).await, "v2");
+        assert_eq!(text(call(None).await).await, "v2");
+    }
+
+    #[tokio::test]
+    async fn dispatch_warns_of_a_deprecated_versions_sunset() {
+        let deprecated = call(Some("0.0.1")).await;
+        assert_eq!(deprecated.headers()[DEPRECATION], "@1735689600");
+        assert_eq!(
+            deprecated.headers()[SUNSET],
+            "Fri, 01 Jan 2100 00:00:00 GMT"
+        );
+
+        let supported = call(Some("0.0.2")).await;
+        assert!(supported.headers().get(DEPRECATION).is_none());
+        assert!(supported.headers().get(SUNSET).is_none());
+    }
+
+    #[tokio::test]
+    async fn dispatch_refuses_versions_past_their_sunset_and_unknown_versions() {
+        assert_eq!(call(Some("0.0.0")).await.status(), StatusCode::GONE);
+        assert_eq!(call(Some("9.9.9")).await.status(), StatusCode::BAD_REQUEST);
+    }
+}